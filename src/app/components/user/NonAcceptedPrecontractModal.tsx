@@ -55,6 +55,8 @@ export default function NonAcceptedPrecontractModal({
             ).file
         );
 
+        // precontracts are created with a 128 bits key (see NewContractModal),
+        // which is the cipher checked by default
         const { success, h_circuit, h_ct } = check_precontract(
             item_description,
             commitment,
@@ -29,6 +29,7 @@ import init, {
     bytes_to_hex,
     check_received_ct_key,
     compile_basic_circuit,
+    CompiledCircuit,
     compute_proof_right,
     compute_proofs,
     compute_proofs_left,
@@ -491,6 +492,7 @@ export default function OngoingContractModal({
         await init();
         const { ct, circuit, evaluated_circuit } = await getLargeData();
         const challenge = await getChallenge(dispute_smart_contract!);
+        const version = CompiledCircuit.from_bytes(circuit).version;
         console.log("hey");
 
         if (state == 2) {
@@ -514,7 +516,7 @@ export default function OngoingContractModal({
                 challenge,
                 gate,
                 values,
-                0,
+                version,
                 curr_acc,
                 proof1,
                 proof2,
@@ -539,7 +541,7 @@ export default function OngoingContractModal({
                 challenge,
                 gate,
                 values,
-                0,
+                version,
                 curr_acc,
                 proof1,
                 proof2,
@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+import {AES128CtrEvaluator} from "./AES128CtrEvaluator.sol";
+
+/**
+ * @title AES256CtrEvaluator
+ * @notice A library for AES-256 decryption using Counter (CTR) mode.
+ * @dev Only the key schedule and the number of rounds differ from AES-128, so the round
+ * functions of AES128CtrEvaluator are reused.
+ */
+library AES256CtrEvaluator {
+    /**
+     * @dev The number of 32-bit words comprising the Cipher Key.
+     */
+    uint8 constant Nk = 8;
+
+    /**
+     * @dev The number of rounds
+     */
+    uint8 constant Nr = 14;
+
+    /**
+     * @notice Expands the cipher key into the key schedule.
+     * @dev This function generates the round keys used in each round of encryption.
+     * @param key The cipher key.
+     * @return w The expanded key schedule.
+     */
+    function keyExpansion(
+        bytes32 key
+    ) internal pure returns (uint32[60] memory w) {
+        for (uint8 i = 0; i < Nk; i++) {
+            w[i] = ((uint32(uint8(key[i * 4])) << 24) |
+                (uint32(uint8(key[i * 4 + 1])) << 16) |
+                (uint32(uint8(key[i * 4 + 2])) << 8) |
+                uint32(uint8(key[i * 4 + 3])));
+        }
+
+        uint32 temp;
+        for (uint8 i = Nk; i < 4 * (Nr + 1); i++) {
+            temp = w[i - 1];
+            if (i % Nk == 0) {
+                temp =
+                    AES128CtrEvaluator.subWord(AES128CtrEvaluator.rotWord(temp)) ^
+                    AES128CtrEvaluator.rcon(i / Nk);
+            } else if (i % Nk == 4) {
+                temp = AES128CtrEvaluator.subWord(temp);
+            }
+            w[i] = w[i - Nk] ^ temp;
+        }
+    }
+
+    /**
+     * @notice Slices a round key from the key schedule.
+     * @dev This function extracts a round key from the key schedule.
+     * @param w The key schedule.
+     * @param offset The offset in the key schedule.
+     * @return rk The round key.
+     */
+    function sliceRoundKey(
+        uint32[60] memory w,
+        uint8 offset
+    ) internal pure returns (uint32[4] memory rk) {
+        for (uint8 i = 0; i < 4; i++) {
+            rk[i] = w[offset + i];
+        }
+    }
+
+    /**
+     * @notice Encrypts a single block of plaintext.
+     * @dev This function performs AES-256 encryption on a single block of plaintext.
+     * @param plaintext The plaintext to encrypt.
+     * @param key The cipher key.
+     * @return The ciphertext.
+     */
+    function encryptBlockInternal(
+        bytes16 plaintext,
+        bytes32 key
+    ) internal pure returns (bytes16) {
+        uint8[16] memory state;
+        for (uint8 i = 0; i < 16; i++) {
+            state[i] = uint8(plaintext[i]);
+        }
+
+        uint32[60] memory roundKeys = keyExpansion(key);
+
+        AES128CtrEvaluator.addRoundKey(state, sliceRoundKey(roundKeys, 0));
+
+        for (uint8 round = 1; round < Nr; round++) {
+            AES128CtrEvaluator.subBytes(state);
+            AES128CtrEvaluator.shiftRows(state);
+            AES128CtrEvaluator.mixColumns(state);
+            AES128CtrEvaluator.addRoundKey(
+                state,
+                sliceRoundKey(roundKeys, round * 4)
+            );
+        }
+
+        AES128CtrEvaluator.subBytes(state);
+        AES128CtrEvaluator.shiftRows(state);
+        AES128CtrEvaluator.addRoundKey(state, sliceRoundKey(roundKeys, Nr * 4));
+
+        bytes16 result;
+        for (uint8 i = 0; i < 16; i++) {
+            result |= bytes16(bytes1(state[i])) >> (i * 8);
+        }
+        return result;
+    }
+
+    /**
+     * @notice Decrypts a block of ciphertext in CTR mode (instruction set version 1).
+     * @dev This function performs AES-256 decryption in CTR mode on a block of ciphertext.
+     * @param _data An array containing the key, ciphertext, and counter.
+     * @return The plaintext.
+     */
+    function decryptBlock(
+        bytes[] memory _data
+    ) public pure returns (bytes memory) {
+        require(_data.length == 3, "Invalid _data array length");
+        require(_data[0].length == 32, "Key must be 32 bytes long");
+        require(
+            _data[1].length <= 64,
+            "Ciphertext must be at most 64 bytes long"
+        );
+
+        if (_data[1].length == 0) {
+            return _data[1];
+        }
+
+        require(_data[2].length == 16, "Counter must be 16 bytes long");
+
+        bytes32 key = bytes32(_data[0]);
+        bytes memory ciphertext = _data[1];
+        bytes16 counter = bytes16(_data[2]);
+
+        uint256 blocks = (ciphertext.length + 15) / 16;
+        bytes memory plaintext = new bytes(ciphertext.length);
+
+        for (uint256 i = 0; i < blocks; i++) {
+            bytes16 keystream = encryptBlockInternal(counter, key);
+
+            for (uint256 j = 0; j < 16 && i * 16 + j < ciphertext.length; j++) {
+                plaintext[i * 16 + j] = ciphertext[i * 16 + j] ^ keystream[j];
+            }
+
+            counter = AES128CtrEvaluator.incrementCounter(counter);
+        }
+
+        return plaintext;
+    }
+}
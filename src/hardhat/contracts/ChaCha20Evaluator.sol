@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+/**
+ * @title ChaCha20Evaluator
+ * @notice A library for ChaCha20 decryption as specified in RFC 8439 (96-bit nonce).
+ * @dev A circuit block is a single ChaCha20 block of 64 bytes, so one call computes one
+ * keystream block.
+ */
+library ChaCha20Evaluator {
+    /**
+     * @notice Rotates a word to the left.
+     * @param x The word to rotate.
+     * @param n The number of bits to rotate by.
+     * @return The rotated word.
+     */
+    function rotl(uint32 x, uint8 n) internal pure returns (uint32) {
+        return (x << n) | (x >> (32 - n));
+    }
+
+    /**
+     * @notice Applies the quarter round to four words of the state.
+     * @dev The additions are modulo 2^32.
+     * @param s The state.
+     * @param a Index of the first word.
+     * @param b Index of the second word.
+     * @param c Index of the third word.
+     * @param d Index of the fourth word.
+     */
+    function quarterRound(
+        uint32[16] memory s,
+        uint8 a,
+        uint8 b,
+        uint8 c,
+        uint8 d
+    ) internal pure {
+        unchecked {
+            s[a] += s[b];
+            s[d] = rotl(s[d] ^ s[a], 16);
+            s[c] += s[d];
+            s[b] = rotl(s[b] ^ s[c], 12);
+            s[a] += s[b];
+            s[d] = rotl(s[d] ^ s[a], 8);
+            s[c] += s[d];
+            s[b] = rotl(s[b] ^ s[c], 7);
+        }
+    }
+
+    /**
+     * @notice Reads a little endian word.
+     * @param data The bytes to read from.
+     * @param offset The position of the word.
+     * @return The word.
+     */
+    function readWord(
+        bytes memory data,
+        uint256 offset
+    ) internal pure returns (uint32) {
+        return
+            uint32(uint8(data[offset])) |
+            (uint32(uint8(data[offset + 1])) << 8) |
+            (uint32(uint8(data[offset + 2])) << 16) |
+            (uint32(uint8(data[offset + 3])) << 24);
+    }
+
+    /**
+     * @notice Computes a keystream block.
+     * @dev This is the ChaCha20 block function of RFC 8439, section 2.3.
+     * @param key The key (32 bytes).
+     * @param nonce The nonce, read from the first 12 bytes.
+     * @param counter The block counter.
+     * @return keystream The serialized block (64 bytes).
+     */
+    function chachaBlock(
+        bytes memory key,
+        bytes memory nonce,
+        uint32 counter
+    ) internal pure returns (bytes memory keystream) {
+        uint32[16] memory state;
+        state[0] = 0x61707865;
+        state[1] = 0x3320646e;
+        state[2] = 0x79622d32;
+        state[3] = 0x6b206574;
+        for (uint8 i = 0; i < 8; i++) {
+            state[4 + i] = readWord(key, i * 4);
+        }
+        state[12] = counter;
+        for (uint8 i = 0; i < 3; i++) {
+            state[13 + i] = readWord(nonce, i * 4);
+        }
+
+        uint32[16] memory x;
+        for (uint8 i = 0; i < 16; i++) {
+            x[i] = state[i];
+        }
+
+        for (uint8 round = 0; round < 10; round++) {
+            // column rounds
+            quarterRound(x, 0, 4, 8, 12);
+            quarterRound(x, 1, 5, 9, 13);
+            quarterRound(x, 2, 6, 10, 14);
+            quarterRound(x, 3, 7, 11, 15);
+            // diagonal rounds
+            quarterRound(x, 0, 5, 10, 15);
+            quarterRound(x, 1, 6, 11, 12);
+            quarterRound(x, 2, 7, 8, 13);
+            quarterRound(x, 3, 4, 9, 14);
+        }
+
+        keystream = new bytes(64);
+        for (uint8 i = 0; i < 16; i++) {
+            uint32 word;
+            unchecked {
+                word = x[i] + state[i];
+            }
+            keystream[i * 4] = bytes1(uint8(word));
+            keystream[i * 4 + 1] = bytes1(uint8(word >> 8));
+            keystream[i * 4 + 2] = bytes1(uint8(word >> 16));
+            keystream[i * 4 + 3] = bytes1(uint8(word >> 24));
+        }
+    }
+
+    /**
+     * @notice Decrypts a block of ciphertext (instruction set version 2).
+     * @dev The counter of the next block is obtained by adding 1 to the last input, as done by
+     * `binAdd`.
+     * @param _data An array containing the key, the ciphertext and the nonce (12 bytes) followed
+     * by the block counter (4 bytes, big endian).
+     * @return The plaintext.
+     */
+    function decryptBlock(
+        bytes[] memory _data
+    ) public pure returns (bytes memory) {
+        require(_data.length == 3, "Invalid _data array length");
+        require(_data[0].length == 32, "Key must be 32 bytes long");
+        require(
+            _data[1].length <= 64,
+            "Ciphertext must be at most 64 bytes long"
+        );
+
+        if (_data[1].length == 0) {
+            return _data[1];
+        }
+
+        require(_data[2].length == 16, "Counter must be 16 bytes long");
+
+        bytes memory nonceCounter = _data[2];
+        uint32 counter = (uint32(uint8(nonceCounter[12])) << 24) |
+            (uint32(uint8(nonceCounter[13])) << 16) |
+            (uint32(uint8(nonceCounter[14])) << 8) |
+            uint32(uint8(nonceCounter[15]));
+
+        bytes memory keystream = chachaBlock(_data[0], nonceCounter, counter);
+
+        bytes memory ciphertext = _data[1];
+        bytes memory plaintext = new bytes(ciphertext.length);
+        for (uint256 i = 0; i < ciphertext.length; i++) {
+            plaintext[i] = ciphertext[i] ^ keystream[i];
+        }
+
+        return plaintext;
+    }
+}
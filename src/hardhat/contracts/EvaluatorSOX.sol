@@ -4,6 +4,9 @@ pragma solidity ^0.8.0;
 import {SHA256Evaluator} from "./SHA256Evaluator.sol";
 import {SimpleOperationsEvaluator} from "./SimpleOperationsEvaluator.sol";
 import {AES128CtrEvaluator} from "./AES128CtrEvaluator.sol";
+import {AES256CtrEvaluator} from "./AES256CtrEvaluator.sol";
+import {ChaCha20Evaluator} from "./ChaCha20Evaluator.sol";
+import {GHASHEvaluator} from "./GHASHEvaluator.sol";
 
 /**
  * @dev Struct representing an instruction in the circuit evaluator.
@@ -21,13 +24,15 @@ struct Instruction {
 library CircuitEvaluator {
     /**
      * @notice Gets the set of instructions for the circuit evaluator.
-     * @dev This function returns an array of instruction sets for different versions.
+     * @dev This function returns an array of instruction sets for different versions. Each
+     * version extends the previous one, and the opcodes a version does not have are filled with
+     * `invalidInstruction`.
      * @return The instruction sets for the circuit evaluator.
      */
     function getInstructionSet()
         internal
         pure
-        returns (Instruction[8][1] memory)
+        returns (Instruction[12][4] memory)
     {
         return [
             /* version 0 */ [
@@ -38,7 +43,53 @@ library CircuitEvaluator {
                 Instruction(binMult),
                 Instruction(equal),
                 Instruction(concat),
-                Instruction(sha256FinalCompressionInstruction)
+                Instruction(sha256FinalCompressionInstruction),
+                Instruction(invalidInstruction),
+                Instruction(invalidInstruction),
+                Instruction(invalidInstruction),
+                Instruction(invalidInstruction)
+            ],
+            /* version 1: AES-256 */ [
+                Instruction(sha256CompressionInstruction),
+                Instruction(encryptBlock),
+                Instruction(decryptBlock),
+                Instruction(binAdd),
+                Instruction(binMult),
+                Instruction(equal),
+                Instruction(concat),
+                Instruction(sha256FinalCompressionInstruction),
+                Instruction(decryptBlock256),
+                Instruction(invalidInstruction),
+                Instruction(invalidInstruction),
+                Instruction(invalidInstruction)
+            ],
+            /* version 2: ChaCha20 */ [
+                Instruction(sha256CompressionInstruction),
+                Instruction(encryptBlock),
+                Instruction(decryptBlock),
+                Instruction(binAdd),
+                Instruction(binMult),
+                Instruction(equal),
+                Instruction(concat),
+                Instruction(sha256FinalCompressionInstruction),
+                Instruction(decryptBlock256),
+                Instruction(decryptBlockChaCha20),
+                Instruction(invalidInstruction),
+                Instruction(invalidInstruction)
+            ],
+            /* version 3: AES-GCM */ [
+                Instruction(sha256CompressionInstruction),
+                Instruction(encryptBlock),
+                Instruction(decryptBlock),
+                Instruction(binAdd),
+                Instruction(binMult),
+                Instruction(equal),
+                Instruction(concat),
+                Instruction(sha256FinalCompressionInstruction),
+                Instruction(decryptBlock256),
+                Instruction(decryptBlockChaCha20),
+                Instruction(ghashMultAcc),
+                Instruction(sliceBytes)
             ]
         ];
     }
@@ -56,7 +107,7 @@ library CircuitEvaluator {
         bytes[] memory _data, // == [v_1, ..., v_a]
         uint32 _version
     ) public pure returns (bytes memory) {
-        Instruction[8][1] memory VERSION_INSTRUCTIONS = getInstructionSet();
+        Instruction[12][4] memory VERSION_INSTRUCTIONS = getInstructionSet();
         require(
             _version < VERSION_INSTRUCTIONS.length,
             "Invalid version number"
@@ -67,9 +118,21 @@ library CircuitEvaluator {
             "Values doesn't have the required length"
         );
 
+        require(
+            _gate[0] < VERSION_INSTRUCTIONS[_version].length,
+            "Invalid opcode"
+        );
+
         return VERSION_INSTRUCTIONS[_version][_gate[0]].f(_data);
     }
 
+    // Fills the opcodes that are not part of an instruction set
+    function invalidInstruction(
+        bytes[] memory
+    ) internal pure returns (bytes memory) {
+        revert("Invalid opcode");
+    }
+
     // wrappers
     // Internal wrapper for SHA256Evaluator.sha256CompressionInstruction
     function sha256CompressionInstruction(
@@ -120,4 +183,32 @@ library CircuitEvaluator {
     ) internal pure returns (bytes memory) {
         return AES128CtrEvaluator.decryptBlock(_data);
     }
+
+    // Internal wrapper for AES256CtrEvaluator.decryptBlock
+    function decryptBlock256(
+        bytes[] memory _data
+    ) internal pure returns (bytes memory) {
+        return AES256CtrEvaluator.decryptBlock(_data);
+    }
+
+    // Internal wrapper for ChaCha20Evaluator.decryptBlock
+    function decryptBlockChaCha20(
+        bytes[] memory _data
+    ) internal pure returns (bytes memory) {
+        return ChaCha20Evaluator.decryptBlock(_data);
+    }
+
+    // Internal wrapper for GHASHEvaluator.ghashMultAcc
+    function ghashMultAcc(
+        bytes[] memory _data
+    ) internal pure returns (bytes memory) {
+        return GHASHEvaluator.ghashMultAcc(_data);
+    }
+
+    // Internal wrapper for SimpleOperationsEvaluator.sliceBytes
+    function sliceBytes(
+        bytes[] memory _data
+    ) internal pure returns (bytes memory) {
+        return SimpleOperationsEvaluator.sliceBytes(_data);
+    }
 }
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+/**
+ * @title GHASHEvaluator
+ * @notice A library for the GHASH function of AES-GCM (NIST SP 800-38D).
+ * @dev This library provides the multiply-accumulate step used by the circuits to recompute the
+ * authentication tag of a ciphertext.
+ */
+library GHASHEvaluator {
+    /**
+     * @dev The reduction constant R = 11100001 || 0^120
+     */
+    uint128 constant R = 0xe1000000000000000000000000000000;
+
+    /**
+     * @notice Multiplies two blocks in GF(2^128).
+     * @dev This is algorithm 1 of NIST SP 800-38D. The first bit of a block is the most
+     * significant bit of the big-endian integer.
+     * @param x The first block.
+     * @param y The second block.
+     * @return z The product.
+     */
+    function gf128Mul(uint128 x, uint128 y) internal pure returns (uint128 z) {
+        uint128 v = y;
+        for (uint256 i = 0; i < 128; i++) {
+            if ((x >> (127 - i)) & 1 == 1) {
+                z ^= v;
+            }
+            if (v & 1 == 1) {
+                v = (v >> 1) ^ R;
+            } else {
+                v = v >> 1;
+            }
+        }
+    }
+
+    /**
+     * @notice Multiply-accumulate step of GHASH (instruction set version 3).
+     * @dev For each block X of 16 bytes of the data (the last one being padded with zeros),
+     * Y = (Y xor X) * H. The first step of a GHASH computation has no previous value and starts
+     * with Y = 0.
+     * @param _data An array containing the hash key H, the previous value (optional) and the
+     * blocks to hash.
+     * @return The new GHASH value (16 bytes).
+     */
+    function ghashMultAcc(
+        bytes[] memory _data
+    ) public pure returns (bytes memory) {
+        require(
+            _data.length == 2 || _data.length == 3,
+            "Invalid _data array length"
+        );
+        require(_data[0].length == 16, "Hash key must be 16 bytes long");
+
+        uint128 h = uint128(bytes16(_data[0]));
+        uint128 y = 0;
+        if (_data.length == 3) {
+            require(
+                _data[1].length == 16,
+                "Previous value must be 16 bytes long"
+            );
+            y = uint128(bytes16(_data[1]));
+        }
+
+        bytes memory blocks = _data[_data.length - 1];
+        require(blocks.length <= 64, "Blocks must be at most 64 bytes long");
+
+        for (uint256 i = 0; i < blocks.length; i += 16) {
+            uint128 x = 0;
+            for (uint256 j = 0; j < 16 && i + j < blocks.length; j++) {
+                x |= uint128(uint8(blocks[i + j])) << (8 * (15 - j));
+            }
+            y = gf128Mul(y ^ x, h);
+        }
+
+        return bytes.concat(bytes16(y));
+    }
+}
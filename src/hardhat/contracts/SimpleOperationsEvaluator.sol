@@ -4,7 +4,7 @@ pragma solidity ^0.8.0;
 /**
  * @title SimpleOperationsEvaluator
  * @notice A library for evaluating simple operations.
- * @dev This library provides functions to perform simple operations such as equality checks, binary addition, binary multiplication, and concatenation.
+ * @dev This library provides functions to perform simple operations such as equality checks, binary addition, binary multiplication, concatenation and slicing.
  */
 library SimpleOperationsEvaluator {
     /**
@@ -114,4 +114,41 @@ library SimpleOperationsEvaluator {
 
         return res;
     }
+
+    /**
+     * @notice Extracts the bytes of an array between two positions.
+     * @dev The positions are big-endian unsigned integers of at most 8 bytes.
+     * @param _data The array, the start position (included) and the end position (excluded).
+     * @return The bytes of the array between the two positions.
+     */
+    function sliceBytes(
+        bytes[] memory _data
+    ) external pure returns (bytes memory) {
+        require(_data.length == 3, "Slicing requires exactly 3 operators");
+        require(
+            _data[1].length <= 8 && _data[2].length <= 8,
+            "Slicing positions must be at most 8 bytes long"
+        );
+
+        uint start = toUint(_data[1]);
+        uint end = toUint(_data[2]);
+        require(
+            start <= end && end <= _data[0].length,
+            "Slicing positions are out of the array's bounds"
+        );
+
+        bytes memory res = new bytes(end - start);
+        for (uint i = 0; i < res.length; ++i) {
+            res[i] = _data[0][start + i];
+        }
+
+        return res;
+    }
+
+    // Reads a big-endian unsigned integer
+    function toUint(bytes memory _data) internal pure returns (uint res) {
+        for (uint i = 0; i < _data.length; ++i) {
+            res = (res << 8) | uint8(_data[i]);
+        }
+    }
 }
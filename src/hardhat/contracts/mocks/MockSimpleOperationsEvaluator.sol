@@ -17,4 +17,8 @@ library MockSimpleOperationsEvaluator {
     function concat(bytes[] memory) external pure returns (bytes memory) {
         return hex"06";
     }
+
+    function sliceBytes(bytes[] memory) external pure returns (bytes memory) {
+        return hex"0b";
+    }
 }
@@ -0,0 +1,19 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+import {AES256CtrEvaluator} from "../AES256CtrEvaluator.sol";
+
+contract TestAES256Ctr {
+    function encrypt(
+        bytes16 plaintext,
+        bytes32 key
+    ) public pure returns (bytes16) {
+        return AES256CtrEvaluator.encryptBlockInternal(plaintext, key);
+    }
+
+    function decryptBlock(
+        bytes[] memory _data
+    ) public pure returns (bytes memory) {
+        return AES256CtrEvaluator.decryptBlock(_data);
+    }
+}
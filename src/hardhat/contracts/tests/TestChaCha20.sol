@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+import {ChaCha20Evaluator} from "../ChaCha20Evaluator.sol";
+
+contract TestChaCha20 {
+    function decryptBlock(
+        bytes[] memory _data
+    ) public pure returns (bytes memory) {
+        return ChaCha20Evaluator.decryptBlock(_data);
+    }
+}
@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+import {GHASHEvaluator} from "../GHASHEvaluator.sol";
+
+contract TestGHASH {
+    function ghashMultAcc(
+        bytes[] memory _data
+    ) public pure returns (bytes memory) {
+        return GHASHEvaluator.ghashMultAcc(_data);
+    }
+}
@@ -29,4 +29,10 @@ contract TestSimpleOperationsEvaluator {
     ) public pure returns (bytes memory) {
         return SimpleOperationsEvaluator.concat(_data);
     }
+
+    function testSliceBytes(
+        bytes[] memory _data
+    ) public pure returns (bytes memory) {
+        return SimpleOperationsEvaluator.sliceBytes(_data);
+    }
 }
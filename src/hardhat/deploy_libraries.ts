@@ -11,6 +11,9 @@ async function main() {
         "SHA256Evaluator",
         "SimpleOperationsEvaluator",
         "AES128CtrEvaluator",
+        "AES256CtrEvaluator",
+        "ChaCha20Evaluator",
+        "GHASHEvaluator",
         "AccumulatorVerifier",
         "CommitmentOpener",
     ]) {
@@ -30,6 +33,9 @@ async function main() {
                     "SimpleOperationsEvaluator"
                 ),
                 AES128CtrEvaluator: await addresses.get("AES128CtrEvaluator"),
+                AES256CtrEvaluator: await addresses.get("AES256CtrEvaluator"),
+                ChaCha20Evaluator: await addresses.get("ChaCha20Evaluator"),
+                GHASHEvaluator: await addresses.get("GHASHEvaluator"),
             },
         }
     );
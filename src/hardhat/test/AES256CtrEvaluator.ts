@@ -0,0 +1,91 @@
+import { ethers } from "hardhat";
+import { expect } from "chai";
+import { createCipheriv, randomBytes } from "node:crypto";
+import { TestAES256Ctr } from "../typechain-types";
+
+describe("AES256 Library", function () {
+    let testAes256: TestAES256Ctr;
+
+    before(async () => {
+        const AES256CtrEvaluatorFactory = await ethers.getContractFactory(
+            "AES256CtrEvaluator"
+        );
+        const aes256CtrEvaluator = await AES256CtrEvaluatorFactory.deploy();
+        await aes256CtrEvaluator.waitForDeployment();
+
+        const TestAES256 = await ethers.getContractFactory("TestAES256Ctr", {
+            libraries: {
+                AES256CtrEvaluator: await aes256CtrEvaluator.getAddress(),
+            },
+        });
+        testAes256 = await TestAES256.deploy();
+        await testAes256.waitForDeployment();
+    });
+
+    it("AES implementation encrypts the NIST test vector correctly", async () => {
+        // NIST test vector: https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.197.pdf Appendix C.3
+        const plaintext = "0x00112233445566778899aabbccddeeff";
+        const key =
+            "0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+        const expectedCiphertext = "0x8ea2b7ca516745bfeafc49904b496089";
+
+        const result = await testAes256.encrypt(plaintext, key);
+
+        expect(result).to.equal(expectedCiphertext);
+    });
+
+    it("AES implementation encrypts a random block in the same way as node's implementation", async () => {
+        const plaintext = randomBytes(16);
+        const key = randomBytes(32);
+
+        const cipher = createCipheriv("aes-256-ecb", key, null); // use ecb mode to encrypt only one block with "pure" AES
+        const expected = cipher.update(plaintext);
+
+        const result = await testAes256.encrypt(plaintext, key);
+        expect(result).to.equal("0x" + expected.toString("hex"));
+    });
+
+    it("decrypts correctly a random plaintext encrypted by node's implementation", async () => {
+        for (let i = 0; i < 5; ++i) {
+            const key = randomBytes(32);
+            const iv = randomBytes(16);
+
+            const length = Math.floor(Math.random() * 64) + 1;
+            const pt = randomBytes(length);
+
+            const ct = createCipheriv("aes-256-ctr", key, iv).update(pt);
+            const decrypted = await testAes256.decryptBlock([key, ct, iv]);
+
+            expect(decrypted).to.equal("0x" + pt.toString("hex"));
+        }
+    });
+
+    it("reverts if the length of the provided data is incorrect", async () => {
+        const key = new Uint8Array(32);
+        const iv = new Uint8Array(16);
+
+        await expect(
+            testAes256.decryptBlock([key, new Uint8Array(64)])
+        ).to.be.revertedWith("Invalid _data array length");
+
+        await expect(
+            testAes256.decryptBlock([iv, new Uint8Array(64), iv])
+        ).to.be.revertedWith("Key must be 32 bytes long");
+
+        await expect(
+            testAes256.decryptBlock([key, new Uint8Array(65), iv])
+        ).to.be.revertedWith("Ciphertext must be at most 64 bytes long");
+
+        await expect(
+            testAes256.decryptBlock([key, new Uint8Array(64), key])
+        ).to.be.revertedWith("Counter must be 16 bytes long");
+    });
+
+    it("returns empty plaintext if ciphertext has zero length", async () => {
+        const key = new Uint8Array(32);
+        const iv = new Uint8Array(16);
+
+        const pt = await testAes256.decryptBlock([key, new Uint8Array(0), iv]);
+        expect(pt).to.equal("0x");
+    });
+});
@@ -0,0 +1,132 @@
+import { ethers } from "hardhat";
+import { expect } from "chai";
+import { createCipheriv, randomBytes } from "node:crypto";
+import { TestChaCha20 } from "../typechain-types";
+
+// nonce (12 bytes) followed by the block counter (4 bytes, big endian)
+function nonceCounter(nonce: Buffer, counter: number): Buffer {
+    const ctr = Buffer.alloc(4);
+    ctr.writeUInt32BE(counter);
+    return Buffer.concat([nonce, ctr]);
+}
+
+describe("ChaCha20 Library", function () {
+    let testChaCha20: TestChaCha20;
+
+    before(async () => {
+        const ChaCha20EvaluatorFactory = await ethers.getContractFactory(
+            "ChaCha20Evaluator"
+        );
+        const chaCha20Evaluator = await ChaCha20EvaluatorFactory.deploy();
+        await chaCha20Evaluator.waitForDeployment();
+
+        const TestChaCha20 = await ethers.getContractFactory("TestChaCha20", {
+            libraries: {
+                ChaCha20Evaluator: await chaCha20Evaluator.getAddress(),
+            },
+        });
+        testChaCha20 = await TestChaCha20.deploy();
+        await testChaCha20.waitForDeployment();
+    });
+
+    it("computes the keystream of the RFC 8439 test vector", async () => {
+        // RFC 8439, section 2.3.2
+        const key = Buffer.from(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "hex"
+        );
+        const nonce = Buffer.from("000000090000004a00000000", "hex");
+
+        const keystream = await testChaCha20.decryptBlock([
+            key,
+            new Uint8Array(64),
+            nonceCounter(nonce, 1),
+        ]);
+        expect(keystream).to.equal(
+            "0x10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e" +
+                "d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e"
+        );
+    });
+
+    it("decrypts the RFC 8439 test vector block by block", async () => {
+        // RFC 8439, section 2.4.2
+        const key = Buffer.from(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "hex"
+        );
+        const nonce = Buffer.from("000000000000004a00000000", "hex");
+        const plaintext = Buffer.from(
+            "Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it."
+        );
+        const ciphertext = createCipheriv(
+            "chacha20",
+            key,
+            Buffer.concat([Buffer.from("01000000", "hex"), nonce])
+        ).update(plaintext);
+        expect(ciphertext.subarray(0, 8).toString("hex")).to.equal(
+            "6e2e359a2568f980"
+        );
+
+        // the encryption starts with the block counter 1
+        for (let i = 0; i * 64 < ciphertext.length; ++i) {
+            const block = ciphertext.subarray(i * 64, (i + 1) * 64);
+            const decrypted = await testChaCha20.decryptBlock([
+                key,
+                block,
+                nonceCounter(nonce, 1 + i),
+            ]);
+            expect(decrypted).to.equal(
+                "0x" + plaintext.subarray(i * 64, (i + 1) * 64).toString("hex")
+            );
+        }
+    });
+
+    it("decrypts correctly a random block encrypted by node's implementation", async () => {
+        for (let i = 0; i < 5; ++i) {
+            const key = randomBytes(32);
+            const nonce = randomBytes(12);
+            const counter = randomBytes(4).readUInt32LE();
+
+            const length = Math.floor(Math.random() * 64) + 1;
+            const pt = randomBytes(length);
+
+            // node's IV is the block counter (4 bytes, little endian) followed by
+            // the nonce
+            const iv = Buffer.alloc(4);
+            iv.writeUInt32LE(counter);
+            const ct = createCipheriv(
+                "chacha20",
+                key,
+                Buffer.concat([iv, nonce])
+            ).update(pt);
+
+            const decrypted = await testChaCha20.decryptBlock([
+                key,
+                ct,
+                nonceCounter(nonce, counter),
+            ]);
+            expect(decrypted).to.equal("0x" + pt.toString("hex"));
+        }
+    });
+
+    it("reverts if the length of the provided data is incorrect", async () => {
+        const key = new Uint8Array(32);
+        const iv = new Uint8Array(16);
+
+        await expect(
+            testChaCha20.decryptBlock([key, new Uint8Array(64)])
+        ).to.be.revertedWith("Invalid _data array length");
+
+        await expect(
+            testChaCha20.decryptBlock([iv, new Uint8Array(64), iv])
+        ).to.be.revertedWith("Key must be 32 bytes long");
+
+        await expect(
+            testChaCha20.decryptBlock([key, new Uint8Array(65), iv])
+        ).to.be.revertedWith("Ciphertext must be at most 64 bytes long");
+
+        await expect(
+            testChaCha20.decryptBlock([key, new Uint8Array(64), key])
+        ).to.be.revertedWith("Counter must be 16 bytes long");
+    });
+});
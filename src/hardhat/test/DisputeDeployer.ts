@@ -27,6 +27,24 @@ describe("DisputeDeployer", () => {
         const aes128CtrEvaluator = await AES128CtrEvaluatorFactory.deploy();
         await aes128CtrEvaluator.waitForDeployment();
 
+        const AES256CtrEvaluatorFactory = await ethers.getContractFactory(
+            "AES256CtrEvaluator"
+        );
+        const aes256CtrEvaluator = await AES256CtrEvaluatorFactory.deploy();
+        await aes256CtrEvaluator.waitForDeployment();
+
+        const ChaCha20EvaluatorFactory = await ethers.getContractFactory(
+            "ChaCha20Evaluator"
+        );
+        const chaCha20Evaluator = await ChaCha20EvaluatorFactory.deploy();
+        await chaCha20Evaluator.waitForDeployment();
+
+        const GHASHEvaluatorFactory = await ethers.getContractFactory(
+            "GHASHEvaluator"
+        );
+        const ghashEvaluator = await GHASHEvaluatorFactory.deploy();
+        await ghashEvaluator.waitForDeployment();
+
         const CircuitEvaluatorFactory = await ethers.getContractFactory(
             "CircuitEvaluator",
             {
@@ -35,6 +53,9 @@ describe("DisputeDeployer", () => {
                     SimpleOperationsEvaluator:
                         await simpleOperationsEvaluator.getAddress(),
                     AES128CtrEvaluator: await aes128CtrEvaluator.getAddress(),
+                    AES256CtrEvaluator: await aes256CtrEvaluator.getAddress(),
+                    ChaCha20Evaluator: await chaCha20Evaluator.getAddress(),
+                    GHASHEvaluator: await ghashEvaluator.getAddress(),
                 },
             }
         );
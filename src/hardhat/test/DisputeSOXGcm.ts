@@ -0,0 +1,135 @@
+import { expect } from "chai";
+import hre from "hardhat";
+import "@nomicfoundation/hardhat-chai-matchers";
+import { HardhatEthersSigner } from "@nomicfoundation/hardhat-ethers/signers";
+import { ZeroHash } from "ethers";
+import { readFile } from "node:fs/promises";
+import { randomBytes } from "node:crypto";
+import { deployDisputeWithMockOptimistic } from "./deployers";
+import {
+    bytes_to_hex,
+    Cipher,
+    CompiledCircuit,
+    compute_precontract_values_with_cipher,
+    compute_proofs,
+    evaluate_circuit,
+    hpre,
+    initSync,
+    Trace,
+} from "../../app/lib/crypto_lib";
+
+const { ethers } = hre;
+
+describe("DisputeSOX with an AES-GCM circuit", function () {
+    let buyer: HardhatEthersSigner;
+    let vendor: HardhatEthersSigner;
+    let buyerDisputeSponsor: HardhatEthersSigner;
+    let vendorDisputeSponsor: HardhatEthersSigner;
+
+    before(async () => {
+        const module = await readFile(
+            "../../app/lib/crypto_lib/crypto_lib_bg.wasm"
+        );
+        initSync({ module: module });
+
+        [buyer, vendor, , buyerDisputeSponsor, vendorDisputeSponsor] =
+            await ethers.getSigners();
+    });
+
+    // Drives the challenge-response until the dispute focuses on the gate
+    // with the given label, then lets the vendor prove that this gate was
+    // evaluated correctly.
+    async function disputeGate(label: string) {
+        const file = new Uint8Array(randomBytes(100));
+        const key = new Uint8Array(randomBytes(16));
+
+        const {
+            ct,
+            circuit_bytes,
+            description,
+            commitment,
+            num_blocks,
+            num_gates,
+        } = compute_precontract_values_with_cipher(
+            file,
+            key,
+            Cipher.Aes128Gcm
+        );
+        const circuit = CompiledCircuit.from_bytes(circuit_bytes);
+
+        let target = -1;
+        for (let i = 0; i < num_gates; ++i) {
+            if (circuit.gate_label(i) == label) target = i;
+        }
+        expect(target).to.be.greaterThan(num_blocks);
+
+        const trace = Trace.from_evaluated_circuit(
+            evaluate_circuit(
+                circuit_bytes,
+                ct,
+                [bytes_to_hex(key)],
+                bytes_to_hex(description),
+                Cipher.Aes128Gcm
+            ).to_bytes()
+        );
+
+        const { contract } = await deployDisputeWithMockOptimistic(
+            BigInt(num_blocks),
+            BigInt(num_gates),
+            commitment.c,
+            buyer,
+            vendor,
+            buyerDisputeSponsor,
+            vendorDisputeSponsor
+        );
+
+        // the buyer agrees with the vendor on every gate before the target
+        // and claims a different value from the target on
+        let state = await contract.currState();
+        while (state == 0n) {
+            const challenge = Number(await contract.chall());
+            const response =
+                challenge < target
+                    ? hpre(trace, num_blocks, challenge)
+                    : ZeroHash;
+            await contract.connect(buyer).respondChallenge(response);
+            await contract.connect(vendor).giveOpinion(challenge < target);
+            state = await contract.currState();
+        }
+
+        expect(state).to.equal(2); // WaitVendorData
+        expect(await contract.a()).to.equal(target);
+
+        const { gate, values, curr_acc, proof1, proof2, proof3, proof_ext } =
+            compute_proofs(circuit_bytes, trace, ct, target);
+
+        await contract
+            .connect(vendor)
+            .submitCommitment(
+                commitment.o,
+                target,
+                gate,
+                values,
+                circuit.version,
+                curr_acc,
+                proof1 as Uint8Array[][],
+                proof2 as Uint8Array[][],
+                proof3 as Uint8Array[][],
+                proof_ext as Uint8Array[][]
+            );
+
+        expect(await contract.currState()).to.equal(5); // Complete
+    }
+
+    it("evaluates the last GHASH gate on chain", async () => {
+        await disputeGate("GHASH lengths");
+    });
+
+    it("evaluates the slicing of the received tag on chain", async () => {
+        await disputeGate("received tag");
+    });
+
+    it("evaluates the tag check on chain", async () => {
+        await disputeGate("compare tag with received tag");
+    });
+});
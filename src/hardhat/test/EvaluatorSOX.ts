@@ -23,6 +23,24 @@ describe("CircuitEvaluator", () => {
         const aesLib = await aesFactory.deploy();
         await aesLib.waitForDeployment();
 
+        const aes256Factory = await ethers.getContractFactory(
+            "MockAES256CtrEvaluator"
+        );
+        const aes256Lib = await aes256Factory.deploy();
+        await aes256Lib.waitForDeployment();
+
+        const chachaFactory = await ethers.getContractFactory(
+            "MockChaCha20Evaluator"
+        );
+        const chachaLib = await chachaFactory.deploy();
+        await chachaLib.waitForDeployment();
+
+        const ghashFactory = await ethers.getContractFactory(
+            "MockGHASHEvaluator"
+        );
+        const ghashLib = await ghashFactory.deploy();
+        await ghashLib.waitForDeployment();
+
         const circuitFactory = await ethers.getContractFactory(
             "CircuitEvaluator",
             {
@@ -30,6 +48,9 @@ describe("CircuitEvaluator", () => {
                     SHA256Evaluator: await shaLib.getAddress(),
                     SimpleOperationsEvaluator: await simpleLib.getAddress(),
                     AES128CtrEvaluator: await aesLib.getAddress(),
+                    AES256CtrEvaluator: await aes256Lib.getAddress(),
+                    ChaCha20Evaluator: await chachaLib.getAddress(),
+                    GHASHEvaluator: await ghashLib.getAddress(),
                 },
             }
         );
@@ -63,8 +84,47 @@ describe("CircuitEvaluator", () => {
         }
     });
 
+    it("should add the AES-256 instruction in version 1", async () => {
+        for (let i = 0; i < 9; i++) {
+            const result: string = await testEvaluator.evaluateGate([i], [], 1);
+            const expectedHex = "0x" + i.toString(16).padStart(2, "0");
+            expect(result).to.equal(expectedHex);
+        }
+    });
+
+    it("should add the ChaCha20 instruction in version 2", async () => {
+        for (let i = 0; i < 10; i++) {
+            const result: string = await testEvaluator.evaluateGate([i], [], 2);
+            const expectedHex = "0x" + i.toString(16).padStart(2, "0");
+            expect(result).to.equal(expectedHex);
+        }
+    });
+
+    it("should add the GHASH and slicing instructions in version 3", async () => {
+        for (let i = 0; i < 12; i++) {
+            const result: string = await testEvaluator.evaluateGate([i], [], 3);
+            const expectedHex = "0x" + i.toString(16).padStart(2, "0");
+            expect(result).to.equal(expectedHex);
+        }
+    });
+
+    it("should fail if the opcode is not part of the version", async () => {
+        await expect(testEvaluator.evaluateGate([8], [], 0)).to.be.revertedWith(
+            "Invalid opcode"
+        );
+        await expect(testEvaluator.evaluateGate([9], [], 1)).to.be.revertedWith(
+            "Invalid opcode"
+        );
+        await expect(
+            testEvaluator.evaluateGate([10], [], 2)
+        ).to.be.revertedWith("Invalid opcode");
+        await expect(
+            testEvaluator.evaluateGate([12], [], 3)
+        ).to.be.revertedWith("Invalid opcode");
+    });
+
     it("should fail if version is invalid", async () => {
-        await expect(testEvaluator.evaluateGate([0], [], 1)).to.be.revertedWith(
+        await expect(testEvaluator.evaluateGate([0], [], 4)).to.be.revertedWith(
             "Invalid version number"
         );
     });
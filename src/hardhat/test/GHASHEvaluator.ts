@@ -0,0 +1,81 @@
+import { ethers } from "hardhat";
+import { expect } from "chai";
+import { TestGHASH } from "../typechain-types";
+
+describe("GHASH Library", function () {
+    let testGhash: TestGHASH;
+
+    before(async () => {
+        const GHASHEvaluatorFactory = await ethers.getContractFactory(
+            "GHASHEvaluator"
+        );
+        const ghashEvaluator = await GHASHEvaluatorFactory.deploy();
+        await ghashEvaluator.waitForDeployment();
+
+        const TestGHASH = await ethers.getContractFactory("TestGHASH", {
+            libraries: {
+                GHASHEvaluator: await ghashEvaluator.getAddress(),
+            },
+        });
+        testGhash = await TestGHASH.deploy();
+        await testGhash.waitForDeployment();
+    });
+
+    it("computes GHASH for test case 2 of the GCM specification", async () => {
+        const h = "0x66e94bd4ef8a2c3b884cfa59ca342b2e";
+        const ct = "0x0388dace60b6a392f328c2b971b2fe78";
+        const lengths = "0x00000000000000000000000000000080";
+
+        // the first step has no previous value
+        const x1 = await testGhash.ghashMultAcc([h, ct]);
+        expect(x1).to.equal("0x5e2ec746917062882c85b0685353deb7");
+
+        const ghash = await testGhash.ghashMultAcc([h, x1, lengths]);
+        expect(ghash).to.equal("0xf38cbb1ad69223dcc3457ae5b6b0f885");
+    });
+
+    it("computes GHASH for test case 3 of the GCM specification", async () => {
+        const h = "0xb83b533708bf535d0aa6e52980d53b78";
+        const ct =
+            "0x42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e" +
+            "21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985";
+        const lengths = "0x00000000000000000000000000000200";
+
+        // four blocks at once
+        const y = await testGhash.ghashMultAcc([h, ct]);
+        const ghash = await testGhash.ghashMultAcc([h, y, lengths]);
+        expect(ghash).to.equal("0x7f1b32b81b820d02614f8895ac1d4eac");
+    });
+
+    it("pads the last block with zeros", async () => {
+        const h = "0xb83b533708bf535d0aa6e52980d53b78";
+        const data = ethers.hexlify(ethers.randomBytes(40));
+
+        const y = await testGhash.ghashMultAcc([h, data]);
+        const padded = await testGhash.ghashMultAcc([
+            h,
+            data + "00".repeat(8),
+        ]);
+        expect(y).to.equal(padded);
+    });
+
+    it("reverts if the length of the provided data is incorrect", async () => {
+        const h = new Uint8Array(16);
+
+        await expect(testGhash.ghashMultAcc([h])).to.be.revertedWith(
+            "Invalid _data array length"
+        );
+
+        await expect(
+            testGhash.ghashMultAcc([new Uint8Array(15), new Uint8Array(16)])
+        ).to.be.revertedWith("Hash key must be 16 bytes long");
+
+        await expect(
+            testGhash.ghashMultAcc([h, new Uint8Array(15), new Uint8Array(16)])
+        ).to.be.revertedWith("Previous value must be 16 bytes long");
+
+        await expect(
+            testGhash.ghashMultAcc([h, new Uint8Array(65)])
+        ).to.be.revertedWith("Blocks must be at most 64 bytes long");
+    });
+});
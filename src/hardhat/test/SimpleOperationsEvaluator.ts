@@ -152,4 +152,62 @@ describe("TestSimpleOperationsEvaluator", function () {
             expect(ethers.toUtf8String(result)).to.equal("AB");
         });
     });
+
+    describe("sliceBytes", () => {
+        const data = "0x000102030405060708090a0b0c0d0e0f";
+
+        it("extracts the bytes between two positions", async () => {
+            const result = await testEval.testSliceBytes([
+                data,
+                "0x03",
+                "0x07",
+            ]);
+            expect(result).to.equal("0x03040506");
+        });
+
+        it("accepts empty and 8 bytes positions", async () => {
+            const result = await testEval.testSliceBytes([
+                data,
+                "0x",
+                "0x0000000000000010",
+            ]);
+            expect(result).to.equal(data);
+        });
+
+        it("returns an empty array for equal positions", async () => {
+            const result = await testEval.testSliceBytes([
+                data,
+                "0x10",
+                "0x10",
+            ]);
+            expect(result).to.equal("0x");
+        });
+
+        it("reverts on positions out of bounds", async () => {
+            await expect(
+                testEval.testSliceBytes([data, "0x00", "0x11"])
+            ).to.be.revertedWith(
+                "Slicing positions are out of the array's bounds"
+            );
+            await expect(
+                testEval.testSliceBytes([data, "0x05", "0x04"])
+            ).to.be.revertedWith(
+                "Slicing positions are out of the array's bounds"
+            );
+        });
+
+        it("reverts on too long positions", async () => {
+            await expect(
+                testEval.testSliceBytes([data, "0x000000000000000000", "0x04"])
+            ).to.be.revertedWith(
+                "Slicing positions must be at most 8 bytes long"
+            );
+        });
+
+        it("reverts on wrong number of inputs", async () => {
+            await expect(
+                testEval.testSliceBytes([data, "0x00"])
+            ).to.be.revertedWith("Slicing requires exactly 3 operators");
+        });
+    });
 });
@@ -10,6 +10,7 @@ import {
     evaluate_circuit,
     hpre,
     initSync,
+    Trace,
 } from "../../../app/lib/crypto_lib";
 
 const { ethers } = hre;
@@ -110,6 +111,7 @@ describe("End-to-end", function () {
             [bytes_to_hex(key)],
             bytes_to_hex(description)
         ).to_bytes();
+        const trace = Trace.from_evaluated_circuit(evaluated_bytes);
 
         for (let i = 0; i < NB_RUNS; ++i) {
             console.log(i);
@@ -161,7 +163,7 @@ describe("End-to-end", function () {
             // do challenge-response until we get to state WaitVendorData
             // buyer responds to challenge
             let challenge = await disputeContract.chall();
-            let hpre_res = hpre(evaluated_bytes, num_blocks, Number(challenge));
+            let hpre_res = hpre(trace, num_blocks, Number(challenge));
             await disputeContract.connect(buyer).respondChallenge(hpre_res);
 
             // vendor disagrees once
@@ -172,7 +174,7 @@ describe("End-to-end", function () {
             while (state == 0n) {
                 // buyer responds to challenge
                 challenge = await disputeContract.chall();
-                hpre_res = hpre(evaluated_bytes, num_blocks, Number(challenge));
+                hpre_res = hpre(trace, num_blocks, Number(challenge));
                 await disputeContract.connect(buyer).respondChallenge(hpre_res);
 
                 // vendor decides randomly if they agree or not
@@ -196,7 +198,7 @@ describe("End-to-end", function () {
                 proof_ext,
             } = compute_proofs(
                 circuit_bytes,
-                evaluated_bytes,
+                trace,
                 ct,
                 Number(gateNum)
             );
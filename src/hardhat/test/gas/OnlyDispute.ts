@@ -12,6 +12,7 @@ import {
     evaluate_circuit,
     hpre,
     initSync,
+    Trace,
 } from "../../../app/lib/crypto_lib";
 import { ZeroHash } from "ethers";
 
@@ -122,6 +123,7 @@ describe("DisputeSOX", function () {
                 [bytes_to_hex(key)],
                 bytes_to_hex(description)
             ).to_bytes();
+            const trace = Trace.from_evaluated_circuit(evaluated_bytes);
 
             const {
                 contract,
@@ -145,7 +147,7 @@ describe("DisputeSOX", function () {
             // // do challenge-response until we get to state WaitVendorData
             // // buyer responds to challenge with incorrect hpre
             // let challenge = await contract.chall();
-            // let hpre_res = hpre(evaluated_bytes, num_blocks, Number(challenge));
+            // let hpre_res = hpre(trace, num_blocks, Number(challenge));
 
             // await contract.connect(buyer).respondChallenge(ZeroHash);
 
@@ -157,7 +159,7 @@ describe("DisputeSOX", function () {
             // while (state == 0n) {
             //     // buyer responds to challenge
             //     challenge = await contract.chall();
-            //     hpre_res = hpre(evaluated_bytes, num_blocks, Number(challenge));
+            //     hpre_res = hpre(trace, num_blocks, Number(challenge));
             //     await contract.connect(buyer).respondChallenge(hpre_res);
 
             //     // after disagreeing once, vendor only agrees
@@ -177,7 +179,7 @@ describe("DisputeSOX", function () {
             // let { gate, values, curr_acc, proof1, proof2, proof3, proof_ext } =
             //     compute_proofs(
             //         circuit_bytes,
-            //         evaluated_bytes,
+            //         trace,
             //         ct,
             //         Number(gateNum)
             //     );
@@ -209,7 +211,7 @@ describe("DisputeSOX", function () {
             //     // buyer responds to challenge
             //     let challenge = await contract.chall();
             //     let hpre_res = hpre(
-            //         evaluated_bytes,
+            //         trace,
             //         num_blocks,
             //         Number(challenge)
             //     );
@@ -232,7 +234,7 @@ describe("DisputeSOX", function () {
             // let { gate, values, curr_acc, proof1, proof2, proof_ext } =
             //     compute_proofs_left(
             //         circuit_bytes,
-            //         evaluated_bytes,
+            //         trace,
             //         ct,
             //         Number(gateNum)
             //     );
@@ -256,11 +258,7 @@ describe("DisputeSOX", function () {
             while (state == 0n) {
                 // buyer responds to challenge
                 let challenge = await contract.chall();
-                let hpre_res = hpre(
-                    evaluated_bytes,
-                    num_blocks,
-                    Number(challenge)
-                );
+                let hpre_res = hpre(trace, num_blocks, Number(challenge));
                 await contract.connect(buyer).respondChallenge(hpre_res);
 
                 // vendor only agrees
@@ -274,11 +272,7 @@ describe("DisputeSOX", function () {
                     `unexpected state, should be 4 but got ${state}`
                 );
 
-            let proof = compute_proof_right(
-                evaluated_bytes,
-                num_blocks,
-                num_gates
-            );
+            let proof = compute_proof_right(trace, num_blocks, num_gates);
 
             await contract.connect(vendor).submitCommitmentRight(proof);
 
@@ -5,6 +5,7 @@ import __wbg_init, {
     bytes_to_hex,
     hex_to_bytes,
     check_received_ct_key,
+    Cipher,
     compute_precontract_values,
     check_precontract,
     make_argument,
@@ -14,6 +15,7 @@ import __wbg_init, {
     compute_proofs,
     compute_proofs_left,
     compute_proof_right,
+    Trace,
 } from "../../../app/lib/crypto_lib";
 import { readFileSync, writeFileSync } from "node:fs";
 
@@ -120,7 +122,7 @@ async function time_bv_make_argument(desc: string, opening_value: string) {
 
     const start = performance.now();
 
-    const argument = make_argument(ct, desc, opening_value);
+    const argument = make_argument(ct, desc, opening_value, Cipher.Aes128Ctr);
 
     const end = performance.now();
     console.log(`\tTook ${end - start} ms`);
@@ -180,7 +182,7 @@ async function time_bv_compute_hpre(num_blocks: number, challenge: number) {
 
     const start = performance.now();
 
-    hpre(values, num_blocks, challenge);
+    hpre(Trace.from_evaluated_circuit(values), num_blocks, challenge);
 
     const end = performance.now();
     console.log(`\tTook ${end - start} ms`);
@@ -199,7 +201,12 @@ async function time_vendor_computes_proofs_8a(challenge: number) {
     const start = performance.now();
 
     const { gate, values, curr_acc, proof1, proof2, proof3, proof_ext } =
-        compute_proofs(circuit, evaluated_circuit, ct, challenge);
+        compute_proofs(
+            circuit,
+            Trace.from_evaluated_circuit(evaluated_circuit),
+            ct,
+            challenge
+        );
 
     const end = performance.now();
     console.log(`\tTook ${end - start} ms`);
@@ -225,7 +232,12 @@ async function time_vendor_computes_proofs_8b(challenge: number) {
     const start = performance.now();
 
     const { gate, values, curr_acc, proof1, proof2, proof_ext } =
-        compute_proofs_left(circuit, evaluated_circuit, ct, challenge);
+        compute_proofs_left(
+            circuit,
+            Trace.from_evaluated_circuit(evaluated_circuit),
+            ct,
+            challenge
+        );
 
     const end = performance.now();
     console.log(`\tTook ${end - start} ms`);
@@ -252,7 +264,11 @@ async function time_vendor_computes_proofs_8c(
 
     const start = performance.now();
 
-    const proof = compute_proof_right(evaluated_circuit, num_blocks, num_gates);
+    const proof = compute_proof_right(
+        Trace.from_evaluated_circuit(evaluated_circuit),
+        num_blocks,
+        num_gates
+    );
 
     const end = performance.now();
     console.log(`\tTook ${end - start} ms`);
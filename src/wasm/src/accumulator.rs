@@ -24,18 +24,39 @@ pub fn uint8_array_to_vec_u8(array: &Uint8Array) -> Vec<u8> {
 /// # Returns
 /// A 32-byte vector containing the accumulated hash
 pub fn acc(values: &[Vec<u8>]) -> Vec<u8> {
-    if values.len() == 0 {
+    acc_leaf_hashes(values.iter().map(hash).collect())
+}
+
+/// Computes the accumulator value from the hashes of the leaves. This is useful when the values
+/// are not all available at the same time (e.g. when they are streamed).
+///
+/// # Arguments
+/// * `hashes` - Leaf hashes, as computed by `leaf_hash`
+///
+/// # Returns
+/// A 32-byte vector containing the accumulated hash (empty if there are no leaves)
+pub fn acc_leaf_hashes(hashes: Vec<Vec<u8>>) -> Vec<u8> {
+    if hashes.is_empty() {
         return vec![];
     }
-    if values.len() == 1 {
-        return hash(&values[0]);
-    }
-
-    let hashes: Vec<Vec<u8>> = values.iter().map(hash).collect();
 
     compute_merkle_root(hashes)
 }
 
+/// Computes the hash of a single value as it is used for the leaves of the accumulator
+///
+/// # Arguments
+/// * `value` - Value to hash
+///
+/// # Returns
+/// The 32-byte Keccak256 hash of the value
+pub fn leaf_hash(value: &[u8]) -> Vec<u8> {
+    let mut hasher = Keccak256::new();
+    hasher.update(value);
+
+    hasher.finalize().to_vec()
+}
+
 /// Computes the accumulator value for a circuit
 ///
 /// # Arguments
@@ -73,14 +94,27 @@ pub fn acc_ct(ct: &[u8], block_size: usize) -> Vec<u8> {
 /// # Returns
 /// A vector of proof components
 pub fn prove(values: &[Vec<u8>], indices: &[u32]) -> Vec<Vec<Vec<u8>>> {
-    if values.len() < indices.len() {
+    prove_leaf_hashes(values.iter().map(hash).collect(), indices)
+}
+
+/// Generates a proof for a subset of values in a sequence from the hashes of the values, as
+/// computed by `leaf_hash`. See `prove`.
+///
+/// # Arguments
+/// * `hashes` - Leaf hashes of the complete sequence of values
+/// * `indices` - Indices of values to include in the proof
+///
+/// # Returns
+/// A vector of proof components
+pub fn prove_leaf_hashes(hashes: Vec<Vec<u8>>, indices: &[u32]) -> Vec<Vec<Vec<u8>>> {
+    if hashes.len() < indices.len() {
         die(&format!(
             "Number of indices ({}) is greater than number of values ({})",
             indices.len(),
-            values.len()
+            hashes.len()
         ));
     }
-    if indices.len() == 0 || values.len() == 0 {
+    if indices.is_empty() || hashes.is_empty() {
         return vec![];
     }
     let mut a = indices.to_vec();
@@ -88,7 +122,7 @@ pub fn prove(values: &[Vec<u8>], indices: &[u32]) -> Vec<Vec<Vec<u8>>> {
 
     let mut proof: Vec<Vec<Vec<u8>>> = vec![];
 
-    let mut curr_layer: Vec<Vec<u8>> = values.iter().map(hash).collect();
+    let mut curr_layer = hashes;
 
     while curr_layer.len() > 1 {
         let mut b: Vec<(u32, u32)> = vec![];
@@ -136,7 +170,19 @@ pub fn prove(values: &[Vec<u8>], indices: &[u32]) -> Vec<Vec<Vec<u8>>> {
 /// # Returns
 /// A vector of proof components demonstrating correct extension
 pub fn prove_ext(values: &[Vec<u8>]) -> Vec<Vec<Vec<u8>>> {
-    prove(values, &vec![(values.len() - 1) as u32])
+    prove(values, &[(values.len() - 1) as u32])
+}
+
+/// Generates an extension proof from the hashes of a sequence of values. See `prove_ext`.
+///
+/// # Arguments
+/// * `hashes` - Leaf hashes of the sequence of values
+///
+/// # Returns
+/// A vector of proof components demonstrating correct extension
+pub fn prove_ext_leaf_hashes(hashes: Vec<Vec<u8>>) -> Vec<Vec<Vec<u8>>> {
+    let last = (hashes.len() - 1) as u32;
+    prove_leaf_hashes(hashes, &[last])
 }
 
 /// Converts a proof to a JavaScript array
@@ -200,6 +246,145 @@ pub fn prove_ext_js(values: Vec<Uint8Array>) -> Array {
     proof_to_js_array(proof)
 }
 
+/// Verifies a proof for multiple values in the accumulator. This mirrors `AccumulatorVerifier.verify`
+/// from the smart contracts so that proofs can be checked before being sent on-chain. Inspired by
+/// https://arxiv.org/pdf/2002.07648
+///
+/// # Arguments
+/// * `root` - Expected accumulator value
+/// * `indices` - Indices of the values being proven
+/// * `values_keccak` - Leaf hashes of the values being proven
+/// * `proof` - Proof layers, where each layer contains the sibling hashes needed for
+///   verification
+///
+/// # Returns
+/// `true` if the proof reconstructs `root` from the values. As in the smart contract, an empty
+/// set of indices is always considered valid.
+pub fn verify(
+    root: &[u8],
+    indices: &[u32],
+    values_keccak: &[Vec<u8>],
+    proof: &[Vec<Vec<u8>>],
+) -> bool {
+    if indices.len() != values_keccak.len() {
+        return false;
+    }
+    if indices.is_empty() {
+        return true;
+    }
+    // the smart contract only accepts bytes32 values
+    if values_keccak
+        .iter()
+        .chain(proof.iter().flatten())
+        .any(|v| v.len() != 32)
+    {
+        return false;
+    }
+
+    let mut paired: Vec<(u32, Vec<u8>)> = indices
+        .iter()
+        .copied()
+        .zip(values_keccak.iter().cloned())
+        .collect();
+    paired.sort_by_key(|pair| pair.0);
+    let (mut indices, mut layer): (Vec<u32>, Vec<Vec<u8>>) = paired.into_iter().unzip();
+
+    for proof_layer in proof {
+        let b: Vec<(u32, u32)> = indices
+            .iter()
+            .map(|i| {
+                let neighbor = get_neighbor_idx(i);
+                if neighbor < *i {
+                    (neighbor, *i)
+                } else {
+                    (*i, neighbor)
+                }
+            })
+            .collect();
+
+        let mut next_indices: Vec<u32> = vec![];
+        let mut next_layer: Vec<Vec<u8>> = vec![];
+        // the proof layers are consumed from the end
+        let mut remaining = proof_layer.len();
+
+        let mut i = 0;
+        while i < b.len() {
+            if i + 1 < b.len() && b[i].0 == b[i + 1].0 {
+                // duplicate found: both values are known and can be hashed together
+                next_layer.push(concat_and_hash(&layer[i], &layer[i + 1]));
+                i += 1;
+            } else if remaining > 0 {
+                let sibling = &proof_layer[remaining - 1];
+                if indices[i] % 2 == 1 {
+                    next_layer.push(concat_and_hash(sibling, &layer[i]));
+                } else {
+                    next_layer.push(concat_and_hash(&layer[i], sibling));
+                }
+                remaining -= 1;
+            } else {
+                // proof layer is empty, move the element that must be combined to the next layer
+                next_layer.push(layer[i].clone());
+            }
+
+            next_indices.push(indices[i] >> 1);
+            i += 1;
+        }
+
+        layer = next_layer;
+        indices = next_indices;
+    }
+
+    layer.len() == 1 && layer[0] == root
+}
+
+/// Verifies the previous root of the accumulator from an extension proof. Mirrors
+/// `AccumulatorVerifier.verifyPrevious`: an empty proof corresponds to the zero root.
+///
+/// # Arguments
+/// * `prev_root` - Expected previous accumulator value (32 bytes)
+/// * `proof` - Extension proof
+///
+/// # Returns
+/// `true` if the proof leads to `prev_root`
+pub fn verify_previous(prev_root: &[u8], proof: &[Vec<Vec<u8>>]) -> bool {
+    let mut computed_root: Option<Vec<u8>> = None;
+
+    for layer in proof {
+        for node in layer.iter().rev() {
+            computed_root = Some(match computed_root {
+                None => node.clone(),
+                Some(root) if node.len() == 32 && root.len() == 32 => concat_and_hash(node, &root),
+                Some(_) => return false,
+            });
+        }
+    }
+
+    computed_root.unwrap_or(vec![0u8; 32]) == prev_root
+}
+
+/// Verifies an extension proof, i.e. that `curr_root` is obtained by adding a value at index `i` to
+/// the accumulator whose value is `prev_root`. Mirrors `AccumulatorVerifier.verifyExt`.
+///
+/// # Arguments
+/// * `i` - Index of the added value
+/// * `prev_root` - Previous accumulator value
+/// * `curr_root` - Current accumulator value
+/// * `added_val_keccak` - Leaf hash of the added value
+/// * `proof` - Extension proof
+///
+/// # Returns
+/// `true` if the proof is valid, `false` otherwise
+pub fn verify_ext(
+    i: u32,
+    prev_root: &[u8],
+    curr_root: &[u8],
+    added_val_keccak: &[u8],
+    proof: &[Vec<Vec<u8>>],
+) -> bool {
+    verify(curr_root, &[i], &[added_val_keccak.to_vec()], proof)
+        && verify_previous(prev_root, proof)
+}
+
 // Computes the root of a Merkle tree given the leaf hashes
 fn compute_merkle_root(hashes: Vec<Vec<u8>>) -> Vec<u8> {
     let mut curr_layer = hashes;
@@ -232,7 +417,7 @@ fn compute_next_layer(curr_layer: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
 
 // Returns the index of the neighbor node
 fn get_neighbor_idx(index: &u32) -> u32 {
-    if index % 2 == 0 {
+    if index.is_multiple_of(2) {
         index + 1
     } else {
         index - 1
@@ -241,7 +426,7 @@ fn get_neighbor_idx(index: &u32) -> u32 {
 
 // Concatenates two 32-byte vectors and hashes the result. Panics if one of the vectors is not 32
 // bytes long
-fn concat_and_hash(left: &Vec<u8>, right: &Vec<u8>) -> Vec<u8> {
+fn concat_and_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
     assert_eq!(left.len(), 32);
     assert_eq!(right.len(), 32);
 
@@ -250,11 +435,8 @@ fn concat_and_hash(left: &Vec<u8>, right: &Vec<u8>) -> Vec<u8> {
 }
 
 // Computes the Keccak256 hash of a vector of bytes
-fn hash(data: &Vec<u8>) -> Vec<u8> {
-    let mut hasher = Keccak256::new();
-    hasher.update(&data);
-
-    hasher.finalize().to_vec()
+fn hash<T: AsRef<[u8]>>(data: T) -> Vec<u8> {
+    leaf_hash(data.as_ref())
 }
 
 // =================================================================================================
@@ -315,8 +497,9 @@ mod tests {
             let proof = prove(&values, &indices);
 
             // Call `verify(&h, &indices, &proof_values, &proof)` and assert that it should be true
+            let proof_keccak: Vec<Vec<u8>> = proof_values.iter().map(hash).collect();
             assert!(
-                verify(&h, &indices, &proof_values, &proof),
+                verify(&h, &indices, &proof_keccak, &proof),
                 "Verification failed for i = {}",
                 1
             );
@@ -333,7 +516,13 @@ mod tests {
             let proof = prove_ext(&values);
 
             assert!(
-                verify_ext(i - 1, &prev_h, &curr_h, values.last().unwrap(), &proof),
+                verify_ext(
+                    i - 1,
+                    &prev_h,
+                    &curr_h,
+                    &hash(values.last().unwrap()),
+                    &proof
+                ),
                 "Verification failed for i = {}",
                 i
             );
@@ -347,136 +536,4 @@ mod tests {
             .map(|_| (0..1).map(|_| rng.random_range(0..=255)).collect())
             .collect()
     }
-
-    /// Verifies an extension proof. Not useful at the moment apart from testing.
-    ///
-    /// # Arguments
-    /// * `i` - Position in the sequence
-    /// * `prev_h` - Previous accumulator value
-    /// * `curr_h` - Current accumulator value
-    /// * `value` - Value being added
-    /// * `proof` - Extension proof components
-    ///
-    /// # Returns
-    /// true if the proof is valid, false otherwise
-    fn verify_ext(
-        i: u32,
-        prev_root: &Vec<u8>,
-        curr_root: &Vec<u8>,
-        added_val: &Vec<u8>,
-        proof: &Vec<Vec<Vec<u8>>>,
-    ) -> bool {
-        verify(curr_root, &vec![i], &vec![added_val.clone()], proof)
-            && verify_previous(prev_root, proof)
-    }
-
-    /// Verifies a Merkle proof for multiple values in a tree. Inspired by
-    /// https://arxiv.org/pdf/2002.07648
-    ///
-    /// # Arguments
-    /// * `root` - Expected Merkle root hash
-    /// * `indices` - Vector of indices for the values being proven
-    /// * `values` - Slice of values being proven
-    /// * `proof` - Vector of proof layers, where each layer contains the sibling hashes needed for
-    ///             verification
-    ///
-    /// # Returns
-    /// `true` if:
-    /// - The number of indices matches the number of values
-    /// - The proof successfully reconstructs the Merkle root
-    /// - All sibling relationships are valid
-    /// `false` otherwise
-    ///
-    fn verify(
-        root: &Vec<u8>,
-        indices: &Vec<u32>,
-        values: &[Vec<u8>],
-        proof: &Vec<Vec<Vec<u8>>>,
-    ) -> bool {
-        if indices.len() != values.len() {
-            return false;
-        }
-
-        let mut proof_copy = proof.clone();
-        let mut current_indices = indices.clone();
-        let mut layer: Vec<Vec<u8>> = values.iter().map(hash).collect();
-
-        let mut paired: Vec<(u32, Vec<u8>)> =
-            current_indices.into_iter().zip(layer.into_iter()).collect();
-        paired.sort_by_key(|pair| pair.0);
-        (current_indices, layer) = paired.into_iter().unzip();
-
-        for proof_layer in &mut proof_copy {
-            let mut b: Vec<(u32, u32)> = vec![];
-
-            for i in &current_indices {
-                let neighbor = get_neighbor_idx(i);
-
-                if neighbor < *i {
-                    b.push((neighbor, i.clone()))
-                } else {
-                    b.push((i.clone(), neighbor))
-                }
-            }
-
-            let mut next_indices: Vec<u32> = vec![];
-            let mut next_layer: Vec<Vec<u8>> = vec![];
-
-            let mut i = 0;
-            while i < b.len() {
-                // use a while loop because we cannot manually increment in for loops
-                if i < b.len() - 1 && b[i].0 == b[i + 1].0 {
-                    // duplicate found
-                    // this means that b[i][0] and b[i][1] are elements of
-                    // nextIndices. Furthermore, b[i] is computed based on
-                    // nextIndices[i] and since we skip the duplicates,
-                    // it can only be that b[i][0] == nextIndices[i]
-                    // => the corresponding values are valuesKeccak[i]
-                    // and valuesKeccak[i+1]
-                    next_layer.push(concat_and_hash(&layer[i], &layer[i + 1]));
-
-                    i += 1;
-                } else if proof_layer.len() > 0 {
-                    let last_layer_val = proof_layer.pop().unwrap();
-
-                    if current_indices[i] % 2 == 1 {
-                        next_layer.push(concat_and_hash(&last_layer_val, &layer[i]));
-                    } else {
-                        next_layer.push(concat_and_hash(&layer[i], &last_layer_val));
-                    }
-                } else {
-                    // proofLayer is empty, move the element that must be combined to the next layer
-                    next_layer.push(layer[i].clone());
-                }
-
-                next_indices.push(current_indices[i] >> 1);
-                i += 1;
-            }
-
-            layer = next_layer.clone();
-            current_indices = next_indices.clone();
-        }
-
-        layer[0].eq(root)
-    }
-
-    // Verifies the previous root of the accumulator. Used only for verify_ext.
-    fn verify_previous(prev_root: &Vec<u8>, proof: &Vec<Vec<Vec<u8>>>) -> bool {
-        let mut proof_copy = proof.clone();
-        let mut first_found = false;
-        let mut computed_root: Vec<u8> = vec![];
-
-        for i in 0..proof_copy.len() {
-            while proof_copy[i].len() > 0 {
-                if !first_found {
-                    computed_root = proof_copy[i].pop().unwrap();
-                    first_found = true;
-                } else {
-                    computed_root = concat_and_hash(&proof_copy[i].pop().unwrap(), &computed_root);
-                }
-            }
-        }
-
-        computed_root.eq(prev_root)
-    }
 }
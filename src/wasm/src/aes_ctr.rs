@@ -1,11 +1,9 @@
 use crate::accumulator::uint8_array_to_vec_u8;
+use crate::encryption::Cipher;
 use crate::utils::die;
-use aes::cipher::{KeyIvInit, StreamCipher};
 use js_sys::Uint8Array;
 use wasm_bindgen::prelude::wasm_bindgen;
 
-type Aes128Ctr128BE = ctr::Ctr128BE<aes::Aes128>;
-
 /*
  * data = [
  *      key (16 bytes),
@@ -24,20 +22,28 @@ type Aes128Ctr128BE = ctr::Ctr128BE<aes::Aes128>;
 ///
 /// # Returns
 /// Encrypted/decrypted bytes
-pub fn encrypt_block(data: &Vec<&Vec<u8>>) -> Vec<u8> {
+pub fn encrypt_block(data: &[&Vec<u8>]) -> Vec<u8> {
+    ctr_block(data, Cipher::Aes128Ctr)
+}
+
+// checks the arguments of the block instructions and applies the keystream
+fn ctr_block(data: &[&Vec<u8>], cipher: Cipher) -> Vec<u8> {
     if data.len() < 3 {
         die("AES encryption/decryption requires a key, blocks and counter starting value")
     }
 
-    if data[0].len() != 16 {
-        die("AES encryption/decryption requires a key of exactly 16 bytes")
+    if data[0].len() != cipher.key_len() {
+        die(&format!(
+            "AES encryption/decryption requires a key of exactly {} bytes",
+            cipher.key_len()
+        ))
     }
 
     if data[1].len() > 64 {
         die("AES encryption/decryption requires blocks of at most 64 bytes");
     }
 
-    if data[1].len() == 0 {
+    if data[1].is_empty() {
         return vec![];
     }
 
@@ -45,11 +51,10 @@ pub fn encrypt_block(data: &Vec<&Vec<u8>>) -> Vec<u8> {
         die("AES encryption/decryption requires a counter starting value of exactly 16 bytes");
     }
 
-    let key = &data[0][..16];
-    let blocks = &data[1][..];
-    let ctr = &data[2][..];
+    let mut res = data[1].to_vec();
+    cipher.apply_keystream(data[0], data[2], &mut res);
 
-    internal_encrypt(key, blocks, ctr)
+    res
 }
 
 /// Decrypts a block using AES-128 in CTR mode (same as encrypt)
@@ -62,10 +67,24 @@ pub fn encrypt_block(data: &Vec<&Vec<u8>>) -> Vec<u8> {
 ///
 /// # Returns  
 /// Decrypted bytes
-pub fn decrypt_block(data: &Vec<&Vec<u8>>) -> Vec<u8> {
+pub fn decrypt_block(data: &[&Vec<u8>]) -> Vec<u8> {
     encrypt_block(data)
 }
 
+/// Decrypts a block using AES-256 in CTR mode (instruction set version 1)
+///
+/// # Arguments
+/// * `data` - Vector containing:
+///   - key (32 bytes)
+///   - blocks to decrypt (<=64 bytes)
+///   - IV/counter starting value (16 bytes)
+///
+/// # Returns
+/// Decrypted bytes
+pub fn decrypt_block_256(data: &[&Vec<u8>]) -> Vec<u8> {
+    ctr_block(data, Cipher::Aes256Ctr)
+}
+
 /// JavaScript wrapper for encrypt_block
 ///
 /// # Arguments
@@ -100,26 +119,21 @@ pub fn decrypt_block_js(data: Vec<Uint8Array>) -> Vec<u8> {
     decrypt_block(&refs)
 }
 
-/// Internal helper for AES-CTR encryption/decryption
+/// JavaScript wrapper for decrypt_block_256
 ///
 /// # Arguments
-/// * `key` - 16 byte key
-/// * `block` - Data to encrypt/decrypt
-/// * `ctr` - 16 byte counter/IV
+/// * `data` - Vector of Uint8Arrays containing:
+///   - key (32 bytes)
+///   - blocks to decrypt (<=64 bytes)
+///   - IV/counter starting value (16 bytes)
 ///
 /// # Returns
-/// Encrypted/decrypted data
-fn internal_encrypt(key: &[u8], block: &[u8], ctr: &[u8]) -> Vec<u8> {
-    let mut res = vec![0u8; block.len()];
-    res.clone_from_slice(block);
-
-    let mut cipher = match Aes128Ctr128BE::new_from_slices(key, ctr) {
-        Ok(c) => c,
-        Err(_) => die("Key should be 16 bytes"),
-    };
-    cipher.apply_keystream(&mut res);
-
-    res
+/// Decrypted bytes
+#[wasm_bindgen]
+pub fn decrypt_block_256_js(data: Vec<Uint8Array>) -> Vec<u8> {
+    let values_vec: Vec<Vec<u8>> = data.iter().map(uint8_array_to_vec_u8).collect();
+    let refs: Vec<&Vec<u8>> = values_vec.iter().collect();
+    decrypt_block_256(&refs)
 }
 
 // =================================================================================================
@@ -144,12 +158,35 @@ mod tests {
             rng.fill_bytes(&mut ctr);
 
             // encrypt
-            let ct = encrypt_block(&vec![&key, &data, &ctr]);
+            let ct = encrypt_block(&[&key, &data, &ctr]);
 
             // decrypt
-            let pt = decrypt_block(&vec![&key, &ct, &ctr]);
+            let pt = decrypt_block(&[&key, &ct, &ctr]);
 
             assert_eq!(pt, data_orig)
         }
     }
+
+    #[test]
+    fn test_aes256_ctr_block() {
+        let mut rng = rand::rng();
+        let mut data = vec![0u8; 64];
+        rng.fill_bytes(&mut data);
+        let mut key = vec![0u8; 32];
+        rng.fill_bytes(&mut key);
+        let mut ctr = vec![0u8; 16];
+        rng.fill_bytes(&mut ctr);
+
+        let pt = decrypt_block_256(&[&key, &data, &ctr]);
+        assert_eq!(
+            pt,
+            crate::encryption::decrypt(&[ctr, data].concat(), &key, Cipher::Aes256Ctr)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "requires a key of exactly 32 bytes")]
+    fn test_aes256_ctr_block_key_length() {
+        decrypt_block_256(&[&vec![0u8; 16], &vec![0u8; 16], &vec![0u8; 16]]);
+    }
 }
@@ -0,0 +1,572 @@
+use crate::accumulator::proof_to_js_array;
+use crate::circuits::CompiledCircuit;
+use crate::dispute::{
+    compute_proof_right_internal, compute_proofs_internal, compute_proofs_left_internal,
+    hpre_internal, hpre_multi_internal, FinalStepProofs,
+};
+use crate::utils::{die, split_ct_blocks};
+use crate::{EvaluatedCircuit, FinalStepComponents};
+use js_sys::Array;
+use wasm_bindgen::prelude::wasm_bindgen;
+use zeroize::Zeroize;
+
+// ============================= SEARCH WINDOW =============================
+
+/// Window of the binary search run by the dispute smart contract (`a`, `b` and `chall`)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchWindow {
+    /// Lower bound of the search
+    pub a: u32,
+
+    /// Upper bound of the search
+    pub b: u32,
+
+    /// The challenged gate
+    pub chall: u32,
+}
+
+/// Final step of the dispute, named after the smart contract function the vendor has to call
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FinalStepKind {
+    /// Step 8a, `submitCommitment`
+    Commitment,
+
+    /// Step 8b, `submitCommitmentLeft`
+    CommitmentLeft,
+
+    /// Step 8c, `submitCommitmentRight`
+    CommitmentRight,
+}
+
+#[wasm_bindgen]
+impl SearchWindow {
+    /// Creates a window from the values read on the smart contract
+    #[wasm_bindgen(constructor)]
+    pub fn new(a: u32, b: u32, chall: u32) -> SearchWindow {
+        SearchWindow { a, b, chall }
+    }
+
+    /// Returns the window of a freshly deployed dispute smart contract
+    pub fn initial(num_blocks: u32, num_gates: u32) -> SearchWindow {
+        SearchWindow {
+            a: num_blocks,
+            b: num_gates,
+            chall: (num_blocks + num_gates) / 2,
+        }
+    }
+
+    /// Returns the window after the vendor gave its opinion, as computed by `giveOpinion`
+    pub fn after_opinion(&self, vendor_agrees: bool) -> SearchWindow {
+        let window = KaryWindow::new(self.a, self.b, 1).after_opinion(vendor_agrees as u32);
+
+        SearchWindow {
+            a: window.a,
+            b: window.b,
+            chall: window.chall(),
+        }
+    }
+
+    /// Returns `true` once the search is over and the vendor has to send the final step
+    pub fn is_converged(&self) -> bool {
+        self.a == self.b
+    }
+
+    /// Returns the final step that is due, or `None` if the search has not converged or ended
+    /// outside of the circuit (in which case `giveOpinion` reverts)
+    pub fn final_step(&self, num_blocks: u32, num_gates: u32) -> Option<FinalStepKind> {
+        if !self.is_converged() {
+            return None;
+        }
+
+        if num_blocks < self.chall && self.chall < num_gates {
+            Some(FinalStepKind::Commitment)
+        } else if self.chall == num_blocks {
+            Some(FinalStepKind::CommitmentLeft)
+        } else if self.chall == num_gates {
+            Some(FinalStepKind::CommitmentRight)
+        } else {
+            None
+        }
+    }
+}
+
+/// Window of a k-ary search, where the buyer answers up to `arity` challenges per round and the
+/// vendor points to the first one it disagrees with. An arity of 1 is the binary search of the
+/// smart contract.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KaryWindow {
+    /// Lower bound of the search
+    pub a: u32,
+
+    /// Upper bound of the search
+    pub b: u32,
+
+    /// Maximum number of challenges per round
+    pub arity: u32,
+}
+
+#[wasm_bindgen]
+impl KaryWindow {
+    /// Creates a k-ary search window
+    #[wasm_bindgen(constructor)]
+    pub fn new(a: u32, b: u32, arity: u32) -> KaryWindow {
+        if arity == 0 {
+            die("The arity of the search must be at least 1");
+        }
+        KaryWindow { a, b, arity }
+    }
+
+    /// Returns the window at the start of the dispute
+    pub fn initial(num_blocks: u32, num_gates: u32, arity: u32) -> KaryWindow {
+        KaryWindow::new(num_blocks, num_gates, arity)
+    }
+
+    /// Returns the challenges of the current round in increasing order. They split the window
+    /// into intervals of (almost) equal size. Empty once the search has converged.
+    pub fn challenges(&self) -> Vec<u32> {
+        if self.is_converged() {
+            return vec![];
+        }
+
+        let len = (self.b - self.a) as u64;
+        if len <= self.arity as u64 {
+            return (self.a..self.b).collect();
+        }
+        (1..=self.arity as u64)
+            .map(|i| self.a + (i * len / (self.arity as u64 + 1)) as u32)
+            .collect()
+    }
+
+    /// Returns the window after the vendor pointed to the first challenge it disagrees with
+    ///
+    /// # Arguments
+    /// * `disagreement` - Index in `challenges()` of the first response the vendor disagrees
+    ///   with, or the number of challenges if it agrees with all of them
+    pub fn after_opinion(&self, disagreement: u32) -> KaryWindow {
+        let challenges = self.challenges();
+        let j = disagreement as usize;
+        if j > challenges.len() {
+            die("The disagreement index is out of the challenges' range");
+        }
+
+        let a = if j == 0 {
+            self.a
+        } else {
+            challenges[j - 1] + 1
+        };
+        let b = if j == challenges.len() {
+            self.b
+        } else {
+            challenges[j]
+        };
+
+        KaryWindow { a, b, ..*self }
+    }
+
+    /// Returns `true` once the search is over and the vendor has to send the final step
+    pub fn is_converged(&self) -> bool {
+        self.a == self.b
+    }
+
+    /// Returns the challenged gate once the search has converged, or the first challenge of the
+    /// round otherwise
+    pub fn chall(&self) -> u32 {
+        self.challenges().first().copied().unwrap_or(self.a)
+    }
+
+    /// Returns the final step that is due, or `None` if the search has not converged
+    pub fn final_step(&self, num_blocks: u32, num_gates: u32) -> Option<FinalStepKind> {
+        self.converged_window()?.final_step(num_blocks, num_gates)
+    }
+
+    /// Returns the window of the smart contract at the final step, in which the proofs are
+    /// the same as for the binary search since the buyer answered for the gate preceding the
+    /// challenged one. `None` if the search has not converged.
+    pub fn converged_window(&self) -> Option<SearchWindow> {
+        if !self.is_converged() {
+            return None;
+        }
+        Some(SearchWindow::new(self.a, self.b, self.a))
+    }
+}
+
+// ============================= AGENTS =============================
+
+/// Inputs of the final step, ready to be sent to the smart contract
+pub enum FinalStepInputs {
+    /// Arguments of `submitCommitment` or `submitCommitmentLeft` (besides the opening value and
+    /// the instruction set version)
+    Commitment {
+        kind: FinalStepKind,
+        gate_num: u32,
+        proofs: FinalStepProofs,
+    },
+
+    /// Argument of `submitCommitmentRight`
+    CommitmentRight { proof: Vec<Vec<Vec<u8>>> },
+}
+
+/// Answers the challenges of the smart contract on behalf of the buyer
+#[wasm_bindgen]
+pub struct BuyerAgent {
+    values: Vec<Vec<u8>>,
+    num_blocks: u32,
+}
+
+#[wasm_bindgen]
+impl BuyerAgent {
+    /// Creates the agent from the buyer's evaluated circuit
+    ///
+    /// # Arguments
+    /// * `evaluated_circuit_bytes` - Serialized evaluated circuit
+    /// * `num_blocks` - Number of blocks of the ciphertext
+    #[wasm_bindgen(constructor)]
+    pub fn new(evaluated_circuit_bytes: &[u8], num_blocks: u32) -> BuyerAgent {
+        BuyerAgent::from_values(
+            std::mem::take(&mut EvaluatedCircuit::from_bytes(evaluated_circuit_bytes).values),
+            num_blocks,
+        )
+    }
+
+    /// Returns the response to send with `respondChallenge`, or `None` if the search has
+    /// converged and no response is expected
+    pub fn respond(&self, window: &SearchWindow) -> Option<Vec<u8>> {
+        if window.is_converged() {
+            return None;
+        }
+        if window.chall < self.num_blocks || window.chall as usize >= self.values.len() {
+            die("The challenge is outside of the circuit");
+        }
+
+        Some(hpre_internal(
+            &self.values,
+            self.num_blocks as usize,
+            window.chall as usize,
+        ))
+    }
+
+    /// Returns the final step that is due, or `None` if the search has not converged
+    pub fn final_step_kind(&self, window: &SearchWindow) -> Option<FinalStepKind> {
+        window.final_step(self.num_blocks, self.values.len() as u32)
+    }
+}
+
+/// Non-WASM methods for the buyer agent
+impl BuyerAgent {
+    /// Creates the agent from the values of the evaluated circuit
+    pub fn from_values(values: Vec<Vec<u8>>, num_blocks: u32) -> BuyerAgent {
+        BuyerAgent { values, num_blocks }
+    }
+
+    /// Returns the responses to all the challenges of a k-ary search round, or `None` if the
+    /// search has converged
+    pub fn respond_multi(&self, window: &KaryWindow) -> Option<Vec<Vec<u8>>> {
+        if window.is_converged() {
+            return None;
+        }
+        if window.a < self.num_blocks || window.b as usize > self.values.len() {
+            die("The challenges are outside of the circuit");
+        }
+
+        Some(hpre_multi_internal(
+            &self.values,
+            self.num_blocks as usize,
+            &window.challenges(),
+        ))
+    }
+}
+
+/// Gives the vendor's opinion on the buyer's responses and prepares the final step
+#[wasm_bindgen]
+pub struct VendorAgent {
+    circuit: CompiledCircuit,
+    ct_blocks: Vec<Vec<u8>>,
+    values: Vec<Vec<u8>>,
+    constants: Vec<Vec<u8>>,
+}
+
+// the constants include the key
+impl Drop for VendorAgent {
+    fn drop(&mut self) {
+        self.constants.zeroize();
+    }
+}
+
+#[wasm_bindgen]
+impl VendorAgent {
+    /// Creates the agent from the vendor's data
+    ///
+    /// # Arguments
+    /// * `circuit_bytes` - Serialized circuit
+    /// * `evaluated_circuit_bytes` - Serialized evaluated circuit
+    /// * `ct` - The ciphertext
+    #[wasm_bindgen(constructor)]
+    pub fn new(circuit_bytes: &[u8], evaluated_circuit_bytes: &[u8], ct: &[u8]) -> VendorAgent {
+        let circuit = CompiledCircuit::from_bytes(circuit_bytes);
+        let mut evaluated_circuit = EvaluatedCircuit::from_bytes(evaluated_circuit_bytes);
+        let ct_blocks = split_ct_blocks(ct, circuit.block_size as usize);
+
+        VendorAgent::from_values(
+            circuit,
+            ct_blocks,
+            std::mem::take(&mut evaluated_circuit.values),
+            std::mem::take(&mut evaluated_circuit.constants),
+        )
+    }
+
+    /// Returns whether the vendor agrees with the buyer's response to the current challenge
+    pub fn opinion(&self, window: &SearchWindow, buyer_response: &[u8]) -> bool {
+        if window.chall < self.num_blocks() || window.chall >= self.num_gates() {
+            die("The challenge is outside of the circuit");
+        }
+
+        let computed = hpre_internal(
+            &self.values,
+            self.num_blocks() as usize,
+            window.chall as usize,
+        );
+        computed == buyer_response
+    }
+
+    /// Returns the final step that is due, or `None` if the search has not converged
+    pub fn final_step_kind(&self, window: &SearchWindow) -> Option<FinalStepKind> {
+        window.final_step(self.num_blocks(), self.num_gates())
+    }
+
+    /// Computes the components for `submitCommitment` or `submitCommitmentLeft`
+    pub fn final_step_components(&self, window: &SearchWindow) -> FinalStepComponents {
+        match self.final_step(window) {
+            Some(FinalStepInputs::Commitment { proofs, .. }) => proofs.into(),
+            _ => die("The final step is not submitCommitment or submitCommitmentLeft"),
+        }
+    }
+
+    /// Computes the proof for `submitCommitmentRight`
+    pub fn final_step_proof_right(&self, window: &SearchWindow) -> Array {
+        match self.final_step(window) {
+            Some(FinalStepInputs::CommitmentRight { proof }) => proof_to_js_array(proof),
+            _ => die("The final step is not submitCommitmentRight"),
+        }
+    }
+}
+
+/// Non-WASM methods for the vendor agent
+impl VendorAgent {
+    /// Creates the agent from the circuit, the ciphertext's blocks and the evaluated circuit
+    pub fn from_values(
+        circuit: CompiledCircuit,
+        ct_blocks: Vec<Vec<u8>>,
+        values: Vec<Vec<u8>>,
+        constants: Vec<Vec<u8>>,
+    ) -> VendorAgent {
+        VendorAgent {
+            circuit,
+            ct_blocks,
+            values,
+            constants,
+        }
+    }
+
+    /// Returns the index of the first response of a k-ary search round the vendor disagrees with,
+    /// or the number of challenges if it agrees with all of them
+    ///
+    /// # Arguments
+    /// * `window` - The current window
+    /// * `buyer_responses` - The buyer's responses, in the order of `window.challenges()`
+    pub fn opinion_multi(&self, window: &KaryWindow, buyer_responses: &[Vec<u8>]) -> u32 {
+        let challenges = window.challenges();
+        if challenges.len() != buyer_responses.len() {
+            die("The number of responses does not match the number of challenges");
+        }
+        if window.a < self.num_blocks() || window.b > self.num_gates() {
+            die("The challenges are outside of the circuit");
+        }
+
+        let computed = hpre_multi_internal(&self.values, self.num_blocks() as usize, &challenges);
+        computed
+            .iter()
+            .zip(buyer_responses)
+            .position(|(c, r)| c != r)
+            .unwrap_or(challenges.len()) as u32
+    }
+
+    /// Returns the inputs of the final step that is due, or `None` if the search has not
+    /// converged
+    pub fn final_step(&self, window: &SearchWindow) -> Option<FinalStepInputs> {
+        let gate_num = window.chall;
+        let kind = self.final_step_kind(window)?;
+
+        Some(match kind {
+            FinalStepKind::Commitment => FinalStepInputs::Commitment {
+                kind,
+                gate_num,
+                proofs: compute_proofs_internal(
+                    &self.circuit,
+                    &self.values,
+                    &self.constants,
+                    &self.ct_blocks,
+                    gate_num,
+                ),
+            },
+            FinalStepKind::CommitmentLeft => FinalStepInputs::Commitment {
+                kind,
+                gate_num,
+                proofs: compute_proofs_left_internal(
+                    &self.circuit,
+                    &self.values,
+                    &self.constants,
+                    &self.ct_blocks,
+                    gate_num,
+                ),
+            },
+            FinalStepKind::CommitmentRight => FinalStepInputs::CommitmentRight {
+                proof: compute_proof_right_internal(
+                    &self.values,
+                    self.num_blocks(),
+                    self.num_gates(),
+                ),
+            },
+        })
+    }
+
+    fn num_blocks(&self) -> u32 {
+        self.ct_blocks.len() as u32
+    }
+
+    fn num_gates(&self) -> u32 {
+        self.circuit.circuit.len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::evaluate_circuit_internal;
+    use crate::compute_precontract_values;
+    use rand::RngCore;
+
+    #[test]
+    fn test_search_window_convergence() {
+        for m in 1..8 {
+            for n in (m + 1)..40 {
+                // the vendor agrees with every response before gate k
+                for k in m..=n {
+                    let mut window = SearchWindow::initial(m, n);
+                    let mut rounds = 0;
+                    while !window.is_converged() {
+                        assert_eq!(None, window.final_step(m, n));
+                        window = window.after_opinion(window.chall < k);
+                        rounds += 1;
+                    }
+
+                    assert_eq!(k, window.chall);
+                    assert!(rounds <= u32::BITS - (n - m).leading_zeros());
+                    let expected = if k == m {
+                        FinalStepKind::CommitmentLeft
+                    } else if k == n {
+                        FinalStepKind::CommitmentRight
+                    } else {
+                        FinalStepKind::Commitment
+                    };
+                    assert_eq!(Some(expected), window.final_step(m, n));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_kary_window() {
+        for arity in 1..6 {
+            for m in 1..4 {
+                for n in (m + 1)..50 {
+                    for k in m..=n {
+                        let mut window = KaryWindow::initial(m, n, arity);
+                        let mut binary = SearchWindow::initial(m, n);
+                        while !window.is_converged() {
+                            let challenges = window.challenges();
+                            assert!(!challenges.is_empty() && challenges.len() <= arity as usize);
+                            assert!(challenges.windows(2).all(|c| c[0] < c[1]));
+                            assert!(
+                                window.a <= challenges[0] && *challenges.last().unwrap() < window.b
+                            );
+                            if arity == 1 {
+                                assert_eq!(binary.chall, challenges[0]);
+                                binary = binary.after_opinion(challenges[0] < k);
+                            }
+
+                            let disagreement = challenges
+                                .iter()
+                                .position(|&c| c >= k)
+                                .unwrap_or(challenges.len());
+                            window = window.after_opinion(disagreement as u32);
+                        }
+
+                        assert_eq!(k, window.chall());
+                        assert_eq!(
+                            window.final_step(m, n),
+                            SearchWindow::new(k, k, k).final_step(m, n)
+                        );
+                        if arity == 1 {
+                            assert_eq!(Some(binary), window.converged_window());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_agents() {
+        let mut rng = rand::rng();
+        let mut data = vec![0u8; 500];
+        rng.fill_bytes(&mut data);
+        let mut key = vec![0u8; 16];
+        rng.fill_bytes(&mut key);
+
+        let precontract = compute_precontract_values(&mut data, &key);
+        let circuit = CompiledCircuit::from_bytes(&precontract.circuit_bytes);
+        let bound = circuit.bind_missing_constants(vec![key]);
+        let ct_blocks = split_ct_blocks(&precontract.ct, circuit.block_size as usize);
+        let values = evaluate_circuit_internal(&ct_blocks, &bound);
+        let constants = bound.into_constants();
+        let (m, n) = (ct_blocks.len() as u32, values.len() as u32);
+
+        // the buyer's trace differs from the vendor's one from gate k
+        let k = (m + n) / 3;
+        let mut buyer_values = values.clone();
+        for v in &mut buyer_values[k as usize..] {
+            v[0] ^= 1;
+        }
+        let buyer = BuyerAgent::from_values(buyer_values, m);
+        let vendor = VendorAgent::from_values(circuit, ct_blocks, values, constants);
+
+        let mut window = SearchWindow::initial(m, n);
+        while let Some(response) = buyer.respond(&window) {
+            window = window.after_opinion(vendor.opinion(&window, &response));
+        }
+
+        assert_eq!(k, window.chall);
+        assert_eq!(
+            Some(FinalStepKind::Commitment),
+            buyer.final_step_kind(&window)
+        );
+        match vendor.final_step(&window) {
+            Some(FinalStepInputs::Commitment {
+                kind,
+                gate_num,
+                proofs,
+            }) => {
+                assert_eq!(FinalStepKind::Commitment, kind);
+                assert_eq!(k, gate_num);
+                assert_eq!(
+                    vendor.circuit.circuit[k as usize].flatten(),
+                    proofs.gate.flatten()
+                );
+            }
+            _ => panic!("Expected the inputs of submitCommitment"),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use crate::accumulator::{acc_circuit, acc_leaf_hashes, leaf_hash};
+use crate::circuits::compile_basic_circuit_with_cipher;
+use crate::commitment::{commit_hashes, commit_key_with_rng, KeyCommitment};
+use crate::encryption::Cipher;
+use crate::gcm::GcmTag;
+use crate::kdf::{check_context_description, derive_file_key, derive_precontract_seed};
+use crate::random::{seeded_rng, RandomSource};
+use crate::utils::die;
+use crate::Precontract;
+use sha2::{Digest, Sha256};
+use wasm_bindgen::prelude::wasm_bindgen;
+use zeroize::Zeroizing;
+
+// size of the blocks of the basic circuits
+const BLOCK_SIZE: usize = 64;
+
+/// Computes the values of a precontract from a file received by chunks, e.g. read from a
+/// `ReadableStream`. Each chunk is hashed for the description, encrypted and appended to the
+/// ciphertext, whose blocks are hashed for `h_ct` as soon as they are complete, so the file is
+/// read once and never held in memory. `finish` gives the same `Precontract` as
+/// `compute_precontract_values_with_rng` for the same key and randomness.
+#[wasm_bindgen]
+pub struct PrecontractBuilder {
+    key: Zeroizing<Vec<u8>>,
+    cipher: Cipher,
+    rng: Box<RandomSource>,
+    hasher: Sha256,
+    tag: Option<GcmTag>,
+    key_commitment: KeyCommitment,
+    key_check: bool,
+    // context the key was derived for, checked against the description
+    context: Option<Vec<u8>>,
+    // IV followed by the encrypted chunks
+    ct: Vec<u8>,
+    // hashes of the complete blocks of the ciphertext, starting with the IV
+    leaf_hashes: Vec<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl PrecontractBuilder {
+    /// Starts a precontract with a random IV and commitment
+    ///
+    /// # Arguments
+    /// * `key` - The encryption key
+    /// * `cipher` - The cipher (selected by the key's length if not given)
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &[u8], cipher: Option<Cipher>) -> PrecontractBuilder {
+        let cipher = cipher.unwrap_or_else(|| Cipher::for_key(key));
+        PrecontractBuilder::with_rng(key, cipher, Box::new(rand::rng()))
+    }
+
+    /// Starts a precontract whose IV and commitment are drawn from a generator seeded with
+    /// `seed`, as done by `compute_precontract_values_seeded`
+    ///
+    /// # Arguments
+    /// * `key` - The encryption key
+    /// * `seed` - Seed of the generator (32 bytes)
+    pub fn seeded(key: &[u8], seed: &[u8]) -> PrecontractBuilder {
+        PrecontractBuilder::with_rng(key, Cipher::for_key(key), Box::new(seeded_rng(seed)))
+    }
+
+    /// Starts a precontract whose key, IV and commitment are derived from the vendor's master
+    /// secret, as done by `compute_precontract_values_from_master`. `finish` fails if the context
+    /// was not encoded for the file's description.
+    ///
+    /// # Arguments
+    /// * `master_secret` - The vendor's master secret (at least 16 bytes)
+    /// * `context` - Context of the precontract, see `file_key_context`
+    /// * `cipher` - The cipher (AES-128 if not given)
+    pub fn from_master(
+        master_secret: &[u8],
+        context: &[u8],
+        cipher: Option<Cipher>,
+    ) -> PrecontractBuilder {
+        let key = Zeroizing::new(derive_file_key(master_secret, context, cipher));
+        let seed = derive_precontract_seed(master_secret, context);
+        let mut builder = PrecontractBuilder::with_rng(
+            &key,
+            cipher.unwrap_or(Cipher::Aes128Ctr),
+            Box::new(seeded_rng(&seed)),
+        );
+        builder.context = Some(context.to_vec());
+        builder
+    }
+
+    /// Adds the next chunk of the file
+    ///
+    /// # Arguments
+    /// * `chunk` - Next bytes of the file
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.hasher.update(chunk);
+
+        let offset = self.ct.len() - 16;
+        self.ct.extend_from_slice(chunk);
+        let (iv, encrypted) = self.ct.split_at_mut(16);
+        let encrypted = &mut encrypted[offset..];
+        self.cipher
+            .apply_keystream_at(&self.key, iv, offset as u64, encrypted);
+        if let Some(tag) = self.tag.as_mut() {
+            tag.update(encrypted);
+        }
+
+        // hashes the blocks completed by the chunk
+        while self.ct.len() >= 16 + self.leaf_hashes.len() * BLOCK_SIZE {
+            let start = 16 + (self.leaf_hashes.len() - 1) * BLOCK_SIZE;
+            self.leaf_hashes
+                .push(leaf_hash(&self.ct[start..start + BLOCK_SIZE]));
+        }
+    }
+
+    /// Makes the circuit check the key against the key commitment, as done by
+    /// `compute_precontract_values_with_key_check`
+    pub fn include_key_check(&mut self) {
+        self.key_check = true;
+    }
+
+    /// Number of bytes of the file added so far
+    pub fn file_len(&self) -> usize {
+        self.ct.len() - 16
+    }
+
+    /// Compiles the circuit and commits to it and to the ciphertext
+    ///
+    /// # Returns
+    /// A `Precontract` containing all necessary components for the optimistic phase of the
+    /// protocol
+    pub fn finish(self) -> Precontract {
+        let PrecontractBuilder {
+            cipher,
+            mut rng,
+            hasher,
+            tag,
+            key_commitment,
+            key_check,
+            context,
+            mut ct,
+            mut leaf_hashes,
+            ..
+        } = self;
+
+        let description = hasher.finalize().to_vec();
+        if let Some(context) = context {
+            check_context_description(&context, &description);
+        }
+        if let Some(tag) = tag {
+            ct.extend(tag.finish());
+        }
+        // hashes the last blocks, which may be incomplete
+        let mut start = 16 + (leaf_hashes.len() - 1) * BLOCK_SIZE;
+        while start < ct.len() {
+            let end = (start + BLOCK_SIZE).min(ct.len());
+            leaf_hashes.push(leaf_hash(&ct[start..end]));
+            start = end;
+        }
+
+        let mut circuit = compile_basic_circuit_with_cipher(ct.len() as u32, &description, cipher);
+        if key_check {
+            circuit = circuit.with_key_check(&key_commitment);
+        }
+        let num_blocks = circuit.num_blocks;
+        let num_gates = circuit.circuit.len() as u32;
+        let circuit_bytes = circuit.to_bytes();
+        let h_ct = acc_leaf_hashes(leaf_hashes);
+        let h_circuit = acc_circuit(circuit);
+        let commitment = commit_hashes(&h_circuit, &h_ct, &mut *rng);
+
+        Precontract {
+            ct,
+            circuit_bytes,
+            description,
+            h_ct,
+            h_circuit,
+            commitment,
+            num_blocks,
+            num_gates,
+            cipher,
+            key_commitment: Some(key_commitment),
+            escrow: None,
+        }
+    }
+}
+
+impl PrecontractBuilder {
+    /// Starts a precontract drawing the IV and the commitment's random bytes from `rng`
+    ///
+    /// # Arguments
+    /// * `key` - The encryption key
+    /// * `cipher` - The cipher
+    /// * `rng` - Source of the IV, of the key commitment's salt and of the commitment's random
+    ///   bytes
+    pub fn with_rng(key: &[u8], cipher: Cipher, mut rng: Box<RandomSource>) -> PrecontractBuilder {
+        if key.len() != cipher.key_len() {
+            die(&format!(
+                "{:?} requires a key of {} bytes",
+                cipher,
+                cipher.key_len()
+            ));
+        }
+        let ct = cipher.generate_iv(&mut *rng);
+        let key_commitment = commit_key_with_rng(key, &mut *rng);
+        let tag = (cipher == Cipher::Aes128Gcm).then(|| GcmTag::new(key, &ct));
+
+        PrecontractBuilder {
+            key: Zeroizing::new(key.to_vec()),
+            cipher,
+            rng,
+            hasher: Sha256::new(),
+            tag,
+            key_commitment,
+            key_check: false,
+            context: None,
+            leaf_hashes: vec![leaf_hash(&ct)],
+            ct,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_precontract_values_from_master, compute_precontract_values_with_rng};
+    use rand::RngCore;
+
+    fn assert_same(expected: &Precontract, actual: &Precontract) {
+        assert_eq!(expected.ct, actual.ct);
+        assert_eq!(expected.circuit_bytes, actual.circuit_bytes);
+        assert_eq!(expected.description, actual.description);
+        assert_eq!(expected.h_ct, actual.h_ct);
+        assert_eq!(expected.h_circuit, actual.h_circuit);
+        assert_eq!(expected.commitment.c, actual.commitment.c);
+        assert_eq!(expected.commitment.o, actual.commitment.o);
+        assert_eq!(expected.num_blocks, actual.num_blocks);
+        assert_eq!(expected.num_gates, actual.num_gates);
+        assert_eq!(expected.cipher, actual.cipher);
+        assert_eq!(expected.key_commitment, actual.key_commitment);
+        assert_eq!(expected.escrow, actual.escrow);
+    }
+
+    #[test]
+    fn test_same_as_one_pass() {
+        let mut rng = rand::rng();
+        let seed = [3u8; 32];
+        for cipher in [
+            Cipher::Aes128Ctr,
+            Cipher::Aes256Ctr,
+            Cipher::ChaCha20,
+            Cipher::Aes128Gcm,
+        ] {
+            for size in [1, 48, 63, 64, 65, 1000, 4096] {
+                let mut file = vec![0u8; size];
+                rng.fill_bytes(&mut file);
+                let mut key = vec![0u8; cipher.key_len()];
+                rng.fill_bytes(&mut key);
+
+                let expected = compute_precontract_values_with_rng(
+                    &mut file.clone(),
+                    &key,
+                    cipher,
+                    size % 2 == 0,
+                    &mut seeded_rng(&seed),
+                );
+
+                // chunks of various sizes, including empty ones
+                let mut builder =
+                    PrecontractBuilder::with_rng(&key, cipher, Box::new(seeded_rng(&seed)));
+                if size % 2 == 0 {
+                    builder.include_key_check();
+                }
+                let mut chunks = file.as_slice();
+                for len in [0, 1, 7, 64, 100, 0, 13].iter().cycle() {
+                    if chunks.is_empty() {
+                        break;
+                    }
+                    let (chunk, rest) = chunks.split_at((*len).min(chunks.len()));
+                    builder.update(chunk);
+                    chunks = rest;
+                }
+                assert_eq!(size, builder.file_len());
+                assert_same(&expected, &builder.finish());
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_master() {
+        let master = b"vendor master secret";
+        let file = vec![5u8; 500];
+        let context = crate::kdf::file_key_context(&crate::sha256::sha256(&file), &[2u8; 20], &[0]);
+        let expected = compute_precontract_values_from_master(
+            &mut file.clone(),
+            master,
+            &context,
+            Some(Cipher::Aes128Gcm),
+        );
+
+        let mut builder =
+            PrecontractBuilder::from_master(master, &context, Some(Cipher::Aes128Gcm));
+        for chunk in file.chunks(100) {
+            builder.update(chunk);
+        }
+        assert_same(&expected, &builder.finish());
+    }
+
+    #[test]
+    #[should_panic(expected = "The context does not match the file's description")]
+    fn test_from_master_other_file() {
+        let context = crate::kdf::file_key_context(&[1u8; 32], &[2u8; 20], &[0]);
+        let mut builder = PrecontractBuilder::from_master(b"vendor master secret", &context, None);
+        builder.update(&[5u8; 500]);
+        builder.finish();
+    }
+
+    #[test]
+    #[should_panic(expected = "Aes256Ctr requires a key of 32 bytes")]
+    fn test_key_length() {
+        PrecontractBuilder::with_rng(&[0u8; 16], Cipher::Aes256Ctr, Box::new(rand::rng()));
+    }
+}
@@ -0,0 +1,580 @@
+use crate::accumulator::{acc_circuit, acc_leaf_hashes, leaf_hash};
+use crate::circuits::{compile_basic_circuit_with_cipher, CompiledCircuit};
+use crate::commitment::{open_commitment_internal, Commitment, KeyCommitment};
+use crate::encryption::Cipher;
+use crate::escrow::KeyEscrow;
+use crate::utils::die;
+use crate::Precontract;
+use std::io::{Read, Write};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/*
+ * Layout of a precontract bundle (all integers are big endian):
+ *
+ *      magic ("SOXP", 4 bytes)
+ *      format version (u16)
+ *      accumulator algorithm identifier (u8)
+ *      hash algorithm identifier (u8)
+ *      sections, each one being
+ *          tag (u8)
+ *          payload length (u64)
+ *          payload
+ *      end section (tag 0, length 0)
+ *
+ * The sections are, in this order: metadata, ciphertext and circuit. The metadata comes first so
+ * that the ciphertext can be checked while it is being read.
+ *
+ * Version 2 adds the key commitment and its salt at the end of the metadata (empty fields if the
+ * precontract has no key commitment). Version 3 adds the key escrow after them (empty field if the
+ * key is not escrowed, see `KeyEscrow::to_bytes` otherwise). Older bundles are still read.
+ */
+
+/// Magic bytes at the start of every precontract bundle
+pub const BUNDLE_MAGIC: [u8; 4] = *b"SOXP";
+
+/// Current version of the bundle format
+pub const BUNDLE_FORMAT_VERSION: u16 = 3;
+
+/// Identifier of the accumulator used for `h_ct` and `h_circuit` (Keccak256 Merkle tree)
+pub const ACC_KECCAK256_MERKLE: u8 = 1;
+
+/// Identifier of the hash used for the description (SHA-256)
+pub const HASH_SHA256: u8 = 1;
+
+const SECTION_END: u8 = 0;
+const SECTION_METADATA: u8 = 1;
+const SECTION_CIPHERTEXT: u8 = 2;
+const SECTION_CIRCUIT: u8 = 3;
+
+// size of the IV at the beginning of the ciphertext
+const IV_SIZE: usize = 16;
+
+/// Metadata section of a bundle
+struct BundleMetadata {
+    num_blocks: u32,
+    num_gates: u32,
+    block_size: u32,
+    ct_size: u64,
+    circuit_size: u64,
+    description: Vec<u8>,
+    h_ct: Vec<u8>,
+    h_circuit: Vec<u8>,
+    commitment: Vec<u8>,
+    opening_value: Vec<u8>,
+    key_commitment: Vec<u8>,
+    key_salt: Vec<u8>,
+    escrow: Vec<u8>,
+}
+
+impl BundleMetadata {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.num_blocks.to_be_bytes());
+        buf.extend(self.num_gates.to_be_bytes());
+        buf.extend(self.block_size.to_be_bytes());
+        buf.extend(self.ct_size.to_be_bytes());
+        buf.extend(self.circuit_size.to_be_bytes());
+        for field in [
+            &self.description,
+            &self.h_ct,
+            &self.h_circuit,
+            &self.commitment,
+            &self.opening_value,
+            &self.key_commitment,
+            &self.key_salt,
+            &self.escrow,
+        ] {
+            buf.extend((field.len() as u32).to_be_bytes());
+            buf.extend(field);
+        }
+
+        buf
+    }
+
+    fn from_bytes(mut bytes: &[u8], format_version: u16) -> Result<BundleMetadata, String> {
+        let reader = &mut bytes;
+        let mut metadata = BundleMetadata {
+            num_blocks: read_u32(reader)?,
+            num_gates: read_u32(reader)?,
+            block_size: read_u32(reader)?,
+            ct_size: read_u64(reader)?,
+            circuit_size: read_u64(reader)?,
+            description: read_field(reader)?,
+            h_ct: read_field(reader)?,
+            h_circuit: read_field(reader)?,
+            commitment: read_field(reader)?,
+            opening_value: read_field(reader)?,
+            key_commitment: Vec::new(),
+            key_salt: Vec::new(),
+            escrow: Vec::new(),
+        };
+        if format_version >= 2 {
+            metadata.key_commitment = read_field(reader)?;
+            metadata.key_salt = read_field(reader)?;
+        }
+        if format_version >= 3 {
+            metadata.escrow = read_field(reader)?;
+        }
+
+        if !reader.is_empty() {
+            return Err("Unexpected trailing bytes in the metadata section".to_string());
+        }
+
+        Ok(metadata)
+    }
+
+    fn key_commitment(&self) -> Result<Option<KeyCommitment>, String> {
+        match (self.key_commitment.len(), self.key_salt.len()) {
+            (0, 0) => Ok(None),
+            (32, 16) => Ok(Some(KeyCommitment::new(
+                self.key_commitment.clone(),
+                self.key_salt.clone(),
+            ))),
+            _ => Err("Malformed key commitment".to_string()),
+        }
+    }
+
+    fn escrow(&self) -> Result<Option<KeyEscrow>, String> {
+        if self.escrow.is_empty() {
+            return Ok(None);
+        }
+
+        KeyEscrow::try_from_bytes(&self.escrow).map(Some)
+    }
+}
+
+/// Writes a precontract as a bundle. The ciphertext is written directly from the precontract
+/// without being copied.
+///
+/// # Arguments
+/// * `writer` - Destination of the bundle
+/// * `precontract` - The precontract to write
+///
+/// # Returns
+/// `Ok(())` if the bundle was written, an error message otherwise
+pub fn write_bundle<W: Write>(writer: &mut W, precontract: &Precontract) -> Result<(), String> {
+    let circuit = CompiledCircuit::try_from_bytes(&precontract.circuit_bytes)?;
+    let metadata = BundleMetadata {
+        num_blocks: precontract.num_blocks,
+        num_gates: precontract.num_gates,
+        block_size: circuit.block_size,
+        ct_size: precontract.ct.len() as u64,
+        circuit_size: precontract.circuit_bytes.len() as u64,
+        description: precontract.description.clone(),
+        h_ct: precontract.h_ct.clone(),
+        h_circuit: precontract.h_circuit.clone(),
+        commitment: precontract.commitment.c.clone(),
+        opening_value: precontract.commitment.o.clone(),
+        key_commitment: precontract
+            .key_commitment
+            .as_ref()
+            .map_or(Vec::new(), |kc| kc.commitment.clone()),
+        key_salt: precontract
+            .key_commitment
+            .as_ref()
+            .map_or(Vec::new(), |kc| kc.salt.clone()),
+        escrow: precontract
+            .escrow
+            .as_ref()
+            .map_or(Vec::new(), |escrow| escrow.to_bytes()),
+    };
+
+    let mut header = Vec::with_capacity(8);
+    header.extend(BUNDLE_MAGIC);
+    header.extend(BUNDLE_FORMAT_VERSION.to_be_bytes());
+    header.push(ACC_KECCAK256_MERKLE);
+    header.push(HASH_SHA256);
+    write_all(writer, &header)?;
+
+    write_section(writer, SECTION_METADATA, &metadata.to_bytes())?;
+    write_section(writer, SECTION_CIPHERTEXT, &precontract.ct)?;
+    write_section(writer, SECTION_CIRCUIT, &precontract.circuit_bytes)?;
+    write_section(writer, SECTION_END, &[])
+}
+
+/// Reads a bundle and checks its integrity. The accumulator values of the ciphertext and circuit
+/// are recomputed and compared to the ones in the metadata, and the commitment is opened. The
+/// ciphertext's leaves are hashed while it is being read.
+///
+/// # Arguments
+/// * `reader` - Source of the bundle
+///
+/// # Returns
+/// The `Precontract` contained in the bundle, or an error message if the bundle is malformed or
+/// fails the integrity checks
+pub fn read_bundle<R: Read>(reader: &mut R) -> Result<Precontract, String> {
+    let mut magic = [0u8; 4];
+    read_exact(reader, &mut magic)?;
+    if magic != BUNDLE_MAGIC {
+        return Err("Not a precontract bundle".to_string());
+    }
+
+    let format_version = read_u16(reader)?;
+    if format_version == 0 || format_version > BUNDLE_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported bundle format version {}",
+            format_version
+        ));
+    }
+
+    let mut algorithms = [0u8; 2];
+    read_exact(reader, &mut algorithms)?;
+    if algorithms[0] != ACC_KECCAK256_MERKLE {
+        return Err(format!("Unsupported accumulator {}", algorithms[0]));
+    }
+    if algorithms[1] != HASH_SHA256 {
+        return Err(format!("Unsupported hash algorithm {}", algorithms[1]));
+    }
+
+    let metadata_len = expect_section(reader, SECTION_METADATA)?;
+    let metadata =
+        BundleMetadata::from_bytes(&read_payload(reader, metadata_len)?, format_version)?;
+    let key_commitment = metadata.key_commitment()?;
+    let escrow = metadata.escrow()?;
+    if escrow.is_some() && key_commitment.is_none() {
+        return Err("A key escrow requires a key commitment".to_string());
+    }
+
+    let ct_len = expect_section(reader, SECTION_CIPHERTEXT)?;
+    if ct_len != metadata.ct_size {
+        return Err("The ciphertext's size does not match the metadata".to_string());
+    }
+    let (ct, ct_leaves) = read_ciphertext(reader, ct_len, metadata.block_size as usize)?;
+
+    let circuit_len = expect_section(reader, SECTION_CIRCUIT)?;
+    if circuit_len != metadata.circuit_size {
+        return Err("The circuit's size does not match the metadata".to_string());
+    }
+    let circuit_bytes = read_payload(reader, circuit_len)?;
+
+    if expect_section(reader, SECTION_END)? != 0 {
+        return Err("The end section must be empty".to_string());
+    }
+
+    // integrity checks
+    let circuit = CompiledCircuit::try_from_bytes(&circuit_bytes)?;
+    if circuit.block_size != metadata.block_size
+        || circuit.num_blocks != metadata.num_blocks
+        || circuit.circuit.len() as u32 != metadata.num_gates
+        || ct_leaves.len() as u32 != metadata.num_blocks
+    {
+        return Err("The circuit does not match the metadata".to_string());
+    }
+    // the cipher is recorded by the circuit's instruction set version
+    let cipher = Cipher::from_version(circuit.version).ok_or(format!(
+        "Unsupported instruction set version {}",
+        circuit.version
+    ))?;
+
+    // a circuit checking the key must check the key commitment of the metadata
+    if ct.len() <= IV_SIZE + cipher.tag_len() || ct.len() > u32::MAX as usize {
+        return Err("The ciphertext's size is not supported by the cipher".to_string());
+    }
+    let base = compile_basic_circuit_with_cipher(ct.len() as u32, &metadata.description, cipher);
+    if let Some(checked) = circuit.checked_key_commitment(&base) {
+        if key_commitment.as_ref() != Some(&checked) {
+            return Err(
+                "The key commitment does not match the one checked by the circuit".to_string(),
+            );
+        }
+    }
+
+    let h_ct = acc_leaf_hashes(ct_leaves);
+    if h_ct != metadata.h_ct {
+        return Err("The ciphertext does not match h_ct".to_string());
+    }
+
+    let h_circuit = acc_circuit(circuit);
+    if h_circuit != metadata.h_circuit {
+        return Err("The circuit does not match h_circuit".to_string());
+    }
+
+    if metadata.opening_value.len() < 16 {
+        return Err("The opening value is too short".to_string());
+    }
+    let opened = open_commitment_internal(&metadata.commitment, &metadata.opening_value)?;
+    if opened != [h_circuit.as_slice(), h_ct.as_slice()].concat() {
+        return Err("The commitment does not open to h_circuit and h_ct".to_string());
+    }
+
+    Ok(Precontract {
+        ct,
+        circuit_bytes,
+        description: metadata.description,
+        h_ct,
+        h_circuit,
+        commitment: Commitment {
+            c: metadata.commitment,
+            o: metadata.opening_value,
+        },
+        num_blocks: metadata.num_blocks,
+        num_gates: metadata.num_gates,
+        cipher,
+        key_commitment,
+        escrow,
+    })
+}
+
+/// Methods for converting precontracts from and to bundles
+#[wasm_bindgen]
+impl Precontract {
+    /// Serializes the precontract into a single self-describing bundle.
+    ///
+    /// Returns a vector containing the bundle.
+    pub fn to_bundle(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.ct.len() + self.circuit_bytes.len() + 256);
+        if let Err(msg) = write_bundle(&mut buf, self) {
+            die(&msg);
+        }
+        buf
+    }
+
+    /// Deserializes a precontract from a bundle and checks its integrity.
+    ///
+    /// # Arguments
+    /// * `bytes` - The bundle bytes
+    ///
+    /// # Returns
+    /// The `Precontract` contained in the bundle. Fails if the bundle is malformed or if the
+    /// integrity checks fail
+    pub fn from_bundle(bytes: &[u8]) -> Precontract {
+        let mut reader = bytes;
+        match read_bundle(&mut reader) {
+            Ok(precontract) if reader.is_empty() => precontract,
+            Ok(_) => die("Unexpected trailing bytes after the bundle"),
+            Err(msg) => die(&msg),
+        }
+    }
+}
+
+// Reads the ciphertext section while hashing its blocks. Returns the ciphertext and the leaf hashes
+// of its blocks (IV first, then blocks of `block_size` bytes).
+fn read_ciphertext<R: Read>(
+    reader: &mut R,
+    len: u64,
+    block_size: usize,
+) -> Result<(Vec<u8>, Vec<Vec<u8>>), String> {
+    if len <= IV_SIZE as u64 {
+        return Err("The ciphertext is too short".to_string());
+    }
+    if block_size == 0 {
+        return Err("The block size cannot be 0".to_string());
+    }
+
+    // the buffer grows chunk by chunk, since `len` cannot be trusted for the allocation
+    let mut ct = Vec::new();
+    let mut leaves = Vec::new();
+    let mut remaining = len;
+    let mut chunk_size = IV_SIZE as u64;
+
+    while remaining > 0 {
+        let size = chunk_size.min(remaining);
+        let chunk = read_payload(reader, size)?;
+        leaves.push(leaf_hash(&chunk));
+        ct.extend(chunk);
+
+        remaining -= size;
+        chunk_size = block_size as u64;
+    }
+
+    Ok((ct, leaves))
+}
+
+// Writes a section with its header
+fn write_section<W: Write>(writer: &mut W, tag: u8, payload: &[u8]) -> Result<(), String> {
+    write_all(writer, &[tag])?;
+    write_all(writer, &(payload.len() as u64).to_be_bytes())?;
+    write_all(writer, payload)
+}
+
+// Reads a section header and checks that its tag is the expected one. Returns the payload length.
+fn expect_section<R: Read>(reader: &mut R, tag: u8) -> Result<u64, String> {
+    let mut found = [0u8; 1];
+    read_exact(reader, &mut found)?;
+    if found[0] != tag {
+        return Err(format!(
+            "Unexpected section {} (expected {})",
+            found[0], tag
+        ));
+    }
+
+    read_u64(reader)
+}
+
+// Reads a payload of `len` bytes without trusting `len` for the allocation
+fn read_payload<R: Read>(reader: &mut R, len: u64) -> Result<Vec<u8>, String> {
+    let mut payload = Vec::new();
+    reader
+        .take(len)
+        .read_to_end(&mut payload)
+        .map_err(|e| e.to_string())?;
+    if payload.len() as u64 != len {
+        return Err("Unexpected end of bundle".to_string());
+    }
+
+    Ok(payload)
+}
+
+// Reads a field prefixed by its u32 length
+fn read_field<R: Read>(reader: &mut R) -> Result<Vec<u8>, String> {
+    let len = read_u32(reader)?;
+    read_payload(reader, len as u64)
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> Result<u16, String> {
+    let mut buf = [0u8; 2];
+    read_exact(reader, &mut buf)?;
+    Ok(u16::from_be_bytes(buf))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, String> {
+    let mut buf = [0u8; 4];
+    read_exact(reader, &mut buf)?;
+    Ok(u32::from_be_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, String> {
+    let mut buf = [0u8; 8];
+    read_exact(reader, &mut buf)?;
+    Ok(u64::from_be_bytes(buf))
+}
+
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), String> {
+    reader
+        .read_exact(buf)
+        .map_err(|_| "Unexpected end of bundle".to_string())
+}
+
+fn write_all<W: Write>(writer: &mut W, buf: &[u8]) -> Result<(), String> {
+    writer.write_all(buf).map_err(|e| e.to_string())
+}
+
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commitment::commit_key_with_rng;
+    use crate::sealing::sealing_public_key;
+    use crate::{compute_precontract_values, compute_precontract_values_with_key_check};
+    use rand::RngCore;
+
+    fn random_precontract(size: usize) -> Precontract {
+        let mut rng = rand::rng();
+        let mut data = vec![0u8; size];
+        rng.fill_bytes(&mut data);
+        let mut key = vec![0u8; 16];
+        rng.fill_bytes(&mut key);
+
+        compute_precontract_values(&mut data, &key)
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        for size in [1, 63, 64, 65, 1000, 4096] {
+            let precontract = random_precontract(size);
+            let bundle = precontract.to_bundle();
+            let decoded = Precontract::from_bundle(&bundle);
+
+            assert_eq!(precontract.ct, decoded.ct);
+            assert_eq!(precontract.circuit_bytes, decoded.circuit_bytes);
+            assert_eq!(precontract.description, decoded.description);
+            assert_eq!(precontract.h_ct, decoded.h_ct);
+            assert_eq!(precontract.h_circuit, decoded.h_circuit);
+            assert_eq!(precontract.commitment.c, decoded.commitment.c);
+            assert_eq!(precontract.commitment.o, decoded.commitment.o);
+            assert_eq!(precontract.num_blocks, decoded.num_blocks);
+            assert_eq!(precontract.num_gates, decoded.num_gates);
+            assert_eq!(precontract.key_commitment, decoded.key_commitment);
+        }
+    }
+
+    #[test]
+    fn test_bundle_escrow_roundtrip() {
+        let key = vec![3u8; 16];
+        let mut precontract = compute_precontract_values(&mut vec![0u8; 1000], &key);
+        let sponsors = vec![sealing_public_key(&[1u8; 32]); 3];
+        precontract.escrow_key_with_rng(&key, 2, &sponsors, &mut rand::rng());
+        assert!(precontract.escrow.is_some());
+
+        let decoded = Precontract::from_bundle(&precontract.to_bundle());
+        assert_eq!(precontract.ct, decoded.ct);
+        assert_eq!(precontract.commitment.c, decoded.commitment.c);
+        assert_eq!(precontract.key_commitment, decoded.key_commitment);
+        assert_eq!(precontract.escrow, decoded.escrow);
+    }
+
+    #[test]
+    fn test_bundle_key_check() {
+        let key = vec![3u8; 16];
+        let mut precontract =
+            compute_precontract_values_with_key_check(&mut [0u8; 200], &key, None);
+        let decoded = Precontract::from_bundle(&precontract.to_bundle());
+        assert_eq!(precontract.key_commitment, decoded.key_commitment);
+
+        // the metadata must give the key commitment checked by the circuit
+        precontract.key_commitment = Some(commit_key_with_rng(&key, &mut rand::rng()));
+        let bundle = precontract.to_bundle();
+        assert_eq!(
+            read_bundle(&mut bundle.as_slice()).err().unwrap(),
+            "The key commitment does not match the one checked by the circuit"
+        );
+
+        precontract.key_commitment = None;
+        let bundle = precontract.to_bundle();
+        assert!(read_bundle(&mut bundle.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_bundle_version_1() {
+        let mut precontract = random_precontract(200);
+        precontract.key_commitment = None;
+        let bundle = precontract.to_bundle();
+
+        // drops the empty key commitment and escrow fields at the end of the metadata
+        let metadata_len = u64::from_be_bytes(bundle[9..17].try_into().unwrap()) as usize;
+        let mut v1 = bundle[..4].to_vec();
+        v1.extend(1u16.to_be_bytes());
+        v1.extend(&bundle[6..9]);
+        v1.extend((metadata_len as u64 - 12).to_be_bytes());
+        v1.extend(&bundle[17..17 + metadata_len - 12]);
+        v1.extend(&bundle[17 + metadata_len..]);
+
+        let decoded = Precontract::from_bundle(&v1);
+        assert_eq!(precontract.ct, decoded.ct);
+        assert_eq!(None, decoded.key_commitment);
+    }
+
+    #[test]
+    fn test_bundle_rejects_tampering() {
+        let precontract = random_precontract(200);
+        let bundle = precontract.to_bundle();
+
+        // flip the last byte of the ciphertext
+        let ct_end = bundle.len() - 9 - 9 - precontract.circuit_bytes.len();
+        let mut tampered = bundle.clone();
+        tampered[ct_end - 1] ^= 1;
+        assert_eq!(
+            read_bundle(&mut tampered.as_slice()).err().unwrap(),
+            "The ciphertext does not match h_ct"
+        );
+
+        let mut tampered = bundle.clone();
+        tampered[0] = b'X';
+        assert!(read_bundle(&mut tampered.as_slice()).is_err());
+
+        let truncated = &bundle[..bundle.len() - 1];
+        assert!(read_bundle(&mut &truncated[..]).is_err());
+    }
+
+    #[test]
+    fn test_read_ciphertext_huge_length() {
+        // the length is not trusted for the allocation
+        assert_eq!(
+            read_ciphertext(&mut &[0u8; 100][..], 1 << 62, 64)
+                .err()
+                .unwrap(),
+            "Unexpected end of bundle"
+        );
+    }
+}
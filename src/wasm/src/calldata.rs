@@ -0,0 +1,367 @@
+use crate::dispute::FinalStepProofs;
+use crate::utils::die;
+use crate::{js_array_to_proof, FinalStepComponents};
+use ethabi::{encode, short_signature, Address, ParamType, Token, Uint};
+use js_sys::Array;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+// ============================= PARAMETER TYPES =============================
+
+fn bytes32() -> ParamType {
+    ParamType::FixedBytes(32)
+}
+
+fn uint32() -> ParamType {
+    ParamType::Uint(32)
+}
+
+// type of the accumulator proofs (bytes32[][])
+fn proof_type() -> ParamType {
+    ParamType::Array(Box::new(ParamType::Array(Box::new(bytes32()))))
+}
+
+// parameters of submitCommitment and submitCommitmentLeft before the proofs
+fn commitment_params(num_proofs: usize) -> Vec<ParamType> {
+    let mut params = vec![
+        ParamType::Bytes,
+        uint32(),
+        ParamType::Array(Box::new(uint32())),
+        ParamType::Array(Box::new(ParamType::Bytes)),
+        uint32(),
+        bytes32(),
+    ];
+    params.extend(std::iter::repeat_n(proof_type(), num_proofs));
+
+    params
+}
+
+// ============================= TOKENS =============================
+
+fn bytes32_token(value: &[u8]) -> Token {
+    if value.len() != 32 {
+        die(&format!("Expected 32 bytes, got {}", value.len()));
+    }
+    Token::FixedBytes(value.to_vec())
+}
+
+fn uint32_token(value: u32) -> Token {
+    Token::Uint(Uint::from(value))
+}
+
+fn proof_token(proof: &[Vec<Vec<u8>>]) -> Token {
+    Token::Array(
+        proof
+            .iter()
+            .map(|layer| Token::Array(layer.iter().map(|v| bytes32_token(v)).collect()))
+            .collect(),
+    )
+}
+
+// tokens of submitCommitment and submitCommitmentLeft before the proofs
+fn commitment_tokens(
+    opening_value: &[u8],
+    gate_num: u32,
+    version: u32,
+    proofs: &FinalStepProofs,
+) -> Vec<Token> {
+    vec![
+        Token::Bytes(opening_value.to_vec()),
+        uint32_token(gate_num),
+        Token::Array(
+            proofs
+                .gate
+                .flatten()
+                .into_iter()
+                .map(uint32_token)
+                .collect(),
+        ),
+        Token::Array(
+            proofs
+                .values
+                .iter()
+                .map(|v| Token::Bytes(v.clone()))
+                .collect(),
+        ),
+        uint32_token(version),
+        bytes32_token(&proofs.curr_acc),
+    ]
+}
+
+// selector followed by the encoded arguments
+fn calldata(name: &str, params: &[ParamType], tokens: &[Token]) -> Vec<u8> {
+    [short_signature(name, params).to_vec(), encode(tokens)].concat()
+}
+
+// ============================= DISPUTE =============================
+
+/// Encodes a call to `DisputeSOX.respondChallenge`
+///
+/// # Arguments
+/// * `response` - The buyer's response (32 bytes)
+///
+/// # Returns
+/// The calldata (selector and arguments)
+#[wasm_bindgen]
+pub fn respond_challenge_calldata(response: &[u8]) -> Vec<u8> {
+    calldata("respondChallenge", &[bytes32()], &[bytes32_token(response)])
+}
+
+/// Encodes a call to `DisputeSOX.giveOpinion`
+///
+/// # Arguments
+/// * `vendor_agrees` - Whether the vendor agrees with the buyer's latest response
+///
+/// # Returns
+/// The calldata (selector and arguments)
+#[wasm_bindgen]
+pub fn give_opinion_calldata(vendor_agrees: bool) -> Vec<u8> {
+    calldata(
+        "giveOpinion",
+        &[ParamType::Bool],
+        &[Token::Bool(vendor_agrees)],
+    )
+}
+
+/// Encodes a call to `DisputeSOX.submitCommitment` (step 8a)
+///
+/// # Arguments
+/// * `opening_value` - The opening value of the commitment
+/// * `gate_num` - The challenged gate
+/// * `version` - The instruction set version
+/// * `proofs` - The components computed by `compute_proofs_internal`
+///
+/// # Returns
+/// The calldata (selector and arguments)
+pub fn submit_commitment_calldata(
+    opening_value: &[u8],
+    gate_num: u32,
+    version: u32,
+    proofs: &FinalStepProofs,
+) -> Vec<u8> {
+    let mut tokens = commitment_tokens(opening_value, gate_num, version, proofs);
+    tokens.extend([
+        proof_token(&proofs.proof1),
+        proof_token(&proofs.proof2),
+        proof_token(&proofs.proof3),
+        proof_token(&proofs.proof_ext),
+    ]);
+
+    calldata("submitCommitment", &commitment_params(4), &tokens)
+}
+
+/// Encodes a call to `DisputeSOX.submitCommitmentLeft` (step 8b). `proofs.proof3` is not sent.
+///
+/// # Arguments
+/// * `opening_value` - The opening value of the commitment
+/// * `gate_num` - The challenged gate
+/// * `version` - The instruction set version
+/// * `proofs` - The components computed by `compute_proofs_left_internal`
+///
+/// # Returns
+/// The calldata (selector and arguments)
+pub fn submit_commitment_left_calldata(
+    opening_value: &[u8],
+    gate_num: u32,
+    version: u32,
+    proofs: &FinalStepProofs,
+) -> Vec<u8> {
+    let mut tokens = commitment_tokens(opening_value, gate_num, version, proofs);
+    tokens.extend([
+        proof_token(&proofs.proof1),
+        proof_token(&proofs.proof2),
+        proof_token(&proofs.proof_ext),
+    ]);
+
+    calldata("submitCommitmentLeft", &commitment_params(3), &tokens)
+}
+
+/// Encodes a call to `DisputeSOX.submitCommitmentRight` (step 8c)
+///
+/// # Arguments
+/// * `proof` - The proof computed by `compute_proof_right_internal`
+///
+/// # Returns
+/// The calldata (selector and arguments)
+pub fn submit_commitment_right_calldata(proof: &[Vec<Vec<u8>>]) -> Vec<u8> {
+    calldata(
+        "submitCommitmentRight",
+        &[proof_type()],
+        &[proof_token(proof)],
+    )
+}
+
+/// JavaScript wrapper of `submit_commitment_calldata`
+#[wasm_bindgen]
+pub fn submit_commitment_calldata_js(
+    opening_value: &[u8],
+    gate_num: u32,
+    version: u32,
+    components: &FinalStepComponents,
+) -> Vec<u8> {
+    submit_commitment_calldata(opening_value, gate_num, version, &components.into())
+}
+
+/// JavaScript wrapper of `submit_commitment_left_calldata`
+#[wasm_bindgen]
+pub fn submit_commitment_left_calldata_js(
+    opening_value: &[u8],
+    gate_num: u32,
+    version: u32,
+    components: &FinalStepComponents,
+) -> Vec<u8> {
+    submit_commitment_left_calldata(opening_value, gate_num, version, &components.into())
+}
+
+/// JavaScript wrapper of `submit_commitment_right_calldata`
+#[wasm_bindgen]
+pub fn submit_commitment_right_calldata_js(proof: Array) -> Vec<u8> {
+    submit_commitment_right_calldata(&js_array_to_proof(&proof))
+}
+
+/// Encodes the arguments of the `DisputeSOX` constructor, to be appended to its bytecode
+///
+/// # Arguments
+/// * `optimistic_contract` - Address of the optimistic smart contract (20 bytes)
+/// * `num_blocks` - Number of blocks of the ciphertext
+/// * `num_gates` - Number of gates in the circuit
+/// * `commitment` - The commitment value (32 bytes)
+///
+/// # Returns
+/// The encoded arguments
+#[wasm_bindgen]
+pub fn dispute_constructor_args(
+    optimistic_contract: &[u8],
+    num_blocks: u32,
+    num_gates: u32,
+    commitment: &[u8],
+) -> Vec<u8> {
+    if optimistic_contract.len() != 20 {
+        die("An address must be 20 bytes long");
+    }
+
+    encode(&[
+        Token::Address(Address::from_slice(optimistic_contract)),
+        uint32_token(num_blocks),
+        uint32_token(num_gates),
+        bytes32_token(commitment),
+    ])
+}
+
+// ============================= OPTIMISTIC =============================
+
+/// Encodes a call to `OptimisticSOX.sendKey`
+///
+/// # Arguments
+/// * `key` - The decryption key
+///
+/// # Returns
+/// The calldata (selector and arguments)
+#[wasm_bindgen]
+pub fn send_key_calldata(key: &[u8]) -> Vec<u8> {
+    calldata(
+        "sendKey",
+        &[ParamType::Bytes],
+        &[Token::Bytes(key.to_vec())],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::Gate;
+    use ethabi::decode;
+    use sha3::{Digest, Keccak256};
+
+    // selector computed from the solidity signature
+    fn selector(signature: &str) -> Vec<u8> {
+        Keccak256::digest(signature.as_bytes())[..4].to_vec()
+    }
+
+    fn proofs() -> FinalStepProofs {
+        let node = |x: u8| vec![x; 32];
+        FinalStepProofs {
+            gate: Gate {
+                opcode: 3,
+                sons: vec![5, 1 << 31],
+            },
+            values: vec![vec![1, 2, 3], vec![4; 16]],
+            curr_acc: node(9),
+            proof1: vec![vec![node(1)], vec![], vec![node(2), node(3)]],
+            proof2: vec![vec![node(4)]],
+            proof3: vec![],
+            proof_ext: vec![vec![node(5)], vec![node(6)]],
+        }
+    }
+
+    #[test]
+    fn test_selectors() {
+        let p = proofs();
+        let cases = [
+            (respond_challenge_calldata(&[7; 32]), "respondChallenge(bytes32)"),
+            (give_opinion_calldata(true), "giveOpinion(bool)"),
+            (
+                submit_commitment_calldata(&[1; 80], 5, 0, &p),
+                "submitCommitment(bytes,uint32,uint32[],bytes[],uint32,bytes32,bytes32[][],bytes32[][],bytes32[][],bytes32[][])",
+            ),
+            (
+                submit_commitment_left_calldata(&[1; 80], 5, 0, &p),
+                "submitCommitmentLeft(bytes,uint32,uint32[],bytes[],uint32,bytes32,bytes32[][],bytes32[][],bytes32[][])",
+            ),
+            (
+                submit_commitment_right_calldata(&p.proof1),
+                "submitCommitmentRight(bytes32[][])",
+            ),
+            (send_key_calldata(&[1; 16]), "sendKey(bytes)"),
+        ];
+
+        for (data, signature) in cases {
+            assert_eq!(selector(signature), data[..4], "{}", signature);
+        }
+    }
+
+    #[test]
+    fn test_submit_commitment_arguments() {
+        let p = proofs();
+        let opening_value = vec![0xab; 80];
+        let data = submit_commitment_calldata(&opening_value, 5, 0, &p);
+        let tokens = decode(&commitment_params(4), &data[4..]).unwrap();
+
+        assert_eq!(Token::Bytes(opening_value), tokens[0]);
+        assert_eq!(uint32_token(5), tokens[1]);
+        assert_eq!(
+            Token::Array(vec![
+                uint32_token(3),
+                uint32_token(5),
+                uint32_token(1 << 31)
+            ]),
+            tokens[2]
+        );
+        assert_eq!(
+            Token::Array(vec![Token::Bytes(vec![1, 2, 3]), Token::Bytes(vec![4; 16])]),
+            tokens[3]
+        );
+        assert_eq!(uint32_token(0), tokens[4]);
+        assert_eq!(Token::FixedBytes(p.curr_acc.clone()), tokens[5]);
+        assert_eq!(proof_token(&p.proof1), tokens[6]);
+        assert_eq!(proof_token(&p.proof2), tokens[7]);
+        assert_eq!(Token::Array(vec![]), tokens[8]);
+        assert_eq!(proof_token(&p.proof_ext), tokens[9]);
+
+        // the left variant skips proof3
+        let data = submit_commitment_left_calldata(&[0xab; 80], 5, 0, &p);
+        let tokens = decode(&commitment_params(3), &data[4..]).unwrap();
+        assert_eq!(proof_token(&p.proof_ext), tokens[8]);
+    }
+
+    #[test]
+    fn test_dispute_constructor_args() {
+        let data = dispute_constructor_args(&[0x11; 20], 3, 10, &[0x22; 32]);
+        let tokens = decode(&[ParamType::Address, uint32(), uint32(), bytes32()], &data).unwrap();
+
+        assert_eq!(4 * 32, data.len());
+        assert_eq!(Token::Address(Address::from_slice(&[0x11; 20])), tokens[0]);
+        assert_eq!(uint32_token(3), tokens[1]);
+        assert_eq!(uint32_token(10), tokens[2]);
+        assert_eq!(Token::FixedBytes(vec![0x22; 32]), tokens[3]);
+    }
+}
@@ -0,0 +1,722 @@
+use crate::circuits::{CompiledCircuit, Gate, GateDebugInfo};
+use crate::dispute::FinalStepProofs;
+use crate::transcript::{DisputeTranscript, TranscriptEntry, TranscriptHeader, TranscriptRecord};
+use crate::{DisputeArgument, EvaluatedCircuit};
+use rmp_serde::from_read;
+use serde::de::DeserializeOwned;
+
+/*
+ * Canonical encoding of the objects exchanged between the parties. All integers are big endian.
+ *
+ * Every encoded object starts with a header:
+ *      magic ("SOXC", 4 bytes)
+ *      type tag (u8)
+ *      encoding version (u8)
+ *
+ * The first byte of the magic can never start one of the MessagePack blobs produced by previous
+ * versions (these are always arrays), which allows decoding both formats.
+ *
+ * Primitive types:
+ *      u32         4 bytes
+ *      bytes       u32 length || raw bytes
+ *      list<T>     u32 number of elements || elements
+ *      option<T>   0x00 if absent, 0x01 || T if present
+ *
+ * Gate:
+ *      opcode (u32) || sons (list<u32>)
+ *
+ * CompiledCircuit (type tag 0x01, version 0x02):
+ *      CircuitFields || debug_info (list<GateDebugInfo>)
+ * where debug_info is either empty or has one element per gate. Version 0x01 only has the
+ * CircuitFields and is still accepted.
+ *
+ * CircuitFields:
+ *      gates (list<Gate>) || constants (list<option<bytes>>) || version (u32) || block_size (u32)
+ *      || num_blocks (u32)
+ *
+ * GateDebugInfo:
+ *      label (bytes, UTF-8) || block_range (option<first (u32) || last (u32)>)
+ *
+ * DisputeArgument (type tag 0x02, version 0x01):
+ *      circuit (CircuitFields) || ct (bytes) || opening_value (bytes)
+ *
+ * EvaluatedCircuit (type tag 0x03, version 0x01):
+ *      values (list<bytes>) || constants (list<bytes>)
+ *
+ * DisputeTranscript (type tag 0x04, version 0x02):
+ *      TranscriptChain || buyer_signature (option<bytes>) || vendor_signature (option<bytes>)
+ * Version 0x01 only has the TranscriptChain and is still accepted.
+ *
+ * TranscriptChain:
+ *      TranscriptHeader || records (list<entry (TranscriptEntry) || hash (bytes)>)
+ *
+ * TranscriptHeader:
+ *      num_blocks (u32) || num_gates (u32) || commitment (bytes) || arity (u32)
+ *
+ * TranscriptEntry, starting with a kind (u8):
+ *      0x01 responses      challenges (list<u32>) || responses (list<bytes>)
+ *      0x02 opinion        disagreement (u32)
+ *      0x03 final step     gate_num (u32) || opening_value (bytes) || version (u32) || gate (Gate)
+ *                          || values (list<bytes>) || curr_acc (bytes) || proof1 || proof2
+ *                          || proof3 || proof_ext
+ *      0x04 final step     proof
+ *           (right)
+ *      0x05 timeout
+ * where each proof is a list<list<bytes>>.
+ *
+ * Decoding is strict: unknown tags or versions, option markers other than 0x00 and 0x01, lengths
+ * going past the end of the input and trailing bytes are all rejected. Each object thus has
+ * exactly one valid encoding.
+ */
+
+/// Magic bytes at the start of every canonically encoded object
+pub const CANONICAL_MAGIC: [u8; 4] = *b"SOXC";
+
+/// Type tag of `CompiledCircuit`
+pub const TAG_COMPILED_CIRCUIT: u8 = 0x01;
+
+/// Type tag of `DisputeArgument`
+pub const TAG_DISPUTE_ARGUMENT: u8 = 0x02;
+
+/// Type tag of `EvaluatedCircuit`
+pub const TAG_EVALUATED_CIRCUIT: u8 = 0x03;
+
+/// Type tag of `DisputeTranscript`
+pub const TAG_DISPUTE_TRANSCRIPT: u8 = 0x04;
+
+const ENTRY_RESPONSES: u8 = 0x01;
+const ENTRY_OPINION: u8 = 0x02;
+const ENTRY_FINAL_STEP: u8 = 0x03;
+const ENTRY_FINAL_STEP_RIGHT: u8 = 0x04;
+const ENTRY_TIMEOUT: u8 = 0x05;
+
+/// Objects that have a canonical encoding
+pub trait Canonical: Sized {
+    /// Type tag written in the header
+    const TAG: u8;
+
+    /// Encoding version written in the header
+    const VERSION: u8;
+
+    /// Appends the encoding of the object (without header) to the encoder
+    fn encode_body(&self, enc: &mut Encoder);
+
+    /// Decodes an object (without header) from the decoder
+    fn decode_body(dec: &mut Decoder) -> Result<Self, String>;
+
+    /// Decodes an object (without header) written with the given encoding version. Only the
+    /// current version is accepted unless the object overrides this method.
+    fn decode_versioned_body(dec: &mut Decoder, version: u8) -> Result<Self, String> {
+        if version != Self::VERSION {
+            return Err(format!("Unsupported encoding version {}", version));
+        }
+        Self::decode_body(dec)
+    }
+
+    /// Encodes the object with its header
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        enc.buf.extend(CANONICAL_MAGIC);
+        enc.u8(Self::TAG);
+        enc.u8(Self::VERSION);
+        self.encode_body(&mut enc);
+        enc.buf
+    }
+
+    /// Decodes an object with its header. The whole input must be consumed.
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut dec = Decoder::new(bytes);
+        if dec.take(4)? != CANONICAL_MAGIC {
+            return Err("Not a canonically encoded object".to_string());
+        }
+        let tag = dec.u8()?;
+        if tag != Self::TAG {
+            return Err(format!(
+                "Unexpected type tag {} (expected {})",
+                tag,
+                Self::TAG
+            ));
+        }
+        let version = dec.u8()?;
+
+        let res = Self::decode_versioned_body(&mut dec, version)?;
+        dec.finish()?;
+        Ok(res)
+    }
+}
+
+/// Decodes an object encoded either canonically or with MessagePack (legacy format)
+///
+/// # Arguments
+/// * `bytes` - The encoded object
+///
+/// # Returns
+/// The decoded object or an error message
+pub fn decode_canonical_or_legacy<T: Canonical + DeserializeOwned>(
+    bytes: &[u8],
+) -> Result<T, String> {
+    if bytes.starts_with(&CANONICAL_MAGIC) {
+        T::from_canonical_bytes(bytes)
+    } else {
+        from_read(bytes).map_err(|e| format!("Could not decode legacy object: {}", e))
+    }
+}
+
+/// Writes the primitive types of the canonical encoding
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn new() -> Encoder {
+        Encoder { buf: Vec::new() }
+    }
+
+    /// Appends a byte
+    pub fn u8(&mut self, x: u8) {
+        self.buf.push(x);
+    }
+
+    /// Appends a u32
+    pub fn u32(&mut self, x: u32) {
+        self.buf.extend(x.to_be_bytes());
+    }
+
+    /// Appends a length-prefixed byte string
+    pub fn bytes(&mut self, x: &[u8]) {
+        self.u32(x.len() as u32);
+        self.buf.extend(x);
+    }
+
+    /// Appends a list, encoding each element with `f`
+    pub fn list<T>(&mut self, items: &[T], mut f: impl FnMut(&mut Encoder, &T)) {
+        self.u32(items.len() as u32);
+        for item in items {
+            f(self, item);
+        }
+    }
+
+    /// Appends an optional value, encoding it with `f` if present
+    pub fn option<T>(&mut self, item: &Option<T>, f: impl FnOnce(&mut Encoder, &T)) {
+        match item {
+            None => self.u8(0),
+            Some(x) => {
+                self.u8(1);
+                f(self, x);
+            }
+        }
+    }
+}
+
+/// Strict reader of the primitive types of the canonical encoding
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8]) -> Decoder<'a> {
+        Decoder { bytes }
+    }
+
+    // Takes the next `n` bytes of the input
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if n > self.bytes.len() {
+            return Err("Unexpected end of input".to_string());
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    /// Reads a byte
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Reads a u32
+    pub fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Reads a length-prefixed byte string
+    pub fn bytes(&mut self) -> Result<Vec<u8>, String> {
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    /// Reads a list, decoding each element with `f`. Every element is encoded with at least one
+    /// byte, which bounds the number of elements by the remaining input.
+    pub fn list<T>(
+        &mut self,
+        mut f: impl FnMut(&mut Decoder<'a>) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let len = self.u32()? as usize;
+        if len > self.bytes.len() {
+            return Err("List length exceeds the input".to_string());
+        }
+        (0..len).map(|_| f(self)).collect()
+    }
+
+    /// Reads an optional value, decoding it with `f` if present
+    pub fn option<T>(
+        &mut self,
+        f: impl FnOnce(&mut Decoder<'a>) -> Result<T, String>,
+    ) -> Result<Option<T>, String> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(f(self)?)),
+            x => Err(format!("Invalid option marker {}", x)),
+        }
+    }
+
+    // Checks that the whole input has been consumed
+    fn finish(&self) -> Result<(), String> {
+        if !self.bytes.is_empty() {
+            return Err(format!("{} trailing bytes", self.bytes.len()));
+        }
+        Ok(())
+    }
+}
+
+// Encodes a gate
+fn encode_gate(enc: &mut Encoder, gate: &Gate) {
+    enc.u32(gate.opcode);
+    enc.list(&gate.sons, |enc, &s| enc.u32(s));
+}
+
+// Decodes a gate
+fn decode_gate(dec: &mut Decoder) -> Result<Gate, String> {
+    Ok(Gate {
+        opcode: dec.u32()?,
+        sons: dec.list(|dec| dec.u32())?,
+    })
+}
+
+// Encodes the fields of a circuit, without its debug metadata
+fn encode_circuit_fields(enc: &mut Encoder, circuit: &CompiledCircuit) {
+    enc.list(&circuit.circuit, encode_gate);
+    enc.list(&circuit.constants, |enc, c| {
+        enc.option(c, |enc, c| enc.bytes(c))
+    });
+    enc.u32(circuit.version);
+    enc.u32(circuit.block_size);
+    enc.u32(circuit.num_blocks);
+}
+
+// Decodes the fields of a circuit, the debug metadata is left empty
+fn decode_circuit_fields(dec: &mut Decoder) -> Result<CompiledCircuit, String> {
+    Ok(CompiledCircuit {
+        circuit: dec.list(decode_gate)?,
+        constants: dec.list(|dec| dec.option(|dec| dec.bytes()))?,
+        version: dec.u32()?,
+        block_size: dec.u32()?,
+        num_blocks: dec.u32()?,
+        debug_info: vec![],
+    })
+}
+
+fn encode_debug_info(enc: &mut Encoder, info: &GateDebugInfo) {
+    enc.bytes(info.label.as_bytes());
+    enc.option(&info.block_range, |enc, &(first, last)| {
+        enc.u32(first);
+        enc.u32(last);
+    });
+}
+
+fn decode_debug_info(dec: &mut Decoder) -> Result<GateDebugInfo, String> {
+    Ok(GateDebugInfo {
+        label: String::from_utf8(dec.bytes()?).map_err(|_| "Invalid UTF-8 label".to_string())?,
+        block_range: dec.option(|dec| Ok((dec.u32()?, dec.u32()?)))?,
+    })
+}
+
+impl Canonical for CompiledCircuit {
+    const TAG: u8 = TAG_COMPILED_CIRCUIT;
+    const VERSION: u8 = 2;
+
+    fn encode_body(&self, enc: &mut Encoder) {
+        encode_circuit_fields(enc, self);
+        enc.list(&self.debug_info, encode_debug_info);
+    }
+
+    fn decode_body(dec: &mut Decoder) -> Result<Self, String> {
+        let mut circuit = decode_circuit_fields(dec)?;
+        circuit.debug_info = dec.list(decode_debug_info)?;
+        if !circuit.debug_info.is_empty() && circuit.debug_info.len() != circuit.circuit.len() {
+            return Err("The debug metadata does not match the gates".to_string());
+        }
+        Ok(circuit)
+    }
+
+    fn decode_versioned_body(dec: &mut Decoder, version: u8) -> Result<Self, String> {
+        match version {
+            1 => decode_circuit_fields(dec),
+            2 => Self::decode_body(dec),
+            _ => Err(format!("Unsupported encoding version {}", version)),
+        }
+    }
+}
+
+impl Canonical for DisputeArgument {
+    const TAG: u8 = TAG_DISPUTE_ARGUMENT;
+    const VERSION: u8 = 1;
+
+    fn encode_body(&self, enc: &mut Encoder) {
+        encode_circuit_fields(enc, &self.circuit);
+        enc.bytes(&self.ct);
+        enc.bytes(&self.opening_value);
+    }
+
+    fn decode_body(dec: &mut Decoder) -> Result<Self, String> {
+        Ok(DisputeArgument {
+            circuit: decode_circuit_fields(dec)?,
+            ct: dec.bytes()?,
+            opening_value: dec.bytes()?,
+        })
+    }
+}
+
+impl Canonical for EvaluatedCircuit {
+    const TAG: u8 = TAG_EVALUATED_CIRCUIT;
+    const VERSION: u8 = 1;
+
+    fn encode_body(&self, enc: &mut Encoder) {
+        enc.list(&self.values, |enc, v| enc.bytes(v));
+        enc.list(&self.constants, |enc, c| enc.bytes(c));
+    }
+
+    fn decode_body(dec: &mut Decoder) -> Result<Self, String> {
+        Ok(EvaluatedCircuit {
+            values: dec.list(|dec| dec.bytes())?,
+            constants: dec.list(|dec| dec.bytes())?,
+        })
+    }
+}
+
+// Encodes an accumulator proof
+fn encode_proof(enc: &mut Encoder, proof: &[Vec<Vec<u8>>]) {
+    enc.list(proof, |enc, layer| enc.list(layer, |enc, x| enc.bytes(x)));
+}
+
+// Decodes an accumulator proof
+fn decode_proof(dec: &mut Decoder) -> Result<Vec<Vec<Vec<u8>>>, String> {
+    dec.list(|dec| dec.list(|dec| dec.bytes()))
+}
+
+fn encode_transcript_header(enc: &mut Encoder, header: &TranscriptHeader) {
+    enc.u32(header.num_blocks);
+    enc.u32(header.num_gates);
+    enc.bytes(&header.commitment);
+    enc.u32(header.arity);
+}
+
+fn decode_transcript_header(dec: &mut Decoder) -> Result<TranscriptHeader, String> {
+    Ok(TranscriptHeader {
+        num_blocks: dec.u32()?,
+        num_gates: dec.u32()?,
+        commitment: dec.bytes()?,
+        arity: dec.u32()?,
+    })
+}
+
+fn encode_transcript_entry(enc: &mut Encoder, entry: &TranscriptEntry) {
+    match entry {
+        TranscriptEntry::Responses {
+            challenges,
+            responses,
+        } => {
+            enc.u8(ENTRY_RESPONSES);
+            enc.list(challenges, |enc, &c| enc.u32(c));
+            enc.list(responses, |enc, r| enc.bytes(r));
+        }
+        TranscriptEntry::Opinion { disagreement } => {
+            enc.u8(ENTRY_OPINION);
+            enc.u32(*disagreement);
+        }
+        TranscriptEntry::FinalStep {
+            gate_num,
+            opening_value,
+            version,
+            proofs,
+        } => {
+            enc.u8(ENTRY_FINAL_STEP);
+            enc.u32(*gate_num);
+            enc.bytes(opening_value);
+            enc.u32(*version);
+            encode_gate(enc, &proofs.gate);
+            enc.list(&proofs.values, |enc, v| enc.bytes(v));
+            enc.bytes(&proofs.curr_acc);
+            for proof in [
+                &proofs.proof1,
+                &proofs.proof2,
+                &proofs.proof3,
+                &proofs.proof_ext,
+            ] {
+                encode_proof(enc, proof);
+            }
+        }
+        TranscriptEntry::FinalStepRight { proof } => {
+            enc.u8(ENTRY_FINAL_STEP_RIGHT);
+            encode_proof(enc, proof);
+        }
+        TranscriptEntry::Timeout => enc.u8(ENTRY_TIMEOUT),
+    }
+}
+
+fn decode_transcript_entry(dec: &mut Decoder) -> Result<TranscriptEntry, String> {
+    Ok(match dec.u8()? {
+        ENTRY_RESPONSES => TranscriptEntry::Responses {
+            challenges: dec.list(|dec| dec.u32())?,
+            responses: dec.list(|dec| dec.bytes())?,
+        },
+        ENTRY_OPINION => TranscriptEntry::Opinion {
+            disagreement: dec.u32()?,
+        },
+        ENTRY_FINAL_STEP => TranscriptEntry::FinalStep {
+            gate_num: dec.u32()?,
+            opening_value: dec.bytes()?,
+            version: dec.u32()?,
+            proofs: FinalStepProofs {
+                gate: decode_gate(dec)?,
+                values: dec.list(|dec| dec.bytes())?,
+                curr_acc: dec.bytes()?,
+                proof1: decode_proof(dec)?,
+                proof2: decode_proof(dec)?,
+                proof3: decode_proof(dec)?,
+                proof_ext: decode_proof(dec)?,
+            },
+        },
+        ENTRY_FINAL_STEP_RIGHT => TranscriptEntry::FinalStepRight {
+            proof: decode_proof(dec)?,
+        },
+        ENTRY_TIMEOUT => TranscriptEntry::Timeout,
+        kind => return Err(format!("Unknown transcript entry kind {}", kind)),
+    })
+}
+
+/// Encoding of a transcript header, used as the start of the transcript's hash chain
+pub fn transcript_header_bytes(header: &TranscriptHeader) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    encode_transcript_header(&mut enc, header);
+    enc.buf
+}
+
+/// Encoding of a transcript entry, used to extend the transcript's hash chain
+pub fn transcript_entry_bytes(entry: &TranscriptEntry) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    encode_transcript_entry(&mut enc, entry);
+    enc.buf
+}
+
+fn decode_transcript_chain(dec: &mut Decoder) -> Result<DisputeTranscript, String> {
+    let mut transcript = DisputeTranscript::from_header(decode_transcript_header(dec)?);
+    transcript.records = dec.list(|dec| {
+        Ok(TranscriptRecord {
+            entry: decode_transcript_entry(dec)?,
+            hash: dec.bytes()?,
+        })
+    })?;
+    Ok(transcript)
+}
+
+impl Canonical for DisputeTranscript {
+    const TAG: u8 = TAG_DISPUTE_TRANSCRIPT;
+    const VERSION: u8 = 2;
+
+    fn encode_body(&self, enc: &mut Encoder) {
+        encode_transcript_header(enc, &self.header);
+        enc.list(&self.records, |enc, record| {
+            encode_transcript_entry(enc, &record.entry);
+            enc.bytes(&record.hash);
+        });
+        enc.option(&self.buyer_signature, |enc, s| enc.bytes(s));
+        enc.option(&self.vendor_signature, |enc, s| enc.bytes(s));
+    }
+
+    fn decode_body(dec: &mut Decoder) -> Result<Self, String> {
+        let mut transcript = decode_transcript_chain(dec)?;
+        transcript.buyer_signature = dec.option(|dec| dec.bytes())?;
+        transcript.vendor_signature = dec.option(|dec| dec.bytes())?;
+        Ok(transcript)
+    }
+
+    fn decode_versioned_body(dec: &mut Decoder, version: u8) -> Result<Self, String> {
+        match version {
+            1 => decode_transcript_chain(dec),
+            2 => Self::decode_body(dec),
+            _ => Err(format!("Unsupported encoding version {}", version)),
+        }
+    }
+}
+
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::{compile_basic_circuit, evaluate_circuit_internal};
+    use crate::utils::{bytes_to_hex, hex_to_bytes, split_ct_blocks};
+    use rmp_serde::encode::write;
+
+    // Golden vectors. The `.hex` files contain the canonical encodings, the `.msgpack.hex` files
+    // contain the encodings produced by the legacy MessagePack serialization.
+    const CIRCUIT_HEX: &str = include_str!("../test_vectors/canonical/compiled_circuit.hex");
+    const CIRCUIT_V1_HEX: &str = include_str!("../test_vectors/canonical/compiled_circuit_v1.hex");
+    const CIRCUIT_MSGPACK_HEX: &str =
+        include_str!("../test_vectors/canonical/compiled_circuit.msgpack.hex");
+    const ARGUMENT_HEX: &str = include_str!("../test_vectors/canonical/dispute_argument.hex");
+    const ARGUMENT_MSGPACK_HEX: &str =
+        include_str!("../test_vectors/canonical/dispute_argument.msgpack.hex");
+    const EVALUATED_HEX: &str = include_str!("../test_vectors/canonical/evaluated_circuit.hex");
+    const EVALUATED_MSGPACK_HEX: &str =
+        include_str!("../test_vectors/canonical/evaluated_circuit.msgpack.hex");
+
+    // Fixed inputs used to produce the golden vectors
+    fn vector_ct() -> Vec<u8> {
+        (0u8..100).collect()
+    }
+
+    fn vector_description() -> Vec<u8> {
+        (0u8..32).map(|x| 0xff - x).collect()
+    }
+
+    fn vector_key() -> Vec<u8> {
+        (0u8..16).map(|x| x * 3).collect()
+    }
+
+    fn vector_circuit() -> CompiledCircuit {
+        compile_basic_circuit(vector_ct().len() as u32, &vector_description())
+    }
+
+    fn vector_argument() -> DisputeArgument {
+        DisputeArgument {
+            circuit: vector_circuit(),
+            ct: vector_ct(),
+            opening_value: (0u8..80).rev().collect(),
+        }
+    }
+
+    fn vector_evaluated() -> EvaluatedCircuit {
+        let circuit = vector_circuit().bind_missing_constants(vec![vector_key()]);
+        let ct_blocks = split_ct_blocks(&vector_ct(), circuit.block_size as usize);
+        let values = evaluate_circuit_internal(&ct_blocks, &circuit);
+        EvaluatedCircuit {
+            values,
+            constants: circuit.into_constants(),
+        }
+    }
+
+    fn golden(hex: &str) -> Vec<u8> {
+        hex_to_bytes(hex.trim().to_string())
+    }
+
+    fn msgpack<T: serde::Serialize>(value: &T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write(&mut buf, value).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_golden_vectors() {
+        assert_eq!(golden(CIRCUIT_HEX), vector_circuit().to_canonical_bytes());
+        assert_eq!(golden(ARGUMENT_HEX), vector_argument().to_canonical_bytes());
+        assert_eq!(
+            golden(EVALUATED_HEX),
+            vector_evaluated().to_canonical_bytes()
+        );
+    }
+
+    #[test]
+    fn test_golden_vectors_roundtrip() {
+        let circuit = CompiledCircuit::from_canonical_bytes(&golden(CIRCUIT_HEX)).unwrap();
+        assert_eq!(golden(CIRCUIT_HEX), circuit.to_canonical_bytes());
+
+        let argument = DisputeArgument::from_canonical_bytes(&golden(ARGUMENT_HEX)).unwrap();
+        assert_eq!(golden(ARGUMENT_HEX), argument.to_canonical_bytes());
+
+        let evaluated = EvaluatedCircuit::from_canonical_bytes(&golden(EVALUATED_HEX)).unwrap();
+        assert_eq!(golden(EVALUATED_HEX), evaluated.to_canonical_bytes());
+    }
+
+    #[test]
+    fn test_legacy_vectors() {
+        assert_eq!(golden(CIRCUIT_MSGPACK_HEX), msgpack(&vector_circuit()));
+        assert_eq!(golden(ARGUMENT_MSGPACK_HEX), msgpack(&vector_argument()));
+        assert_eq!(golden(EVALUATED_MSGPACK_HEX), msgpack(&vector_evaluated()));
+
+        // the debug metadata is not part of the legacy encoding
+        let circuit: CompiledCircuit =
+            decode_canonical_or_legacy(&golden(CIRCUIT_MSGPACK_HEX)).unwrap();
+        assert_eq!(
+            vector_circuit().without_debug_info().to_canonical_bytes(),
+            circuit.to_canonical_bytes()
+        );
+
+        let argument: DisputeArgument =
+            decode_canonical_or_legacy(&golden(ARGUMENT_MSGPACK_HEX)).unwrap();
+        assert_eq!(golden(ARGUMENT_HEX), argument.to_canonical_bytes());
+
+        let evaluated: EvaluatedCircuit =
+            decode_canonical_or_legacy(&golden(EVALUATED_MSGPACK_HEX)).unwrap();
+        assert_eq!(golden(EVALUATED_HEX), evaluated.to_canonical_bytes());
+    }
+
+    #[test]
+    fn test_previous_versions() {
+        let circuit = CompiledCircuit::from_canonical_bytes(&golden(CIRCUIT_V1_HEX)).unwrap();
+        assert!(circuit.debug_info.is_empty());
+        assert_eq!(
+            vector_circuit().without_debug_info().to_canonical_bytes(),
+            circuit.to_canonical_bytes()
+        );
+
+        let circuit = CompiledCircuit::from_canonical_bytes(&golden(CIRCUIT_HEX)).unwrap();
+        assert_eq!(vector_circuit().debug_info, circuit.debug_info);
+    }
+
+    #[test]
+    fn test_strict_decoding() {
+        let bytes = golden(CIRCUIT_V1_HEX);
+
+        // trailing bytes
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(CompiledCircuit::from_canonical_bytes(&trailing).is_err());
+
+        // truncated input
+        assert!(CompiledCircuit::from_canonical_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        // wrong type tag
+        assert!(DisputeArgument::from_canonical_bytes(&bytes).is_err());
+
+        // unknown version
+        let mut version = bytes.clone();
+        version[5] = 3;
+        assert!(CompiledCircuit::from_canonical_bytes(&version).is_err());
+
+        // non-canonical option marker: the last constant (key placeholder) is absent and
+        // directly followed by the version, block size and number of blocks
+        let mut option = bytes.clone();
+        let marker = option.len() - 13;
+        assert_eq!(option[marker], 0);
+        option[marker] = 2;
+        assert!(CompiledCircuit::from_canonical_bytes(&option).is_err());
+
+        // debug metadata that does not cover every gate
+        let mut circuit = vector_circuit();
+        circuit.debug_info.pop();
+        assert!(CompiledCircuit::from_canonical_bytes(&circuit.to_canonical_bytes()).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn print_golden_vectors() {
+        // run with `cargo test print_golden_vectors -- --ignored --nocapture` to regenerate
+        println!("{}", bytes_to_hex(vector_circuit().to_canonical_bytes()));
+        println!("{}", bytes_to_hex(msgpack(&vector_circuit())));
+        println!("{}", bytes_to_hex(vector_argument().to_canonical_bytes()));
+        println!("{}", bytes_to_hex(msgpack(&vector_argument())));
+        println!("{}", bytes_to_hex(vector_evaluated().to_canonical_bytes()));
+        println!("{}", bytes_to_hex(msgpack(&vector_evaluated())));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::accumulator::uint8_array_to_vec_u8;
+use crate::encryption::Cipher;
+use crate::utils::die;
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/*
+ * data = [
+ *      key (32 bytes),
+ *      block (<=64 bytes),
+ *      nonce (12 bytes) || block counter (4 bytes, big endian)
+ * ]
+ */
+
+/// Decrypts a block using ChaCha20 (RFC 8439, 96-bit nonce). A block is a single ChaCha20 block
+/// of 64 bytes, so the counter of the next block is obtained by adding 1 to the last input (a
+/// 128-bit big endian addition, as done by `binary_add`).
+///
+/// # Arguments
+/// * `data` - Vector containing:
+///   - key (32 bytes)
+///   - block to decrypt (<=64 bytes)
+///   - nonce (12 bytes) followed by the block counter (4 bytes, big endian)
+///
+/// # Returns
+/// Decrypted bytes
+pub fn decrypt_block_chacha20(data: &[&Vec<u8>]) -> Vec<u8> {
+    if data.len() < 3 {
+        die("ChaCha20 decryption requires a key, a block and a nonce with its block counter")
+    }
+
+    if data[0].len() != 32 {
+        die("ChaCha20 decryption requires a key of exactly 32 bytes")
+    }
+
+    if data[1].len() > 64 {
+        die("ChaCha20 decryption requires a block of at most 64 bytes");
+    }
+
+    if data[1].is_empty() {
+        return vec![];
+    }
+
+    if data[2].len() != 16 {
+        die("ChaCha20 decryption requires a nonce and block counter of exactly 16 bytes");
+    }
+
+    let mut res = data[1].to_vec();
+    Cipher::ChaCha20.apply_keystream(data[0], data[2], &mut res);
+
+    res
+}
+
+/// JavaScript wrapper for decrypt_block_chacha20
+///
+/// # Arguments
+/// * `data` - Vector of Uint8Arrays containing:
+///   - key (32 bytes)
+///   - block to decrypt (<=64 bytes)
+///   - nonce (12 bytes) followed by the block counter (4 bytes, big endian)
+///
+/// # Returns
+/// Decrypted bytes
+#[wasm_bindgen]
+pub fn decrypt_block_chacha20_js(data: Vec<Uint8Array>) -> Vec<u8> {
+    let values_vec: Vec<Vec<u8>> = data.iter().map(uint8_array_to_vec_u8).collect();
+    let refs: Vec<&Vec<u8>> = values_vec.iter().collect();
+    decrypt_block_chacha20(&refs)
+}
+
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hex_to_bytes;
+
+    // RFC 8439, section 2.4.2
+    const KEY: &str = "0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    const NONCE: &str = "0x000000000000004a00000000";
+    const PLAINTEXT: &str = "Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+    const CIPHERTEXT: &str = "0x6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0bf91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d807ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab77937365af90bbf74a35be6b40b8eedf2785e42874d";
+
+    fn counter_block(counter: u32) -> Vec<u8> {
+        [
+            hex_to_bytes(NONCE.to_string()),
+            counter.to_be_bytes().to_vec(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_rfc8439_blocks() {
+        let key = hex_to_bytes(KEY.to_string());
+        let ct = hex_to_bytes(CIPHERTEXT.to_string());
+
+        // the encryption starts with the block counter 1
+        let pt: Vec<u8> = ct
+            .chunks(64)
+            .enumerate()
+            .flat_map(|(i, block)| {
+                decrypt_block_chacha20(&[&key, &block.to_vec(), &counter_block(1 + i as u32)])
+            })
+            .collect();
+        assert_eq!(PLAINTEXT.as_bytes(), pt);
+    }
+
+    #[test]
+    fn test_rfc8439_keystream() {
+        // RFC 8439, section 2.3.2: serialized block for the block counter 1
+        let key = hex_to_bytes(KEY.to_string());
+        let nonce_counter = [
+            hex_to_bytes("0x000000090000004a00000000".to_string()),
+            1u32.to_be_bytes().to_vec(),
+        ]
+        .concat();
+        let keystream = decrypt_block_chacha20(&[&key, &vec![0u8; 64], &nonce_counter]);
+        assert_eq!(
+            hex_to_bytes("0x10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4ed2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e".to_string()),
+            keystream
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "requires a key of exactly 32 bytes")]
+    fn test_key_length() {
+        decrypt_block_chacha20(&[&vec![0u8; 16], &vec![0u8; 16], &counter_block(1)]);
+    }
+}
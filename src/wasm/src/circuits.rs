@@ -1,13 +1,16 @@
+use crate::canonical::{decode_canonical_or_legacy, Canonical};
+use crate::commitment::KeyCommitment;
+use crate::encryption::Cipher;
 use crate::utils::die;
-use crate::{aes_ctr, sha256, simple_operations};
+use crate::{aes_ctr, chacha_ctr, gcm, sha256, simple_operations};
 use ethabi::{encode, Token};
 use rmp_serde::encode::write;
-use rmp_serde::from_read;
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::wasm_bindgen;
+use zeroize::Zeroize;
 
 /// Function type for instructions
-type Instruction = fn(data: &Vec<&Vec<u8>>) -> Vec<u8>;
+type Instruction = fn(data: &[&Vec<u8>]) -> Vec<u8>;
 
 fn version_instructions(version: usize) -> Vec<Instruction> {
     match version {
@@ -23,13 +26,64 @@ fn version_instructions(version: usize) -> Vec<Instruction> {
                 sha256::sha256_compress_final,
             ]
         }
+        1 => {
+            let mut instructions = version_instructions(0);
+            instructions.push(aes_ctr::decrypt_block_256);
+            instructions
+        }
+        2 => {
+            let mut instructions = version_instructions(1);
+            instructions.push(chacha_ctr::decrypt_block_chacha20);
+            instructions
+        }
+        3 => {
+            let mut instructions = version_instructions(2);
+            instructions.push(gcm::ghash_mult_acc);
+            instructions.push(simple_operations::slice_bytes);
+            instructions
+        }
         _ => vec![],
     }
 }
 
+/// Returns the name of an instruction, or `None` if the opcode is not part of the instruction set
+///
+/// # Arguments
+/// * `version` - Version of the instruction set
+/// * `opcode` - Opcode of the instruction
+pub fn opcode_name(version: u32, opcode: u32) -> Option<&'static str> {
+    const VERSION_0: [&str; 8] = [
+        "sha256_compress",
+        "encrypt_block",
+        "decrypt_block",
+        "binary_add",
+        "binary_mult",
+        "equal",
+        "concat_bytes",
+        "sha256_compress_final",
+    ];
+    let extension: &[&str] = match version {
+        0 => &[],
+        1 => &["decrypt_block_256"],
+        2 => &["decrypt_block_256", "decrypt_block_chacha20"],
+        3 => &[
+            "decrypt_block_256",
+            "decrypt_block_chacha20",
+            "ghash_mult_acc",
+            "slice_bytes",
+        ],
+        _ => return None,
+    };
+    VERSION_0
+        .iter()
+        .chain(extension)
+        .nth(opcode as usize)
+        .copied()
+}
+
 /// Represents a gate in the circuit with an operation code and connections to other gates
 #[wasm_bindgen]
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Gate {
     /// Opcode determining the gate's function
     pub opcode: u32,
@@ -84,6 +138,18 @@ impl Gate {
     }
 }
 
+/// Debug metadata attached to a gate. It is not part of the circuit's accumulator value, and can
+/// thus be changed or dropped without affecting the dispute.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GateDebugInfo {
+    /// Human readable description of the gate, e.g. "AES decrypt block 37"
+    pub label: String,
+
+    /// First and last ciphertext blocks (inclusive) the gate's value depends on, `None` if it only
+    /// depends on constants
+    pub block_range: Option<(u32, u32)>,
+}
+
 /// Represents a compiled circuit with gates and their associated constants
 #[wasm_bindgen]
 #[derive(Serialize, Deserialize, Clone)]
@@ -105,20 +171,25 @@ pub struct CompiledCircuit {
 
     /// Number of blocks in the circuit
     pub num_blocks: u32,
+
+    /// Debug metadata of each gate, or empty if the circuit has none. Not part of the legacy
+    /// MessagePack encoding.
+    #[wasm_bindgen(skip)]
+    #[serde(skip)]
+    pub debug_info: Vec<GateDebugInfo>,
 }
 
 #[wasm_bindgen]
 impl CompiledCircuit {
-    /// Serializes the compiled circuit into bytes.
+    /// Serializes the compiled circuit into bytes using the canonical encoding.
     ///
     /// Returns a vector containing the serialized circuit data.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        write(&mut buf, self).unwrap();
-        buf
+        self.to_canonical_bytes()
     }
 
-    /// Deserializes a compiled circuit from bytes.
+    /// Deserializes a compiled circuit from bytes. Both the canonical encoding and the legacy
+    /// MessagePack encoding are accepted.
     ///
     /// # Arguments
     /// * `bytes` - The serialized circuit bytes
@@ -126,12 +197,176 @@ impl CompiledCircuit {
     /// # Returns
     /// A new `CompiledCircuit` instance
     pub fn from_bytes(bytes: &[u8]) -> CompiledCircuit {
-        from_read(bytes).unwrap()
+        match Self::try_from_bytes(bytes) {
+            Ok(circuit) => circuit,
+            Err(msg) => die(&msg),
+        }
+    }
+
+    /// Returns the human readable label of gate `i`, or `None` if the circuit has no debug
+    /// metadata
+    pub fn gate_label(&self, i: u32) -> Option<String> {
+        self.debug_info
+            .get(i as usize)
+            .map(|info| info.label.clone())
+    }
+
+    /// Returns the first and last ciphertext blocks (inclusive) gate `i` depends on, or `None` if
+    /// the circuit has no debug metadata or the gate only depends on constants
+    pub fn gate_block_range(&self, i: u32) -> Option<Vec<u32>> {
+        self.debug_info
+            .get(i as usize)
+            .and_then(|info| info.block_range)
+            .map(|(first, last)| vec![first, last])
     }
 }
 
 /// Non-WASM methods for compiled circuit
 impl CompiledCircuit {
+    /// Deserializes a compiled circuit from bytes without panicking on malformed input.
+    ///
+    /// # Arguments
+    /// * `bytes` - The serialized circuit bytes (canonical or legacy MessagePack encoding)
+    ///
+    /// # Returns
+    /// The decoded circuit or an error message
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<CompiledCircuit, String> {
+        decode_canonical_or_legacy(bytes)
+    }
+
+    /// Attaches debug metadata to the circuit. The block range of each gate is computed from the
+    /// circuit.
+    ///
+    /// # Arguments
+    /// * `labels` - Label of each gate
+    ///
+    /// # Returns
+    /// The circuit with its debug metadata
+    pub fn with_labels(mut self, labels: Vec<String>) -> CompiledCircuit {
+        if labels.len() != self.circuit.len() {
+            die("The number of labels does not match the number of gates in the circuit");
+        }
+
+        let mut ranges: Vec<Option<(u32, u32)>> = Vec::with_capacity(self.circuit.len());
+        for (i, gate) in self.circuit.iter().enumerate() {
+            let range = if gate.is_dummy() {
+                Some((i as u32, i as u32))
+            } else {
+                gate.sons
+                    .iter()
+                    .filter(|&&s| !is_constant_idx(s))
+                    .filter_map(|&s| ranges.get(s as usize).copied().flatten())
+                    .reduce(|(a, b), (c, d)| (a.min(c), b.max(d)))
+            };
+            ranges.push(range);
+        }
+
+        self.debug_info = labels
+            .into_iter()
+            .zip(ranges)
+            .map(|(label, block_range)| GateDebugInfo { label, block_range })
+            .collect();
+        self
+    }
+
+    /// Returns a copy of the circuit without debug metadata
+    pub fn without_debug_info(&self) -> CompiledCircuit {
+        CompiledCircuit {
+            debug_info: vec![],
+            ..self.clone()
+        }
+    }
+
+    /// Adds gates checking the bound key against a key commitment. The last gate is then true if
+    /// the last gate of the circuit is true and `SHA-256(key || salt)` is the commitment. The key
+    /// is the constant left to bind.
+    ///
+    /// # Arguments
+    /// * `key_commitment` - The commitment to the key
+    ///
+    /// # Returns
+    /// The circuit with the key check gates
+    pub fn with_key_check(mut self, key_commitment: &KeyCommitment) -> CompiledCircuit {
+        let key = match self.constants.iter().position(|c| c.is_none()) {
+            Some(i) => array_idx_to_constant_idx(i as u32),
+            None => die("The circuit has no key to check"),
+        };
+        let key_len = match Cipher::from_version(self.version) {
+            Some(cipher) => cipher.key_len(),
+            None => die("The instruction set version does not determine the key's length"),
+        };
+        // the key and the salt are hashed with a single compression
+        if key_len + key_commitment.salt.len() > 55 {
+            die("The salt of the key commitment is too long");
+        }
+
+        let n = self.constants.len() as u32;
+        self.constants.extend([
+            Some(key_commitment.salt.clone()), // salt
+            Some(
+                ((key_len + key_commitment.salt.len()) as u64)
+                    .to_be_bytes()
+                    .to_vec(),
+            ), // size of key || salt
+            Some(key_commitment.commitment.clone()), // key commitment
+            Some(vec![1]),                     // true
+        ]);
+
+        let last = self.circuit.len() as u32 - 1;
+        self.circuit.extend([
+            Gate {
+                opcode: 6,
+                sons: vec![key, array_idx_to_constant_idx(n)],
+            },
+            Gate {
+                opcode: 7,
+                sons: vec![last + 1, array_idx_to_constant_idx(n + 1)],
+            },
+            Gate {
+                opcode: 5,
+                sons: vec![last + 2, array_idx_to_constant_idx(n + 2)],
+            },
+            Gate {
+                opcode: 5,
+                sons: vec![last, last + 3, array_idx_to_constant_idx(n + 3)],
+            },
+        ]);
+
+        if self.debug_info.is_empty() {
+            return self;
+        }
+        let mut labels: Vec<String> = self.debug_info.drain(..).map(|i| i.label).collect();
+        labels.extend([
+            "key and salt".to_string(),
+            "SHA-256 compress key and salt (final)".to_string(),
+            "compare key with key commitment".to_string(),
+            "check key and result".to_string(),
+        ]);
+        self.with_labels(labels)
+    }
+
+    /// Returns the key commitment checked by this circuit if it is `base.with_key_check(..)`.
+    /// Only the number of constants and the sizes of the salt and commitment are checked, the
+    /// circuit must be compared to the one rebuilt from the commitment.
+    ///
+    /// # Arguments
+    /// * `base` - The circuit without key check
+    pub fn checked_key_commitment(&self, base: &CompiledCircuit) -> Option<KeyCommitment> {
+        let n = base.constants.len();
+        if self.constants.len() != n + 4 {
+            return None;
+        }
+        match (&self.constants[n], &self.constants[n + 2]) {
+            (Some(salt), Some(commitment)) if salt.len() == 16 && commitment.len() == 32 => {
+                Some(KeyCommitment {
+                    commitment: commitment.clone(),
+                    salt: salt.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+
     /// Binds constant values to the circuit. Completely replaces the old constants.
     ///
     /// # Arguments
@@ -161,19 +396,20 @@ impl CompiledCircuit {
     /// A new `CompiledCircuitWithConstants` instance with all constants bound
     pub fn bind_missing_constants(&self, constants: Vec<Vec<u8>>) -> CompiledCircuitWithConstants {
         let mut all_constants = Vec::with_capacity(self.constants.len());
-        let mut i = 0;
+        // the bound values are moved rather than copied since they include the key
+        let mut constants = constants.into_iter();
 
         for c in &self.constants {
             if let Some(val) = c {
                 all_constants.push(val.to_owned());
             } else {
-                if i >= constants.len() {
-                    die("Not enough constants to bind");
+                match constants.next() {
+                    Some(val) => all_constants.push(val),
+                    None => die("Not enough constants to bind"),
                 }
-                all_constants.push(constants[i].to_owned());
-                i += 1;
             }
         }
+        constants.for_each(|mut extra| extra.zeroize());
 
         self.bind_constants(all_constants)
     }
@@ -214,8 +450,8 @@ fn array_idx_to_constant_idx(array_idx: u32) -> u32 {
     CONSTANT_FLAG | array_idx
 }
 
-// converts constant index to array index
-fn constant_idx_to_array_idx(constant_idx: u32) -> usize {
+/// Converts a constant index (as found in the sons of a gate) to an index in the constants array
+pub fn constant_idx_to_array_idx(constant_idx: u32) -> usize {
     (CONSTANT_FLAG ^ constant_idx) as usize
 }
 
@@ -236,8 +472,7 @@ fn compile_basic_circuit_one_block(
     let mut circuit: Vec<Gate> = Vec::with_capacity(5);
 
     // dummy gates
-    circuit.push(Gate::dummy());
-    circuit.push(Gate::dummy());
+    circuit.extend([Gate::dummy(), Gate::dummy()]);
 
     // AES decryption gate
     circuit.push(Gate {
@@ -275,7 +510,15 @@ fn compile_basic_circuit_one_block(
         version: 0,
         block_size,
         num_blocks: 2, // iv + <64B block
+        debug_info: vec![],
     }
+    .with_labels(vec![
+        "IV".to_string(),
+        "ciphertext block 1".to_string(),
+        "AES decrypt block 1".to_string(),
+        "SHA-256 compress block 1 (final)".to_string(),
+        "compare hash with description".to_string(),
+    ])
 }
 
 /// Compiles a basic circuit for processing ciphertext. Once the key is bound, the circuit computes
@@ -293,7 +536,7 @@ pub fn compile_basic_circuit(ct_size: u32, description: &[u8]) -> CompiledCircui
     let pt_size = ct_size - 16; // remove the size of the iv
     let ct_blocks_number = 1  // iv
             + pt_size / block_size // number of blocks of the plaintext
-            + if pt_size % block_size == 0 { 0 } else { 1 }; // ceiling
+            + if pt_size.is_multiple_of(block_size) { 0 } else { 1 }; // ceiling
     if ct_blocks_number < 2 {
         die("The ciphertext's length should be at least 17 bytes (incl. IV)");
     }
@@ -397,6 +640,491 @@ pub fn compile_basic_circuit(ct_size: u32, description: &[u8]) -> CompiledCircui
         version: 0,
         block_size,
         num_blocks: ct_blocks_number,
+        debug_info: vec![],
+    }
+    .with_labels(basic_circuit_labels(ct_blocks_number))
+}
+
+/// Compiles the basic circuit for a file encrypted with the given cipher. AES-128 circuits are the
+/// ones of `compile_basic_circuit`, AES-256 circuits use instruction set version 1 and its
+/// `decrypt_block_256` instruction, and ChaCha20 circuits use instruction set version 2 and its
+/// `decrypt_block_chacha20` instruction. Since a ChaCha20 block is a whole 64 bytes circuit
+/// block, the counter increment gates of ChaCha20 circuits add 1 instead of 4. AES-GCM circuits
+/// are compiled by `compile_gcm_circuit` and use instruction set version 3.
+///
+/// # Arguments
+/// * `ct_size` - Size of the ciphertext (including IV!)
+/// * `description` - Description of the plaintext
+/// * `cipher` - Cipher used to encrypt the file
+///
+/// # Returns
+/// A `CompiledCircuit` configured for the given parameters
+#[wasm_bindgen]
+pub fn compile_basic_circuit_with_cipher(
+    ct_size: u32,
+    description: &[u8],
+    cipher: Cipher,
+) -> CompiledCircuit {
+    let (version, opcode) = match cipher {
+        Cipher::Aes128Ctr => return compile_basic_circuit(ct_size, description),
+        Cipher::Aes256Ctr => (1, 8),
+        Cipher::ChaCha20 => (2, 9),
+        Cipher::Aes128Gcm => return compile_gcm_circuit(ct_size, description),
+    };
+
+    let mut circuit = compile_basic_circuit(ct_size, description);
+    circuit.version = version;
+    for gate in circuit.circuit.iter_mut().filter(|g| g.opcode == 2) {
+        gate.opcode = opcode;
+    }
+    if cipher == Cipher::ChaCha20 {
+        // same width as the AES increment
+        let width = circuit.constants[0].as_ref().map_or(2, |c| c.len());
+        let mut increment = vec![0u8; width];
+        increment[width - 1] = 1;
+        circuit.constants[0] = Some(increment);
+        for info in circuit.debug_info.iter_mut() {
+            info.label = info.label.replacen("AES", "ChaCha20", 1);
+        }
+    }
+
+    circuit
+}
+
+// appends a gate and its label, returns the index of the gate
+fn push_gate(gates: &mut Vec<Gate>, labels: &mut Vec<String>, gate: Gate, label: String) -> u32 {
+    gates.push(gate);
+    labels.push(label);
+    gates.len() as u32 - 1
+}
+
+/// Compiles the basic circuit for a file encrypted with AES-128-GCM. The ciphertext is
+/// `J0 (16 bytes) || Enc_k(data) || tag (16 bytes)`. Besides decrypting and hashing the plaintext
+/// like the basic circuit, the circuit recomputes the tag from the ciphertext blocks with GHASH
+/// and compares it to the received one. The last gate is true if both the tag and the description
+/// match, and the tag comparison gate only depends on the key and the ciphertext, so a corrupted
+/// ciphertext can be shown without hashing the plaintext.
+///
+/// # Arguments
+/// * `ct_size` - Size of the ciphertext (including IV and tag!)
+/// * `description` - Description of the plaintext
+///
+/// # Returns
+/// A `CompiledCircuit` configured for the given parameters
+pub fn compile_gcm_circuit(ct_size: u32, description: &[u8]) -> CompiledCircuit {
+    let block_size = 64;
+    if ct_size < 33 {
+        die("The ciphertext's length should be at least 33 bytes (incl. IV and tag)");
+    }
+    let pt_size = ct_size - 32; // remove the size of J0 and of the tag
+    let ct_blocks_number = 1 + (pt_size + 16).div_ceil(block_size);
+    // number of blocks holding the encrypted plaintext, the last one may also hold the tag
+    let pt_blocks_number = pt_size.div_ceil(block_size);
+    let tail = pt_size % block_size;
+
+    let mut constants = vec![
+        Some(4u16.to_be_bytes().to_vec()),             // counter increment
+        Some(description.to_vec()),                    // description
+        Some((pt_size as u64).to_be_bytes().to_vec()), // size of the plaintext
+        None,                                          // key placeholder
+        Some(vec![1]),                                 // J0 increment and true
+        Some(vec![0u8; 16]),                           // zero block
+        Some([0u64, pt_size as u64 * 8].map(u64::to_be_bytes).concat()), // GHASH lengths block
+    ];
+    if tail != 0 {
+        constants.extend([
+            Some(0u64.to_be_bytes().to_vec()), // start of the last block
+            Some((tail as u64).to_be_bytes().to_vec()), // start of the tag
+            Some((tail as u64 + 16).to_be_bytes().to_vec()), // end of the tag
+        ]);
+    }
+    let c = array_idx_to_constant_idx;
+
+    let mut gates = vec![Gate::dummy(); ct_blocks_number as usize];
+    let mut labels = vec!["IV (J0)".to_string()];
+    labels.extend((1..ct_blocks_number).map(|k| format!("ciphertext block {}", k)));
+
+    // counter increment gates, the first counter is J0 + 1
+    let mut counters = vec![push_gate(
+        &mut gates,
+        &mut labels,
+        Gate {
+            opcode: 3,
+            sons: vec![0, c(4)],
+        },
+        "AES counter for block 1".to_string(),
+    )];
+    for k in 2..=pt_blocks_number {
+        let gate = Gate {
+            opcode: 3,
+            sons: vec![counters[counters.len() - 1], c(0)],
+        };
+        let label = format!("AES counter for block {}", k);
+        counters.push(push_gate(&mut gates, &mut labels, gate, label));
+    }
+
+    // encrypted plaintext blocks, the tag is cut from the last one
+    let mut blocks: Vec<u32> = (1..=pt_blocks_number).collect();
+    if tail != 0 {
+        let gate = Gate {
+            opcode: 11,
+            sons: vec![pt_blocks_number, c(7), c(8)],
+        };
+        let label = format!("ciphertext block {} without tag", pt_blocks_number);
+        blocks[pt_blocks_number as usize - 1] = push_gate(&mut gates, &mut labels, gate, label);
+    }
+
+    // AES decryption gates
+    let mut pt_blocks = Vec::with_capacity(pt_blocks_number as usize);
+    for k in 1..=pt_blocks_number as usize {
+        let gate = Gate {
+            opcode: 2,
+            sons: vec![c(3), blocks[k - 1], counters[k - 1]],
+        };
+        let label = format!("AES decrypt block {}", k);
+        pt_blocks.push(push_gate(&mut gates, &mut labels, gate, label));
+    }
+
+    // GHASH gates, H = E_k(0^128)
+    let h = push_gate(
+        &mut gates,
+        &mut labels,
+        Gate {
+            opcode: 1,
+            sons: vec![c(3), c(5), c(5)],
+        },
+        "GHASH key".to_string(),
+    );
+    let mut ghash = push_gate(
+        &mut gates,
+        &mut labels,
+        Gate {
+            opcode: 10,
+            sons: vec![h, blocks[0]],
+        },
+        "GHASH block 1".to_string(),
+    );
+    for k in 2..=pt_blocks_number as usize {
+        let gate = Gate {
+            opcode: 10,
+            sons: vec![h, ghash, blocks[k - 1]],
+        };
+        ghash = push_gate(&mut gates, &mut labels, gate, format!("GHASH block {}", k));
+    }
+    ghash = push_gate(
+        &mut gates,
+        &mut labels,
+        Gate {
+            opcode: 10,
+            sons: vec![h, ghash, c(6)],
+        },
+        "GHASH lengths".to_string(),
+    );
+
+    // tag gates, the expected tag is E_k(J0) xor GHASH
+    let expected_tag = push_gate(
+        &mut gates,
+        &mut labels,
+        Gate {
+            opcode: 1,
+            sons: vec![c(3), ghash, 0],
+        },
+        "expected tag".to_string(),
+    );
+    let tag = if tail == 0 {
+        // the tag is the last ciphertext block
+        ct_blocks_number - 1
+    } else {
+        let mut tag_block = pt_blocks_number;
+        if ct_blocks_number > pt_blocks_number + 1 {
+            // the tag is split between the last two ciphertext blocks
+            let gate = Gate {
+                opcode: 6,
+                sons: vec![pt_blocks_number, pt_blocks_number + 1],
+            };
+            let label = format!(
+                "ciphertext blocks {} and {}",
+                pt_blocks_number,
+                pt_blocks_number + 1
+            );
+            tag_block = push_gate(&mut gates, &mut labels, gate, label);
+        }
+        let gate = Gate {
+            opcode: 11,
+            sons: vec![tag_block, c(8), c(9)],
+        };
+        push_gate(&mut gates, &mut labels, gate, "received tag".to_string())
+    };
+    let tag_check = push_gate(
+        &mut gates,
+        &mut labels,
+        Gate {
+            opcode: 5,
+            sons: vec![expected_tag, tag],
+        },
+        "compare tag with received tag".to_string(),
+    );
+
+    // SHA256 compression gates, the last one does the padding as well
+    let mut hash = None;
+    for k in 1..pt_blocks_number as usize {
+        let sons = match hash {
+            Some(prev) => vec![prev, pt_blocks[k - 1]],
+            None => vec![pt_blocks[k - 1]],
+        };
+        let label = format!("SHA-256 compress block {}", k);
+        hash = Some(push_gate(
+            &mut gates,
+            &mut labels,
+            Gate { opcode: 0, sons },
+            label,
+        ));
+    }
+    let mut sons: Vec<u32> = hash.into_iter().collect();
+    sons.extend([pt_blocks[pt_blocks.len() - 1], c(2)]);
+    let label = format!("SHA-256 compress block {} (final)", pt_blocks_number);
+    let hash = push_gate(&mut gates, &mut labels, Gate { opcode: 7, sons }, label);
+
+    // comparison gates
+    let description_check = push_gate(
+        &mut gates,
+        &mut labels,
+        Gate {
+            opcode: 5,
+            sons: vec![hash, c(1)],
+        },
+        "compare hash with description".to_string(),
+    );
+    push_gate(
+        &mut gates,
+        &mut labels,
+        Gate {
+            opcode: 5,
+            sons: vec![description_check, tag_check, c(4)],
+        },
+        "check tag and description".to_string(),
+    );
+
+    CompiledCircuit {
+        circuit: gates,
+        constants,
+        version: 3,
+        block_size,
+        num_blocks: ct_blocks_number,
+        debug_info: vec![],
+    }
+    .with_labels(labels)
+}
+
+// labels of the gates of the basic circuit with m > 2 ciphertext blocks, following the order in
+// which `compile_basic_circuit` creates them. Block numbers are ciphertext block numbers, the IV
+// being block 0.
+fn basic_circuit_labels(m: u32) -> Vec<String> {
+    let mut labels = Vec::with_capacity((4 * m - 3) as usize);
+    labels.push("IV".to_string());
+    labels.extend((1..m).map(|k| format!("ciphertext block {}", k)));
+    labels.extend((2..m).map(|k| format!("AES counter for block {}", k)));
+    labels.extend((1..m).map(|k| format!("AES decrypt block {}", k)));
+    labels.extend((1..m - 1).map(|k| format!("SHA-256 compress block {}", k)));
+    labels.push(format!("SHA-256 compress block {} (final)", m - 1));
+    labels.push("compare hash with description".to_string());
+
+    labels
+}
+
+// ============================= SLICING =============================
+
+/// Everything a gate transitively depends on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cone {
+    /// Indices of the gates (ciphertext blocks excluded), in increasing order. The gate the cone
+    /// was computed for is the last one, unless it is a ciphertext block.
+    pub gates: Vec<u32>,
+
+    /// Indices of the ciphertext blocks (i.e. of the dummy gates), in increasing order
+    pub blocks: Vec<u32>,
+
+    /// Indices in the constants array, in increasing order
+    pub constants: Vec<u32>,
+}
+
+/// A sub-circuit computing a single gate of a larger circuit. It can be evaluated on its own
+/// with `evaluate_circuit_internal`, using the ciphertext blocks given by `blocks` as input.
+#[derive(Clone)]
+pub struct SlicedCircuit {
+    /// The sub-circuit. Its constants are the ones of the original circuit, so that they can be
+    /// bound the same way.
+    pub circuit: CompiledCircuit,
+
+    /// Index in the original circuit of each gate of the sub-circuit
+    pub gate_map: Vec<u32>,
+
+    /// Indices in the original circuit of the ciphertext blocks used as input
+    pub blocks: Vec<u32>,
+}
+
+impl SlicedCircuit {
+    /// Selects the input of the sub-circuit among the ciphertext blocks of the original circuit
+    ///
+    /// # Arguments
+    /// * `ct_blocks` - All the ciphertext blocks
+    ///
+    /// # Returns
+    /// The ciphertext blocks used by the sub-circuit, in order
+    pub fn inputs(&self, ct_blocks: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        self.blocks
+            .iter()
+            .map(|&b| ct_blocks[b as usize].clone())
+            .collect()
+    }
+}
+
+impl CompiledCircuit {
+    // marks the gates `gate` transitively depends on (itself included)
+    fn cone_marks(&self, gate: u32) -> Vec<bool> {
+        if gate as usize >= self.circuit.len() {
+            die(&format!("Gate {} is not in the circuit", gate));
+        }
+
+        // sons always come before their parent, so a single backward pass is enough
+        let mut marks = vec![false; gate as usize + 1];
+        marks[gate as usize] = true;
+        for i in (0..=gate as usize).rev() {
+            if !marks[i] {
+                continue;
+            }
+            for &s in &self.circuit[i].sons {
+                if !is_constant_idx(s) {
+                    marks[s as usize] = true;
+                }
+            }
+        }
+
+        marks
+    }
+
+    /// Computes the gates, ciphertext blocks and constants a gate transitively depends on
+    ///
+    /// # Arguments
+    /// * `gate` - Index of the gate
+    ///
+    /// # Returns
+    /// The `Cone` of the gate
+    pub fn cone(&self, gate: u32) -> Cone {
+        let marks = self.cone_marks(gate);
+        let (blocks, gates): (Vec<u32>, Vec<u32>) = (0..marks.len() as u32)
+            .filter(|&i| marks[i as usize])
+            .partition(|&i| self.circuit[i as usize].is_dummy());
+
+        let mut constants: Vec<u32> = gates
+            .iter()
+            .flat_map(|&i| &self.circuit[i as usize].sons)
+            .filter(|&&s| is_constant_idx(s))
+            .map(|&s| constant_idx_to_array_idx(s) as u32)
+            .collect();
+        constants.sort_unstable();
+        constants.dedup();
+
+        Cone {
+            gates,
+            blocks,
+            constants,
+        }
+    }
+
+    /// Computes the gates whose value depends on a ciphertext block
+    ///
+    /// # Arguments
+    /// * `block` - Index of the ciphertext block
+    ///
+    /// # Returns
+    /// The indices of the affected gates, in increasing order
+    pub fn affected_by_block(&self, block: u32) -> Vec<u32> {
+        if block >= self.num_blocks {
+            die(&format!("Block {} is not in the circuit", block));
+        }
+
+        let mut marks = vec![false; self.circuit.len()];
+        marks[block as usize] = true;
+        let mut affected = Vec::new();
+        for (i, gate) in self
+            .circuit
+            .iter()
+            .enumerate()
+            .skip(self.num_blocks as usize)
+        {
+            if gate
+                .sons
+                .iter()
+                .any(|&s| !is_constant_idx(s) && marks[s as usize])
+            {
+                marks[i] = true;
+                affected.push(i as u32);
+            }
+        }
+
+        affected
+    }
+
+    /// Extracts the sub-circuit computing a gate. The value of the gate is the last value of the
+    /// evaluated sub-circuit.
+    ///
+    /// # Arguments
+    /// * `gate` - Index of the gate
+    ///
+    /// # Returns
+    /// The `SlicedCircuit` of the gate
+    pub fn slice(&self, gate: u32) -> SlicedCircuit {
+        let cone = self.cone(gate);
+
+        let mut new_idx = vec![0u32; gate as usize + 1];
+        let mut gate_map = Vec::with_capacity(cone.blocks.len() + cone.gates.len());
+        for &i in cone.blocks.iter().chain(&cone.gates) {
+            new_idx[i as usize] = gate_map.len() as u32;
+            gate_map.push(i);
+        }
+
+        let circuit = gate_map
+            .iter()
+            .map(|&i| {
+                let gate = &self.circuit[i as usize];
+                Gate {
+                    opcode: gate.opcode,
+                    sons: gate
+                        .sons
+                        .iter()
+                        .map(|&s| {
+                            if is_constant_idx(s) {
+                                s
+                            } else {
+                                new_idx[s as usize]
+                            }
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        let debug_info = if self.debug_info.is_empty() {
+            vec![]
+        } else {
+            gate_map
+                .iter()
+                .map(|&i| self.debug_info[i as usize].clone())
+                .collect()
+        };
+
+        SlicedCircuit {
+            circuit: CompiledCircuit {
+                circuit,
+                constants: self.constants.clone(),
+                version: self.version,
+                block_size: self.block_size,
+                num_blocks: cone.blocks.len() as u32,
+                debug_info,
+            },
+            gate_map,
+            blocks: cone.blocks,
+        }
     }
 }
 
@@ -420,6 +1148,23 @@ pub struct CompiledCircuitWithConstants {
     pub block_size: u32,
 }
 
+impl CompiledCircuitWithConstants {
+    /// Takes the bound constants out of the circuit without copying them
+    ///
+    /// # Returns
+    /// The constant values, the key included
+    pub fn into_constants(mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.constants)
+    }
+}
+
+// the bound constants include the key
+impl Drop for CompiledCircuitWithConstants {
+    fn drop(&mut self) {
+        self.constants.zeroize();
+    }
+}
+
 /// Retrieves the evaluated values for a gate's sons
 ///
 /// # Arguments
@@ -431,8 +1176,8 @@ pub struct CompiledCircuitWithConstants {
 /// Vector of references to the evaluated values for the gate's sons
 pub fn get_evaluated_sons<'a>(
     gate: &Gate,
-    evaluated_circuit: &'a Vec<Vec<u8>>,
-    constants: &'a Vec<Vec<u8>>,
+    evaluated_circuit: &'a [Vec<u8>],
+    constants: &'a [Vec<u8>],
 ) -> Vec<&'a Vec<u8>> {
     let mut sons = Vec::with_capacity(gate.sons.len());
 
@@ -450,6 +1195,33 @@ pub fn get_evaluated_sons<'a>(
     sons
 }
 
+/// Evaluates a single gate on the values of its sons, as done by `CircuitEvaluator.evaluateGate` in
+/// the smart contracts.
+///
+/// # Arguments
+/// * `gate` - The gate to evaluate
+/// * `values` - Values of the gate's sons, in the same order as the sons
+/// * `version` - Version of the instruction set
+///
+/// # Returns
+/// The gate's value, or an error message in the cases where the smart contract would revert
+pub fn evaluate_gate(gate: &Gate, values: &[Vec<u8>], version: u32) -> Result<Vec<u8>, String> {
+    let instructions = version_instructions(version as usize);
+    if instructions.is_empty() {
+        return Err("Invalid version number".to_string());
+    }
+    if values.len() != gate.sons.len() {
+        return Err("Values doesn't have the required length".to_string());
+    }
+    let op = match instructions.get(gate.opcode as usize) {
+        Some(op) => op,
+        None => return Err(format!("Invalid opcode {}", gate.opcode)),
+    };
+
+    let refs: Vec<&Vec<u8>> = values.iter().collect();
+    Ok(op(&refs))
+}
+
 /// Evaluates a circuit with the given input and constants
 ///
 /// # Arguments
@@ -460,17 +1232,17 @@ pub fn get_evaluated_sons<'a>(
 /// Vector of evaluated values for each gate in the circuit
 pub fn evaluate_circuit_internal(
     input: &[Vec<u8>],
-    compiled_circuit: CompiledCircuitWithConstants,
+    compiled_circuit: &CompiledCircuitWithConstants,
 ) -> Vec<Vec<u8>> {
     let instructions = version_instructions(compiled_circuit.version as usize);
 
     let mut evaluated_circuit: Vec<Vec<u8>> = Vec::with_capacity(compiled_circuit.circuit.len());
 
-    for i in 0..input.len() {
+    for (i, block) in input.iter().enumerate() {
         if !compiled_circuit.circuit[i].is_dummy() {
             die(&format!("The ciphertext is too large, the number of blocks for the cipher text in this circuit should be {}", i));
         }
-        evaluated_circuit.push(input[i].clone());
+        evaluated_circuit.push(block.clone());
     }
 
     for gate in &compiled_circuit.circuit[input.len()..] {
@@ -486,3 +1258,175 @@ pub fn evaluate_circuit_internal(
 
     evaluated_circuit
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::split_ct_blocks;
+    use rand::RngCore;
+
+    fn setup(ct_size: usize) -> (CompiledCircuit, Vec<Vec<u8>>, Vec<u8>) {
+        let mut rng = rand::rng();
+        let mut ct = vec![0u8; ct_size];
+        rng.fill_bytes(&mut ct);
+        let mut key = vec![0u8; 16];
+        rng.fill_bytes(&mut key);
+
+        let circuit = compile_basic_circuit(ct_size as u32, &[0u8; 32]);
+        let ct_blocks = split_ct_blocks(&ct, circuit.block_size as usize);
+        (circuit, ct_blocks, key)
+    }
+
+    #[test]
+    fn test_cone() {
+        let (circuit, _, _) = setup(1000);
+        let n = circuit.circuit.len() as u32;
+        let m = circuit.num_blocks;
+
+        // the last gate depends on everything
+        let cone = circuit.cone(n - 1);
+        assert_eq!((0..m).collect::<Vec<_>>(), cone.blocks);
+        assert_eq!((m..n).collect::<Vec<_>>(), cone.gates);
+        assert_eq!(vec![0, 1, 2, 3], cone.constants);
+
+        // a ciphertext block only depends on itself
+        let cone = circuit.cone(3);
+        assert_eq!(vec![3], cone.blocks);
+        assert!(cone.gates.is_empty() && cone.constants.is_empty());
+
+        // the decryption of a block depends on the block, the IV, the counters and the key
+        for i in m..n {
+            if circuit.gate_label(i).unwrap().starts_with("AES decrypt") {
+                let k = circuit.gate_block_range(i).unwrap()[1];
+                let cone = circuit.cone(i);
+                assert_eq!(vec![0, k], cone.blocks);
+                assert_eq!(Some(&i), cone.gates.last());
+                assert!(cone.constants.contains(&3));
+            }
+        }
+    }
+
+    #[test]
+    fn test_affected_by_block() {
+        let (circuit, _, _) = setup(500);
+        let n = circuit.circuit.len() as u32;
+
+        // the IV affects every gate
+        assert_eq!(
+            (circuit.num_blocks..n).collect::<Vec<_>>(),
+            circuit.affected_by_block(0)
+        );
+
+        // inverse of the cone
+        for b in 0..circuit.num_blocks {
+            let affected = circuit.affected_by_block(b);
+            for g in circuit.num_blocks..n {
+                assert_eq!(
+                    affected.contains(&g),
+                    circuit.cone(g).blocks.contains(&b),
+                    "block {}, gate {}",
+                    b,
+                    g
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_slice() {
+        for ct_size in [17, 200, 1000] {
+            let (circuit, ct_blocks, key) = setup(ct_size);
+            let values = evaluate_circuit_internal(
+                &ct_blocks,
+                &circuit.bind_missing_constants(vec![key.clone()]),
+            );
+
+            for g in circuit.num_blocks..circuit.circuit.len() as u32 {
+                let sliced = circuit.slice(g);
+                assert_eq!(
+                    circuit.cone(g).gates.len() + sliced.blocks.len(),
+                    sliced.gate_map.len()
+                );
+                assert_eq!(Some(&g), sliced.gate_map.last());
+
+                let sliced_values = evaluate_circuit_internal(
+                    &sliced.inputs(&ct_blocks),
+                    &sliced.circuit.bind_missing_constants(vec![key.clone()]),
+                );
+                assert_eq!(values[g as usize], *sliced_values.last().unwrap());
+                for (j, &i) in sliced.gate_map.iter().enumerate() {
+                    assert_eq!(values[i as usize], sliced_values[j]);
+                    assert_eq!(circuit.gate_label(i), sliced.circuit.gate_label(j as u32));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gcm_circuit() {
+        let mut rng = rand::rng();
+        let gate = |circuit: &CompiledCircuit, label: &str| {
+            (0..circuit.circuit.len() as u32)
+                .find(|&i| circuit.gate_label(i).unwrap() == label)
+                .unwrap() as usize
+        };
+
+        // the tag is alone in its block, shares the last block or is split between two blocks
+        for pt_size in [1, 16, 48, 49, 63, 64, 100, 128, 1000] {
+            let mut data = vec![0u8; pt_size];
+            rng.fill_bytes(&mut data);
+            let description = sha256::sha256(&data);
+            let mut key = vec![0u8; 16];
+            rng.fill_bytes(&mut key);
+            let mut ct = crate::encryption::encrypt_and_prepend_iv(
+                &mut data,
+                &key,
+                Cipher::Aes128Gcm,
+                &mut rng,
+            );
+
+            let circuit =
+                compile_basic_circuit_with_cipher(ct.len() as u32, &description, Cipher::Aes128Gcm);
+            assert_eq!(3, circuit.version);
+            let tag_check = gate(&circuit, "compare tag with received tag");
+            let description_check = gate(&circuit, "compare hash with description");
+
+            // the tag check does not depend on the SHA-256 gates
+            let cone = circuit.cone(tag_check as u32);
+            assert!(cone.gates.iter().all(|&g| {
+                let opcode = circuit.circuit[g as usize].opcode;
+                opcode != 0 && opcode != 7
+            }));
+
+            let evaluate = |ct: &[u8], key: &[u8]| {
+                evaluate_circuit_internal(
+                    &split_ct_blocks(ct, circuit.block_size as usize),
+                    &circuit.bind_missing_constants(vec![key.to_vec()]),
+                )
+            };
+            let values = evaluate(&ct, &key);
+            assert_eq!(vec![1u8], values[tag_check]);
+            assert_eq!(vec![1u8], *values.last().unwrap());
+
+            // a corrupted tag is caught by the tag check only
+            let last = ct.len() - 1;
+            ct[last] ^= 1;
+            let values = evaluate(&ct, &key);
+            assert_eq!(vec![0u8], values[tag_check]);
+            assert_eq!(vec![1u8], values[description_check]);
+            assert_eq!(vec![0u8], *values.last().unwrap());
+            ct[last] ^= 1;
+
+            // a corrupted ciphertext block is caught by both
+            ct[16] ^= 1;
+            let values = evaluate(&ct, &key);
+            assert_eq!(vec![0u8], values[tag_check]);
+            assert_eq!(vec![0u8], values[description_check]);
+            ct[16] ^= 1;
+
+            let mut wrong_key = key.clone();
+            wrong_key[0] ^= 1;
+            assert_eq!(vec![0u8], *evaluate(&ct, &wrong_key).last().unwrap());
+        }
+    }
+}
@@ -1,6 +1,9 @@
-use rand::RngCore;
+use crate::random::RandomSource;
+use crate::secret::ct_eq;
+use crate::sha256::sha256;
 use sha3::{Digest, Keccak256};
 use wasm_bindgen::prelude::wasm_bindgen;
+use zeroize::Zeroizing;
 
 /// Represents a commitment with its commitment value and opening value
 #[wasm_bindgen]
@@ -24,7 +27,18 @@ pub struct Commitment {
 /// A `Commitment` containing the commitment hash and opening value
 #[wasm_bindgen]
 pub fn commit(data: &[u8]) -> Commitment {
-    let mut rng = rand::rng();
+    commit_with_rng(data, &mut rand::rng())
+}
+
+/// Creates a commitment for the given data, drawing the random bytes from `rng`
+///
+/// # Arguments
+/// * `data` - Data to commit to
+/// * `rng` - Source of the random bytes
+///
+/// # Returns
+/// A `Commitment` containing the commitment hash and opening value
+pub fn commit_with_rng(data: &[u8], rng: &mut RandomSource) -> Commitment {
     let mut r = [0u8; 16];
     rng.fill_bytes(&mut r);
     let opening_value = [data, &r].concat();
@@ -43,11 +57,68 @@ pub fn commit(data: &[u8]) -> Commitment {
 /// # Arguments
 /// * `h_circuit` - Hash of the circuit
 /// * `h_ct` - Hash of the ciphertext
+/// * `rng` - Source of the random bytes
 ///
 /// # Returns
 /// A `Commitment` containing the combined commitment hash and opening value
-pub fn commit_hashes(h_circuit: &[u8], h_ct: &[u8]) -> Commitment {
-    commit(&[h_circuit, h_ct].concat())
+pub fn commit_hashes(h_circuit: &[u8], h_ct: &[u8], rng: &mut RandomSource) -> Commitment {
+    commit_with_rng(&[h_circuit, h_ct].concat(), rng)
+}
+
+/// Commitment to the key of a precontract: `SHA-256(key || salt)`. The salt is sent with the
+/// precontract since the key is revealed alone by `sendKey`, so the commitment hides the key
+/// because the key is random, and the salt makes the commitments of different precontracts
+/// unrelated. SHA-256 lets the basic circuit check the bound key against the commitment.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyCommitment {
+    /// The commitment value (32 bytes)
+    #[wasm_bindgen(getter_with_clone)]
+    pub commitment: Vec<u8>,
+
+    /// The salt (16 bytes)
+    #[wasm_bindgen(getter_with_clone)]
+    pub salt: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl KeyCommitment {
+    /// Creates a key commitment from the values sent with a precontract
+    #[wasm_bindgen(constructor)]
+    pub fn new(commitment: Vec<u8>, salt: Vec<u8>) -> KeyCommitment {
+        KeyCommitment { commitment, salt }
+    }
+
+    /// Checks a key against the commitment
+    ///
+    /// # Arguments
+    /// * `key` - The key revealed by the vendor
+    ///
+    /// # Returns
+    /// `true` if the commitment is a commitment to `key`
+    pub fn verify(&self, key: &[u8]) -> bool {
+        let preimage = Zeroizing::new([key, &self.salt].concat());
+        ct_eq(&sha256(&preimage), &self.commitment)
+    }
+}
+
+/// Commits to a key with a salt drawn from `rng`
+///
+/// # Arguments
+/// * `key` - The key
+/// * `rng` - Source of the salt
+///
+/// # Returns
+/// The `KeyCommitment` to send with the precontract
+pub fn commit_key_with_rng(key: &[u8], rng: &mut RandomSource) -> KeyCommitment {
+    let mut salt = vec![0u8; 16];
+    rng.fill_bytes(&mut salt);
+    let preimage = Zeroizing::new([key, &salt].concat());
+
+    KeyCommitment {
+        commitment: sha256(&preimage),
+        salt,
+    }
 }
 
 /// Verifies and opens a commitment using its opening value
@@ -60,12 +131,12 @@ pub fn commit_hashes(h_circuit: &[u8], h_ct: &[u8]) -> Commitment {
 /// * `Ok(Vec<u8>)` - The original committed data if verification succeeds
 /// * `Err(&str)` - Error message if verification fails
 pub fn open_commitment_internal(
-    commitment: &Vec<u8>,
-    opening_value: &Vec<u8>,
+    commitment: &[u8],
+    opening_value: &[u8],
 ) -> Result<Vec<u8>, &'static str> {
     let mut hasher = Keccak256::new();
-    hasher.update(&opening_value);
-    if !commitment.eq(&hasher.finalize().to_vec()) {
+    hasher.update(opening_value);
+    if !ct_eq(commitment, &hasher.finalize()) {
         return Err("The commitments do not match");
     }
 
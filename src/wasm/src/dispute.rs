@@ -0,0 +1,934 @@
+use crate::accumulator::{
+    acc, acc_leaf_hashes, leaf_hash, prove, prove_ext, prove_ext_leaf_hashes, prove_leaf_hashes,
+    verify, verify_ext,
+};
+use crate::circuits::{evaluate_gate, get_evaluated_sons, is_constant_idx, CompiledCircuit, Gate};
+use crate::commitment::open_commitment_internal;
+use crate::trace::TraceSource;
+use crate::utils::die;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Data sent by the vendor for steps 8a and 8b of the dispute. Native counterpart of
+/// `FinalStepComponents`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FinalStepProofs {
+    /// The challenged gate
+    pub gate: Gate,
+
+    /// Values of the gate's sons (including the constants)
+    pub values: Vec<Vec<u8>>,
+
+    /// Current accumulator value (w_i)
+    pub curr_acc: Vec<u8>,
+
+    /// Proof of the gate against h_circuit
+    pub proof1: Vec<Vec<Vec<u8>>>,
+
+    /// Proof of the sons that are ciphertext blocks against h_ct
+    pub proof2: Vec<Vec<Vec<u8>>>,
+
+    /// Proof of the other sons against the buyer's previous response (empty for step 8b)
+    pub proof3: Vec<Vec<Vec<u8>>>,
+
+    /// Extension proof
+    pub proof_ext: Vec<Vec<Vec<u8>>>,
+}
+
+/// Computes the answer to a challenge from the values of an evaluated circuit
+///
+/// # Arguments
+/// * `values` - Values of the evaluated circuit (ciphertext blocks followed by the gates' values)
+/// * `num_blocks` - Number of blocks for the ciphertext
+/// * `challenge` - Challenge issued by the smart contract
+///
+/// # Returns
+/// The accumulator value of the gates' values up to the challenge (included)
+pub fn hpre_internal(values: &[Vec<u8>], num_blocks: usize, challenge: usize) -> Vec<u8> {
+    acc(&values[num_blocks..=challenge])
+}
+
+/// Computes the answers to several challenges at once, as done by `hpre_internal`
+///
+/// # Arguments
+/// * `values` - Values of the evaluated circuit (ciphertext blocks followed by the gates' values)
+/// * `num_blocks` - Number of blocks for the ciphertext
+/// * `challenges` - Challenges issued in the current round
+///
+/// # Returns
+/// The accumulator values of the gates' values up to each challenge (included)
+pub fn hpre_multi_internal(
+    values: &[Vec<u8>],
+    num_blocks: usize,
+    challenges: &[u32],
+) -> Vec<Vec<u8>> {
+    challenges
+        .iter()
+        .map(|&c| hpre_internal(values, num_blocks, c as usize))
+        .collect()
+}
+
+/// Computes the answers to several challenges from any trace, as done by `hpre_multi_internal`.
+/// The leaf hashes are computed once, up to the last challenge.
+///
+/// # Arguments
+/// * `trace` - The trace, e.g. an `EvaluatedCircuit` or a `LazyTrace`
+/// * `num_blocks` - Number of blocks for the ciphertext
+/// * `challenges` - Challenges issued in the current round
+///
+/// # Returns
+/// The accumulator values of the gates' values up to each challenge (included)
+pub fn hpre_multi_trace<T: TraceSource + ?Sized>(
+    trace: &T,
+    num_blocks: u32,
+    challenges: &[u32],
+) -> Vec<Vec<u8>> {
+    let end = match challenges.iter().max() {
+        Some(&end) => end,
+        None => return vec![],
+    };
+    let hashes = prefix_leaf_hashes(trace, num_blocks, end);
+    challenges
+        .iter()
+        .map(|&c| {
+            if c < num_blocks {
+                die(&format!("Gate {} is not a challengeable gate", c));
+            }
+            acc_leaf_hashes(hashes[..=(c - num_blocks) as usize].to_vec())
+        })
+        .collect()
+}
+
+// leaf hashes of the gates' values from the first gate after the ciphertext blocks up to `end`
+fn prefix_leaf_hashes<T: TraceSource + ?Sized>(
+    trace: &T,
+    num_blocks: u32,
+    end: u32,
+) -> Vec<Vec<u8>> {
+    if end < num_blocks || end >= trace.num_values() {
+        die(&format!("Gate {} is not a challengeable gate", end));
+    }
+    trace
+        .leaf_hashes(num_blocks, end)
+        .unwrap_or_else(|| die("The trace does not hold the values up to the challenge"))
+}
+
+// values of the sons of the gate
+fn sons_values<T: TraceSource + ?Sized>(trace: &T, gate: &Gate) -> Vec<Vec<u8>> {
+    trace
+        .sons(gate)
+        .unwrap_or_else(|| die("The trace does not hold the values of the gate's sons"))
+}
+
+/// Splits the sons according to the paper's set L. Constant indices are not kept.
+///
+/// # Arguments
+/// * `sons` - The gate's sons
+/// * `num_blocks` - Number of blocks for the ciphertext
+///
+/// # Returns
+/// The sons that are ciphertext blocks and the other sons minus `num_blocks`
+pub fn split_sons_indices(sons: &[u32], num_blocks: u32) -> (Vec<u32>, Vec<u32>) {
+    let mut in_l = Vec::new();
+    let mut not_in_l_minus_m = Vec::new();
+
+    for &s in sons {
+        if is_constant_idx(s) {
+            continue;
+        }
+        if s < num_blocks {
+            // strictly inferior because we start counting from 0
+            in_l.push(s)
+        } else {
+            not_in_l_minus_m.push(s - num_blocks)
+        }
+    }
+
+    (in_l, not_in_l_minus_m)
+}
+
+/// Computes the proofs for step 8a.
+///
+/// # Arguments
+/// * `circuit` - The compiled circuit
+/// * `values` - Values of the evaluated circuit
+/// * `constants` - Constants of the evaluated circuit
+/// * `ct_blocks` - Blocks of the ciphertext
+/// * `challenge` - Challenge point in the circuit
+///
+/// # Returns
+/// The `FinalStepProofs` for the challenged gate
+pub fn compute_proofs_internal(
+    circuit: &CompiledCircuit,
+    values: &[Vec<u8>],
+    constants: &[Vec<u8>],
+    ct_blocks: &[Vec<u8>],
+    challenge: u32,
+) -> FinalStepProofs {
+    let num_blocks = ct_blocks.len();
+    let gate = circuit.circuit[challenge as usize].clone();
+    let (s_in_l, not_in_l_minus_m) = split_sons_indices(&gate.sons, num_blocks as u32);
+
+    let sons_values = get_evaluated_sons(&gate, values, constants);
+    FinalStepProofs {
+        values: sons_values.into_iter().cloned().collect(),
+        curr_acc: acc(&values[num_blocks..=(challenge as usize)]),
+        proof1: prove(&circuit.to_abi_encoded(), &[challenge]),
+        proof2: prove(ct_blocks, &s_in_l),
+        proof3: prove(&values[num_blocks..(challenge as usize)], &not_in_l_minus_m),
+        proof_ext: prove_ext(&values[num_blocks..=(challenge as usize)]),
+        gate,
+    }
+}
+
+/// Computes the proofs for step 8b.
+///
+/// # Arguments
+/// * `circuit` - The compiled circuit
+/// * `values` - Values of the evaluated circuit
+/// * `constants` - Constants of the evaluated circuit
+/// * `ct_blocks` - Blocks of the ciphertext
+/// * `challenge` - Challenge point in the circuit
+///
+/// # Returns
+/// The `FinalStepProofs` for the challenged gate, with an empty `proof3`
+pub fn compute_proofs_left_internal(
+    circuit: &CompiledCircuit,
+    values: &[Vec<u8>],
+    constants: &[Vec<u8>],
+    ct_blocks: &[Vec<u8>],
+    challenge: u32,
+) -> FinalStepProofs {
+    let num_blocks = ct_blocks.len();
+    let gate = circuit.circuit[challenge as usize].clone();
+    let non_constant_sons: Vec<u32> = gate
+        .sons
+        .iter()
+        .copied()
+        .filter(|&x| !is_constant_idx(x))
+        .collect();
+
+    let sons_values = get_evaluated_sons(&gate, values, constants);
+    FinalStepProofs {
+        values: sons_values.into_iter().cloned().collect(),
+        curr_acc: acc(&values[num_blocks..=(challenge as usize)]),
+        proof1: prove(&circuit.to_abi_encoded(), &[challenge]),
+        proof2: prove(ct_blocks, &non_constant_sons),
+        proof3: vec![],
+        proof_ext: prove_ext(&[values[num_blocks].clone()]),
+        gate,
+    }
+}
+
+/// Computes the proofs for step 8a from any trace, as done by `compute_proofs_internal`
+///
+/// # Arguments
+/// * `circuit` - The compiled circuit
+/// * `trace` - The trace, e.g. an `EvaluatedCircuit` or a `LazyTrace`
+/// * `ct_blocks` - Blocks of the ciphertext
+/// * `challenge` - Challenge point in the circuit
+///
+/// # Returns
+/// The `FinalStepProofs` for the challenged gate
+pub fn compute_proofs_trace<T: TraceSource + ?Sized>(
+    circuit: &CompiledCircuit,
+    trace: &T,
+    ct_blocks: &[Vec<u8>],
+    challenge: u32,
+) -> FinalStepProofs {
+    let m = ct_blocks.len() as u32;
+    let gate = circuit.circuit[challenge as usize].clone();
+    let (s_in_l, not_in_l_minus_m) = split_sons_indices(&gate.sons, m);
+
+    let hashes = prefix_leaf_hashes(trace, m, challenge);
+    let prev = hashes[..hashes.len() - 1].to_vec();
+    FinalStepProofs {
+        values: sons_values(trace, &gate),
+        curr_acc: acc_leaf_hashes(hashes.clone()),
+        proof1: prove(&circuit.to_abi_encoded(), &[challenge]),
+        proof2: prove(ct_blocks, &s_in_l),
+        proof3: prove_leaf_hashes(prev, &not_in_l_minus_m),
+        proof_ext: prove_ext_leaf_hashes(hashes),
+        gate,
+    }
+}
+
+/// Computes the proofs for step 8b from any trace, as done by `compute_proofs_left_internal`
+///
+/// # Arguments
+/// * `circuit` - The compiled circuit
+/// * `trace` - The trace, e.g. an `EvaluatedCircuit` or a `LazyTrace`
+/// * `ct_blocks` - Blocks of the ciphertext
+/// * `challenge` - Challenge point in the circuit
+///
+/// # Returns
+/// The `FinalStepProofs` for the challenged gate, with an empty `proof3`
+pub fn compute_proofs_left_trace<T: TraceSource + ?Sized>(
+    circuit: &CompiledCircuit,
+    trace: &T,
+    ct_blocks: &[Vec<u8>],
+    challenge: u32,
+) -> FinalStepProofs {
+    let gate = circuit.circuit[challenge as usize].clone();
+    let non_constant_sons: Vec<u32> = gate
+        .sons
+        .iter()
+        .copied()
+        .filter(|&x| !is_constant_idx(x))
+        .collect();
+
+    let hashes = prefix_leaf_hashes(trace, ct_blocks.len() as u32, challenge);
+    FinalStepProofs {
+        values: sons_values(trace, &gate),
+        curr_acc: acc_leaf_hashes(hashes.clone()),
+        proof1: prove(&circuit.to_abi_encoded(), &[challenge]),
+        proof2: prove(ct_blocks, &non_constant_sons),
+        proof3: vec![],
+        proof_ext: prove_ext_leaf_hashes(hashes[..1].to_vec()),
+        gate,
+    }
+}
+
+/// Computes the proof for step 8c from any trace, as done by `compute_proof_right_internal`
+///
+/// # Arguments
+/// * `trace` - The trace, e.g. an `EvaluatedCircuit` or a `LazyTrace`
+/// * `num_blocks` - Number of blocks for the ciphertext
+/// * `num_gates` - Total number of gates in the circuit
+///
+/// # Returns
+/// The proof that the last gate's value is accumulated in the buyer's last response
+pub fn compute_proof_right_trace<T: TraceSource + ?Sized>(
+    trace: &T,
+    num_blocks: u32,
+    num_gates: u32,
+) -> Vec<Vec<Vec<u8>>> {
+    let hashes = prefix_leaf_hashes(trace, num_blocks, num_gates - 1);
+    prove_leaf_hashes(hashes, &[num_gates - num_blocks - 1])
+}
+
+/// Computes the proof for step 8c.
+///
+/// # Arguments
+/// * `values` - Values of the evaluated circuit
+/// * `num_blocks` - Number of blocks for the ciphertext
+/// * `num_gates` - Total number of gates in the circuit
+///
+/// # Returns
+/// The proof that the last gate's value is accumulated in the buyer's last response
+pub fn compute_proof_right_internal(
+    values: &[Vec<u8>],
+    num_blocks: u32,
+    num_gates: u32,
+) -> Vec<Vec<Vec<u8>>> {
+    prove(
+        &values[(num_blocks as usize)..],
+        &[num_gates - num_blocks - 1],
+    )
+}
+
+// ============================= PRE-FLIGHT VERIFICATION =============================
+
+/// On-chain values of the dispute smart contract read during the vendor's final step
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct FinalStepContext {
+    /// The commitment value
+    #[wasm_bindgen(getter_with_clone)]
+    pub commitment: Vec<u8>,
+
+    /// Number of blocks of the ciphertext
+    pub num_blocks: u32,
+
+    /// Number of gates in the circuit
+    pub num_gates: u32,
+
+    /// The challenged gate once the binary search is over
+    pub challenge: u32,
+
+    /// Buyer's response for the gate preceding the challenged one (`buyerResponses[i - 1]`), used
+    /// by steps 8a and 8c
+    #[wasm_bindgen(getter_with_clone)]
+    pub prev_response: Vec<u8>,
+
+    /// Buyer's response for the challenged gate (`buyerResponses[i]`)
+    #[wasm_bindgen(getter_with_clone)]
+    pub curr_response: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl FinalStepContext {
+    /// Creates the context from values read on the smart contract. Responses that the buyer never
+    /// sent must be given as 32 zero bytes.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        commitment: Vec<u8>,
+        num_blocks: u32,
+        num_gates: u32,
+        challenge: u32,
+        prev_response: Vec<u8>,
+        curr_response: Vec<u8>,
+    ) -> FinalStepContext {
+        FinalStepContext {
+            commitment,
+            num_blocks,
+            num_gates,
+            challenge,
+            prev_response,
+            curr_response,
+        }
+    }
+}
+
+/// Data sent by the vendor for the final step of the dispute
+pub enum FinalStepSubmission<'a> {
+    /// Arguments of `submitCommitment` (8a) or `submitCommitmentLeft` (8b)
+    Commitment {
+        gate_num: u32,
+        opening_value: &'a [u8],
+        version: u32,
+        proofs: &'a FinalStepProofs,
+    },
+
+    /// Argument of `submitCommitmentRight` (8c)
+    Right { proof: &'a [Vec<Vec<u8>>] },
+}
+
+/// Check of the final step that fails, in the order in which the smart contract runs them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinalStepError {
+    /// The submission does not match the step required by the challenged gate
+    WrongStep,
+
+    /// The gate number is not the challenged gate. The smart contract does not check it and runs
+    /// the other checks against the sent gate number.
+    GateNumber,
+
+    /// The number of values does not match the number of sons of the gate
+    ValuesLength,
+
+    /// The opening value does not open the commitment
+    Opening(String),
+
+    /// The committed value is too short to contain h_circuit and h_ct. The smart contract reads
+    /// them anyway, so the checks against them fail.
+    OpenedValueLength,
+
+    /// The gate cannot be evaluated by the instruction set
+    GateEvaluation(String),
+
+    /// The current accumulator value is the buyer's response, i.e. the vendor agrees with it
+    SameAccumulator,
+
+    /// proof1 does not prove the gate against h_circuit
+    GateProof,
+
+    /// proof2 does not prove the ciphertext blocks against h_ct
+    CiphertextProof,
+
+    /// proof3 does not prove the sons' values against the buyer's previous response
+    PreviousValuesProof,
+
+    /// proof_ext does not prove the extension of the buyer's previous response with the gate's
+    /// value
+    ExtensionProof,
+
+    /// The proof of step 8c does not prove that the last gate evaluated to true
+    LastGateProof,
+}
+
+impl FinalStepError {
+    /// Returns `true` if the error makes the transaction revert, `false` if the transaction goes
+    /// through and the vendor loses the dispute. Only the checks made by a `require` of the smart
+    /// contract (or of the libraries it calls) revert.
+    pub fn reverts(&self) -> bool {
+        matches!(
+            self,
+            FinalStepError::WrongStep
+                | FinalStepError::ValuesLength
+                | FinalStepError::Opening(_)
+                | FinalStepError::GateEvaluation(_)
+        )
+    }
+}
+
+impl fmt::Display for FinalStepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FinalStepError::WrongStep => {
+                write!(f, "The submission does not match the challenged gate")
+            }
+            FinalStepError::GateNumber => write!(f, "The gate number is not the challenged gate"),
+            FinalStepError::ValuesLength => {
+                write!(f, "Values' and gate's length do not match the requirements")
+            }
+            FinalStepError::Opening(msg) => write!(f, "Cannot open the commitment: {}", msg),
+            FinalStepError::OpenedValueLength => write!(f, "The committed value is too short"),
+            FinalStepError::GateEvaluation(msg) => write!(f, "Cannot evaluate the gate: {}", msg),
+            FinalStepError::SameAccumulator => {
+                write!(f, "The current accumulator value is the buyer's response")
+            }
+            FinalStepError::GateProof => write!(f, "Invalid proof of the gate against h_circuit"),
+            FinalStepError::CiphertextProof => {
+                write!(f, "Invalid proof of the ciphertext blocks against h_ct")
+            }
+            FinalStepError::PreviousValuesProof => {
+                write!(
+                    f,
+                    "Invalid proof of the sons' values against the buyer's previous response"
+                )
+            }
+            FinalStepError::ExtensionProof => write!(f, "Invalid extension proof"),
+            FinalStepError::LastGateProof => {
+                write!(f, "Invalid proof that the last gate evaluated to true")
+            }
+        }
+    }
+}
+
+/// Runs the checks of `submitCommitment` (8a), `submitCommitmentLeft` (8b) or
+/// `submitCommitmentRight` (8c) off-chain. The step is chosen from the challenged gate like the
+/// smart contract does. The gate number sent for steps 8a and 8b must be the challenged gate,
+/// which the smart contract does not enforce, so it is checked after the checks that revert.
+///
+/// # Arguments
+/// * `ctx` - On-chain values of the dispute
+/// * `submission` - The data the vendor is about to send
+///
+/// # Returns
+/// `Ok(())` if the smart contract would complete the dispute, or the first check that fails
+pub fn verify_final_step(
+    ctx: &FinalStepContext,
+    submission: &FinalStepSubmission,
+) -> Result<(), FinalStepError> {
+    let chall = ctx.challenge;
+    match *submission {
+        FinalStepSubmission::Commitment {
+            gate_num,
+            opening_value,
+            version,
+            proofs,
+        } if ctx.num_blocks <= chall && chall < ctx.num_gates => {
+            verify_commitment(ctx, gate_num, opening_value, version, proofs)
+        }
+        FinalStepSubmission::Right { proof } if chall == ctx.num_gates => {
+            let verified = verify(
+                &ctx.prev_response,
+                &[ctx.num_gates - ctx.num_blocks - 1],
+                &[leaf_hash(&[1])],
+                proof,
+            );
+            check(verified, FinalStepError::LastGateProof)
+        }
+        _ => Err(FinalStepError::WrongStep),
+    }
+}
+
+// checks of steps 8a and 8b
+fn verify_commitment(
+    ctx: &FinalStepContext,
+    gate_num: u32,
+    opening_value: &[u8],
+    version: u32,
+    proofs: &FinalStepProofs,
+) -> Result<(), FinalStepError> {
+    if proofs.gate.sons.len() != proofs.values.len() {
+        return Err(FinalStepError::ValuesLength);
+    }
+    let opened = open_commitment(&ctx.commitment, opening_value)?;
+    let gate_res = evaluate_gate(&proofs.gate, &proofs.values, version)
+        .map_err(FinalStepError::GateEvaluation)?;
+
+    // nothing below reverts
+    check(gate_num == ctx.challenge, FinalStepError::GateNumber)?;
+    check(opened.len() >= 64, FinalStepError::OpenedValueLength)?;
+    let (h_circuit, h_ct) = (&opened[..32], &opened[32..64]);
+
+    // hashes of the non constant sons' values, split according to the set L of the paper
+    let mut v_in_l = Vec::new();
+    let mut v_not_in_l = Vec::new();
+    for (&s, v) in proofs.gate.sons.iter().zip(&proofs.values) {
+        if is_constant_idx(s) {
+            continue;
+        }
+        if s < ctx.num_blocks || gate_num == ctx.num_blocks {
+            v_in_l.push(leaf_hash(v));
+        } else {
+            v_not_in_l.push(leaf_hash(v));
+        }
+    }
+
+    check(
+        proofs.curr_acc != ctx.curr_response,
+        FinalStepError::SameAccumulator,
+    )?;
+    check(
+        verify(
+            h_circuit,
+            &[gate_num],
+            &[leaf_hash(&proofs.gate.abi_encoded())],
+            &proofs.proof1,
+        ),
+        FinalStepError::GateProof,
+    )?;
+
+    if gate_num == ctx.num_blocks {
+        // 8b: all the non constant sons are proven against h_ct and there is no previous response
+        let sons: Vec<u32> = proofs
+            .gate
+            .sons
+            .iter()
+            .copied()
+            .filter(|&s| !is_constant_idx(s))
+            .collect();
+        check(
+            verify(h_ct, &sons, &v_in_l, &proofs.proof2),
+            FinalStepError::CiphertextProof,
+        )?;
+        return check(
+            verify_ext(
+                1,
+                &[0u8; 32],
+                &proofs.curr_acc,
+                &leaf_hash(&gate_res),
+                &proofs.proof_ext,
+            ),
+            FinalStepError::ExtensionProof,
+        );
+    }
+
+    let (s_in_l, s_not_in_l_minus_m) = split_sons_indices(&proofs.gate.sons, ctx.num_blocks);
+    check(
+        verify(h_ct, &s_in_l, &v_in_l, &proofs.proof2),
+        FinalStepError::CiphertextProof,
+    )?;
+    check(
+        verify(
+            &ctx.prev_response,
+            &s_not_in_l_minus_m,
+            &v_not_in_l,
+            &proofs.proof3,
+        ),
+        FinalStepError::PreviousValuesProof,
+    )?;
+    check(
+        verify_ext(
+            gate_num - ctx.num_blocks,
+            &ctx.prev_response,
+            &proofs.curr_acc,
+            &leaf_hash(&gate_res),
+            &proofs.proof_ext,
+        ),
+        FinalStepError::ExtensionProof,
+    )
+}
+
+// opens the commitment like `CommitmentOpener.open`, which reverts if the opening value is
+// shorter than the blinding factor
+fn open_commitment(commitment: &[u8], opening_value: &[u8]) -> Result<Vec<u8>, FinalStepError> {
+    if opening_value.len() < 16 {
+        return Err(FinalStepError::Opening(
+            "The opening value is too short".to_string(),
+        ));
+    }
+    open_commitment_internal(commitment, opening_value)
+        .map_err(|msg| FinalStepError::Opening(msg.to_string()))
+}
+
+fn check(verified: bool, err: FinalStepError) -> Result<(), FinalStepError> {
+    if verified {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::evaluate_circuit_internal;
+    use crate::commitment::{commit, Commitment};
+    use crate::compute_precontract_values;
+    use crate::utils::split_ct_blocks;
+    use rand::RngCore;
+
+    struct Setup {
+        circuit: CompiledCircuit,
+        ct_blocks: Vec<Vec<u8>>,
+        values: Vec<Vec<u8>>,
+        constants: Vec<Vec<u8>>,
+        commitment: Commitment,
+    }
+
+    fn setup(size: usize) -> Setup {
+        let mut rng = rand::rng();
+        let mut data = vec![0u8; size];
+        rng.fill_bytes(&mut data);
+        let mut key = vec![0u8; 16];
+        rng.fill_bytes(&mut key);
+
+        let precontract = compute_precontract_values(&mut data, &key);
+        let circuit = CompiledCircuit::from_bytes(&precontract.circuit_bytes);
+        let bound = circuit.bind_missing_constants(vec![key]);
+        let ct_blocks = split_ct_blocks(&precontract.ct, circuit.block_size as usize);
+        let values = evaluate_circuit_internal(&ct_blocks, &bound);
+        let constants = bound.into_constants();
+
+        Setup {
+            circuit,
+            ct_blocks,
+            values,
+            constants,
+            commitment: precontract.commitment,
+        }
+    }
+
+    impl Setup {
+        // context where the buyer answered honestly up to the challenge and lied at the challenge
+        fn context(&self, challenge: u32) -> FinalStepContext {
+            let m = self.ct_blocks.len() as u32;
+            let prev_response = if challenge > m {
+                hpre_internal(&self.values, m as usize, challenge as usize - 1)
+            } else {
+                vec![0u8; 32]
+            };
+            FinalStepContext::new(
+                self.commitment.c.clone(),
+                m,
+                self.circuit.circuit.len() as u32,
+                challenge,
+                prev_response,
+                vec![0u8; 32],
+            )
+        }
+
+        fn proofs(&self, challenge: u32) -> FinalStepProofs {
+            let f = if challenge == self.ct_blocks.len() as u32 {
+                compute_proofs_left_internal
+            } else {
+                compute_proofs_internal
+            };
+            f(
+                &self.circuit,
+                &self.values,
+                &self.constants,
+                &self.ct_blocks,
+                challenge,
+            )
+        }
+
+        fn verify(
+            &self,
+            ctx: &FinalStepContext,
+            proofs: &FinalStepProofs,
+        ) -> Result<(), FinalStepError> {
+            verify_final_step(
+                ctx,
+                &FinalStepSubmission::Commitment {
+                    gate_num: ctx.challenge,
+                    opening_value: &self.commitment.o,
+                    version: self.circuit.version,
+                    proofs,
+                },
+            )
+        }
+    }
+
+    // flips a bit of the first node of the proof
+    fn tamper(proof: &mut [Vec<Vec<u8>>]) {
+        let node = proof.iter_mut().flatten().next().unwrap();
+        node[0] ^= 1;
+    }
+
+    #[test]
+    fn test_valid_final_steps() {
+        for size in [1, 100, 1000] {
+            let s = setup(size);
+            let m = s.ct_blocks.len() as u32;
+            let n = s.circuit.circuit.len() as u32;
+
+            for i in m..n {
+                assert_eq!(Ok(()), s.verify(&s.context(i), &s.proofs(i)), "gate {}", i);
+            }
+
+            let mut ctx = s.context(n);
+            ctx.prev_response = hpre_internal(&s.values, m as usize, n as usize - 1);
+            let proof = compute_proof_right_internal(&s.values, m, n);
+            assert_eq!(
+                Ok(()),
+                verify_final_step(&ctx, &FinalStepSubmission::Right { proof: &proof })
+            );
+        }
+    }
+
+    #[test]
+    fn test_failing_checks() {
+        let s = setup(300);
+        let m = s.ct_blocks.len() as u32;
+        let n = s.circuit.circuit.len() as u32;
+        // the last gate has a non constant son that is not a ciphertext block
+        let i = n - 1;
+        let ctx = s.context(i);
+        let proofs = s.proofs(i);
+
+        let mut c = ctx.clone();
+        c.curr_response = proofs.curr_acc.clone();
+        assert_eq!(Err(FinalStepError::SameAccumulator), s.verify(&c, &proofs));
+
+        let mut c = ctx.clone();
+        c.prev_response[0] ^= 1;
+        assert_eq!(
+            Err(FinalStepError::PreviousValuesProof),
+            s.verify(&c, &proofs)
+        );
+
+        let mut c = ctx.clone();
+        c.commitment[0] ^= 1;
+        assert!(matches!(
+            s.verify(&c, &proofs),
+            Err(FinalStepError::Opening(_))
+        ));
+
+        let mut p = proofs.clone();
+        tamper(&mut p.proof1);
+        assert_eq!(Err(FinalStepError::GateProof), s.verify(&ctx, &p));
+
+        let mut p = proofs.clone();
+        tamper(&mut p.proof_ext);
+        assert_eq!(Err(FinalStepError::ExtensionProof), s.verify(&ctx, &p));
+
+        let mut p = proofs.clone();
+        p.values.pop();
+        assert_eq!(Err(FinalStepError::ValuesLength), s.verify(&ctx, &p));
+
+        // first gate using a ciphertext block
+        let i = (m..n)
+            .find(|&i| s.circuit.circuit[i as usize].sons.iter().any(|&x| x < m))
+            .unwrap();
+        let mut p = s.proofs(i);
+        let son = s.circuit.circuit[i as usize]
+            .sons
+            .iter()
+            .position(|&x| x < m)
+            .unwrap();
+        p.values[son][0] ^= 1;
+        assert_eq!(
+            Err(FinalStepError::CiphertextProof),
+            s.verify(&s.context(i), &p)
+        );
+
+        let proofs = s.proofs(i);
+        let submission = FinalStepSubmission::Commitment {
+            gate_num: i,
+            opening_value: &s.commitment.o,
+            version: s.circuit.version,
+            proofs: &proofs,
+        };
+        assert_eq!(
+            Err(FinalStepError::GateNumber),
+            verify_final_step(&s.context(i + 1), &submission)
+        );
+        assert_eq!(
+            Err(FinalStepError::WrongStep),
+            verify_final_step(&s.context(i), &FinalStepSubmission::Right { proof: &[] })
+        );
+    }
+
+    #[test]
+    fn test_reverting_checks() {
+        let s = setup(300);
+        let m = s.ct_blocks.len() as u32;
+        let i = m + 1;
+        let ctx = s.context(i);
+        let proofs = s.proofs(i);
+        let submit = |gate_num: u32, opening_value: &[u8], proofs: &FinalStepProofs| {
+            verify_final_step(
+                &ctx,
+                &FinalStepSubmission::Commitment {
+                    gate_num,
+                    opening_value,
+                    version: s.circuit.version,
+                    proofs,
+                },
+            )
+        };
+
+        // onlyExpected: "Cannot run this function in the current state"
+        let res = verify_final_step(&ctx, &FinalStepSubmission::Right { proof: &[] });
+        assert_eq!(Err(FinalStepError::WrongStep), res);
+        assert!(res.unwrap_err().reverts());
+
+        // "Values' and gate's length do not match the requirements", checked before the gate
+        // number which the smart contract ignores
+        let mut p = proofs.clone();
+        p.values.pop();
+        let res = submit(i + 1, &s.commitment.o, &p);
+        assert_eq!(Err(FinalStepError::ValuesLength), res);
+        assert!(res.unwrap_err().reverts());
+
+        // CommitmentOpener: "Commitment and opening value do not match" and the underflow of
+        // the opening value's length
+        let res = submit(i, &s.commitment.o[1..], &proofs);
+        assert!(matches!(res, Err(FinalStepError::Opening(_))));
+        assert!(res.unwrap_err().reverts());
+        let res = submit(i, &s.commitment.o[..15], &proofs);
+        assert!(matches!(res, Err(FinalStepError::Opening(_))));
+        assert!(res.unwrap_err().reverts());
+
+        // CircuitEvaluator: out of bounds access to the instruction set
+        let mut p = proofs.clone();
+        p.gate.opcode = 200;
+        let res = submit(i, &s.commitment.o, &p);
+        assert!(matches!(res, Err(FinalStepError::GateEvaluation(_))));
+        assert!(res.unwrap_err().reverts());
+
+        // no require on the gate number
+        let res = submit(i + 1, &s.commitment.o, &proofs);
+        assert_eq!(Err(FinalStepError::GateNumber), res);
+        assert!(!res.unwrap_err().reverts());
+
+        // no require on the length of the committed value
+        let short = commit(&[0u8; 63]);
+        let mut c = ctx.clone();
+        c.commitment = short.c.clone();
+        let res = verify_final_step(
+            &c,
+            &FinalStepSubmission::Commitment {
+                gate_num: i,
+                opening_value: &short.o,
+                version: s.circuit.version,
+                proofs: &proofs,
+            },
+        );
+        assert_eq!(Err(FinalStepError::OpenedValueLength), res);
+        assert!(!res.unwrap_err().reverts());
+
+        // the proofs are checked in the final condition, which never reverts
+        for err in [
+            FinalStepError::SameAccumulator,
+            FinalStepError::GateProof,
+            FinalStepError::CiphertextProof,
+            FinalStepError::PreviousValuesProof,
+            FinalStepError::ExtensionProof,
+            FinalStepError::LastGateProof,
+        ] {
+            assert!(!err.reverts(), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_failing_last_gate() {
+        let mut s = setup(300);
+        let m = s.ct_blocks.len() as u32;
+        let n = s.circuit.circuit.len() as u32;
+        *s.values.last_mut().unwrap() = vec![0];
+
+        let mut ctx = s.context(n);
+        ctx.prev_response = hpre_internal(&s.values, m as usize, n as usize - 1);
+        let proof = compute_proof_right_internal(&s.values, m, n);
+        let res = verify_final_step(&ctx, &FinalStepSubmission::Right { proof: &proof });
+        assert_eq!(Err(FinalStepError::LastGateProof), res);
+        assert!(!res.unwrap_err().reverts());
+    }
+}
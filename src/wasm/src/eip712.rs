@@ -0,0 +1,551 @@
+use crate::canonical::Canonical;
+use crate::utils::die;
+use crate::{DisputeArgument, Precontract};
+use ethabi::{encode, Address, Token, Uint};
+use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use sha3::{Digest, Keccak256};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/*
+ * EIP-712 typed data signed by the buyer and the vendor. The domain is
+ *
+ *      EIP712Domain(string name,string version,uint256 chainId)
+ *
+ * with name "OptimisticSOX" and version "1", and the signed structs are
+ *
+ *      PrecontractOffer(address buyer,bytes32 commitment,bytes32 description,uint32 numBlocks,
+ *          uint32 numGates,uint256 agreedPrice,uint256 completionTip,uint256 disputeTip,
+ *          uint256 timeoutIncrement)
+ *      DisputeArgument(bytes32 commitment,bytes32 argumentHash)
+ *      DisputeTranscript(bytes32 commitment,bytes32 head)
+ *
+ * where argumentHash is the keccak256 of the canonical encoding of the argument and head is the
+ * last hash of the transcript's chain. Signatures are
+ * 65 bytes: r, s (low) and v = 27 + recovery id, as produced by `eth_signTypedData_v4`.
+ */
+
+const DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId)";
+const DOMAIN_NAME: &str = "OptimisticSOX";
+const DOMAIN_VERSION: &str = "1";
+const PRECONTRACT_OFFER_TYPE: &str = "PrecontractOffer(address buyer,bytes32 commitment,bytes32 description,uint32 numBlocks,uint32 numGates,uint256 agreedPrice,uint256 completionTip,uint256 disputeTip,uint256 timeoutIncrement)";
+const DISPUTE_ARGUMENT_TYPE: &str = "DisputeArgument(bytes32 commitment,bytes32 argumentHash)";
+const DISPUTE_TRANSCRIPT_TYPE: &str = "DisputeTranscript(bytes32 commitment,bytes32 head)";
+
+fn keccak256(data: &[u8]) -> Vec<u8> {
+    Keccak256::digest(data).to_vec()
+}
+
+fn bytes32_token(value: &[u8]) -> Result<Token, String> {
+    if value.len() != 32 {
+        return Err(format!("Expected 32 bytes, got {}", value.len()));
+    }
+    Ok(Token::FixedBytes(value.to_vec()))
+}
+
+fn address_token(value: &[u8]) -> Result<Token, String> {
+    if value.len() != 20 {
+        return Err("An address must be 20 bytes long".to_string());
+    }
+    Ok(Token::Address(Address::from_slice(value)))
+}
+
+/// Computes `hashStruct` of EIP-712 for a struct whose fields are already encoded: dynamic
+/// values (strings, bytes) as the keccak256 of their content and nested structs as their
+/// `hashStruct`
+///
+/// # Arguments
+/// * `encoded_type` - The encoded type, e.g. `Mail(address from,string contents)`
+/// * `fields` - The encoded fields, in the order of the type
+///
+/// # Returns
+/// The hash (32 bytes)
+pub fn hash_struct(encoded_type: &str, fields: &[Token]) -> Vec<u8> {
+    let mut tokens = vec![Token::FixedBytes(keccak256(encoded_type.as_bytes()))];
+    tokens.extend_from_slice(fields);
+
+    keccak256(&encode(&tokens))
+}
+
+/// Computes the digest signed for a struct: `keccak256(0x19 0x01 domainSeparator hashStruct)`
+///
+/// # Arguments
+/// * `chain_id` - Identifier of the chain
+/// * `struct_hash` - `hashStruct` of the signed struct
+///
+/// # Returns
+/// The digest (32 bytes)
+fn typed_data_digest(chain_id: u64, struct_hash: &[u8]) -> Vec<u8> {
+    let domain_separator = hash_struct(
+        DOMAIN_TYPE,
+        &[
+            Token::FixedBytes(keccak256(DOMAIN_NAME.as_bytes())),
+            Token::FixedBytes(keccak256(DOMAIN_VERSION.as_bytes())),
+            Token::Uint(Uint::from(chain_id)),
+        ],
+    );
+
+    keccak256(&[&[0x19, 0x01], domain_separator.as_slice(), struct_hash].concat())
+}
+
+/// Terms of a precontract offered by the vendor to a buyer, i.e. the arguments of the
+/// `OptimisticSOX` constructor agreed on before its deployment
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrecontractTerms {
+    /// Address of the buyer (20 bytes)
+    #[wasm_bindgen(getter_with_clone)]
+    pub buyer: Vec<u8>,
+
+    /// Commitment of the ciphertext and circuit (32 bytes)
+    #[wasm_bindgen(getter_with_clone)]
+    pub commitment: Vec<u8>,
+
+    /// Description of the file (32 bytes)
+    #[wasm_bindgen(getter_with_clone)]
+    pub description: Vec<u8>,
+
+    /// Number of blocks in the ciphertext
+    pub num_blocks: u32,
+
+    /// Number of gates in the circuit
+    pub num_gates: u32,
+
+    /// Agreed price (wei)
+    pub agreed_price: u128,
+
+    /// Tip of the sponsor if the transaction completes (wei)
+    pub completion_tip: u128,
+
+    /// Tip of the sponsors if there is a dispute (wei)
+    pub dispute_tip: u128,
+
+    /// Increment of the timeouts (seconds)
+    pub timeout_increment: u64,
+}
+
+#[wasm_bindgen]
+impl PrecontractTerms {
+    /// Creates the terms of an offer for a precontract
+    ///
+    /// # Arguments
+    /// * `precontract` - The precontract
+    /// * `buyer` - Address of the buyer (20 bytes)
+    /// * `agreed_price` - Agreed price (wei)
+    /// * `completion_tip` - Tip of the sponsor if the transaction completes (wei)
+    /// * `dispute_tip` - Tip of the sponsors if there is a dispute (wei)
+    /// * `timeout_increment` - Increment of the timeouts (seconds)
+    pub fn from_precontract(
+        precontract: &Precontract,
+        buyer: Vec<u8>,
+        agreed_price: u128,
+        completion_tip: u128,
+        dispute_tip: u128,
+        timeout_increment: u64,
+    ) -> PrecontractTerms {
+        PrecontractTerms {
+            buyer,
+            commitment: precontract.commitment.c.clone(),
+            description: precontract.description.clone(),
+            num_blocks: precontract.num_blocks,
+            num_gates: precontract.num_gates,
+            agreed_price,
+            completion_tip,
+            dispute_tip,
+            timeout_increment,
+        }
+    }
+}
+
+fn precontract_offer_digest_internal(
+    terms: &PrecontractTerms,
+    chain_id: u64,
+) -> Result<Vec<u8>, String> {
+    let struct_hash = hash_struct(
+        PRECONTRACT_OFFER_TYPE,
+        &[
+            address_token(&terms.buyer)?,
+            bytes32_token(&terms.commitment)?,
+            bytes32_token(&terms.description)?,
+            Token::Uint(Uint::from(terms.num_blocks)),
+            Token::Uint(Uint::from(terms.num_gates)),
+            Token::Uint(Uint::from(terms.agreed_price)),
+            Token::Uint(Uint::from(terms.completion_tip)),
+            Token::Uint(Uint::from(terms.dispute_tip)),
+            Token::Uint(Uint::from(terms.timeout_increment)),
+        ],
+    );
+
+    Ok(typed_data_digest(chain_id, &struct_hash))
+}
+
+fn argument_digest_internal(
+    argument: &DisputeArgument,
+    commitment: &[u8],
+    chain_id: u64,
+) -> Result<Vec<u8>, String> {
+    let struct_hash = hash_struct(
+        DISPUTE_ARGUMENT_TYPE,
+        &[
+            bytes32_token(commitment)?,
+            Token::FixedBytes(keccak256(&argument.to_canonical_bytes())),
+        ],
+    );
+
+    Ok(typed_data_digest(chain_id, &struct_hash))
+}
+
+/// Computes the EIP-712 digest of the head of a dispute transcript
+///
+/// # Arguments
+/// * `commitment` - Commitment of the dispute (32 bytes)
+/// * `head` - Last hash of the transcript (32 bytes)
+/// * `chain_id` - Identifier of the chain
+///
+/// # Returns
+/// The digest (32 bytes)
+pub fn transcript_digest(commitment: &[u8], head: &[u8], chain_id: u64) -> Result<Vec<u8>, String> {
+    let struct_hash = hash_struct(
+        DISPUTE_TRANSCRIPT_TYPE,
+        &[bytes32_token(commitment)?, bytes32_token(head)?],
+    );
+
+    Ok(typed_data_digest(chain_id, &struct_hash))
+}
+
+/// Computes the EIP-712 digest of a precontract offer
+///
+/// # Arguments
+/// * `terms` - Terms of the offer
+/// * `chain_id` - Identifier of the chain
+///
+/// # Returns
+/// The digest (32 bytes)
+#[wasm_bindgen]
+pub fn precontract_offer_digest(terms: &PrecontractTerms, chain_id: u64) -> Vec<u8> {
+    match precontract_offer_digest_internal(terms, chain_id) {
+        Ok(digest) => digest,
+        Err(msg) => die(&msg),
+    }
+}
+
+/// Computes the EIP-712 digest of a dispute argument. Both encodings of the argument give the
+/// same digest.
+///
+/// # Arguments
+/// * `argument_bin` - Serialized dispute argument bytes
+/// * `commitment` - Commitment of the precontract (32 bytes)
+/// * `chain_id` - Identifier of the chain
+///
+/// # Returns
+/// The digest (32 bytes)
+#[wasm_bindgen]
+pub fn argument_digest(argument_bin: &[u8], commitment: &[u8], chain_id: u64) -> Vec<u8> {
+    let argument = DisputeArgument::from_bytes(argument_bin);
+    match argument_digest_internal(&argument, commitment, chain_id) {
+        Ok(digest) => digest,
+        Err(msg) => die(&msg),
+    }
+}
+
+/// Computes the Ethereum address of a secp256k1 secret key
+///
+/// # Arguments
+/// * `secret_key` - The secret key (32 bytes)
+///
+/// # Returns
+/// The address (20 bytes)
+#[wasm_bindgen]
+pub fn eth_address(secret_key: &[u8]) -> Vec<u8> {
+    match SigningKey::from_slice(secret_key) {
+        Ok(key) => address_of(key.verifying_key()),
+        Err(_) => die("Invalid secp256k1 secret key"),
+    }
+}
+
+// last 20 bytes of the keccak256 of the uncompressed public key (without its prefix)
+fn address_of(key: &VerifyingKey) -> Vec<u8> {
+    let point = key.as_affine().to_encoded_point(false);
+    keccak256(&point.as_bytes()[1..])[12..].to_vec()
+}
+
+/// Signs a digest with a secp256k1 secret key
+///
+/// # Arguments
+/// * `secret_key` - The secret key (32 bytes)
+/// * `digest` - The digest (32 bytes)
+///
+/// # Returns
+/// The signature (65 bytes: r, s and v)
+#[wasm_bindgen]
+pub fn sign_digest(secret_key: &[u8], digest: &[u8]) -> Vec<u8> {
+    let key = match SigningKey::from_slice(secret_key) {
+        Ok(key) => key,
+        Err(_) => die("Invalid secp256k1 secret key"),
+    };
+    if digest.len() != 32 {
+        die("A digest must be 32 bytes");
+    }
+    match key.sign_prehash_recoverable(digest) {
+        Ok((signature, recovery_id)) => {
+            let mut bytes = signature.to_bytes().to_vec();
+            bytes.push(27 + recovery_id.to_byte());
+            bytes
+        }
+        Err(_) => die("The digest cannot be signed"),
+    }
+}
+
+/// Recovers the address of the signer of a digest
+///
+/// # Arguments
+/// * `digest` - The digest (32 bytes)
+/// * `signature` - The signature (65 bytes: r, s and v, v being 27 or 28, or 0 or 1)
+///
+/// # Returns
+/// The signer's address (20 bytes), or an error if the signature is malformed
+pub fn recover_signer(digest: &[u8], signature: &[u8]) -> Result<Vec<u8>, String> {
+    let invalid = || "Invalid signature".to_string();
+    if digest.len() != 32 || signature.len() != 65 {
+        return Err(invalid());
+    }
+    let v = signature[64];
+    let recovery_id =
+        RecoveryId::from_byte(if v >= 27 { v - 27 } else { v }).ok_or_else(invalid)?;
+    let signature = Signature::from_slice(&signature[..64]).map_err(|_| invalid())?;
+    // high s values are malleable copies of low ones
+    if signature.normalize_s().is_some() {
+        return Err(invalid());
+    }
+    let key = VerifyingKey::recover_from_prehash(digest, &signature, recovery_id)
+        .map_err(|_| invalid())?;
+
+    Ok(address_of(&key))
+}
+
+/// Checks that a digest was signed by the expected address
+///
+/// # Arguments
+/// * `digest` - The digest (32 bytes)
+/// * `signature` - The signature (65 bytes)
+/// * `signer` - The expected signer's address (20 bytes)
+///
+/// # Returns
+/// `true` if the signature is valid and made by `signer`
+pub fn is_signed_by(digest: &[u8], signature: &[u8], signer: &[u8]) -> bool {
+    recover_signer(digest, signature).is_ok_and(|recovered| recovered == signer)
+}
+
+/// Signs a precontract offer
+///
+/// # Arguments
+/// * `terms` - Terms of the offer
+/// * `chain_id` - Identifier of the chain
+/// * `secret_key` - The vendor's secret key (32 bytes)
+///
+/// # Returns
+/// The signature (65 bytes)
+#[wasm_bindgen]
+pub fn sign_precontract_offer(
+    terms: &PrecontractTerms,
+    chain_id: u64,
+    secret_key: &[u8],
+) -> Vec<u8> {
+    sign_digest(secret_key, &precontract_offer_digest(terms, chain_id))
+}
+
+/// Checks that a precontract offer was signed by the vendor
+///
+/// # Arguments
+/// * `terms` - Terms of the offer
+/// * `chain_id` - Identifier of the chain
+/// * `signature` - The signature of the offer (65 bytes)
+/// * `vendor` - Address of the vendor (20 bytes)
+///
+/// # Returns
+/// `true` if the terms are well formed and signed by the vendor
+#[wasm_bindgen]
+pub fn check_precontract_offer(
+    terms: &PrecontractTerms,
+    chain_id: u64,
+    signature: &[u8],
+    vendor: &[u8],
+) -> bool {
+    precontract_offer_digest_internal(terms, chain_id)
+        .is_ok_and(|digest| is_signed_by(&digest, signature, vendor))
+}
+
+/// Signs a dispute argument
+///
+/// # Arguments
+/// * `argument_bin` - Serialized dispute argument bytes
+/// * `commitment` - Commitment of the precontract (32 bytes)
+/// * `chain_id` - Identifier of the chain
+/// * `secret_key` - The buyer's or vendor's secret key (32 bytes)
+///
+/// # Returns
+/// The signature (65 bytes)
+#[wasm_bindgen]
+pub fn sign_argument(
+    argument_bin: &[u8],
+    commitment: &[u8],
+    chain_id: u64,
+    secret_key: &[u8],
+) -> Vec<u8> {
+    sign_digest(
+        secret_key,
+        &argument_digest(argument_bin, commitment, chain_id),
+    )
+}
+
+/// Signature of a dispute argument and the address it must be from
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ArgumentSigner {
+    /// Expected signer's address (20 bytes)
+    #[wasm_bindgen(getter_with_clone)]
+    pub address: Vec<u8>,
+
+    /// The signature of the argument (65 bytes)
+    #[wasm_bindgen(getter_with_clone)]
+    pub signature: Vec<u8>,
+
+    /// Identifier of the chain
+    pub chain_id: u64,
+}
+
+#[wasm_bindgen]
+impl ArgumentSigner {
+    /// Creates the expected signer of an argument
+    #[wasm_bindgen(constructor)]
+    pub fn new(address: Vec<u8>, signature: Vec<u8>, chain_id: u64) -> ArgumentSigner {
+        ArgumentSigner {
+            address,
+            signature,
+            chain_id,
+        }
+    }
+}
+
+impl ArgumentSigner {
+    /// Checks that an argument was signed by the expected address
+    ///
+    /// # Arguments
+    /// * `argument` - The argument
+    /// * `commitment` - Commitment of the precontract (32 bytes)
+    ///
+    /// # Returns
+    /// `true` if the signature is valid and made by the expected address
+    pub fn verify(&self, argument: &DisputeArgument, commitment: &[u8]) -> bool {
+        argument_digest_internal(argument, commitment, self.chain_id)
+            .is_ok_and(|digest| is_signed_by(&digest, &self.signature, &self.address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_precontract_values;
+    use crate::utils::hex_to_bytes;
+
+    fn hex(s: &str) -> Vec<u8> {
+        hex_to_bytes(format!("0x{}", s))
+    }
+
+    #[test]
+    fn test_hash_struct() {
+        // example of EIP-712
+        let person = |name: &str, wallet: &str| {
+            Token::FixedBytes(hash_struct(
+                "Person(string name,address wallet)",
+                &[
+                    Token::FixedBytes(keccak256(name.as_bytes())),
+                    address_token(&hex(wallet)).unwrap(),
+                ],
+            ))
+        };
+        let mail = hash_struct(
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)",
+            &[
+                person("Cow", "CD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"),
+                person("Bob", "bBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"),
+                Token::FixedBytes(keccak256(b"Hello, Bob!")),
+            ],
+        );
+        assert_eq!(
+            hex("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"),
+            mail
+        );
+    }
+
+    #[test]
+    fn test_sign_recover() {
+        // account of the web3.js documentation
+        let secret_key = hex("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318");
+        let address = hex("2c7536e3605d9c16a7a3d7b1898e529396a65c23");
+        assert_eq!(address, eth_address(&secret_key));
+
+        let digest = keccak256(b"digest");
+        let signature = sign_digest(&secret_key, &digest);
+        assert_eq!(65, signature.len());
+        assert_eq!(Ok(address.clone()), recover_signer(&digest, &signature));
+        assert!(is_signed_by(&digest, &signature, &address));
+        assert!(!is_signed_by(&keccak256(b"other"), &signature, &address));
+
+        // v as a recovery id
+        let mut raw = signature.clone();
+        raw[64] -= 27;
+        assert!(is_signed_by(&digest, &raw, &address));
+        assert!(recover_signer(&digest, &signature[..64]).is_err());
+    }
+
+    #[test]
+    fn test_precontract_offer() {
+        let vendor = [0x11u8; 32];
+        let precontract = compute_precontract_values(&mut [1u8; 300], &[2u8; 16]);
+        let terms = PrecontractTerms::from_precontract(
+            &precontract,
+            vec![0x22; 20],
+            10u128.pow(18),
+            5,
+            6,
+            3600,
+        );
+
+        let signature = sign_precontract_offer(&terms, 1, &vendor);
+        assert!(check_precontract_offer(
+            &terms,
+            1,
+            &signature,
+            &eth_address(&vendor)
+        ));
+        assert!(!check_precontract_offer(&terms, 1, &signature, &[0x33; 20]));
+        assert!(!check_precontract_offer(
+            &terms,
+            5,
+            &signature,
+            &eth_address(&vendor)
+        ));
+
+        let changed = PrecontractTerms {
+            agreed_price: terms.agreed_price + 1,
+            ..terms.clone()
+        };
+        assert!(!check_precontract_offer(
+            &changed,
+            1,
+            &signature,
+            &eth_address(&vendor)
+        ));
+        let malformed = PrecontractTerms {
+            buyer: vec![0x22; 19],
+            ..terms
+        };
+        assert!(!check_precontract_offer(
+            &malformed,
+            1,
+            &signature,
+            &eth_address(&vendor)
+        ));
+    }
+}
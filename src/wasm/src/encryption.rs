@@ -1,81 +1,419 @@
+use crate::gcm::compute_tag;
+use crate::random::RandomSource;
+use crate::secret::ct_eq;
 use crate::utils::die;
-use aes::cipher::{KeyIvInit, StreamCipher};
-use rand::RngCore;
+use aes::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::ChaCha20;
+use wasm_bindgen::prelude::wasm_bindgen;
 
 type Aes128Ctr128BE = ctr::Ctr128BE<aes::Aes128>;
+type Aes256Ctr128BE = ctr::Ctr128BE<aes::Aes256>;
+type Aes128Ctr32BE = ctr::Ctr32BE<aes::Aes128>;
 
-/// Encrypts data using AES-128 in counter mode and prepends IV in 16 bytes big endian
-/// representation. `ct = IV (16 bytes) || Enc_k(data) (variable size)`
+/// Cipher used to encrypt a file. It is recorded in the precontract and determines the
+/// instruction set version of the circuit.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cipher {
+    /// AES-128 in counter mode (instruction set version 0)
+    Aes128Ctr = 0,
+
+    /// AES-256 in counter mode (instruction set version 1)
+    Aes256Ctr = 1,
+
+    /// ChaCha20 with a 96-bit nonce as specified in RFC 8439 (instruction set version 2)
+    ChaCha20 = 2,
+
+    /// AES-128 in Galois/Counter Mode with a 96-bit nonce and a 16 bytes tag (instruction set
+    /// version 3)
+    Aes128Gcm = 3,
+}
+
+impl Cipher {
+    /// Returns the AES-CTR cipher using keys of the given length, or `None` if there is none.
+    /// Keys of 32 bytes are AES-256 keys, ChaCha20 and AES-GCM must always be selected
+    /// explicitly.
+    ///
+    /// # Arguments
+    /// * `len` - Length of the key in bytes
+    pub fn from_key_len(len: usize) -> Option<Cipher> {
+        match len {
+            16 => Some(Cipher::Aes128Ctr),
+            32 => Some(Cipher::Aes256Ctr),
+            _ => None,
+        }
+    }
+
+    /// Returns the AES-CTR cipher using keys of the same length as `key`
+    ///
+    /// # Arguments
+    /// * `key` - The key
+    pub fn for_key(key: &[u8]) -> Cipher {
+        match Cipher::from_key_len(key.len()) {
+            Some(cipher) => cipher,
+            None => die("Key must be 16 or 32 bytes"),
+        }
+    }
+
+    /// Returns the cipher of the basic circuits with the given instruction set version, or `None`
+    /// if there is none
+    ///
+    /// # Arguments
+    /// * `version` - Version of the instruction set
+    pub fn from_version(version: u32) -> Option<Cipher> {
+        match version {
+            0 => Some(Cipher::Aes128Ctr),
+            1 => Some(Cipher::Aes256Ctr),
+            2 => Some(Cipher::ChaCha20),
+            3 => Some(Cipher::Aes128Gcm),
+            _ => None,
+        }
+    }
+
+    /// Length of the keys in bytes
+    pub fn key_len(self) -> usize {
+        match self {
+            Cipher::Aes128Ctr | Cipher::Aes128Gcm => 16,
+            Cipher::Aes256Ctr | Cipher::ChaCha20 => 32,
+        }
+    }
+
+    /// Length of the authentication tag appended to the ciphertext in bytes (0 if the cipher
+    /// does not authenticate the ciphertext)
+    pub fn tag_len(self) -> usize {
+        match self {
+            Cipher::Aes128Gcm => 16,
+            _ => 0,
+        }
+    }
+
+    /// Draws the 16 bytes IV prepended to the ciphertext. For AES-CTR, it is the initial counter
+    /// block. For ChaCha20, it is `nonce (12 bytes) || block counter (4 bytes, big endian)` and
+    /// the block counter starts at 1 as in RFC 8439. For AES-GCM, it is the pre-counter block
+    /// `J0 = nonce (12 bytes) || 1 (4 bytes, big endian)`.
+    ///
+    /// # Arguments
+    /// * `rng` - Source of the IV
+    pub fn generate_iv(self, rng: &mut RandomSource) -> Vec<u8> {
+        let mut iv = vec![0u8; 16];
+        match self {
+            Cipher::Aes128Ctr | Cipher::Aes256Ctr => rng.fill_bytes(&mut iv),
+            Cipher::ChaCha20 | Cipher::Aes128Gcm => {
+                rng.fill_bytes(&mut iv[..12]);
+                iv[12..].copy_from_slice(&1u32.to_be_bytes());
+            }
+        }
+        iv
+    }
+
+    /// Encrypts or decrypts data in place, starting with the counter block `iv`. For AES, it is a
+    /// 128-bit big endian counter. For ChaCha20, it is `nonce (12 bytes) || block counter (4
+    /// bytes, big endian)`, so that both are incremented with a 128-bit big endian addition. For
+    /// AES-GCM, it is the pre-counter block J0, which must be `nonce (12 bytes) || 1`, and the
+    /// keystream starts at the counter J0 + 1.
+    ///
+    /// # Arguments
+    /// * `key` - Key bytes (must be `key_len()` bytes)
+    /// * `iv` - Counter starting value (16 bytes)
+    /// * `data` - Data to encrypt or decrypt
+    pub fn apply_keystream(self, key: &[u8], iv: &[u8], data: &mut [u8]) {
+        self.apply_keystream_at(key, iv, 0, data)
+    }
+
+    /// Encrypts or decrypts data in place with the keystream starting `offset` bytes after the
+    /// beginning of the one of `apply_keystream`, so that a file can be encrypted chunk by chunk
+    ///
+    /// # Arguments
+    /// * `key` - Key bytes (must be `key_len()` bytes)
+    /// * `iv` - Counter starting value (16 bytes)
+    /// * `offset` - Position of `data` in the file
+    /// * `data` - Data to encrypt or decrypt
+    pub fn apply_keystream_at(self, key: &[u8], iv: &[u8], offset: u64, data: &mut [u8]) {
+        let res = match self {
+            Cipher::Aes128Ctr => Aes128Ctr128BE::new_from_slices(key, iv).map(|mut cipher| {
+                cipher.seek(offset);
+                cipher.apply_keystream(data)
+            }),
+            Cipher::Aes256Ctr => Aes256Ctr128BE::new_from_slices(key, iv).map(|mut cipher| {
+                cipher.seek(offset);
+                cipher.apply_keystream(data)
+            }),
+            Cipher::ChaCha20 if iv.len() == 16 => {
+                ChaCha20::new_from_slices(key, &iv[..12]).map(|mut cipher| {
+                    let counter = u32::from_be_bytes([iv[12], iv[13], iv[14], iv[15]]);
+                    cipher.seek(counter as u64 * 64 + offset);
+                    if cipher.try_apply_keystream(data).is_err() {
+                        die("The ChaCha20 block counter overflows");
+                    }
+                })
+            }
+            Cipher::ChaCha20 => die("ChaCha20 requires a 16 bytes counter"),
+            Cipher::Aes128Gcm if is_gcm_pre_counter_block(iv) => {
+                let counter = [&iv[..12], &2u32.to_be_bytes()].concat();
+                Aes128Ctr32BE::new_from_slices(key, &counter).map(|mut cipher| {
+                    cipher.seek(offset);
+                    cipher.apply_keystream(data)
+                })
+            }
+            Cipher::Aes128Gcm => die("AES-GCM requires a pre-counter block nonce || 1"),
+        };
+        if res.is_err() {
+            die(&format!(
+                "{:?} requires a key of {} bytes and a 16 bytes counter",
+                self,
+                self.key_len()
+            ));
+        }
+    }
+}
+
+/// Checks that an IV is an AES-GCM pre-counter block `J0 = nonce (12 bytes) || 1 (4 bytes, big
+/// endian)`. GCM increments only the last 32 bits of the counter (inc32) while the circuits use a
+/// 128-bit addition. Both agree for such a J0, since the counter starts at 2 and a GCM plaintext
+/// is at most 2^32 - 2 blocks long.
+///
+/// # Arguments
+/// * `iv` - The IV
+pub fn is_gcm_pre_counter_block(iv: &[u8]) -> bool {
+    iv.len() == 16 && iv[12..] == 1u32.to_be_bytes()
+}
+
+/// Encrypts data with the given cipher and prepends the IV. `ct = IV (16 bytes) || Enc_k(data)
+/// (variable size) || tag (`cipher.tag_len()` bytes)`, see `Cipher::generate_iv` for the content
+/// of the IV.
 ///
 /// # Arguments
 /// * `data` - Mutable slice containing data to encrypt
-/// * `key` - Key bytes (must be 16 bytes)
+/// * `key` - Key bytes (`cipher.key_len()` bytes)
+/// * `cipher` - The cipher
+/// * `rng` - Source of the IV
 ///
 /// # Returns
-/// Vector containing IV (16 bytes) followed by encrypted data
-pub fn encrypt_and_prepend_iv(mut data: &mut [u8], key: &[u8]) -> Vec<u8> {
-    let mut rng = rand::rng();
-    let mut iv = vec![0u8; 16];
-    rng.fill_bytes(&mut iv);
+/// Vector containing IV (16 bytes) followed by encrypted data and the tag
+pub fn encrypt_and_prepend_iv(
+    data: &mut [u8],
+    key: &[u8],
+    cipher: Cipher,
+    rng: &mut RandomSource,
+) -> Vec<u8> {
+    let mut iv = cipher.generate_iv(rng);
 
-    let mut cipher = match Aes128Ctr128BE::new_from_slices(key, &iv) {
-        Ok(c) => c,
-        Err(_) => die("Key must be 16 bytes"),
-    };
+    cipher.apply_keystream(key, &iv, data);
 
-    cipher.apply_keystream(&mut data);
-
-    // let mut ct = vec![0u8; block_size - 16];
-    // iv.extend(iv);
     iv.extend(data.iter());
+    if cipher == Cipher::Aes128Gcm {
+        let tag = compute_tag(key, &iv[..16], data);
+        iv.extend(tag);
+    }
 
     iv
 }
 
-/// Decrypts AES-128 CTR mode ciphertext. The IV must be in big-endian representation.
+/// Decrypts a ciphertext produced by `encrypt_and_prepend_iv`. The tag is not checked, see
+/// `check_tag`.
 ///
 /// # Arguments
-/// * `ct` - Ciphertext bytes in format: IV (16 bytes) || Encrypted data
-/// * `key` - Key bytes (must be 16 bytes)
+/// * `ct` - Ciphertext bytes in format: IV (16 bytes) || Encrypted data || Tag
+/// * `key` - Key bytes (`cipher.key_len()` bytes)
+/// * `cipher` - The cipher
 ///
 /// # Returns
 /// Decrypted plaintext bytes
-pub fn decrypt(ct: &[u8], key: &[u8]) -> Vec<u8> {
+pub fn decrypt(ct: &[u8], key: &[u8], cipher: Cipher) -> Vec<u8> {
+    if ct.len() < 16 + cipher.tag_len() {
+        die("The ciphertext is too short");
+    }
     let iv = &ct[..16];
-    let mut cipher = match Aes128Ctr128BE::new_from_slices(key, &iv) {
-        Ok(c) => c,
-        Err(_) => die("Key should be 16 bytes"),
-    };
-
-    let mut res = ct[16..].to_vec();
+    let mut res = ct[16..ct.len() - cipher.tag_len()].to_vec();
 
-    cipher.apply_keystream(&mut res);
+    cipher.apply_keystream(key, iv, &mut res);
 
     res
 }
 
+/// Checks the authentication tag of a ciphertext produced by `encrypt_and_prepend_iv`. Ciphers
+/// without tag always pass the check. An AES-GCM ciphertext whose IV is not a pre-counter block
+/// (see `is_gcm_pre_counter_block`) fails it.
+///
+/// # Arguments
+/// * `ct` - Ciphertext bytes in format: IV (16 bytes) || Encrypted data || Tag
+/// * `key` - Key bytes (`cipher.key_len()` bytes)
+/// * `cipher` - The cipher
+///
+/// # Returns
+/// `true` if the tag authenticates the ciphertext under the key
+pub fn check_tag(ct: &[u8], key: &[u8], cipher: Cipher) -> bool {
+    if cipher.tag_len() == 0 {
+        return true;
+    }
+    if ct.len() < 16 + cipher.tag_len() || key.len() != cipher.key_len() {
+        return false;
+    }
+
+    let (body, tag) = ct.split_at(ct.len() - cipher.tag_len());
+    if !is_gcm_pre_counter_block(&body[..16]) {
+        return false;
+    }
+    ct_eq(&compute_tag(key, &body[..16], &body[16..]), tag)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::hex_to_bytes;
+    use rand::RngCore;
 
     #[test]
     fn test_encrypt_decrypt_random_data() {
         let mut rng = rand::rng();
+        let ciphers = [
+            Cipher::Aes128Ctr,
+            Cipher::Aes256Ctr,
+            Cipher::ChaCha20,
+            Cipher::Aes128Gcm,
+        ];
         for i in 1..(1 << 16) {
+            let cipher = ciphers[i % ciphers.len()];
             let mut data = vec![0u8; i];
             rng.fill_bytes(&mut data);
             let plaintext = data.clone();
 
-            let mut key = vec![0u8; 16];
+            let mut key = vec![0u8; cipher.key_len()];
             rng.fill_bytes(&mut key);
 
             // encrypt
-            let ct = encrypt_and_prepend_iv(&mut data, &key);
+            let ct = encrypt_and_prepend_iv(&mut data, &key, cipher, &mut rng);
 
             // decrypt
-            let dec_ct = decrypt(&ct, &key);
+            let dec_ct = decrypt(&ct, &key, cipher);
 
             assert_eq!(plaintext, dec_ct);
+            assert!(check_tag(&ct, &key, cipher));
+        }
+    }
+
+    #[test]
+    fn test_keystream_offset() {
+        let mut rng = rand::rng();
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        for cipher in [
+            Cipher::Aes128Ctr,
+            Cipher::Aes256Ctr,
+            Cipher::ChaCha20,
+            Cipher::Aes128Gcm,
+        ] {
+            let mut key = vec![0u8; cipher.key_len()];
+            rng.fill_bytes(&mut key);
+            let iv = cipher.generate_iv(&mut rng);
+            let mut expected = data.clone();
+            cipher.apply_keystream(&key, &iv, &mut expected);
+
+            // chunks that do not start at a block boundary
+            let mut chunked = data.clone();
+            let mut offset = 0;
+            for len in [1, 15, 64, 100, 3, 817] {
+                let chunk = &mut chunked[offset..offset + len];
+                cipher.apply_keystream_at(&key, &iv, offset as u64, chunk);
+                offset += len;
+            }
+            assert_eq!(expected, chunked);
+        }
+    }
+
+    #[test]
+    fn test_aes256_ctr() {
+        // NIST SP 800-38A, F.5.5 CTR-AES256.Encrypt
+        let key = hex_to_bytes(
+            "0x603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4".to_string(),
+        );
+        let ct = hex_to_bytes(
+            "0xf0f1f2f3f4f5f6f7f8f9fafbfcfdfeff601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5".to_string(),
+        );
+        let pt = hex_to_bytes(
+            "0x6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51".to_string(),
+        );
+        assert_eq!(Cipher::Aes256Ctr, Cipher::for_key(&key));
+        assert_eq!(pt, decrypt(&ct, &key, Cipher::Aes256Ctr));
+        assert_ne!(pt, decrypt(&ct, &key[..16], Cipher::Aes128Ctr));
+    }
+
+    #[test]
+    fn test_chacha20() {
+        // RFC 8439, section 2.4.2
+        let key = hex_to_bytes(
+            "0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f".to_string(),
+        );
+        let iv = hex_to_bytes("0x000000000000004a0000000000000001".to_string());
+        let ct = hex_to_bytes(
+            "0x6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0bf91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d807ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab77937365af90bbf74a35be6b40b8eedf2785e42874d".to_string(),
+        );
+        let pt = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        assert_eq!(
+            pt.to_vec(),
+            decrypt(&[iv, ct].concat(), &key, Cipher::ChaCha20)
+        );
+
+        // the block counter starts at 1
+        let mut data = pt.to_vec();
+        let ct = encrypt_and_prepend_iv(&mut data, &key, Cipher::ChaCha20, &mut rand::rng());
+        assert_eq!(1u32.to_be_bytes(), ct[12..16]);
+        assert_eq!(pt.to_vec(), decrypt(&ct, &key, Cipher::ChaCha20));
+        assert_ne!(pt.to_vec(), decrypt(&ct, &key, Cipher::Aes256Ctr));
+    }
+
+    #[test]
+    fn test_aes128_gcm() {
+        // test case 3 of the GCM specification (McGrew and Viega)
+        let key = hex_to_bytes("0xfeffe9928665731c6d6a8f9467308308".to_string());
+        let j0 = hex_to_bytes("0xcafebabefacedbaddecaf88800000001".to_string());
+        let ct = hex_to_bytes(
+            "0x42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985".to_string(),
+        );
+        let tag = hex_to_bytes("0x4d5c2af327cd64a62cf35abd2ba6fab4".to_string());
+        let pt = hex_to_bytes(
+            "0xd9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255".to_string(),
+        );
+        let mut ct = [j0, ct, tag].concat();
+        assert!(check_tag(&ct, &key, Cipher::Aes128Gcm));
+        assert_eq!(pt, decrypt(&ct, &key, Cipher::Aes128Gcm));
+
+        // the nonce is random and J0's counter is 1
+        let mut data = pt.clone();
+        let fresh = encrypt_and_prepend_iv(&mut data, &key, Cipher::Aes128Gcm, &mut rand::rng());
+        assert_eq!(16 + pt.len() + 16, fresh.len());
+        assert_eq!(1u32.to_be_bytes(), fresh[12..16]);
+
+        // any change of the ciphertext or the tag is detected
+        for i in [0, 16, 40, ct.len() - 1] {
+            ct[i] ^= 1;
+            assert!(!check_tag(&ct, &key, Cipher::Aes128Gcm));
+            ct[i] ^= 1;
         }
+        assert!(!check_tag(&ct, &[0u8; 16], Cipher::Aes128Gcm));
+        assert!(check_tag(&ct, &[0u8; 16], Cipher::Aes128Ctr));
+
+        // J0's counter must be 1
+        ct[15] = 2;
+        assert!(!check_tag(&ct, &key, Cipher::Aes128Gcm));
+    }
+
+    #[test]
+    #[should_panic(expected = "AES-GCM requires a pre-counter block nonce || 1")]
+    fn test_gcm_pre_counter_block() {
+        let mut iv = vec![0u8; 16];
+        iv[12..].copy_from_slice(&u32::MAX.to_be_bytes());
+        decrypt(&[iv, vec![0u8; 48]].concat(), &[0u8; 16], Cipher::Aes128Gcm);
+    }
+
+    #[test]
+    #[should_panic(expected = "Key must be 16 or 32 bytes")]
+    fn test_invalid_key_length() {
+        Cipher::for_key(&[0u8; 24]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Aes128Ctr requires a key of 16 bytes")]
+    fn test_key_length_mismatch() {
+        decrypt(&[0u8; 32], &[0u8; 32], Cipher::Aes128Ctr);
     }
 }
@@ -0,0 +1,550 @@
+use crate::accumulator::uint8_array_to_vec_u8;
+use crate::commitment::KeyCommitment;
+use crate::random::RandomSource;
+use crate::sealing::{open_sealed, seal_with_rng};
+use crate::secret::{ct_eq, SecretBytes};
+use crate::sha256::sha256;
+use crate::utils::die;
+use crate::Precontract;
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::wasm_bindgen;
+use zeroize::Zeroizing;
+
+/*
+ * Shamir secret sharing of the key over GF(2^8), each byte of the key being shared with its own
+ * polynomial of degree threshold - 1. A share is
+ *
+ *      share = [x (1 byte, 1..=n), P_1(x), ..., P_len(x)]
+ *
+ * Share i (0-based) is given to sponsor i, sealed to its X25519 public key for the context
+ * `key commitment || x`, and its verification hash `SHA-256(key commitment || share)` is
+ * published in the precontract. Both tie the shares to the key committed in the precontract,
+ * which the recombined key is finally checked against.
+ */
+
+const SHARE_CONTEXT_LABEL: &[u8] = b"sox key share";
+
+// multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1, without branches on the values
+fn gf256_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0u8;
+    for _ in 0..8 {
+        p ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = a >> 7;
+        a = (a << 1) ^ (0x1b & 0u8.wrapping_sub(carry));
+        b >>= 1;
+    }
+
+    p
+}
+
+// inverse in GF(2^8) as a^254 (0 for 0)
+fn gf256_inv(a: u8) -> u8 {
+    let a2 = gf256_mul(a, a);
+    let a3 = gf256_mul(a2, a);
+    let a6 = gf256_mul(a3, a3);
+    let a12 = gf256_mul(a6, a6);
+    let a15 = gf256_mul(a12, a3);
+    let a30 = gf256_mul(a15, a15);
+    let a60 = gf256_mul(a30, a30);
+    let a63 = gf256_mul(a60, a3);
+    let a126 = gf256_mul(a63, a63);
+    let a127 = gf256_mul(a126, a);
+
+    gf256_mul(a127, a127)
+}
+
+/// Splits a secret into `n` shares, any `threshold` of which give back the secret while fewer
+/// reveal nothing about it
+///
+/// # Arguments
+/// * `secret` - The secret
+/// * `threshold` - Number of shares needed to recombine the secret (at least 1)
+/// * `n` - Number of shares (at least `threshold`)
+/// * `rng` - Source of the polynomials' coefficients
+///
+/// # Returns
+/// The shares, the first byte of each one being its x coordinate
+pub fn split_secret(
+    secret: &[u8],
+    threshold: u8,
+    n: u8,
+    rng: &mut RandomSource,
+) -> Result<Vec<SecretBytes>, String> {
+    if threshold == 0 || threshold > n {
+        return Err("The threshold must be between 1 and the number of shares".to_string());
+    }
+
+    // coefficients of degree 1 to threshold - 1 of each byte's polynomial
+    let mut coefficients = Zeroizing::new(vec![0u8; secret.len() * (threshold as usize - 1)]);
+    rng.fill_bytes(&mut coefficients);
+
+    Ok((1..=n)
+        .map(|x| {
+            let mut share = Zeroizing::new(Vec::with_capacity(1 + secret.len()));
+            share.push(x);
+            for (i, s) in secret.iter().enumerate() {
+                // Horner's method, from the highest degree
+                let row = &coefficients[i * (threshold as usize - 1)..][..threshold as usize - 1];
+                let y = row.iter().rev().fold(0u8, |acc, c| gf256_mul(acc, x) ^ c);
+                share.push(gf256_mul(y, x) ^ s);
+            }
+            share
+        })
+        .collect())
+}
+
+/// Recombines a secret from shares of `split_secret` by Lagrange interpolation at 0. If there
+/// are fewer shares than the threshold, the result is unrelated to the secret.
+///
+/// # Arguments
+/// * `shares` - The shares
+///
+/// # Returns
+/// The secret, or an error if the shares are malformed
+pub fn combine_shares(shares: &[&[u8]]) -> Result<SecretBytes, String> {
+    let len = match shares.first() {
+        Some(share) if !share.is_empty() => share.len(),
+        _ => return Err("At least one share is needed".to_string()),
+    };
+    if shares.iter().any(|share| share.len() != len) {
+        return Err("The shares must have the same length".to_string());
+    }
+    let xs: Vec<u8> = shares.iter().map(|share| share[0]).collect();
+    for (i, x) in xs.iter().enumerate() {
+        if *x == 0 || xs[..i].contains(x) {
+            return Err("The shares must have distinct non-zero indices".to_string());
+        }
+    }
+
+    let mut secret = Zeroizing::new(vec![0u8; len - 1]);
+    for (i, share) in shares.iter().enumerate() {
+        // l_i(0) = prod_{j != i} x_j / (x_j - x_i)
+        let l = xs
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .fold(1u8, |acc, (_, &xj)| {
+                gf256_mul(acc, gf256_mul(xj, gf256_inv(xj ^ xs[i])))
+            });
+        for (s, y) in secret.iter_mut().zip(&share[1..]) {
+            *s ^= gf256_mul(l, *y);
+        }
+    }
+
+    Ok(secret)
+}
+
+// hash published in the precontract for a share
+fn share_hash(key_commitment: &KeyCommitment, share: &[u8]) -> Vec<u8> {
+    sha256(&Zeroizing::new(
+        [&key_commitment.commitment, share].concat(),
+    ))
+}
+
+// context for which a share is sealed
+fn share_context(key_commitment: &KeyCommitment, x: u8) -> Vec<u8> {
+    [SHARE_CONTEXT_LABEL, &key_commitment.commitment, &[x]].concat()
+}
+
+/// Escrow of a precontract's key among sponsors: the verification hashes of the shares and the
+/// shares sealed to the sponsors
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyEscrow {
+    /// Number of shares needed to recover the key
+    pub threshold: u8,
+
+    share_hashes: Vec<Vec<u8>>,
+    sealed_shares: Vec<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl KeyEscrow {
+    /// Number of shares, i.e. of sponsors
+    pub fn num_shares(&self) -> u8 {
+        self.share_hashes.len() as u8
+    }
+
+    /// Verification hash of the share of sponsor `index`
+    pub fn share_hash(&self, index: u8) -> Vec<u8> {
+        match self.share_hashes.get(index as usize) {
+            Some(hash) => hash.clone(),
+            None => die("No share has this index"),
+        }
+    }
+
+    /// Share of sponsor `index`, sealed to its public key
+    pub fn sealed_share(&self, index: u8) -> Vec<u8> {
+        match self.sealed_shares.get(index as usize) {
+            Some(sealed) => sealed.clone(),
+            None => die("No share has this index"),
+        }
+    }
+
+    /// Checks a share against its verification hash
+    ///
+    /// # Arguments
+    /// * `key_commitment` - The key commitment of the precontract
+    /// * `share` - The share
+    ///
+    /// # Returns
+    /// `true` if the share is the one whose hash was published
+    pub fn verify_share(&self, key_commitment: &KeyCommitment, share: &[u8]) -> bool {
+        match share.first() {
+            Some(&x) if x >= 1 && x as usize <= self.share_hashes.len() => ct_eq(
+                &share_hash(key_commitment, share),
+                &self.share_hashes[x as usize - 1],
+            ),
+            _ => false,
+        }
+    }
+
+    /// Serializes the escrow: threshold (u8), number of shares (u8), then for each share its
+    /// verification hash (32 bytes) and its sealed share prefixed by its length (u32, big
+    /// endian)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![self.threshold, self.num_shares()];
+        for (hash, sealed) in self.share_hashes.iter().zip(&self.sealed_shares) {
+            buf.extend(hash);
+            buf.extend((sealed.len() as u32).to_be_bytes());
+            buf.extend(sealed);
+        }
+
+        buf
+    }
+
+    /// Deserializes an escrow written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> KeyEscrow {
+        match KeyEscrow::try_from_bytes(bytes) {
+            Ok(escrow) => escrow,
+            Err(msg) => die(&msg),
+        }
+    }
+}
+
+impl KeyEscrow {
+    /// Deserializes an escrow written by `to_bytes`
+    ///
+    /// # Arguments
+    /// * `bytes` - The serialized escrow
+    ///
+    /// # Returns
+    /// The escrow, or an error if the bytes are malformed
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<KeyEscrow, String> {
+        let malformed = || "Malformed key escrow".to_string();
+        let (&[threshold, n], mut rest) = bytes.split_first_chunk::<2>().ok_or_else(malformed)?;
+        if threshold == 0 || threshold > n {
+            return Err(malformed());
+        }
+
+        let mut share_hashes = Vec::with_capacity(n as usize);
+        let mut sealed_shares = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            let (hash, tail) = rest.split_first_chunk::<32>().ok_or_else(malformed)?;
+            let (len, tail) = tail.split_first_chunk::<4>().ok_or_else(malformed)?;
+            let len = u32::from_be_bytes(*len) as usize;
+            if tail.len() < len {
+                return Err(malformed());
+            }
+            share_hashes.push(hash.to_vec());
+            sealed_shares.push(tail[..len].to_vec());
+            rest = &tail[len..];
+        }
+        if !rest.is_empty() {
+            return Err(malformed());
+        }
+
+        Ok(KeyEscrow {
+            threshold,
+            share_hashes,
+            sealed_shares,
+        })
+    }
+}
+
+/// Splits a key into shares sealed to the sponsors
+///
+/// # Arguments
+/// * `key` - The key
+/// * `key_commitment` - The commitment to the key in the precontract
+/// * `threshold` - Number of sponsors needed to recover the key
+/// * `sponsor_keys` - X25519 public keys of the sponsors (at most 255)
+/// * `rng` - Source of the shares and of the sealing keys
+///
+/// # Returns
+/// The `KeyEscrow` to publish with the precontract, or an error if the key does not match the
+/// commitment or the parameters are invalid
+pub fn escrow_key_with_rng(
+    key: &[u8],
+    key_commitment: &KeyCommitment,
+    threshold: u8,
+    sponsor_keys: &[Vec<u8>],
+    rng: &mut RandomSource,
+) -> Result<KeyEscrow, String> {
+    if !key_commitment.verify(key) {
+        return Err("The key does not match the key commitment".to_string());
+    }
+    let n = u8::try_from(sponsor_keys.len()).map_err(|_| "Too many sponsors".to_string())?;
+    let shares = split_secret(key, threshold, n, rng)?;
+
+    let mut sealed_shares = Vec::with_capacity(shares.len());
+    for (share, public_key) in shares.iter().zip(sponsor_keys) {
+        let context = share_context(key_commitment, share[0]);
+        sealed_shares.push(seal_with_rng(public_key, share, &context, rng)?);
+    }
+
+    Ok(KeyEscrow {
+        threshold,
+        share_hashes: shares
+            .iter()
+            .map(|share| share_hash(key_commitment, share))
+            .collect(),
+        sealed_shares,
+    })
+}
+
+/// Recovers the key from shares of its escrow. Shares that do not match their verification hash
+/// are ignored.
+///
+/// # Arguments
+/// * `escrow` - The escrow of the precontract
+/// * `key_commitment` - The key commitment of the precontract
+/// * `shares` - Shares opened by the sponsors
+///
+/// # Returns
+/// The key, or an error if there are fewer valid shares than the threshold or if they do not
+/// recombine to the committed key
+pub fn recover_key_internal(
+    escrow: &KeyEscrow,
+    key_commitment: &KeyCommitment,
+    shares: &[Vec<u8>],
+) -> Result<SecretBytes, String> {
+    let mut valid: Vec<&[u8]> = Vec::with_capacity(shares.len());
+    for share in shares {
+        if escrow.verify_share(key_commitment, share) && !valid.iter().any(|s| s[0] == share[0]) {
+            valid.push(share);
+        }
+    }
+    if valid.len() < escrow.threshold as usize {
+        return Err(format!(
+            "{} valid shares are needed, {} were given",
+            escrow.threshold,
+            valid.len()
+        ));
+    }
+
+    let key = combine_shares(&valid[..escrow.threshold as usize])?;
+    if !key_commitment.verify(&key) {
+        return Err("The shares do not recombine to the committed key".to_string());
+    }
+
+    Ok(key)
+}
+
+/// Opens the share of a sponsor and checks it against its verification hash
+///
+/// # Arguments
+/// * `escrow` - The escrow of the precontract
+/// * `key_commitment` - The key commitment of the precontract
+/// * `index` - Index of the sponsor
+/// * `secret_key` - X25519 secret key of the sponsor
+///
+/// # Returns
+/// The share, to send when the key must be recovered
+#[wasm_bindgen]
+pub fn open_escrow_share(
+    escrow: &KeyEscrow,
+    key_commitment: &KeyCommitment,
+    index: u8,
+    secret_key: &[u8],
+) -> Vec<u8> {
+    let context = share_context(key_commitment, index.wrapping_add(1));
+    let share = match open_sealed(secret_key, &escrow.sealed_share(index), &context) {
+        Ok(share) => share,
+        Err(msg) => die(&msg),
+    };
+    if !escrow.verify_share(key_commitment, &share) {
+        die("The share does not match its verification hash");
+    }
+
+    share.to_vec()
+}
+
+/// Recovers the key from shares of its escrow, see `recover_key_internal`
+///
+/// # Arguments
+/// * `escrow` - The escrow of the precontract
+/// * `key_commitment` - The key commitment of the precontract
+/// * `shares` - Shares opened by the sponsors
+///
+/// # Returns
+/// The key
+#[wasm_bindgen]
+pub fn recover_escrowed_key(
+    escrow: &KeyEscrow,
+    key_commitment: &KeyCommitment,
+    shares: Vec<Uint8Array>,
+) -> Vec<u8> {
+    let shares: Vec<Vec<u8>> = shares.iter().map(uint8_array_to_vec_u8).collect();
+    match recover_key_internal(escrow, key_commitment, &shares) {
+        Ok(key) => key.to_vec(),
+        Err(msg) => die(&msg),
+    }
+}
+
+/// Methods for escrowing the key of a precontract
+#[wasm_bindgen]
+impl Precontract {
+    /// Escrows the key among sponsors, any `threshold` of which can recover it if the vendor
+    /// disappears. The escrow is stored in the precontract.
+    ///
+    /// # Arguments
+    /// * `key` - The encryption key
+    /// * `threshold` - Number of sponsors needed to recover the key
+    /// * `sponsor_keys` - X25519 public keys of the sponsors
+    pub fn escrow_key(&mut self, key: &[u8], threshold: u8, sponsor_keys: Vec<Uint8Array>) {
+        let sponsor_keys: Vec<Vec<u8>> = sponsor_keys.iter().map(uint8_array_to_vec_u8).collect();
+        self.escrow_key_with_rng(key, threshold, &sponsor_keys, &mut rand::rng());
+    }
+}
+
+impl Precontract {
+    /// Escrows the key among sponsors, drawing the shares and sealing keys from `rng`
+    ///
+    /// # Arguments
+    /// * `key` - The encryption key
+    /// * `threshold` - Number of sponsors needed to recover the key
+    /// * `sponsor_keys` - X25519 public keys of the sponsors
+    /// * `rng` - Source of randomness
+    pub fn escrow_key_with_rng(
+        &mut self,
+        key: &[u8],
+        threshold: u8,
+        sponsor_keys: &[Vec<u8>],
+        rng: &mut RandomSource,
+    ) {
+        let key_commitment = match &self.key_commitment {
+            Some(key_commitment) => key_commitment,
+            None => die("The precontract has no key commitment"),
+        };
+        match escrow_key_with_rng(key, key_commitment, threshold, sponsor_keys, rng) {
+            Ok(escrow) => self.escrow = Some(escrow),
+            Err(msg) => die(&msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_precontract_values;
+    use crate::sealing::sealing_public_key;
+
+    #[test]
+    fn test_gf256() {
+        // example of FIPS 197, section 4.2
+        assert_eq!(0xc1, gf256_mul(0x57, 0x83));
+        for a in 1..=255u8 {
+            assert_eq!(1, gf256_mul(a, gf256_inv(a)));
+        }
+    }
+
+    #[test]
+    fn test_split_combine() {
+        let mut rng = rand::rng();
+        let secret = b"0123456789abcdef".to_vec();
+        let shares = split_secret(&secret, 3, 5, &mut rng).unwrap();
+        assert_eq!(5, shares.len());
+
+        // every subset of 3 shares gives the secret
+        for i in 0..5 {
+            for j in i + 1..5 {
+                for k in j + 1..5 {
+                    let subset = [&shares[i][..], &shares[j][..], &shares[k][..]];
+                    assert_eq!(secret, *combine_shares(&subset).unwrap());
+                }
+            }
+        }
+        let all: Vec<&[u8]> = shares.iter().map(|s| &s[..]).collect();
+        assert_eq!(secret, *combine_shares(&all).unwrap());
+        assert_ne!(secret, *combine_shares(&all[..2]).unwrap());
+
+        // a threshold of 1 copies the secret
+        let shares = split_secret(&secret, 1, 2, &mut rng).unwrap();
+        assert_eq!(secret, shares[1][1..]);
+
+        assert!(split_secret(&secret, 3, 2, &mut rng).is_err());
+        assert!(combine_shares(&[all[0], all[0]]).is_err());
+    }
+
+    #[test]
+    fn test_combine_empty_share() {
+        let shares = split_secret(b"0123456789abcdef", 2, 3, &mut rand::rng()).unwrap();
+        assert_eq!(
+            "The shares must have the same length",
+            combine_shares(&[&shares[0][..], &[]]).err().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_escrow() {
+        let mut rng = rand::rng();
+        let key = vec![9u8; 16];
+        let mut precontract = compute_precontract_values(&mut [1u8; 200], &key);
+        let key_commitment = precontract.key_commitment.clone().unwrap();
+        let secret_keys: Vec<Vec<u8>> = (0..4).map(|i| vec![i + 1; 32]).collect();
+        let public_keys: Vec<Vec<u8>> = secret_keys
+            .iter()
+            .map(|sk| sealing_public_key(sk))
+            .collect();
+
+        precontract.escrow_key_with_rng(&key, 2, &public_keys, &mut rng);
+        let escrow = precontract.escrow.clone().unwrap();
+        assert_eq!(escrow, KeyEscrow::from_bytes(&escrow.to_bytes()));
+
+        let shares: Vec<Vec<u8>> = (0..4)
+            .map(|i| open_escrow_share(&escrow, &key_commitment, i, &secret_keys[i as usize]))
+            .collect();
+        let recovered = recover_key_internal(&escrow, &key_commitment, &shares[2..]).unwrap();
+        assert_eq!(key, *recovered);
+
+        // invalid shares are ignored
+        let mut forged = shares[0].clone();
+        forged[1] ^= 1;
+        let res = recover_key_internal(&escrow, &key_commitment, &[forged, shares[1].clone()]);
+        assert_eq!(
+            Err("2 valid shares are needed, 1 were given".to_string()),
+            res
+        );
+        let res = recover_key_internal(
+            &escrow,
+            &key_commitment,
+            &[shares[1].clone(), shares[1].clone()],
+        );
+        assert!(res.is_err());
+
+        // the escrow must be for the committed key
+        let res = escrow_key_with_rng(&[0u8; 16], &key_commitment, 2, &public_keys, &mut rng);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "The sealed message cannot be opened")]
+    fn test_open_share_of_other_sponsor() {
+        let mut rng = rand::rng();
+        let key = vec![9u8; 16];
+        let mut precontract = compute_precontract_values(&mut [1u8; 100], &key);
+        let public_keys = vec![
+            sealing_public_key(&[1u8; 32]),
+            sealing_public_key(&[2u8; 32]),
+        ];
+        precontract.escrow_key_with_rng(&key, 1, &public_keys, &mut rng);
+
+        open_escrow_share(
+            precontract.escrow.as_ref().unwrap(),
+            precontract.key_commitment.as_ref().unwrap(),
+            1,
+            &[1u8; 32],
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::accumulator::uint8_array_to_vec_u8;
+use crate::encryption::Cipher;
+use crate::utils::die;
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::wasm_bindgen;
+use zeroize::{Zeroize, Zeroizing};
+
+/*
+ * data = [
+ *      hash key H (16 bytes),
+ *      previous GHASH value (16 bytes, optional),
+ *      blocks (<=64 bytes)
+ * ]
+ */
+
+// reduction constant of GF(2^128): x^128 + x^7 + x^2 + x + 1 in the bit order of GCM
+const R: u128 = 0xe1 << 120;
+
+// multiplication in GF(2^128) as specified by NIST SP 800-38D, algorithm 1. The first bit of a
+// block is the most significant bit of the big endian integer. Masks are used instead of branches
+// since H is secret.
+fn gf128_mul(x: u128, y: u128) -> u128 {
+    let mut z = 0u128;
+    let mut v = y;
+    for i in 0..128 {
+        z ^= v & 0u128.wrapping_sub((x >> (127 - i)) & 1);
+        v = (v >> 1) ^ (R & 0u128.wrapping_sub(v & 1));
+    }
+
+    z
+}
+
+/// Updates a GHASH value with data. The data is processed by blocks of 16 bytes, the last one
+/// being padded with zeros.
+///
+/// # Arguments
+/// * `h` - Hash key H (16 bytes)
+/// * `y` - Previous GHASH value (16 bytes)
+/// * `data` - Data to hash
+///
+/// # Returns
+/// The new GHASH value
+pub fn ghash_update(h: &[u8; 16], y: &[u8; 16], data: &[u8]) -> [u8; 16] {
+    let h = u128::from_be_bytes(*h);
+    let mut y = u128::from_be_bytes(*y);
+    for chunk in data.chunks(16) {
+        let mut block = [0u8; 16];
+        block[..chunk.len()].copy_from_slice(chunk);
+        y = gf128_mul(y ^ u128::from_be_bytes(block), h);
+    }
+
+    y.to_be_bytes()
+}
+
+/// Multiply-accumulate step of GHASH: for each block X of 16 bytes of the data (the last one
+/// being padded with zeros), `Y = (Y xor X) * H` in GF(2^128). The first step of a GHASH
+/// computation has no previous value and starts with `Y = 0`.
+///
+/// # Arguments
+/// * `data` - Vector containing:
+///   - hash key H (16 bytes)
+///   - previous GHASH value (16 bytes, optional)
+///   - blocks to hash (<=64 bytes)
+///
+/// # Returns
+/// The new GHASH value (16 bytes)
+pub fn ghash_mult_acc(data: &[&Vec<u8>]) -> Vec<u8> {
+    if data.len() != 2 && data.len() != 3 {
+        die("GHASH requires a hash key, an optional previous value and blocks");
+    }
+
+    let h: [u8; 16] = match data[0].as_slice().try_into() {
+        Ok(h) => h,
+        Err(_) => die("GHASH requires a hash key of exactly 16 bytes"),
+    };
+    let y: [u8; 16] = if data.len() == 2 {
+        [0u8; 16]
+    } else {
+        match data[1].as_slice().try_into() {
+            Ok(y) => y,
+            Err(_) => die("GHASH requires a previous value of exactly 16 bytes"),
+        }
+    };
+
+    let blocks = data[data.len() - 1];
+    if blocks.len() > 64 {
+        die("GHASH requires blocks of at most 64 bytes");
+    }
+
+    ghash_update(&h, &y, blocks).to_vec()
+}
+
+/// Computes the authentication tag of AES-128-GCM for a ciphertext without additional
+/// authenticated data
+///
+/// # Arguments
+/// * `key` - Key bytes (16 bytes)
+/// * `j0` - Pre-counter block J0 (16 bytes)
+/// * `ct` - Ciphertext, without IV nor tag
+///
+/// # Returns
+/// The tag (16 bytes)
+pub fn compute_tag(key: &[u8], j0: &[u8], ct: &[u8]) -> Vec<u8> {
+    let mut tag = GcmTag::new(key, j0);
+    tag.update(ct);
+    tag.finish()
+}
+
+/// Computes the authentication tag of AES-128-GCM for a ciphertext received by chunks
+pub struct GcmTag {
+    key: Zeroizing<Vec<u8>>,
+    j0: Vec<u8>,
+    h: [u8; 16],
+    y: [u8; 16],
+    // bytes of the last incomplete 16 bytes block
+    pending: Vec<u8>,
+    ct_len: u64,
+}
+
+// H and the GHASH value reveal information about the key
+impl Drop for GcmTag {
+    fn drop(&mut self) {
+        self.h.zeroize();
+        self.y.zeroize();
+    }
+}
+
+impl GcmTag {
+    /// Starts the computation of a tag
+    ///
+    /// # Arguments
+    /// * `key` - Key bytes (16 bytes)
+    /// * `j0` - Pre-counter block J0 (16 bytes)
+    pub fn new(key: &[u8], j0: &[u8]) -> GcmTag {
+        // H = E_K(0^128)
+        let mut h = [0u8; 16];
+        Cipher::Aes128Ctr.apply_keystream(key, &[0u8; 16], &mut h);
+
+        GcmTag {
+            key: Zeroizing::new(key.to_vec()),
+            j0: j0.to_vec(),
+            h,
+            y: [0u8; 16],
+            pending: Vec::with_capacity(16),
+            ct_len: 0,
+        }
+    }
+
+    /// Adds the next chunk of the ciphertext
+    ///
+    /// # Arguments
+    /// * `ct` - Next bytes of the ciphertext
+    pub fn update(&mut self, mut ct: &[u8]) {
+        self.ct_len += ct.len() as u64;
+        if !self.pending.is_empty() {
+            let missing = (16 - self.pending.len()).min(ct.len());
+            self.pending.extend(&ct[..missing]);
+            ct = &ct[missing..];
+            if self.pending.len() < 16 {
+                return;
+            }
+            self.y = ghash_update(&self.h, &self.y, &self.pending);
+            self.pending.clear();
+        }
+
+        let full = ct.len() - ct.len() % 16;
+        self.y = ghash_update(&self.h, &self.y, &ct[..full]);
+        self.pending.extend(&ct[full..]);
+    }
+
+    /// Finishes the computation
+    ///
+    /// # Returns
+    /// The tag (16 bytes)
+    pub fn finish(mut self) -> Vec<u8> {
+        let mut lengths = [0u8; 16];
+        lengths[8..].copy_from_slice(&(self.ct_len * 8).to_be_bytes());
+        let y = ghash_update(&self.h, &self.y, &self.pending);
+        self.y = ghash_update(&self.h, &y, &lengths);
+
+        // T = E_K(J0) xor S
+        let mut tag = self.y.to_vec();
+        Cipher::Aes128Ctr.apply_keystream(&self.key, &self.j0, &mut tag);
+
+        tag
+    }
+}
+
+/// JavaScript wrapper for ghash_mult_acc
+///
+/// # Arguments
+/// * `data` - Vector of Uint8Arrays containing:
+///   - hash key H (16 bytes)
+///   - previous GHASH value (16 bytes, optional)
+///   - blocks to hash (<=64 bytes)
+///
+/// # Returns
+/// The new GHASH value
+#[wasm_bindgen]
+pub fn ghash_mult_acc_js(data: Vec<Uint8Array>) -> Vec<u8> {
+    let values_vec: Vec<Vec<u8>> = data.iter().map(uint8_array_to_vec_u8).collect();
+    let refs: Vec<&Vec<u8>> = values_vec.iter().collect();
+    ghash_mult_acc(&refs)
+}
+
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hex_to_bytes;
+
+    fn hex(s: &str) -> Vec<u8> {
+        hex_to_bytes(format!("0x{}", s))
+    }
+
+    fn j0(nonce: &str) -> Vec<u8> {
+        [hex(nonce), 1u32.to_be_bytes().to_vec()].concat()
+    }
+
+    #[test]
+    fn test_gcm_spec_tags() {
+        // test cases 1 and 2 of the GCM specification (McGrew and Viega)
+        let key = vec![0u8; 16];
+        let nonce = "000000000000000000000000";
+        assert_eq!(
+            hex("58e2fccefa7e3061367f1d57a4e7455a"),
+            compute_tag(&key, &j0(nonce), &[])
+        );
+        assert_eq!(
+            hex("ab6e47d42cec13bdf53a67b21257bddf"),
+            compute_tag(&key, &j0(nonce), &hex("0388dace60b6a392f328c2b971b2fe78"))
+        );
+
+        // test case 3
+        let key = hex("feffe9928665731c6d6a8f9467308308");
+        let ct = hex("42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985");
+        assert_eq!(
+            hex("4d5c2af327cd64a62cf35abd2ba6fab4"),
+            compute_tag(&key, &j0("cafebabefacedbaddecaf888"), &ct)
+        );
+    }
+
+    #[test]
+    fn test_ghash_mult_acc() {
+        let key = hex("feffe9928665731c6d6a8f9467308308");
+        let mut h = vec![0u8; 16];
+        Cipher::Aes128Ctr.apply_keystream(&key, &[0u8; 16], &mut h);
+        let data: Vec<u8> = (0..150).collect();
+        let expected = ghash_update(&h.clone().try_into().unwrap(), &[0u8; 16], &data);
+
+        // chaining the instruction over blocks of 64 bytes gives the same value
+        let mut y = ghash_mult_acc(&[&h, &data[..64].to_vec()]);
+        for block in data[64..].chunks(64) {
+            y = ghash_mult_acc(&[&h, &y, &block.to_vec()]);
+        }
+        assert_eq!(expected.to_vec(), y);
+    }
+
+    #[test]
+    fn test_gcm_tag_chunks() {
+        let key = hex("feffe9928665731c6d6a8f9467308308");
+        let j0 = j0("cafebabefacedbaddecaf888");
+        let ct: Vec<u8> = (0..200).collect();
+        let expected = compute_tag(&key, &j0, &ct);
+
+        let mut tag = GcmTag::new(&key, &j0);
+        for chunk in [&ct[..3], &ct[3..10], &ct[10..40], &ct[40..40], &ct[40..]] {
+            tag.update(chunk);
+        }
+        assert_eq!(expected, tag.finish());
+    }
+
+    #[test]
+    #[should_panic(expected = "GHASH requires blocks of at most 64 bytes")]
+    fn test_ghash_block_size() {
+        ghash_mult_acc(&[&vec![0u8; 16], &vec![0u8; 65]]);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::encryption::Cipher;
+use crate::random::seeded_rng;
+use crate::utils::die;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use wasm_bindgen::prelude::wasm_bindgen;
+use zeroize::Zeroizing;
+
+// HKDF info labels, followed by the context
+const FILE_KEY_LABEL: &[u8] = b"sox file key";
+const RANDOMNESS_LABEL: &[u8] = b"sox precontract randomness";
+
+// HKDF-SHA256 without salt (RFC 5869)
+fn hkdf_sha256(ikm: &[u8], info: &[u8], len: usize) -> Zeroizing<Vec<u8>> {
+    let mut okm = Zeroizing::new(vec![0u8; len]);
+    if Hkdf::<Sha256>::new(None, ikm)
+        .expand(info, &mut okm)
+        .is_err()
+    {
+        die("HKDF cannot derive that many bytes");
+    }
+
+    okm
+}
+
+// derives `len` bytes for the given label and context
+fn derive(master_secret: &[u8], label: &[u8], context: &[u8], len: usize) -> Zeroizing<Vec<u8>> {
+    if master_secret.len() < 16 {
+        die("The master secret must be at least 16 bytes");
+    }
+
+    hkdf_sha256(master_secret, &[label, context].concat(), len)
+}
+
+/// Encodes the context of a precontract's derived values. Each field is prefixed by its length
+/// (4 bytes, big endian), so that different fields never give the same context.
+///
+/// # Arguments
+/// * `description` - Description of the file (32 bytes)
+/// * `buyer` - Address of the buyer (20 bytes)
+/// * `nonce` - Nonce distinguishing several sales of the same file to the same buyer
+///
+/// # Returns
+/// The encoded context
+#[wasm_bindgen]
+pub fn file_key_context(description: &[u8], buyer: &[u8], nonce: &[u8]) -> Vec<u8> {
+    if description.len() != 32 {
+        die("The description must be 32 bytes");
+    }
+    if buyer.len() != 20 {
+        die("The buyer's address must be 20 bytes");
+    }
+
+    [description, buyer, nonce]
+        .iter()
+        .flat_map(|field| [&(field.len() as u32).to_be_bytes()[..], field].concat())
+        .collect()
+}
+
+/// Checks that a context was encoded by `file_key_context` for the given description. Two files
+/// must never share a context, since they would be encrypted with the same key and IV.
+///
+/// # Arguments
+/// * `context` - Context of the precontract, see `file_key_context`
+/// * `description` - Description of the file (32 bytes)
+pub fn check_context_description(context: &[u8], description: &[u8]) {
+    let field = [&(description.len() as u32).to_be_bytes()[..], description].concat();
+    if !context.starts_with(&field) {
+        die("The context does not match the file's description");
+    }
+}
+
+/// Derives the key of a file from the vendor's master secret with HKDF-SHA256. Keys derived for
+/// different contexts or ciphers are independent.
+///
+/// # Arguments
+/// * `master_secret` - The vendor's master secret (at least 16 bytes)
+/// * `context` - Context of the precontract, see `file_key_context`
+/// * `cipher` - Cipher of the key (AES-128 if not given)
+///
+/// # Returns
+/// The key (`cipher.key_len()` bytes)
+#[wasm_bindgen]
+pub fn derive_file_key(master_secret: &[u8], context: &[u8], cipher: Option<Cipher>) -> Vec<u8> {
+    let cipher = cipher.unwrap_or(Cipher::Aes128Ctr);
+    let label = [FILE_KEY_LABEL, &[cipher as u8]].concat();
+
+    derive(master_secret, &label, context, cipher.key_len()).to_vec()
+}
+
+/// Derives the seed of the generator from which the IV and the commitment's random bytes of a
+/// precontract are drawn
+///
+/// # Arguments
+/// * `master_secret` - The vendor's master secret (at least 16 bytes)
+/// * `context` - Context of the precontract, see `file_key_context`
+///
+/// # Returns
+/// The seed (32 bytes)
+pub fn derive_precontract_seed(master_secret: &[u8], context: &[u8]) -> Zeroizing<Vec<u8>> {
+    derive(master_secret, RANDOMNESS_LABEL, context, 32)
+}
+
+/// Derives the IV of a file from the vendor's master secret. It is the IV of the precontract
+/// computed by `compute_precontract_values_from_master` with the same arguments.
+///
+/// # Arguments
+/// * `master_secret` - The vendor's master secret (at least 16 bytes)
+/// * `context` - Context of the precontract, see `file_key_context`
+/// * `cipher` - Cipher of the file (AES-128 if not given)
+///
+/// # Returns
+/// The IV (16 bytes), see `Cipher::generate_iv` for its content
+#[wasm_bindgen]
+pub fn derive_file_iv(master_secret: &[u8], context: &[u8], cipher: Option<Cipher>) -> Vec<u8> {
+    let seed = derive_precontract_seed(master_secret, context);
+
+    cipher
+        .unwrap_or(Cipher::Aes128Ctr)
+        .generate_iv(&mut seeded_rng(&seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hex_to_bytes;
+
+    const MASTER: &[u8] = b"vendor master secret";
+
+    fn context(nonce: u64) -> Vec<u8> {
+        file_key_context(&[1u8; 32], &[2u8; 20], &nonce.to_be_bytes())
+    }
+
+    #[test]
+    fn test_hkdf_sha256() {
+        // RFC 5869, test case 3 (no salt, no info)
+        let okm = hkdf_sha256(&[0x0b; 22], &[], 42);
+        assert_eq!(
+            hex_to_bytes("0x8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8".to_string()),
+            *okm
+        );
+    }
+
+    #[test]
+    fn test_derive_file_key() {
+        let key = derive_file_key(MASTER, &context(0), None);
+        assert_eq!(16, key.len());
+        assert_eq!(key, derive_file_key(MASTER, &context(0), None));
+        assert_ne!(key, derive_file_key(MASTER, &context(1), None));
+        assert_ne!(
+            key,
+            derive_file_key(b"other master secret", &context(0), None)
+        );
+
+        // keys of different ciphers are independent
+        let gcm_key = derive_file_key(MASTER, &context(0), Some(Cipher::Aes128Gcm));
+        assert_ne!(key, gcm_key);
+        let chacha_key = derive_file_key(MASTER, &context(0), Some(Cipher::ChaCha20));
+        assert_eq!(32, chacha_key.len());
+
+        // the key is not the IV seed
+        assert_ne!(key[..], derive_precontract_seed(MASTER, &context(0))[..16]);
+    }
+
+    #[test]
+    fn test_file_key_context() {
+        // the fields cannot be shifted into each other
+        assert_ne!(
+            file_key_context(&[1u8; 32], &[2u8; 20], &[3, 4]),
+            file_key_context(&[1u8; 32], &[2u8; 20], &[3, 4, 0])
+        );
+        assert_eq!(4 + 32 + 4 + 20 + 4 + 8, context(0).len());
+    }
+
+    #[test]
+    fn test_derive_file_iv() {
+        let iv = derive_file_iv(MASTER, &context(0), None);
+        assert_eq!(16, iv.len());
+        assert_eq!(iv, derive_file_iv(MASTER, &context(0), None));
+        assert_ne!(iv, derive_file_iv(MASTER, &context(1), None));
+
+        let iv = derive_file_iv(MASTER, &context(0), Some(Cipher::ChaCha20));
+        assert_eq!(1u32.to_be_bytes(), iv[12..]);
+    }
+
+    #[test]
+    fn test_check_context_description() {
+        check_context_description(&context(0), &[1u8; 32]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The context does not match the file's description")]
+    fn test_check_context_other_description() {
+        check_context_description(&context(0), &[3u8; 32]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The master secret must be at least 16 bytes")]
+    fn test_short_master_secret() {
+        derive_file_key(&[0u8; 8], &context(0), None);
+    }
+}
@@ -0,0 +1,380 @@
+use crate::random::RandomSource;
+use crate::secret::{ct_eq, SecretBytes};
+use crate::sha256::sha256;
+use crate::utils::die;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use hkdf::Hkdf;
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::PrimeField;
+use k256::{FieldBytes, ProjectivePoint, PublicKey, Scalar, SecretKey, U256};
+use sha2::Sha256;
+use wasm_bindgen::prelude::wasm_bindgen;
+use zeroize::Zeroizing;
+
+/*
+ * ECIES over secp256k1, so that the key is delivered to the buyer's Ethereum key:
+ *
+ *      wrapped key = [
+ *          ephemeral public key E = eG (33 bytes, compressed),
+ *          key encrypted with ChaCha20-Poly1305 (key length + 16 bytes)
+ *      ]
+ *
+ * The encryption key is derived with HKDF-SHA256 from the shared point S = eP = xE (compressed),
+ * where P = xG is the buyer's public key, E, P and the context. Revealing S with a proof that
+ * log_G(P) = log_E(S) (Chaum-Pedersen) lets anyone unwrap the key without the buyer's secret
+ * key.
+ */
+
+const WRAP_LABEL: &[u8] = b"sox key delivery";
+const DLEQ_LABEL: &[u8] = b"sox shared secret disclosure";
+
+// size of a compressed point
+const POINT_LEN: usize = 33;
+
+fn parse_public_key(bytes: &[u8]) -> Result<ProjectivePoint, String> {
+    PublicKey::from_sec1_bytes(bytes)
+        .map(|pk| pk.to_projective())
+        .map_err(|_| "Invalid secp256k1 public key".to_string())
+}
+
+fn parse_secret_key(bytes: &[u8]) -> Result<SecretKey, String> {
+    SecretKey::from_slice(bytes).map_err(|_| "Invalid secp256k1 secret key".to_string())
+}
+
+fn compress(point: &ProjectivePoint) -> Vec<u8> {
+    point.to_affine().to_encoded_point(true).as_bytes().to_vec()
+}
+
+// splits a wrapped key into its ephemeral public key and the encrypted key
+fn split_wrapped(wrapped_key: &[u8]) -> Result<(ProjectivePoint, &[u8]), String> {
+    if wrapped_key.len() < POINT_LEN + 16 {
+        return Err("The wrapped key is too short".to_string());
+    }
+    let (ephemeral, encrypted) = wrapped_key.split_at(POINT_LEN);
+
+    Ok((parse_public_key(ephemeral)?, encrypted))
+}
+
+// ChaCha20-Poly1305 keyed by the shared point
+fn aead(
+    shared: &ProjectivePoint,
+    ephemeral: &ProjectivePoint,
+    buyer: &ProjectivePoint,
+    context: &[u8],
+) -> ChaCha20Poly1305 {
+    let info = [WRAP_LABEL, &compress(ephemeral), &compress(buyer), context].concat();
+    let mut key = Zeroizing::new([0u8; 32]);
+    if Hkdf::<Sha256>::new(None, &compress(shared))
+        .expand(&info, key.as_mut())
+        .is_err()
+    {
+        die("HKDF cannot derive that many bytes");
+    }
+
+    ChaCha20Poly1305::new(key.as_ref().into())
+}
+
+// decrypts the key once the shared point is known
+fn unwrap_with_shared(
+    shared: &ProjectivePoint,
+    ephemeral: &ProjectivePoint,
+    buyer: &ProjectivePoint,
+    encrypted: &[u8],
+    context: &[u8],
+) -> Result<SecretBytes, String> {
+    aead(shared, ephemeral, buyer, context)
+        .decrypt(
+            &Nonce::default(),
+            Payload {
+                msg: encrypted,
+                aad: context,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| "The wrapped key cannot be unwrapped".to_string())
+}
+
+fn random_scalar(rng: &mut RandomSource) -> Zeroizing<Scalar> {
+    let mut bytes = Zeroizing::new([0u8; 32]);
+    rng.fill_bytes(bytes.as_mut());
+
+    Zeroizing::new(<Scalar as Reduce<U256>>::reduce_bytes(&FieldBytes::from(
+        *bytes,
+    )))
+}
+
+// challenge of the Chaum-Pedersen proof
+fn dleq_challenge(points: [&ProjectivePoint; 6]) -> Scalar {
+    let mut preimage = DLEQ_LABEL.to_vec();
+    for point in points {
+        preimage.extend(compress(point));
+    }
+    let hash: [u8; 32] = sha256(&preimage).try_into().unwrap();
+
+    <Scalar as Reduce<U256>>::reduce_bytes(&FieldBytes::from(hash))
+}
+
+/// Computes the compressed secp256k1 public key of a secret key (e.g. of an Ethereum account)
+///
+/// # Arguments
+/// * `secret_key` - The secret key (32 bytes)
+///
+/// # Returns
+/// The public key (33 bytes)
+#[wasm_bindgen]
+pub fn delivery_public_key(secret_key: &[u8]) -> Vec<u8> {
+    match parse_secret_key(secret_key) {
+        Ok(secret) => compress(&secret.public_key().to_projective()),
+        Err(msg) => die(&msg),
+    }
+}
+
+/// Encrypts a key to the buyer's secp256k1 public key
+///
+/// # Arguments
+/// * `key` - The key to deliver
+/// * `buyer_public_key` - The buyer's public key (SEC1 encoded, compressed or not)
+/// * `context` - Data the key is bound to, e.g. the precontract's commitment
+/// * `rng` - Source of the ephemeral key
+///
+/// # Returns
+/// The wrapped key, 49 bytes longer than the key
+pub fn wrap_key_with_rng(
+    key: &[u8],
+    buyer_public_key: &[u8],
+    context: &[u8],
+    rng: &mut RandomSource,
+) -> Result<Vec<u8>, String> {
+    let buyer = parse_public_key(buyer_public_key)?;
+    let e = random_scalar(rng);
+    let ephemeral = ProjectivePoint::GENERATOR * *e;
+    let shared = buyer * *e;
+
+    let encrypted = aead(&shared, &ephemeral, &buyer, context)
+        .encrypt(
+            &Nonce::default(),
+            Payload {
+                msg: key,
+                aad: context,
+            },
+        )
+        .map_err(|_| "The key cannot be wrapped".to_string())?;
+
+    Ok([compress(&ephemeral), encrypted].concat())
+}
+
+/// Encrypts a key to the buyer's secp256k1 public key, see `wrap_key_with_rng`
+///
+/// # Arguments
+/// * `key` - The key to deliver
+/// * `buyer_public_key` - The buyer's public key (SEC1 encoded, compressed or not)
+/// * `context` - Data the key is bound to, e.g. the precontract's commitment
+///
+/// # Returns
+/// The wrapped key, to send instead of the key
+#[wasm_bindgen]
+pub fn wrap_key(key: &[u8], buyer_public_key: &[u8], context: &[u8]) -> Vec<u8> {
+    match wrap_key_with_rng(key, buyer_public_key, context, &mut rand::rng()) {
+        Ok(wrapped) => wrapped,
+        Err(msg) => die(&msg),
+    }
+}
+
+/// Decrypts a wrapped key with the buyer's secret key
+///
+/// # Arguments
+/// * `wrapped_key` - The wrapped key
+/// * `buyer_secret_key` - The buyer's secret key (32 bytes)
+/// * `context` - Data the key was bound to
+///
+/// # Returns
+/// The key, or an error if it was not wrapped for this buyer and context
+pub fn unwrap_key(
+    wrapped_key: &[u8],
+    buyer_secret_key: &[u8],
+    context: &[u8],
+) -> Result<SecretBytes, String> {
+    let secret = parse_secret_key(buyer_secret_key)?;
+    let (ephemeral, encrypted) = split_wrapped(wrapped_key)?;
+    let x = secret.to_nonzero_scalar();
+    let buyer = ProjectivePoint::GENERATOR * *x;
+
+    unwrap_with_shared(&(ephemeral * *x), &ephemeral, &buyer, encrypted, context)
+}
+
+/// Shared point of a wrapped key revealed with a proof that it is the one of the buyer's key, so
+/// that anyone (e.g. a sponsor during a dispute) can unwrap the key. Only the buyer can compute
+/// it, but anyone it gives the disclosure to can publish it.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyDisclosure {
+    /// The shared point (33 bytes, compressed)
+    #[wasm_bindgen(getter_with_clone)]
+    pub shared_secret: Vec<u8>,
+
+    /// Chaum-Pedersen proof: challenge and response (32 bytes each)
+    #[wasm_bindgen(getter_with_clone)]
+    pub proof: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl KeyDisclosure {
+    /// Creates a disclosure from the values published by the buyer
+    #[wasm_bindgen(constructor)]
+    pub fn new(shared_secret: Vec<u8>, proof: Vec<u8>) -> KeyDisclosure {
+        KeyDisclosure {
+            shared_secret,
+            proof,
+        }
+    }
+}
+
+/// Reveals the shared point of a wrapped key with a proof of its correctness
+///
+/// # Arguments
+/// * `wrapped_key` - The wrapped key
+/// * `buyer_secret_key` - The buyer's secret key (32 bytes)
+/// * `rng` - Source of the proof's nonce
+///
+/// # Returns
+/// The `KeyDisclosure`
+pub fn disclose_shared_secret_with_rng(
+    wrapped_key: &[u8],
+    buyer_secret_key: &[u8],
+    rng: &mut RandomSource,
+) -> Result<KeyDisclosure, String> {
+    let secret = parse_secret_key(buyer_secret_key)?;
+    let (ephemeral, _) = split_wrapped(wrapped_key)?;
+    let x = secret.to_nonzero_scalar();
+    let buyer = ProjectivePoint::GENERATOR * *x;
+    let shared = ephemeral * *x;
+
+    let r = random_scalar(rng);
+    let c = dleq_challenge([
+        &buyer,
+        &ephemeral,
+        &shared,
+        &(ProjectivePoint::GENERATOR * *r),
+        &(ephemeral * *r),
+        &ProjectivePoint::GENERATOR,
+    ]);
+    let s = *r + c * *x;
+
+    Ok(KeyDisclosure {
+        shared_secret: compress(&shared),
+        proof: [c.to_bytes(), s.to_bytes()].concat(),
+    })
+}
+
+/// Reveals the shared point of a wrapped key, see `disclose_shared_secret_with_rng`
+///
+/// # Arguments
+/// * `wrapped_key` - The wrapped key
+/// * `buyer_secret_key` - The buyer's secret key (32 bytes)
+///
+/// # Returns
+/// The `KeyDisclosure` to publish
+#[wasm_bindgen]
+pub fn disclose_shared_secret(wrapped_key: &[u8], buyer_secret_key: &[u8]) -> KeyDisclosure {
+    match disclose_shared_secret_with_rng(wrapped_key, buyer_secret_key, &mut rand::rng()) {
+        Ok(disclosure) => disclosure,
+        Err(msg) => die(&msg),
+    }
+}
+
+/// Unwraps a key with a disclosed shared point after checking its proof
+///
+/// # Arguments
+/// * `wrapped_key` - The wrapped key
+/// * `buyer_public_key` - The buyer's public key (SEC1 encoded, compressed or not)
+/// * `context` - Data the key was bound to
+/// * `disclosure` - The disclosed shared point and its proof
+///
+/// # Returns
+/// `Ok(Some(key))` if the proof is valid and the key can be unwrapped, `Ok(None)` if the proof is
+/// valid but the wrapped key cannot be unwrapped, an error if the proof is invalid
+pub fn unwrap_disclosed_key(
+    wrapped_key: &[u8],
+    buyer_public_key: &[u8],
+    context: &[u8],
+    disclosure: &KeyDisclosure,
+) -> Result<Option<SecretBytes>, String> {
+    let invalid = || "Invalid shared secret disclosure".to_string();
+    let buyer = parse_public_key(buyer_public_key)?;
+    let (ephemeral, encrypted) = split_wrapped(wrapped_key)?;
+    let shared = parse_public_key(&disclosure.shared_secret).map_err(|_| invalid())?;
+    if disclosure.proof.len() != 64 {
+        return Err(invalid());
+    }
+    let scalar = |bytes: &[u8]| {
+        Option::<Scalar>::from(Scalar::from_repr(*FieldBytes::from_slice(bytes)))
+            .ok_or_else(invalid)
+    };
+    let c = scalar(&disclosure.proof[..32])?;
+    let s = scalar(&disclosure.proof[32..])?;
+
+    // sG - cP = rG and sE - cS = rE if S = xE
+    let expected = dleq_challenge([
+        &buyer,
+        &ephemeral,
+        &shared,
+        &(ProjectivePoint::GENERATOR * s - buyer * c),
+        &(ephemeral * s - shared * c),
+        &ProjectivePoint::GENERATOR,
+    ]);
+    if !ct_eq(&expected.to_bytes(), &c.to_bytes()) {
+        return Err(invalid());
+    }
+
+    Ok(unwrap_with_shared(&shared, &ephemeral, &buyer, encrypted, context).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUYER: [u8; 32] = [0x42; 32];
+
+    #[test]
+    fn test_wrap_unwrap() {
+        let mut rng = rand::rng();
+        let key = vec![5u8; 16];
+        let public_key = delivery_public_key(&BUYER);
+        assert_eq!(POINT_LEN, public_key.len());
+
+        let wrapped = wrap_key_with_rng(&key, &public_key, b"context", &mut rng).unwrap();
+        assert_eq!(key.len() + 49, wrapped.len());
+        assert_eq!(key, *unwrap_key(&wrapped, &BUYER, b"context").unwrap());
+
+        assert!(unwrap_key(&wrapped, &[0x43; 32], b"context").is_err());
+        assert!(unwrap_key(&wrapped, &BUYER, b"other context").is_err());
+        let mut modified = wrapped.clone();
+        modified[40] ^= 1;
+        assert!(unwrap_key(&modified, &BUYER, b"context").is_err());
+    }
+
+    #[test]
+    fn test_disclosure() {
+        let mut rng = rand::rng();
+        let key = vec![5u8; 32];
+        let public_key = delivery_public_key(&BUYER);
+        let wrapped = wrap_key_with_rng(&key, &public_key, b"context", &mut rng).unwrap();
+
+        let disclosure = disclose_shared_secret_with_rng(&wrapped, &BUYER, &mut rng).unwrap();
+        let unwrapped = unwrap_disclosed_key(&wrapped, &public_key, b"context", &disclosure);
+        assert_eq!(key, *unwrapped.unwrap().unwrap());
+
+        // the proof binds the shared point to the buyer's key
+        let other = disclose_shared_secret_with_rng(&wrapped, &[0x43; 32], &mut rng).unwrap();
+        assert!(unwrap_disclosed_key(&wrapped, &public_key, b"context", &other).is_err());
+        let mut forged = disclosure.clone();
+        forged.shared_secret = other.shared_secret;
+        assert!(unwrap_disclosed_key(&wrapped, &public_key, b"context", &forged).is_err());
+
+        // a valid disclosure of a wrapped key that cannot be unwrapped
+        let unwrapped = unwrap_disclosed_key(&wrapped, &public_key, b"other context", &disclosure);
+        assert!(unwrapped.unwrap().is_none());
+    }
+}
@@ -1,25 +1,59 @@
 mod accumulator;
 mod aes_ctr;
+mod agents;
+mod builder;
+mod bundle;
+mod calldata;
+mod canonical;
+mod chacha_ctr;
 mod circuits;
 mod commitment;
+mod dispute;
+mod eip712;
 mod encryption;
+mod escrow;
+mod gcm;
+mod kdf;
+mod key_delivery;
+mod random;
+mod sealing;
+mod secret;
 mod sha256;
 mod simple_operations;
+pub mod simulator;
+pub mod trace;
+mod transcript;
 mod utils;
 
-use crate::accumulator::{acc, acc_circuit, acc_ct, proof_to_js_array, prove, prove_ext};
+use crate::accumulator::{acc_circuit, acc_ct, proof_to_js_array, uint8_array_to_vec_u8};
+use crate::canonical::{decode_canonical_or_legacy, Canonical};
 use crate::circuits::{
-    compile_basic_circuit, evaluate_circuit_internal, get_evaluated_sons, is_constant_idx,
-    CompiledCircuit,
+    compile_basic_circuit_with_cipher, evaluate_circuit_internal, CompiledCircuit, Gate,
 };
-use crate::commitment::{commit_hashes, open_commitment_internal, Commitment};
-use crate::encryption::{decrypt, encrypt_and_prepend_iv};
+use crate::commitment::{
+    commit_hashes, commit_key_with_rng, open_commitment_internal, Commitment, KeyCommitment,
+};
+use crate::dispute::{
+    compute_proof_right_trace, compute_proofs_left_trace, compute_proofs_trace, hpre_multi_trace,
+    verify_final_step, FinalStepContext, FinalStepError, FinalStepProofs, FinalStepSubmission,
+};
+use crate::eip712::ArgumentSigner;
+use crate::encryption::{
+    check_tag, decrypt, encrypt_and_prepend_iv, is_gcm_pre_counter_block, Cipher,
+};
+use crate::escrow::KeyEscrow;
+use crate::kdf::{check_context_description, derive_file_key, derive_precontract_seed};
+use crate::key_delivery::{unwrap_disclosed_key, unwrap_key, KeyDisclosure};
+use crate::random::{seeded_rng, RandomSource};
+use crate::sealing::{open_sealed_from, seal_from_with_rng};
+use crate::secret::{ct_eq, secret_from_hex, secrets_from_hex};
 use crate::sha256::sha256;
-use crate::utils::{error, hex_to_bytes, split_ct_blocks};
+use crate::trace::Trace;
+use crate::utils::{die, error, hex_to_bytes, split_ct_blocks};
 use js_sys::{Array, Number, Uint8Array};
-use rmp_serde::{decode::from_read, encode::write};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
+use zeroize::{Zeroize, Zeroizing};
 
 // ####################################
 // ###     PRECONTRACT VENDOR       ###
@@ -58,6 +92,18 @@ pub struct Precontract {
 
     /// Number of gates in the circuit
     pub num_gates: u32,
+
+    /// Cipher used to encrypt the file
+    pub cipher: Cipher,
+
+    /// Commitment to the key, sent to the buyer with the precontract (absent from the bundles
+    /// written before it was introduced)
+    #[wasm_bindgen(getter_with_clone)]
+    pub key_commitment: Option<KeyCommitment>,
+
+    /// Escrow of the key among sponsors, if the vendor escrowed it with `escrow_key`
+    #[wasm_bindgen(getter_with_clone)]
+    pub escrow: Option<KeyEscrow>,
 }
 
 /// Computes precontract values for a file. This includes encryption, circuit compilation,
@@ -65,21 +111,143 @@ pub struct Precontract {
 ///
 /// # Arguments
 /// * `file` - The file data to be encrypted
-/// * `key` - The encryption key
+/// * `key` - The encryption key (16 bytes for AES-128, 32 bytes for AES-256)
 ///
 /// # Returns
 /// A `Precontract` containing all necessary components for the optimistic phase of the protocol
 #[wasm_bindgen]
 pub fn compute_precontract_values(file: &mut [u8], key: &[u8]) -> Precontract {
+    compute_precontract_values_with_rng(file, key, Cipher::for_key(key), false, &mut rand::rng())
+}
+
+/// Computes precontract values for a file encrypted with the given cipher. This is the only way
+/// to use ChaCha20, since its keys have the same length as AES-256 keys.
+///
+/// # Arguments
+/// * `file` - The file data to be encrypted
+/// * `key` - The encryption key (`cipher`'s key length)
+/// * `cipher` - The cipher
+///
+/// # Returns
+/// A `Precontract` containing all necessary components for the optimistic phase of the protocol
+#[wasm_bindgen]
+pub fn compute_precontract_values_with_cipher(
+    file: &mut [u8],
+    key: &[u8],
+    cipher: Cipher,
+) -> Precontract {
+    compute_precontract_values_with_rng(file, key, cipher, false, &mut rand::rng())
+}
+
+/// Computes precontract values for a file whose circuit also checks the bound key against the
+/// precontract's key commitment, so that a wrong key makes the circuit fail even if it happens to
+/// decrypt the file correctly
+///
+/// # Arguments
+/// * `file` - The file data to be encrypted
+/// * `key` - The encryption key
+/// * `cipher` - The cipher (selected by the key's length if not given)
+///
+/// # Returns
+/// A `Precontract` containing all necessary components for the optimistic phase of the protocol
+#[wasm_bindgen]
+pub fn compute_precontract_values_with_key_check(
+    file: &mut [u8],
+    key: &[u8],
+    cipher: Option<Cipher>,
+) -> Precontract {
+    let cipher = cipher.unwrap_or_else(|| Cipher::for_key(key));
+    compute_precontract_values_with_rng(file, key, cipher, true, &mut rand::rng())
+}
+
+/// Computes precontract values for a file deterministically: the IV and the commitment's random
+/// bytes are drawn from a ChaCha20 generator seeded with `seed`, so the same inputs always give
+/// the same precontract.
+///
+/// # Arguments
+/// * `file` - The file data to be encrypted
+/// * `key` - The encryption key
+/// * `seed` - Seed of the generator (32 bytes)
+///
+/// # Returns
+/// A `Precontract` containing all necessary components for the optimistic phase of the protocol
+#[wasm_bindgen]
+pub fn compute_precontract_values_seeded(file: &mut [u8], key: &[u8], seed: &[u8]) -> Precontract {
+    compute_precontract_values_with_rng(
+        file,
+        key,
+        Cipher::for_key(key),
+        false,
+        &mut seeded_rng(seed),
+    )
+}
+
+/// Computes precontract values for a file with a key derived from the vendor's master secret. The
+/// IV and the commitment's random bytes are derived as well, so the vendor can regenerate the
+/// key, the ciphertext and the commitment's opening value from the master secret and the
+/// context only.
+///
+/// # Arguments
+/// * `file` - The file data to be encrypted
+/// * `master_secret` - The vendor's master secret (at least 16 bytes)
+/// * `context` - Context of the precontract, see `file_key_context`
+/// * `cipher` - The cipher (AES-128 if not given)
+///
+/// # Returns
+/// A `Precontract` containing all necessary components for the optimistic phase of the protocol.
+/// The key is `derive_file_key(master_secret, context, cipher)`. Fails if the context was not
+/// encoded for the file's description.
+#[wasm_bindgen]
+pub fn compute_precontract_values_from_master(
+    file: &mut [u8],
+    master_secret: &[u8],
+    context: &[u8],
+    cipher: Option<Cipher>,
+) -> Precontract {
+    check_context_description(context, &sha256(file));
+    let key = Zeroizing::new(derive_file_key(master_secret, context, cipher));
+    let seed = derive_precontract_seed(master_secret, context);
+    compute_precontract_values_with_rng(
+        file,
+        &key,
+        cipher.unwrap_or(Cipher::Aes128Ctr),
+        false,
+        &mut seeded_rng(&seed),
+    )
+}
+
+/// Computes precontract values for a file, drawing all the randomness from `rng`
+///
+/// # Arguments
+/// * `file` - The file data to be encrypted
+/// * `key` - The encryption key
+/// * `cipher` - The cipher
+/// * `key_check` - Whether the circuit checks the key against the key commitment
+/// * `rng` - Source of the IV, of the key commitment's salt and of the commitment's random bytes
+///   (drawn in that order)
+///
+/// # Returns
+/// A `Precontract` containing all necessary components for the optimistic phase of the protocol
+pub fn compute_precontract_values_with_rng(
+    file: &mut [u8],
+    key: &[u8],
+    cipher: Cipher,
+    key_check: bool,
+    rng: &mut RandomSource,
+) -> Precontract {
     let description = sha256(file);
-    let ct = encrypt_and_prepend_iv(file, key);
-    let circuit = compile_basic_circuit(ct.len() as u32, &description);
+    let ct = encrypt_and_prepend_iv(file, key, cipher, rng);
+    let key_commitment = commit_key_with_rng(key, rng);
+    let mut circuit = compile_basic_circuit_with_cipher(ct.len() as u32, &description, cipher);
+    if key_check {
+        circuit = circuit.with_key_check(&key_commitment);
+    }
     let num_blocks = circuit.num_blocks;
     let num_gates = circuit.circuit.len() as u32;
     let circuit_bytes = circuit.to_bytes();
     let h_ct = acc_ct(&ct, circuit.block_size as usize);
     let h_circuit = acc_circuit(circuit);
-    let commitment = commit_hashes(&h_circuit, &h_ct);
+    let commitment = commit_hashes(&h_circuit, &h_ct, rng);
 
     Precontract {
         ct,
@@ -90,6 +258,9 @@ pub fn compute_precontract_values(file: &mut [u8], key: &[u8]) -> Precontract {
         commitment,
         num_blocks,
         num_gates,
+        cipher,
+        key_commitment: Some(key_commitment),
+        escrow: None,
     }
 }
 
@@ -120,6 +291,9 @@ pub struct CheckPrecontractResult {
 /// * `commitment` - Hex-encoded commitment
 /// * `opening_value` - Hex-encoded opening value
 /// * `ct` - Ciphertext bytes
+/// * `cipher` - The cipher of the precontract (AES-128 if not given)
+/// * `key_commitment` - The key commitment checked by the circuit, if the precontract was
+///   computed with a key check
 ///
 /// # Returns
 /// A `CheckPrecontractResult` containing the verification status and hash values
@@ -129,9 +303,18 @@ pub fn check_precontract(
     commitment: String,
     opening_value: String,
     ct: &[u8],
+    cipher: Option<Cipher>,
+    key_commitment: Option<KeyCommitment>,
 ) -> CheckPrecontractResult {
     let description_bytes = hex_to_bytes(description);
-    let circuit = compile_basic_circuit(ct.len() as u32, &description_bytes);
+    let mut circuit = compile_basic_circuit_with_cipher(
+        ct.len() as u32,
+        &description_bytes,
+        cipher.unwrap_or(Cipher::Aes128Ctr),
+    );
+    if let Some(key_commitment) = key_commitment {
+        circuit = circuit.with_key_check(&key_commitment);
+    }
     let h_ct = acc_ct(ct, circuit.block_size as usize);
     let h_circuit = acc_circuit(circuit);
     match open_commitment_internal(&hex_to_bytes(commitment), &hex_to_bytes(opening_value)) {
@@ -159,38 +342,125 @@ pub fn check_precontract(
 // ###    BUYER CHECK CT DECRYPTION ###
 // ####################################
 
+/// Verdict of the verification of a received key
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CtCheckVerdict {
+    /// The ciphertext decrypts to a plaintext matching the description
+    Correct,
+
+    /// The key is not the committed one or does not suit the cipher. The ciphertext is not
+    /// decrypted.
+    WrongKey,
+
+    /// The key is the committed one (or no key commitment was given) but the plaintext does not
+    /// match the description or the tag does not authenticate the ciphertext
+    WrongContent,
+}
+
 /// Result of checking ciphertext decryption.
 #[wasm_bindgen]
 pub struct CheckCtResult {
     /// Whether the decryption verification succeeded
     pub success: bool,
 
+    /// Verdict of the verification
+    pub verdict: CtCheckVerdict,
+
     /// The decrypted file contents
     #[wasm_bindgen(getter_with_clone)]
     pub decrypted_file: Vec<u8>,
 }
 
-/// Verifies ciphertext decryption by checking against the description.
+/// Verifies ciphertext decryption by checking against the description. If the precontract
+/// contains a key commitment, the key is checked against it first, so that a wrong key is
+/// reported without decrypting the ciphertext.
 ///
 /// # Arguments
 /// * `ct` - Ciphertext bytes to decrypt
 /// * `key` - Decryption key
 /// * `description` - Expected description hash in hex
+/// * `cipher` - Cipher recorded in the precontract (selected by the key's length if not given)
+/// * `key_commitment` - Key commitment of the precontract, if any
 ///
 /// # Returns
-/// A `CheckCtResult` containing the verification status and decrypted data
+/// A `CheckCtResult` containing the verification status, its verdict and decrypted data (empty
+/// for a wrong key)
 #[wasm_bindgen]
-pub fn check_received_ct_key(ct: &mut [u8], key: &[u8], description: String) -> CheckCtResult {
-    let decrypted_file = decrypt(ct, key);
+pub fn check_received_ct_key(
+    ct: &mut [u8],
+    key: &[u8],
+    description: String,
+    cipher: Option<Cipher>,
+    key_commitment: Option<KeyCommitment>,
+) -> CheckCtResult {
+    let cipher = cipher.unwrap_or_else(|| Cipher::for_key(key));
+    if key.len() != cipher.key_len() || key_commitment.is_some_and(|kc| !kc.verify(key)) {
+        return CheckCtResult {
+            success: false,
+            verdict: CtCheckVerdict::WrongKey,
+            decrypted_file: vec![],
+        };
+    }
+
+    if cipher == Cipher::Aes128Gcm && !is_gcm_pre_counter_block(&ct[..16.min(ct.len())]) {
+        return CheckCtResult {
+            success: false,
+            verdict: CtCheckVerdict::WrongContent,
+            decrypted_file: vec![],
+        };
+    }
+
+    let decrypted_file = decrypt(ct, key, cipher);
     let description_computed = sha256(&decrypted_file);
-    let success = hex_to_bytes(description).eq(&description_computed);
+    let success =
+        ct_eq(&hex_to_bytes(description), &description_computed) && check_tag(ct, key, cipher);
 
     CheckCtResult {
         success,
+        verdict: if success {
+            CtCheckVerdict::Correct
+        } else {
+            CtCheckVerdict::WrongContent
+        },
         decrypted_file,
     }
 }
 
+/// Verifies ciphertext decryption, as done by `check_received_ct_key`, with a key delivered
+/// wrapped to the buyer's secp256k1 key. A key that cannot be unwrapped is a wrong key.
+///
+/// # Arguments
+/// * `ct` - Ciphertext bytes to decrypt
+/// * `wrapped_key` - The wrapped key sent by the vendor
+/// * `buyer_secret_key` - The buyer's secret key (32 bytes)
+/// * `context` - Data the key was bound to, e.g. the precontract's commitment
+/// * `description` - Expected description hash in hex
+/// * `cipher` - Cipher recorded in the precontract (selected by the key's length if not given)
+/// * `key_commitment` - Key commitment of the precontract, if any
+///
+/// # Returns
+/// A `CheckCtResult` containing the verification status, its verdict and decrypted data
+#[wasm_bindgen]
+pub fn check_received_ct_wrapped_key(
+    ct: &mut [u8],
+    wrapped_key: &[u8],
+    buyer_secret_key: &[u8],
+    context: &[u8],
+    description: String,
+    cipher: Option<Cipher>,
+    key_commitment: Option<KeyCommitment>,
+) -> CheckCtResult {
+    match unwrap_key(wrapped_key, buyer_secret_key, context) {
+        Ok(key) => check_received_ct_key(ct, &key, description, cipher, key_commitment),
+        Err(_) => CheckCtResult {
+            success: false,
+            verdict: CtCheckVerdict::WrongKey,
+            decrypted_file: vec![],
+        },
+    }
+}
+
 // ####################################
 // ###    B/V MAKE ARGUMENT         ###
 // ####################################
@@ -215,16 +485,15 @@ pub struct DisputeArgument {
 /// Methods for dispute argument serialization and deserialization
 #[wasm_bindgen]
 impl DisputeArgument {
-    /// Serializes the dispute argument into a byte vector.
+    /// Serializes the dispute argument into a byte vector using the canonical encoding.
     ///
     /// Returns a vector containing the serialized dispute argument data.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        write(&mut buf, self).unwrap();
-        buf
+        self.to_canonical_bytes()
     }
 
-    /// Deserializes a dispute argument from bytes.
+    /// Deserializes a dispute argument from bytes. Both the canonical encoding and the legacy
+    /// MessagePack encoding are accepted.
     ///
     /// # Arguments
     /// * `bytes` - The serialized dispute argument bytes
@@ -232,7 +501,10 @@ impl DisputeArgument {
     /// # Returns
     /// A new `DisputeArgument` instance
     pub fn from_bytes(bytes: &[u8]) -> DisputeArgument {
-        from_read(bytes).unwrap()
+        match decode_canonical_or_legacy(bytes) {
+            Ok(argument) => argument,
+            Err(msg) => die(&msg),
+        }
     }
 }
 
@@ -242,80 +514,307 @@ impl DisputeArgument {
 /// * `ct` - Ciphertext bytes
 /// * `description` - Description hash in hex format
 /// * `opening_value` - Opening value in hex format
+/// * `cipher` - Cipher recorded in the precontract, which selects the committed circuit
+/// * `key_commitment` - Key commitment checked by the precontract's circuit, if any
 ///
 /// # Returns
 /// Serialized dispute argument bytes
 #[wasm_bindgen]
-pub fn make_argument(ct: Vec<u8>, description: String, opening_value: String) -> Vec<u8> {
+pub fn make_argument(
+    ct: Vec<u8>,
+    description: String,
+    opening_value: String,
+    cipher: Cipher,
+    key_commitment: Option<KeyCommitment>,
+) -> Vec<u8> {
+    let mut circuit =
+        compile_basic_circuit_with_cipher(ct.len() as u32, &hex_to_bytes(description), cipher);
+    if let Some(key_commitment) = key_commitment {
+        circuit = circuit.with_key_check(&key_commitment);
+    }
     DisputeArgument {
-        circuit: compile_basic_circuit(ct.len() as u32, &hex_to_bytes(description)),
+        circuit,
         ct,
         opening_value: hex_to_bytes(opening_value),
     }
     .to_bytes()
 }
 
+/// Seals a dispute argument to a sponsor, so that it can be stored and served without revealing
+/// the ciphertext and the opening value. The sponsor can check that the sender sealed it for the
+/// given commitment.
+///
+/// # Arguments
+/// * `argument_bin` - Serialized dispute argument bytes, see `make_argument`
+/// * `commitment` - Commitment in hex format
+/// * `sender_secret_key` - X25519 secret key of the buyer or vendor sending the argument
+/// * `sponsor_public_key` - X25519 public key of the sponsor
+///
+/// # Returns
+/// The sealed argument
+#[wasm_bindgen]
+pub fn seal_argument(
+    argument_bin: &[u8],
+    commitment: String,
+    sender_secret_key: &[u8],
+    sponsor_public_key: &[u8],
+) -> Vec<u8> {
+    match seal_from_with_rng(
+        sender_secret_key,
+        sponsor_public_key,
+        argument_bin,
+        &hex_to_bytes(commitment),
+        &mut rand::rng(),
+    ) {
+        Ok(sealed) => sealed,
+        Err(msg) => die(&msg),
+    }
+}
+
 // ####################################
 // ###    SB/SV CHECK ARGUMENT      ###
 // ####################################
 
+/// Verdict of the verification of a dispute argument
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArgumentVerdict {
+    /// The opening value does not open the commitment
+    InvalidOpening,
+
+    /// The circuit is not the committed one or does not encode the description and the
+    /// ciphertext's length
+    CircuitMismatch,
+
+    /// The ciphertext is not the committed one
+    CtMismatch,
+
+    /// The ciphertext decrypts to a plaintext matching the description (supports the vendor)
+    DecryptsCorrectly,
+
+    /// The ciphertext does not decrypt to a plaintext matching the description or its tag does
+    /// not authenticate it (supports the buyer)
+    DecryptsIncorrectly,
+
+    /// The disclosed shared secret of a wrapped key is not the one of the buyer's key
+    InvalidDisclosure,
+
+    /// The sealed argument was not sealed by the expected sender for the sponsor and the
+    /// commitment, or was modified
+    InvalidSeal,
+
+    /// The argument is not signed by the expected buyer or vendor
+    InvalidSignature,
+}
+
 /// Result of checking a dispute argument.
 #[wasm_bindgen]
 pub struct ArgumentCheckResult {
-    /// Whether the argument is valid
-    pub is_valid: bool,
-
-    /// Whether the argument supports the buyer's position
-    pub supports_buyer: bool,
+    /// Verdict of the verification
+    pub verdict: ArgumentVerdict,
 
     /// Optional error message
     #[wasm_bindgen(getter_with_clone)]
     pub error: Option<String>,
 }
 
-/// Verifies a dispute argument.
+/// Verifies a dispute argument. The argument's circuit must be the one compiled from the
+/// description, the ciphertext's length and the cipher recorded by its instruction set version,
+/// possibly with a key check, and both the circuit and the ciphertext must be the committed ones.
+/// If a signer is given, the argument must first be signed by it.
 ///
 /// # Arguments
 /// * `argument_bin` - Serialized dispute argument bytes
 /// * `commitment` - Commitment in hex format
 /// * `description` - Description hash in hex format
 /// * `key` - Encryption key in hex format
+/// * `signer` - Expected signer of the argument and its signature, if the argument is signed
 ///
 /// # Returns
-/// An `ArgumentCheckResult` containing the verification results
+/// An `ArgumentCheckResult` containing the verdict
 #[wasm_bindgen]
 pub fn check_argument(
     argument_bin: &[u8],
     commitment: String,
     description: String,
     key: String,
+    signer: Option<ArgumentSigner>,
+) -> ArgumentCheckResult {
+    check_argument_internal(
+        argument_bin,
+        commitment,
+        description,
+        &secret_from_hex(key),
+        signer,
+    )
+}
+
+// verifies a dispute argument with a decoded key, see `check_argument`
+fn check_argument_internal(
+    argument_bin: &[u8],
+    commitment: String,
+    description: String,
+    key: &[u8],
+    signer: Option<ArgumentSigner>,
 ) -> ArgumentCheckResult {
     let argument = DisputeArgument::from_bytes(argument_bin);
-    let block_size = argument.circuit.block_size;
-    let h_circuit = acc_circuit(argument.circuit);
-    let h_ct = acc_ct(argument.ct.as_slice(), block_size as usize);
+    let commitment = hex_to_bytes(commitment);
+    let description = hex_to_bytes(description);
+    let verdict = |verdict, error: Option<&str>| ArgumentCheckResult {
+        verdict,
+        error: error.map(|e| e.to_string()),
+    };
+
+    if signer.is_some_and(|signer| !signer.verify(&argument, &commitment)) {
+        return verdict(ArgumentVerdict::InvalidSignature, None);
+    }
 
-    match open_commitment_internal(&hex_to_bytes(commitment), &argument.opening_value) {
-        Ok(opened) => {
-            let is_valid =
-                opened.len() == 64 && opened[..32].eq(&h_circuit) && opened[32..].eq(&h_ct);
-            let pt = decrypt(&argument.ct, &hex_to_bytes(key));
-            let description_computed = sha256(&pt);
-            let supports_buyer = !hex_to_bytes(description).eq(&description_computed);
-            ArgumentCheckResult {
-                is_valid,
-                supports_buyer,
-                error: None,
-            }
+    let opened = match open_commitment_internal(&commitment, &argument.opening_value) {
+        Ok(opened) if opened.len() == 64 => opened,
+        Ok(_) => {
+            return verdict(
+                ArgumentVerdict::InvalidOpening,
+                Some("The committed value does not contain h_circuit and h_ct"),
+            )
         }
         Err(msg) => {
+            #[cfg(target_arch = "wasm32")]
             error(msg);
-            ArgumentCheckResult {
-                is_valid: false,
-                supports_buyer: false,
-                error: Some(msg.to_string()),
-            }
+            return verdict(ArgumentVerdict::InvalidOpening, Some(msg));
         }
+    };
+
+    // the committed circuit records the cipher
+    let cipher = match Cipher::from_version(argument.circuit.version) {
+        Some(cipher) => cipher,
+        None => return verdict(ArgumentVerdict::CircuitMismatch, None),
+    };
+    // a ciphertext this short cannot have been committed since it cannot be compiled
+    if argument.ct.len() <= 16 + cipher.tag_len() {
+        return verdict(ArgumentVerdict::CtMismatch, None);
+    }
+    let expected =
+        compile_basic_circuit_with_cipher(argument.ct.len() as u32, &description, cipher);
+    let key_commitment = argument.circuit.checked_key_commitment(&expected);
+    let expected = match &key_commitment {
+        Some(key_commitment) => expected.with_key_check(key_commitment),
+        None => expected,
+    };
+    if acc_ct(argument.ct.as_slice(), expected.block_size as usize) != opened[32..] {
+        return verdict(ArgumentVerdict::CtMismatch, None);
+    }
+    // the debug metadata is not part of the argument
+    if argument.circuit.without_debug_info().to_canonical_bytes()
+        != expected.without_debug_info().to_canonical_bytes()
+        || acc_circuit(argument.circuit) != opened[..32]
+    {
+        return verdict(ArgumentVerdict::CircuitMismatch, None);
+    }
+
+    if key.len() != cipher.key_len() {
+        return verdict(
+            ArgumentVerdict::DecryptsIncorrectly,
+            Some("The key's length does not match the cipher"),
+        );
+    }
+    if key_commitment.is_some_and(|kc| !kc.verify(key)) {
+        return verdict(
+            ArgumentVerdict::DecryptsIncorrectly,
+            Some("The key does not match the key commitment"),
+        );
+    }
+    if cipher == Cipher::Aes128Gcm && !is_gcm_pre_counter_block(&argument.ct[..16]) {
+        return verdict(
+            ArgumentVerdict::DecryptsIncorrectly,
+            Some("The IV is not an AES-GCM pre-counter block"),
+        );
+    }
+    if !check_tag(&argument.ct, key, cipher) {
+        return verdict(
+            ArgumentVerdict::DecryptsIncorrectly,
+            Some("The tag does not authenticate the ciphertext"),
+        );
+    }
+    let pt = Zeroizing::new(decrypt(&argument.ct, key, cipher));
+    if ct_eq(&sha256(&pt), &description) {
+        verdict(ArgumentVerdict::DecryptsCorrectly, None)
+    } else {
+        verdict(ArgumentVerdict::DecryptsIncorrectly, None)
+    }
+}
+
+/// Verifies a dispute argument, as done by `check_argument`, when the key was delivered wrapped
+/// to the buyer's secp256k1 key. The key is unwrapped with the shared secret disclosed by the
+/// buyer or a sponsor. A wrapped key that cannot be unwrapped supports the buyer.
+///
+/// # Arguments
+/// * `argument_bin` - Serialized dispute argument bytes
+/// * `commitment` - Commitment in hex format
+/// * `description` - Description hash in hex format
+/// * `wrapped_key` - The wrapped key sent by the vendor
+/// * `buyer_public_key` - The buyer's public key (SEC1 encoded, compressed or not)
+/// * `context` - Data the key was bound to
+/// * `disclosure` - The disclosed shared secret and its proof
+///
+/// # Returns
+/// An `ArgumentCheckResult` containing the verdict
+#[wasm_bindgen]
+pub fn check_argument_with_disclosure(
+    argument_bin: &[u8],
+    commitment: String,
+    description: String,
+    wrapped_key: &[u8],
+    buyer_public_key: &[u8],
+    context: &[u8],
+    disclosure: &KeyDisclosure,
+) -> ArgumentCheckResult {
+    match unwrap_disclosed_key(wrapped_key, buyer_public_key, context, disclosure) {
+        Ok(Some(key)) => check_argument_internal(argument_bin, commitment, description, &key, None),
+        Ok(None) => ArgumentCheckResult {
+            verdict: ArgumentVerdict::DecryptsIncorrectly,
+            error: Some("The wrapped key cannot be unwrapped".to_string()),
+        },
+        Err(msg) => ArgumentCheckResult {
+            verdict: ArgumentVerdict::InvalidDisclosure,
+            error: Some(msg),
+        },
+    }
+}
+
+/// Opens an argument sealed by `seal_argument` and verifies it, as done by `check_argument`. The
+/// seal already authenticates the sender, so the argument needs no signature.
+///
+/// # Arguments
+/// * `sealed_argument` - The sealed argument
+/// * `sponsor_secret_key` - X25519 secret key of the sponsor
+/// * `sender_public_key` - X25519 public key of the expected sender
+/// * `commitment` - Commitment in hex format
+/// * `description` - Description hash in hex format
+/// * `key` - Encryption key in hex format
+///
+/// # Returns
+/// An `ArgumentCheckResult` containing the verdict
+#[wasm_bindgen]
+pub fn check_sealed_argument(
+    sealed_argument: &[u8],
+    sponsor_secret_key: &[u8],
+    sender_public_key: &[u8],
+    commitment: String,
+    description: String,
+    key: String,
+) -> ArgumentCheckResult {
+    let context = hex_to_bytes(commitment.clone());
+    match open_sealed_from(
+        sponsor_secret_key,
+        sender_public_key,
+        sealed_argument,
+        &context,
+    ) {
+        Ok(argument_bin) => check_argument(&argument_bin, commitment, description, key, None),
+        Err(msg) => ArgumentCheckResult {
+            verdict: ArgumentVerdict::InvalidSeal,
+            error: Some(msg),
+        },
     }
 }
 
@@ -331,19 +830,25 @@ pub struct EvaluatedCircuit {
     constants: Vec<Vec<u8>>,
 }
 
+// the constants include the key
+impl Drop for EvaluatedCircuit {
+    fn drop(&mut self) {
+        self.constants.zeroize();
+    }
+}
+
 /// Methods for evaluated circuit data access
 #[wasm_bindgen]
 impl EvaluatedCircuit {
-    /// Serializes the evaluated circuit into bytes.
+    /// Serializes the evaluated circuit into bytes using the canonical encoding.
     ///
     /// Returns a vector containing the serialized circuit data.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        write(&mut buf, self).unwrap();
-        buf
+        self.to_canonical_bytes()
     }
 
-    /// Deserializes an evaluated circuit from bytes.
+    /// Deserializes an evaluated circuit from bytes. Both the canonical encoding and the legacy
+    /// MessagePack encoding are accepted.
     ///
     /// # Arguments
     /// * `bytes` - The serialized circuit bytes
@@ -351,7 +856,10 @@ impl EvaluatedCircuit {
     /// # Returns
     /// A new `EvaluatedCircuit` instance
     pub fn from_bytes(bytes: &[u8]) -> EvaluatedCircuit {
-        from_read(bytes).unwrap()
+        match decode_canonical_or_legacy(bytes) {
+            Ok(evaluated) => evaluated,
+            Err(msg) => die(&msg),
+        }
     }
 }
 
@@ -362,6 +870,8 @@ impl EvaluatedCircuit {
 /// * `ct` - Ciphertext bytes to evaluate
 /// * `constants` - Vector of hex-encoded constant values
 /// * `description` - Description hash in hex format
+/// * `cipher` - Cipher of the basic circuit, selected by the key's length if not given. Ignored
+///   if `circuit_bytes` is not empty
 ///
 /// # Returns
 /// An `EvaluatedCircuit` containing the evaluation results and circuit constants
@@ -376,22 +886,30 @@ pub fn evaluate_circuit(
     ct: &[u8],
     constants: Vec<String>,
     description: String,
+    cipher: Option<Cipher>,
 ) -> EvaluatedCircuit {
-    if circuit_bytes.len() == 0 {
-        let circuit = compile_basic_circuit(ct.len() as u32, &hex_to_bytes(description))
-            .bind_missing_constants(constants.into_iter().map(hex_to_bytes).collect());
-        let ct_blocks = split_ct_blocks(&ct, circuit.block_size as usize);
+    if circuit_bytes.is_empty() {
+        let constants = secrets_from_hex(constants);
+        let cipher = cipher.unwrap_or_else(|| {
+            Cipher::for_key(constants.first().map_or(&[][..], |k| k.as_slice()))
+        });
+        let circuit =
+            compile_basic_circuit_with_cipher(ct.len() as u32, &hex_to_bytes(description), cipher)
+                .bind_missing_constants(constants);
+        let ct_blocks = split_ct_blocks(ct, circuit.block_size as usize);
+        let values = evaluate_circuit_internal(&ct_blocks, &circuit);
         EvaluatedCircuit {
-            constants: circuit.constants.clone(),
-            values: evaluate_circuit_internal(&ct_blocks, circuit),
+            values,
+            constants: circuit.into_constants(),
         }
     } else {
         let circuit = CompiledCircuit::from_bytes(circuit_bytes)
-            .bind_missing_constants(constants.into_iter().map(hex_to_bytes).collect());
-        let ct_blocks = split_ct_blocks(&ct, circuit.block_size as usize);
+            .bind_missing_constants(secrets_from_hex(constants));
+        let ct_blocks = split_ct_blocks(ct, circuit.block_size as usize);
+        let values = evaluate_circuit_internal(&ct_blocks, &circuit);
         EvaluatedCircuit {
-            constants: circuit.constants.clone(),
-            values: evaluate_circuit_internal(&ct_blocks, circuit),
+            values,
+            constants: circuit.into_constants(),
         }
     }
 }
@@ -403,16 +921,32 @@ pub fn evaluate_circuit(
 /// Computes the answer to send to a smart contract based on the issued challenge.
 ///
 /// # Arguments
-/// * `evaluated_circuit_bytes` - Serialized evaluated circuit bytes
+/// * `trace` - The trace of the circuit, stored or lazy
 /// * `num_blocks` - Number of blocks for the ciphertext
 /// * `challenge` - Challenge issued by the smart contract
 ///
 /// # Returns
 /// The response to the challenge
 #[wasm_bindgen]
-pub fn hpre(evaluated_circuit_bytes: &[u8], num_blocks: usize, challenge: usize) -> Vec<u8> {
-    let evaluated_circuit = EvaluatedCircuit::from_bytes(evaluated_circuit_bytes);
-    acc(&evaluated_circuit.values[num_blocks..=challenge])
+pub fn hpre(trace: &Trace, num_blocks: u32, challenge: u32) -> Vec<u8> {
+    hpre_multi_trace(trace, num_blocks, &[challenge]).remove(0)
+}
+
+/// Computes the answers to several challenges of a k-ary search round at once.
+///
+/// # Arguments
+/// * `trace` - The trace of the circuit, stored or lazy
+/// * `num_blocks` - Number of blocks for the ciphertext
+/// * `challenges` - Challenges of the round
+///
+/// # Returns
+/// The responses to the challenges, in the same order
+#[wasm_bindgen]
+pub fn hpre_multi(trace: &Trace, num_blocks: u32, challenges: Vec<u32>) -> Vec<Uint8Array> {
+    hpre_multi_trace(trace, num_blocks, &challenges)
+        .iter()
+        .map(|r| Uint8Array::from(r.as_slice()))
+        .collect()
 }
 
 // ####################################
@@ -452,31 +986,73 @@ pub struct FinalStepComponents {
     pub proof_ext: Array,
 }
 
-// Splits the sons according to the paper's set L. Constant indices are not kept.
-fn split_sons_indices(sons: &[u32], num_blocks: u32) -> (Vec<u32>, Vec<u32>) {
-    let mut in_l = Vec::new();
-    let mut not_in_l_minus_m = Vec::new();
-
-    for &s in sons {
-        if is_constant_idx(s) {
-            continue;
+impl From<FinalStepProofs> for FinalStepComponents {
+    fn from(proofs: FinalStepProofs) -> FinalStepComponents {
+        FinalStepComponents {
+            gate: proofs
+                .gate
+                .flatten()
+                .iter()
+                .map(|&x| Number::from(x))
+                .collect(),
+            values: proofs
+                .values
+                .iter()
+                .map(|x| Uint8Array::from(x.as_slice()))
+                .collect(),
+            curr_acc: proofs.curr_acc,
+            proof1: proof_to_js_array(proofs.proof1),
+            proof2: proof_to_js_array(proofs.proof2),
+            proof3: proof_to_js_array(proofs.proof3),
+            proof_ext: proof_to_js_array(proofs.proof_ext),
         }
-        if s < num_blocks {
-            // strictly inferior because we start counting from 0
-            in_l.push(s)
-        } else {
-            not_in_l_minus_m.push(s - num_blocks)
+    }
+}
+
+impl From<&FinalStepComponents> for FinalStepProofs {
+    fn from(components: &FinalStepComponents) -> FinalStepProofs {
+        let gate: Vec<u32> = components
+            .gate
+            .iter()
+            .map(|x| x.value_of() as u32)
+            .collect();
+        FinalStepProofs {
+            gate: Gate {
+                opcode: gate.first().copied().unwrap_or(u32::MAX),
+                sons: gate.iter().skip(1).copied().collect(),
+            },
+            values: components
+                .values
+                .iter()
+                .map(uint8_array_to_vec_u8)
+                .collect(),
+            curr_acc: components.curr_acc.clone(),
+            proof1: js_array_to_proof(&components.proof1),
+            proof2: js_array_to_proof(&components.proof2),
+            proof3: js_array_to_proof(&components.proof3),
+            proof_ext: js_array_to_proof(&components.proof_ext),
         }
     }
+}
 
-    (in_l, not_in_l_minus_m)
+// inverse of `proof_to_js_array`
+fn js_array_to_proof(proof: &Array) -> Vec<Vec<Vec<u8>>> {
+    proof
+        .iter()
+        .map(|layer| {
+            Array::from(&layer)
+                .iter()
+                .map(|v| uint8_array_to_vec_u8(&Uint8Array::new(&v)))
+                .collect()
+        })
+        .collect()
 }
 
 /// Computes proofs for step 8a.
 ///
 /// # Arguments
 /// * `circuit_bytes` - Serialized circuit bytes
-/// * `evaluated_circuit_bytes` - Serialized evaluated circuit bytes
+/// * `trace` - The trace of the circuit, stored or lazy
 /// * `ct` - Ciphertext bytes
 /// * `challenge` - Challenge point in the circuit
 ///
@@ -489,50 +1065,21 @@ fn split_sons_indices(sons: &[u32], num_blocks: u32) -> (Vec<u32>, Vec<u32>) {
 #[wasm_bindgen]
 pub fn compute_proofs(
     circuit_bytes: &[u8],
-    evaluated_circuit_bytes: &[u8],
+    trace: &Trace,
     ct: &[u8],
     challenge: u32,
 ) -> FinalStepComponents {
     let circuit = CompiledCircuit::from_bytes(circuit_bytes);
     let ct_blocks = split_ct_blocks(ct, circuit.block_size as usize);
-    let num_blocks = ct_blocks.len() as u32;
-    let evaluated_circuit = EvaluatedCircuit::from_bytes(evaluated_circuit_bytes);
-    let gate = circuit.circuit[challenge as usize].clone();
-    let (s_in_l, not_in_l_minus_m) = split_sons_indices(&gate.sons, num_blocks);
-
-    let values = get_evaluated_sons(
-        &gate,
-        &evaluated_circuit.values,
-        &evaluated_circuit.constants,
-    );
-    let curr_acc = acc(&evaluated_circuit.values[(num_blocks as usize)..=(challenge as usize)]);
-    let proof1 = prove(&circuit.to_abi_encoded(), &[challenge]);
-    let proof2 = prove(&ct_blocks, &s_in_l);
-    let proof3 = prove(
-        &evaluated_circuit.values[(num_blocks as usize)..(challenge as usize)],
-        &not_in_l_minus_m,
-    );
-    let proof_ext =
-        prove_ext(&evaluated_circuit.values[(num_blocks as usize)..=(challenge as usize)]);
-    FinalStepComponents {
-        gate: gate.flatten().iter().map(|&x| Number::from(x)).collect(),
-        values: values
-            .iter()
-            .map(|&x| Uint8Array::from(x.as_slice()))
-            .collect(),
-        curr_acc,
-        proof1: proof_to_js_array(proof1),
-        proof2: proof_to_js_array(proof2),
-        proof3: proof_to_js_array(proof3),
-        proof_ext: proof_to_js_array(proof_ext),
-    }
+
+    compute_proofs_trace(&circuit, trace, &ct_blocks, challenge).into()
 }
 
 /// Computes proofs for step 8b.
 ///
 /// # Arguments
 /// * `circuit_bytes` - Serialized circuit bytes
-/// * `evaluated_circuit_bytes` - Serialized evaluated circuit bytes
+/// * `trace` - The trace of the circuit, stored or lazy
 /// * `ct` - Ciphertext bytes
 /// * `challenge` - Challenge point in the circuit
 ///
@@ -542,71 +1089,120 @@ pub fn compute_proofs(
 /// - Evaluated values at the challenge point
 /// - Current accumulator value
 /// - Multiple proofs (proof1, proof2, proof_ext)
+///
 /// Note that the returning object will have a proof3 component which is an empty array.
 #[wasm_bindgen]
 pub fn compute_proofs_left(
     circuit_bytes: &[u8],
-    evaluated_circuit_bytes: &[u8],
+    trace: &Trace,
     ct: &[u8],
     challenge: u32,
 ) -> FinalStepComponents {
     let circuit = CompiledCircuit::from_bytes(circuit_bytes);
     let ct_blocks = split_ct_blocks(ct, circuit.block_size as usize);
-    let num_blocks = ct_blocks.len() as u32;
-    let evaluated_circuit = EvaluatedCircuit::from_bytes(evaluated_circuit_bytes);
-    let gate = circuit.circuit[challenge as usize].clone();
-    let non_constant_sons: Vec<u32> = gate
-        .sons
-        .iter()
-        .copied()
-        .filter(|&x| !is_constant_idx(x))
-        .collect();
-
-    let values = get_evaluated_sons(
-        &gate,
-        &evaluated_circuit.values,
-        &evaluated_circuit.constants,
-    );
-    let curr_acc = acc(&evaluated_circuit.values[(num_blocks as usize)..=(challenge as usize)]);
-    let proof1 = prove(&circuit.to_abi_encoded(), &[challenge]);
-    let proof2 = prove(&ct_blocks, &non_constant_sons);
-    let proof_ext = prove_ext(&[evaluated_circuit.values[num_blocks as usize].clone()]);
-
-    FinalStepComponents {
-        gate: gate.flatten().iter().map(|&x| Number::from(x)).collect(),
-        values: values
-            .iter()
-            .map(|&x| Uint8Array::from(x.as_slice()))
-            .collect(),
-        curr_acc,
-        proof1: proof_to_js_array(proof1),
-        proof2: proof_to_js_array(proof2),
-        proof3: Array::new(),
-        proof_ext: proof_to_js_array(proof_ext),
-    }
+
+    compute_proofs_left_trace(&circuit, trace, &ct_blocks, challenge).into()
 }
 
 /// Computes the proof for step 8c.
 ///
 /// # Arguments
-/// * `evaluated_circuit_bytes` - Serialized evaluated circuit bytes
+/// * `trace` - The trace of the circuit, stored or lazy
 /// * `num_blocks` - Number of blocks for the ciphertext
 /// * `num_gates` - Total number of gates in the circuit
 ///
 /// # Returns
 /// A JavaScript `Array` containing the proof
 #[wasm_bindgen]
-pub fn compute_proof_right(
-    evaluated_circuit_bytes: &[u8],
-    num_blocks: u32,
-    num_gates: u32,
-) -> Array {
-    let evaluated_circuit = EvaluatedCircuit::from_bytes(evaluated_circuit_bytes);
+pub fn compute_proof_right(trace: &Trace, num_blocks: u32, num_gates: u32) -> Array {
+    proof_to_js_array(compute_proof_right_trace(trace, num_blocks, num_gates))
+}
 
-    proof_to_js_array(prove(
-        &evaluated_circuit.values[(num_blocks as usize)..],
-        &[num_gates - num_blocks - 1],
-    ))
+// ####################################
+// ###    VENDOR PRE-FLIGHT CHECK   ###
+// ####################################
+
+/// Result of the off-chain verification of the vendor's final step
+#[wasm_bindgen]
+pub struct FinalStepCheckResult {
+    /// Whether the smart contract would complete the dispute in favor of the vendor
+    pub is_valid: bool,
+
+    /// Whether the transaction would revert
+    pub reverts: bool,
+
+    /// Description of the failing check
+    #[wasm_bindgen(getter_with_clone)]
+    pub error: Option<String>,
+}
+
+impl From<Result<(), FinalStepError>> for FinalStepCheckResult {
+    fn from(result: Result<(), FinalStepError>) -> FinalStepCheckResult {
+        match result {
+            Ok(()) => FinalStepCheckResult {
+                is_valid: true,
+                reverts: false,
+                error: None,
+            },
+            Err(e) => FinalStepCheckResult {
+                is_valid: false,
+                reverts: e.reverts(),
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// Checks the components computed by `compute_proofs` or `compute_proofs_left` before sending
+/// them to the smart contract.
+///
+/// # Arguments
+/// * `ctx` - Values read on the dispute smart contract
+/// * `gate_num` - The gate number that will be sent
+/// * `opening_value` - The opening value of the commitment
+/// * `version` - The instruction set version
+/// * `components` - The components that will be sent
+///
+/// # Returns
+/// A `FinalStepCheckResult` describing the first check that fails, if any
+#[wasm_bindgen]
+pub fn check_final_step(
+    ctx: &FinalStepContext,
+    gate_num: u32,
+    opening_value: &[u8],
+    version: u32,
+    components: &FinalStepComponents,
+) -> FinalStepCheckResult {
+    let proofs = FinalStepProofs::from(components);
+    verify_final_step(
+        ctx,
+        &FinalStepSubmission::Commitment {
+            gate_num,
+            opening_value,
+            version,
+            proofs: &proofs,
+        },
+    )
+    .into()
+}
+
+/// Checks the proof computed by `compute_proof_right` before sending it to the smart contract.
+///
+/// # Arguments
+/// * `ctx` - Values read on the dispute smart contract
+/// * `proof` - The proof that will be sent
+///
+/// # Returns
+/// A `FinalStepCheckResult` describing the first check that fails, if any
+#[wasm_bindgen]
+pub fn check_final_step_right(ctx: &FinalStepContext, proof: Array) -> FinalStepCheckResult {
+    verify_final_step(
+        ctx,
+        &FinalStepSubmission::Right {
+            proof: &js_array_to_proof(&proof),
+        },
+    )
+    .into()
 }
 
 // =================================================================================================
@@ -614,6 +1210,7 @@ pub fn compute_proof_right(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::circuits::compile_basic_circuit;
     use crate::utils::bytes_to_hex;
     use rand::RngCore;
 
@@ -629,7 +1226,7 @@ mod tests {
             rng.fill_bytes(&mut key);
 
             // encrypt
-            let ct = encrypt_and_prepend_iv(&mut data, &key);
+            let ct = encrypt_and_prepend_iv(&mut data, &key, Cipher::Aes128Ctr, &mut rng);
 
             let circuit = compile_basic_circuit(ct.len() as u32, &description);
 
@@ -638,6 +1235,7 @@ mod tests {
                 &ct,
                 vec![bytes_to_hex(key)],
                 bytes_to_hex(description),
+                None,
             );
 
             assert_eq!(
@@ -646,4 +1244,732 @@ mod tests {
             )
         }
     }
+
+    #[test]
+    fn test_basic_circuit_labels() {
+        for ct_size in [17, 80, 81, 1000] {
+            let circuit = compile_basic_circuit(ct_size, &[0u8; 32]);
+            assert_eq!(circuit.circuit.len(), circuit.debug_info.len());
+
+            for (i, gate) in circuit.circuit.iter().enumerate() {
+                let label = circuit.gate_label(i as u32).unwrap();
+                let range = circuit.gate_block_range(i as u32).unwrap();
+                let prefix = match gate.opcode {
+                    u32::MAX if i == 0 => "IV",
+                    u32::MAX => "ciphertext block",
+                    0 | 7 => "SHA-256 compress block",
+                    2 => "AES decrypt block",
+                    3 => "AES counter for block",
+                    5 => "compare",
+                    _ => unreachable!(),
+                };
+                assert!(label.starts_with(prefix), "{}: {}", i, label);
+
+                // the labeled block is the last one the gate depends on, except for the counters
+                // which only depend on the IV
+                let block = label.split(' ').find_map(|w| w.parse::<u32>().ok());
+                match (gate.opcode, block) {
+                    (3, _) => assert_eq!(vec![0, 0], range),
+                    (_, Some(k)) => assert_eq!(k, range[1], "{}: {}", i, label),
+                    _ => {}
+                }
+            }
+
+            // the whole file is needed to check the description
+            let last = circuit.circuit.len() as u32 - 1;
+            assert_eq!(
+                Some(vec![0, circuit.num_blocks - 1]),
+                circuit.gate_block_range(last)
+            );
+            assert_eq!(
+                acc_circuit(circuit.without_debug_info()),
+                acc_circuit(circuit)
+            );
+        }
+    }
+
+    #[test]
+    fn test_seeded_precontract() {
+        let file = vec![42u8; 1000];
+        let key = [3u8; 16];
+        let seed = [9u8; 32];
+        let precontract =
+            |seed: &[u8]| compute_precontract_values_seeded(&mut file.clone(), &key, seed);
+
+        let a = precontract(&seed);
+        let b = precontract(&seed);
+        assert_eq!(a.ct, b.ct);
+        assert_eq!(a.commitment.c, b.commitment.c);
+        assert_eq!(a.commitment.o, b.commitment.o);
+        assert_eq!(file, decrypt(&a.ct, &key, Cipher::Aes128Ctr));
+
+        // the IV and the blinding both change with the seed
+        let c = precontract(&[0u8; 32]);
+        assert_ne!(a.ct[..16], c.ct[..16]);
+        assert_ne!(a.commitment.o[64..], c.commitment.o[64..]);
+    }
+
+    #[test]
+    fn test_ciphers_end_to_end() {
+        let mut rng = rand::rng();
+        let cases = [
+            (Cipher::Aes256Ctr, 1, 8, "AES decrypt block"),
+            (Cipher::ChaCha20, 2, 9, "ChaCha20 decrypt block"),
+        ];
+        for (cipher, version, opcode, label) in cases {
+            for size in [40, 1000] {
+                let mut data = vec![0u8; size];
+                rng.fill_bytes(&mut data);
+                let file = data.clone();
+                let description = bytes_to_hex(sha256(&data));
+                let mut key = vec![0u8; 32];
+                rng.fill_bytes(&mut key);
+                let hex_key = bytes_to_hex(key.clone());
+
+                let precontract = compute_precontract_values_with_cipher(&mut data, &key, cipher);
+                assert_eq!(cipher, precontract.cipher);
+                let circuit = CompiledCircuit::from_bytes(&precontract.circuit_bytes);
+                assert_eq!(version, circuit.version);
+                assert!(circuit.circuit.iter().all(|g| g.opcode != 2));
+                let decryptions: Vec<u32> = (0..circuit.circuit.len() as u32)
+                    .filter(|&i| circuit.circuit[i as usize].opcode == opcode)
+                    .collect();
+                assert_eq!(circuit.num_blocks - 1, decryptions.len() as u32);
+                for i in decryptions {
+                    assert!(circuit.gate_label(i).unwrap().starts_with(label));
+                }
+
+                let mut ct = precontract.ct.clone();
+                let res =
+                    check_received_ct_key(&mut ct, &key, description.clone(), Some(cipher), None);
+                assert!(res.success);
+                assert_eq!(file, res.decrypted_file);
+
+                // the last gate checks the description
+                for circuit_bytes in [&precontract.circuit_bytes[..], &[]] {
+                    let evaluated = evaluate_circuit(
+                        circuit_bytes,
+                        &precontract.ct,
+                        vec![hex_key.clone()],
+                        description.clone(),
+                        Some(cipher),
+                    );
+                    assert_eq!(vec![1u8], *evaluated.values.last().unwrap());
+                }
+
+                let commitment = bytes_to_hex(precontract.commitment.c.clone());
+                let opening_value = bytes_to_hex(precontract.commitment.o.clone());
+                let check = |cipher, key: &[u8]| {
+                    let argument = make_argument(
+                        precontract.ct.clone(),
+                        description.clone(),
+                        opening_value.clone(),
+                        cipher,
+                        None,
+                    );
+                    check_argument(
+                        &argument,
+                        commitment.clone(),
+                        description.clone(),
+                        bytes_to_hex(key.to_vec()),
+                        None,
+                    )
+                    .verdict
+                };
+                assert_eq!(ArgumentVerdict::DecryptsCorrectly, check(cipher, &key));
+                assert_eq!(
+                    ArgumentVerdict::DecryptsIncorrectly,
+                    check(cipher, &key[..16])
+                );
+                // the committed circuit records the cipher
+                assert_eq!(
+                    ArgumentVerdict::CircuitMismatch,
+                    check(Cipher::Aes128Ctr, &key)
+                );
+            }
+        }
+
+        // a 32 bytes key is an AES-256 key unless ChaCha20 is asked for
+        let mut data = vec![0u8; 100];
+        let precontract = compute_precontract_values(&mut data, &[1u8; 32]);
+        assert_eq!(Cipher::Aes256Ctr, precontract.cipher);
+    }
+
+    #[test]
+    fn test_gcm_end_to_end() {
+        let mut rng = rand::rng();
+        for size in [40, 1000] {
+            let mut data = vec![0u8; size];
+            rng.fill_bytes(&mut data);
+            let file = data.clone();
+            let description = bytes_to_hex(sha256(&data));
+            let mut key = vec![0u8; 16];
+            rng.fill_bytes(&mut key);
+
+            let precontract =
+                compute_precontract_values_with_cipher(&mut data, &key, Cipher::Aes128Gcm);
+            assert_eq!(16 + size + 16, precontract.ct.len());
+            assert_eq!(
+                3,
+                CompiledCircuit::from_bytes(&precontract.circuit_bytes).version
+            );
+
+            let mut ct = precontract.ct.clone();
+            let cipher = Some(Cipher::Aes128Gcm);
+            let res = check_received_ct_key(&mut ct, &key, description.clone(), cipher, None);
+            assert!(res.success);
+            assert_eq!(file, res.decrypted_file);
+
+            let evaluated = evaluate_circuit(
+                &[],
+                &precontract.ct,
+                vec![bytes_to_hex(key.clone())],
+                description.clone(),
+                cipher,
+            );
+            assert_eq!(vec![1u8], *evaluated.values.last().unwrap());
+
+            let commitment = bytes_to_hex(precontract.commitment.c.clone());
+            let opening_value = bytes_to_hex(precontract.commitment.o.clone());
+            let argument = make_argument(
+                precontract.ct.clone(),
+                description.clone(),
+                opening_value,
+                Cipher::Aes128Gcm,
+                None,
+            );
+            let res = check_argument(
+                &argument,
+                commitment,
+                description.clone(),
+                bytes_to_hex(key.clone()),
+                None,
+            );
+            assert_eq!(ArgumentVerdict::DecryptsCorrectly, res.verdict);
+
+            // a committed ciphertext with a wrong tag supports the buyer
+            let mut data = file.clone();
+            let mut ct = encrypt_and_prepend_iv(&mut data, &key, Cipher::Aes128Gcm, &mut rng);
+            let last = ct.len() - 1;
+            ct[last] ^= 1;
+            let circuit = compile_basic_circuit_with_cipher(
+                ct.len() as u32,
+                &hex_to_bytes(description.clone()),
+                Cipher::Aes128Gcm,
+            );
+            let commitment = commit_hashes(&acc_circuit(circuit), &acc_ct(&ct, 64), &mut rng);
+            let argument = make_argument(
+                ct.clone(),
+                description.clone(),
+                bytes_to_hex(commitment.o.clone()),
+                Cipher::Aes128Gcm,
+                None,
+            );
+            let res = check_argument(
+                &argument,
+                bytes_to_hex(commitment.c.clone()),
+                description.clone(),
+                bytes_to_hex(key.clone()),
+                None,
+            );
+            assert_eq!(ArgumentVerdict::DecryptsIncorrectly, res.verdict);
+            assert!(res.error.is_some());
+            assert!(
+                !check_received_ct_key(&mut ct, &key, description.clone(), cipher, None).success
+            );
+
+            // so does a committed ciphertext whose J0 does not end with a counter of 1
+            ct[last] ^= 1;
+            ct[15] = 2;
+            let circuit = compile_basic_circuit_with_cipher(
+                ct.len() as u32,
+                &hex_to_bytes(description.clone()),
+                Cipher::Aes128Gcm,
+            );
+            let commitment = commit_hashes(&acc_circuit(circuit), &acc_ct(&ct, 64), &mut rng);
+            let argument = make_argument(
+                ct.clone(),
+                description.clone(),
+                bytes_to_hex(commitment.o.clone()),
+                Cipher::Aes128Gcm,
+                None,
+            );
+            let res = check_argument(
+                &argument,
+                bytes_to_hex(commitment.c.clone()),
+                description.clone(),
+                bytes_to_hex(key.clone()),
+                None,
+            );
+            assert_eq!(ArgumentVerdict::DecryptsIncorrectly, res.verdict);
+            assert_eq!(
+                Some("The IV is not an AES-GCM pre-counter block".to_string()),
+                res.error
+            );
+            assert!(
+                !check_received_ct_key(&mut ct, &key, description.clone(), cipher, None).success
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_precontract() {
+        let mut rng = rand::rng();
+        for cipher in [
+            Cipher::Aes128Ctr,
+            Cipher::Aes256Ctr,
+            Cipher::ChaCha20,
+            Cipher::Aes128Gcm,
+        ] {
+            let mut file = vec![0u8; 300];
+            rng.fill_bytes(&mut file);
+            let mut key = vec![0u8; cipher.key_len()];
+            rng.fill_bytes(&mut key);
+
+            let precontract = compute_precontract_values_with_cipher(&mut file, &key, cipher);
+            let check = |cipher| {
+                check_precontract(
+                    bytes_to_hex(precontract.description.clone()),
+                    bytes_to_hex(precontract.commitment.c.clone()),
+                    bytes_to_hex(precontract.commitment.o.clone()),
+                    &precontract.ct,
+                    cipher,
+                    None,
+                )
+            };
+
+            let res = check(Some(cipher));
+            assert!(res.success);
+            assert_eq!(precontract.h_circuit, res.h_circuit);
+            assert_eq!(precontract.h_ct, res.h_ct);
+
+            // the circuit depends on the cipher
+            assert_eq!(cipher == Cipher::Aes128Ctr, check(None).success);
+        }
+    }
+
+    #[test]
+    fn test_check_precontract_key_check() {
+        let key = [5u8; 16];
+        let precontract = compute_precontract_values_with_key_check(&mut [1u8; 300], &key, None);
+        let check = |key_commitment| {
+            check_precontract(
+                bytes_to_hex(precontract.description.clone()),
+                bytes_to_hex(precontract.commitment.c.clone()),
+                bytes_to_hex(precontract.commitment.o.clone()),
+                &precontract.ct,
+                None,
+                key_commitment,
+            )
+            .success
+        };
+
+        assert!(check(precontract.key_commitment.clone()));
+        assert!(!check(None));
+    }
+
+    #[test]
+    fn test_precontract_from_master() {
+        let master = b"vendor master secret".to_vec();
+        let file = vec![7u8; 300];
+        let description = sha256(&file);
+        let context = kdf::file_key_context(&description, &[2u8; 20], &[0]);
+
+        for cipher in [None, Some(Cipher::ChaCha20), Some(Cipher::Aes128Gcm)] {
+            let precontract = compute_precontract_values_from_master(
+                &mut file.clone(),
+                &master,
+                &context,
+                cipher,
+            );
+            assert_eq!(
+                kdf::derive_file_iv(&master, &context, cipher),
+                precontract.ct[..16]
+            );
+
+            // everything can be regenerated after losing it
+            let again = compute_precontract_values_from_master(
+                &mut file.clone(),
+                &master,
+                &context,
+                cipher,
+            );
+            assert_eq!(precontract.ct, again.ct);
+            assert_eq!(precontract.commitment.c, again.commitment.c);
+            assert_eq!(precontract.commitment.o, again.commitment.o);
+
+            let key = derive_file_key(&master, &context, cipher);
+            let res = check_received_ct_key(
+                &mut again.ct.clone(),
+                &key,
+                bytes_to_hex(description.clone()),
+                Some(precontract.cipher),
+                precontract.key_commitment.clone(),
+            );
+            assert!(res.success);
+        }
+
+        let other = kdf::file_key_context(&description, &[3u8; 20], &[0]);
+        assert_ne!(
+            compute_precontract_values_from_master(&mut file.clone(), &master, &context, None).ct,
+            compute_precontract_values_from_master(&mut file.clone(), &master, &other, None).ct
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The context does not match the file's description")]
+    fn test_precontract_from_master_other_file() {
+        let context = kdf::file_key_context(&sha256(&[7u8; 300]), &[2u8; 20], &[0]);
+        compute_precontract_values_from_master(
+            &mut vec![8u8; 300],
+            b"vendor master secret",
+            &context,
+            None,
+        );
+    }
+
+    #[test]
+    fn test_key_commitment() {
+        let mut rng = rand::rng();
+        for cipher in [Cipher::Aes128Ctr, Cipher::Aes128Gcm] {
+            let mut data = vec![0u8; 300];
+            rng.fill_bytes(&mut data);
+            let description = bytes_to_hex(sha256(&data));
+            let key = vec![4u8; 16];
+            let wrong_key = vec![5u8; 16];
+
+            let precontract =
+                compute_precontract_values_with_key_check(&mut data, &key, Some(cipher));
+            let key_commitment = precontract.key_commitment.clone().unwrap();
+            assert!(key_commitment.verify(&key));
+            assert!(!key_commitment.verify(&wrong_key));
+
+            // the key check gate only depends on the key
+            let circuit = CompiledCircuit::from_bytes(&precontract.circuit_bytes);
+            let key_gate = circuit.circuit.len() - 2;
+            assert_eq!(
+                "compare key with key commitment",
+                circuit.gate_label(key_gate as u32).unwrap()
+            );
+            for (k, expected) in [(&key, 1u8), (&wrong_key, 0u8)] {
+                let evaluated = evaluate_circuit(
+                    &precontract.circuit_bytes,
+                    &precontract.ct,
+                    vec![bytes_to_hex(k.clone())],
+                    description.clone(),
+                    Some(cipher),
+                );
+                assert_eq!(vec![expected], evaluated.values[key_gate]);
+                assert_eq!(vec![expected], *evaluated.values.last().unwrap());
+            }
+
+            // a wrong key is reported without decrypting, a wrong description after
+            let check_ct = |key: &[u8], description: &str| {
+                check_received_ct_key(
+                    &mut precontract.ct.clone(),
+                    key,
+                    description.to_string(),
+                    Some(cipher),
+                    Some(key_commitment.clone()),
+                )
+            };
+            let res = check_ct(&key, &description);
+            assert_eq!(CtCheckVerdict::Correct, res.verdict);
+            assert!(res.success);
+            let res = check_ct(&wrong_key, &description);
+            assert_eq!(CtCheckVerdict::WrongKey, res.verdict);
+            assert!(res.decrypted_file.is_empty());
+            assert_eq!(
+                CtCheckVerdict::WrongKey,
+                check_ct(&[4u8; 32], &description).verdict
+            );
+            let res = check_ct(&key, &bytes_to_hex(vec![0u8; 32]));
+            assert_eq!(CtCheckVerdict::WrongContent, res.verdict);
+            assert!(!res.success);
+
+            let commitment = bytes_to_hex(precontract.commitment.c.clone());
+            let opening_value = bytes_to_hex(precontract.commitment.o.clone());
+            let check = |key_commitment: Option<KeyCommitment>, key: &[u8]| {
+                let argument = make_argument(
+                    precontract.ct.clone(),
+                    description.clone(),
+                    opening_value.clone(),
+                    cipher,
+                    key_commitment,
+                );
+                check_argument(
+                    &argument,
+                    commitment.clone(),
+                    description.clone(),
+                    bytes_to_hex(key.to_vec()),
+                    None,
+                )
+            };
+            assert_eq!(
+                ArgumentVerdict::DecryptsCorrectly,
+                check(Some(key_commitment.clone()), &key).verdict
+            );
+            let res = check(Some(key_commitment.clone()), &wrong_key);
+            assert_eq!(ArgumentVerdict::DecryptsIncorrectly, res.verdict);
+            assert_eq!(
+                Some("The key does not match the key commitment".to_string()),
+                res.error
+            );
+            // the committed circuit includes the key check
+            assert_eq!(ArgumentVerdict::CircuitMismatch, check(None, &key).verdict);
+        }
+    }
+
+    #[test]
+    fn test_wrapped_key() {
+        let mut rng = rand::rng();
+        let mut data = vec![0u8; 500];
+        rng.fill_bytes(&mut data);
+        let description = bytes_to_hex(sha256(&data));
+        let key = vec![6u8; 16];
+        let buyer_secret_key = [0x42u8; 32];
+        let buyer_public_key = key_delivery::delivery_public_key(&buyer_secret_key);
+
+        let precontract = compute_precontract_values(&mut data, &key);
+        let context = precontract.commitment.c.clone();
+        let wrapped =
+            key_delivery::wrap_key_with_rng(&key, &buyer_public_key, &context, &mut rng).unwrap();
+        let check_ct = |wrapped: &[u8], buyer_secret_key: &[u8]| {
+            check_received_ct_wrapped_key(
+                &mut precontract.ct.clone(),
+                wrapped,
+                buyer_secret_key,
+                &context,
+                description.clone(),
+                None,
+                precontract.key_commitment.clone(),
+            )
+            .verdict
+        };
+        assert_eq!(
+            CtCheckVerdict::Correct,
+            check_ct(&wrapped, &buyer_secret_key)
+        );
+        assert_eq!(CtCheckVerdict::WrongKey, check_ct(&wrapped, &[0x43; 32]));
+        let other =
+            key_delivery::wrap_key_with_rng(&[7u8; 16], &buyer_public_key, &context, &mut rng)
+                .unwrap();
+        assert_eq!(
+            CtCheckVerdict::WrongKey,
+            check_ct(&other, &buyer_secret_key)
+        );
+
+        // the key is unwrapped with the disclosed shared secret during a dispute
+        let argument = make_argument(
+            precontract.ct.clone(),
+            description.clone(),
+            bytes_to_hex(precontract.commitment.o.clone()),
+            Cipher::Aes128Ctr,
+            None,
+        );
+        let check = |wrapped: &[u8], disclosure: &KeyDisclosure| {
+            check_argument_with_disclosure(
+                &argument,
+                bytes_to_hex(precontract.commitment.c.clone()),
+                description.clone(),
+                wrapped,
+                &buyer_public_key,
+                &context,
+                disclosure,
+            )
+            .verdict
+        };
+        let disclosure =
+            key_delivery::disclose_shared_secret_with_rng(&wrapped, &buyer_secret_key, &mut rng)
+                .unwrap();
+        assert_eq!(
+            ArgumentVerdict::DecryptsCorrectly,
+            check(&wrapped, &disclosure)
+        );
+        let disclosure =
+            key_delivery::disclose_shared_secret_with_rng(&other, &buyer_secret_key, &mut rng)
+                .unwrap();
+        assert_eq!(
+            ArgumentVerdict::DecryptsIncorrectly,
+            check(&other, &disclosure)
+        );
+        let forged =
+            key_delivery::disclose_shared_secret_with_rng(&wrapped, &[0x43; 32], &mut rng).unwrap();
+        assert_eq!(ArgumentVerdict::InvalidDisclosure, check(&wrapped, &forged));
+    }
+
+    #[test]
+    fn test_sealed_argument() {
+        let mut data = vec![3u8; 700];
+        let description = bytes_to_hex(sha256(&data));
+        let key = vec![8u8; 16];
+        let precontract = compute_precontract_values(&mut data, &key);
+        let commitment = bytes_to_hex(precontract.commitment.c.clone());
+        let argument = make_argument(
+            precontract.ct.clone(),
+            description.clone(),
+            bytes_to_hex(precontract.commitment.o.clone()),
+            Cipher::Aes128Ctr,
+            None,
+        );
+
+        let (vendor, sponsor) = ([1u8; 32], [2u8; 32]);
+        let vendor_public_key = sealing::sealing_public_key(&vendor);
+        let sealed = seal_argument(
+            &argument,
+            commitment.clone(),
+            &vendor,
+            &sealing::sealing_public_key(&sponsor),
+        );
+        // the argument is not readable from the sealed one
+        assert!(!sealed
+            .windows(precontract.commitment.o.len())
+            .any(|w| w == precontract.commitment.o));
+
+        let check = |sponsor: &[u8], sender_public_key: &[u8], commitment: &str| {
+            check_sealed_argument(
+                &sealed,
+                sponsor,
+                sender_public_key,
+                commitment.to_string(),
+                description.clone(),
+                bytes_to_hex(key.clone()),
+            )
+            .verdict
+        };
+        assert_eq!(
+            ArgumentVerdict::DecryptsCorrectly,
+            check(&sponsor, &vendor_public_key, &commitment)
+        );
+        let buyer_public_key = sealing::sealing_public_key(&[3u8; 32]);
+        assert_eq!(
+            ArgumentVerdict::InvalidSeal,
+            check(&sponsor, &buyer_public_key, &commitment)
+        );
+        assert_eq!(
+            ArgumentVerdict::InvalidSeal,
+            check(&[4u8; 32], &vendor_public_key, &commitment)
+        );
+        assert_eq!(
+            ArgumentVerdict::InvalidSeal,
+            check(&sponsor, &vendor_public_key, &bytes_to_hex(vec![0u8; 32]))
+        );
+    }
+
+    #[test]
+    fn test_signed_argument() {
+        let mut data = vec![4u8; 300];
+        let description = bytes_to_hex(sha256(&data));
+        let key = vec![5u8; 16];
+        let precontract = compute_precontract_values(&mut data, &key);
+        let argument = make_argument(
+            precontract.ct.clone(),
+            description.clone(),
+            bytes_to_hex(precontract.commitment.o.clone()),
+            Cipher::Aes128Ctr,
+            None,
+        );
+
+        let vendor = [0x11u8; 32];
+        let signature = eip712::sign_argument(&argument, &precontract.commitment.c, 1, &vendor);
+        let check = |address: Vec<u8>, chain_id: u64| {
+            check_argument(
+                &argument,
+                bytes_to_hex(precontract.commitment.c.clone()),
+                description.clone(),
+                bytes_to_hex(key.clone()),
+                Some(ArgumentSigner::new(address, signature.clone(), chain_id)),
+            )
+            .verdict
+        };
+        assert_eq!(
+            ArgumentVerdict::DecryptsCorrectly,
+            check(eip712::eth_address(&vendor), 1)
+        );
+        assert_eq!(
+            ArgumentVerdict::InvalidSignature,
+            check(eip712::eth_address(&[0x12u8; 32]), 1)
+        );
+        assert_eq!(
+            ArgumentVerdict::InvalidSignature,
+            check(eip712::eth_address(&vendor), 5)
+        );
+
+        // the digest does not depend on the encoding of the argument
+        let legacy = rmp_serde::to_vec(&DisputeArgument::from_bytes(&argument)).unwrap();
+        assert_eq!(
+            eip712::argument_digest(&argument, &precontract.commitment.c, 1),
+            eip712::argument_digest(&legacy, &precontract.commitment.c, 1)
+        );
+    }
+
+    #[test]
+    fn test_check_argument() {
+        let mut rng = rand::rng();
+        let mut data = vec![0u8; 1000];
+        rng.fill_bytes(&mut data);
+        let description = bytes_to_hex(sha256(&data));
+        let mut key = vec![0u8; 16];
+        rng.fill_bytes(&mut key);
+
+        let precontract = compute_precontract_values(&mut data, &key);
+        let commitment = bytes_to_hex(precontract.commitment.c.clone());
+        let opening_value = bytes_to_hex(precontract.commitment.o.clone());
+        let check = |argument: &[u8], commitment: &str, key: &[u8]| {
+            check_argument(
+                argument,
+                commitment.to_string(),
+                description.clone(),
+                bytes_to_hex(key.to_vec()),
+                None,
+            )
+            .verdict
+        };
+
+        let argument = make_argument(
+            precontract.ct.clone(),
+            description.clone(),
+            opening_value.clone(),
+            Cipher::Aes128Ctr,
+            None,
+        );
+        assert_eq!(
+            ArgumentVerdict::DecryptsCorrectly,
+            check(&argument, &commitment, &key)
+        );
+        assert_eq!(
+            ArgumentVerdict::DecryptsIncorrectly,
+            check(&argument, &commitment, &[0u8; 16])
+        );
+        assert_eq!(
+            ArgumentVerdict::InvalidOpening,
+            check(&argument, &bytes_to_hex(vec![0u8; 32]), &key)
+        );
+
+        let mut ct = precontract.ct.clone();
+        ct[20] ^= 1;
+        let argument = make_argument(
+            ct,
+            description.clone(),
+            opening_value.clone(),
+            Cipher::Aes128Ctr,
+            None,
+        );
+        assert_eq!(
+            ArgumentVerdict::CtMismatch,
+            check(&argument, &commitment, &key)
+        );
+
+        // the constants are not committed, so only the recompilation catches this one
+        let argument = DisputeArgument {
+            circuit: compile_basic_circuit(precontract.ct.len() as u32, &[0u8; 32]),
+            ct: precontract.ct.clone(),
+            opening_value: precontract.commitment.o.clone(),
+        }
+        .to_bytes();
+        assert_eq!(
+            ArgumentVerdict::CircuitMismatch,
+            check(&argument, &commitment, &key)
+        );
+    }
 }
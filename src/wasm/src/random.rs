@@ -0,0 +1,51 @@
+use crate::utils::die;
+use rand::CryptoRng;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+/// Source of randomness of the crate. Every random value (commitment blinding, IV, ...) is drawn
+/// from one of these so that callers can inject a deterministic generator. The default one is
+/// the thread-local generator of `rand`.
+pub type RandomSource = dyn CryptoRng;
+
+/// Creates a deterministic generator from a 32-byte seed. Two generators with the same seed draw
+/// the same values, which makes precontracts reproducible.
+///
+/// # Arguments
+/// * `seed` - The seed (must be 32 bytes)
+///
+/// # Returns
+/// A ChaCha20 based generator
+pub fn seeded_rng(seed: &[u8]) -> ChaCha20Rng {
+    match <[u8; 32]>::try_from(seed) {
+        Ok(seed) => ChaCha20Rng::from_seed(seed),
+        Err(_) => die("The seed must be 32 bytes"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(rng: &mut RandomSource) -> Vec<u8> {
+        let mut bytes = vec![0u8; 64];
+        rng.fill_bytes(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn test_seeded_rng() {
+        let seed = [7u8; 32];
+        assert_eq!(draw(&mut seeded_rng(&seed)), draw(&mut seeded_rng(&seed)));
+        assert_ne!(
+            draw(&mut seeded_rng(&seed)),
+            draw(&mut seeded_rng(&[8u8; 32]))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The seed must be 32 bytes")]
+    fn test_seeded_rng_invalid_seed() {
+        seeded_rng(&[0u8; 16]);
+    }
+}
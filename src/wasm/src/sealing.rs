@@ -0,0 +1,327 @@
+use crate::random::RandomSource;
+use crate::secret::SecretBytes;
+use crate::utils::die;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use wasm_bindgen::prelude::wasm_bindgen;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+/*
+ * sealed = [
+ *      ephemeral public key (32 bytes),
+ *      ciphertext (same size as the plaintext),
+ *      tag (16 bytes)
+ * ]
+ *
+ * The ChaCha20-Poly1305 key is derived with HKDF-SHA256 from the X25519 shared secret, the
+ * ephemeral public key and the recipient's public key. Each message has its own ephemeral key,
+ * hence its own encryption key, so the nonce is always 0.
+ *
+ * When the sender is authenticated, the shared secret of the sender's static key and the
+ * recipient's key is appended to the ephemeral one and the sender's public key to the HKDF info,
+ * so that only the holder of the sender's secret key (or of the recipient's) can seal a message
+ * that opens.
+ */
+
+const SEAL_LABEL: &[u8] = b"sox sealed box";
+const AUTH_SEAL_LABEL: &[u8] = b"sox authenticated sealed box";
+
+/// Size of X25519 secret and public keys
+pub const SEALING_KEY_LEN: usize = 32;
+
+/// Overhead of sealing: ephemeral public key and tag
+pub const SEAL_OVERHEAD: usize = SEALING_KEY_LEN + 16;
+
+fn to_key(bytes: &[u8]) -> Option<[u8; SEALING_KEY_LEN]> {
+    bytes.try_into().ok()
+}
+
+// derives the ChaCha20-Poly1305 key from the (secret, public) pairs, or None if a shared secret
+// is all zeros (the public key is a low order point)
+fn derive_key(
+    pairs: &[(&StaticSecret, &PublicKey)],
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+    sender: Option<&PublicKey>,
+) -> Option<ChaCha20Poly1305> {
+    let mut ikm = Zeroizing::new(Vec::with_capacity(32 * pairs.len()));
+    for (secret, their_public) in pairs {
+        let shared = secret.diffie_hellman(their_public);
+        if !shared.was_contributory() {
+            return None;
+        }
+        ikm.extend(shared.as_bytes());
+    }
+
+    let info = match sender {
+        Some(sender) => [
+            AUTH_SEAL_LABEL,
+            ephemeral.as_bytes(),
+            recipient.as_bytes(),
+            sender.as_bytes(),
+        ]
+        .concat(),
+        None => [SEAL_LABEL, ephemeral.as_bytes(), recipient.as_bytes()].concat(),
+    };
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(None, &ikm)
+        .expand(&info, key.as_mut())
+        .ok()?;
+
+    Some(ChaCha20Poly1305::new(key.as_ref().into()))
+}
+
+fn parse_secret_key(bytes: &[u8]) -> Result<StaticSecret, String> {
+    Ok(StaticSecret::from(
+        to_key(bytes).ok_or("A secret key must be 32 bytes")?,
+    ))
+}
+
+fn parse_public_key(bytes: &[u8]) -> Result<PublicKey, String> {
+    Ok(PublicKey::from(
+        to_key(bytes).ok_or("A public key must be 32 bytes")?,
+    ))
+}
+
+// encrypts with a key derived for a fresh ephemeral key, see the layout above
+fn seal_internal(
+    recipient: &PublicKey,
+    sender: Option<(&StaticSecret, &PublicKey)>,
+    plaintext: &[u8],
+    context: &[u8],
+    rng: &mut RandomSource,
+) -> Result<Vec<u8>, String> {
+    let mut bytes = Zeroizing::new([0u8; SEALING_KEY_LEN]);
+    rng.fill_bytes(bytes.as_mut());
+    let ephemeral_secret = StaticSecret::from(*bytes);
+    let ephemeral = PublicKey::from(&ephemeral_secret);
+
+    let mut pairs = vec![(&ephemeral_secret, recipient)];
+    pairs.extend(sender.map(|(secret, _)| (secret, recipient)));
+    let aead = derive_key(
+        &pairs,
+        &ephemeral,
+        recipient,
+        sender.map(|(_, public)| public),
+    )
+    .ok_or("Invalid public key")?;
+    let ct = aead
+        .encrypt(
+            &Nonce::default(),
+            Payload {
+                msg: plaintext,
+                aad: context,
+            },
+        )
+        .map_err(|_| "The message is too long to be sealed")?;
+
+    Ok([ephemeral.as_bytes().as_slice(), &ct].concat())
+}
+
+// decrypts a message of `seal_internal`
+fn open_internal(
+    secret: &StaticSecret,
+    sender: Option<&PublicKey>,
+    sealed: &[u8],
+    context: &[u8],
+) -> Result<SecretBytes, String> {
+    if sealed.len() < SEAL_OVERHEAD {
+        return Err("The sealed message is too short".to_string());
+    }
+    let (ephemeral, ct) = sealed.split_at(SEALING_KEY_LEN);
+    let ephemeral = parse_public_key(ephemeral)?;
+
+    let mut pairs = vec![(secret, &ephemeral)];
+    pairs.extend(sender.map(|sender| (secret, sender)));
+    let aead = derive_key(&pairs, &ephemeral, &PublicKey::from(secret), sender)
+        .ok_or("Invalid public key")?;
+    aead.decrypt(
+        &Nonce::default(),
+        Payload {
+            msg: ct,
+            aad: context,
+        },
+    )
+    .map(Zeroizing::new)
+    .map_err(|_| "The sealed message cannot be opened".to_string())
+}
+
+/// Computes the X25519 public key of a secret key. Any 32 random bytes are a secret key.
+///
+/// # Arguments
+/// * `secret_key` - The secret key (32 bytes)
+///
+/// # Returns
+/// The public key (32 bytes)
+#[wasm_bindgen]
+pub fn sealing_public_key(secret_key: &[u8]) -> Vec<u8> {
+    match to_key(secret_key) {
+        Some(bytes) => PublicKey::from(&StaticSecret::from(bytes))
+            .as_bytes()
+            .to_vec(),
+        None => die("A sealing secret key must be 32 bytes"),
+    }
+}
+
+/// Encrypts a message to the holder of an X25519 key with an ephemeral key. The recipient can
+/// check that the message was not modified and that it was sealed for `context`, but not who
+/// sealed it.
+///
+/// # Arguments
+/// * `public_key` - The recipient's public key (32 bytes)
+/// * `plaintext` - The message
+/// * `context` - Data authenticated with the message but not included in it
+/// * `rng` - Source of the ephemeral key
+///
+/// # Returns
+/// The sealed message, `SEAL_OVERHEAD` bytes longer than the plaintext
+pub fn seal_with_rng(
+    public_key: &[u8],
+    plaintext: &[u8],
+    context: &[u8],
+    rng: &mut RandomSource,
+) -> Result<Vec<u8>, String> {
+    seal_internal(
+        &parse_public_key(public_key)?,
+        None,
+        plaintext,
+        context,
+        rng,
+    )
+}
+
+/// Decrypts a message sealed by `seal_with_rng`
+///
+/// # Arguments
+/// * `secret_key` - The recipient's secret key (32 bytes)
+/// * `sealed` - The sealed message
+/// * `context` - Data the message was sealed for
+///
+/// # Returns
+/// The message, or an error if it was not sealed for this key and context or was modified
+pub fn open_sealed(
+    secret_key: &[u8],
+    sealed: &[u8],
+    context: &[u8],
+) -> Result<SecretBytes, String> {
+    open_internal(&parse_secret_key(secret_key)?, None, sealed, context)
+}
+
+/// Encrypts a message to the holder of an X25519 key, as done by `seal_with_rng`, and
+/// authenticates the sender with its static X25519 key
+///
+/// # Arguments
+/// * `sender_secret_key` - The sender's secret key (32 bytes)
+/// * `public_key` - The recipient's public key (32 bytes)
+/// * `plaintext` - The message
+/// * `context` - Data authenticated with the message but not included in it
+/// * `rng` - Source of the ephemeral key
+///
+/// # Returns
+/// The sealed message, `SEAL_OVERHEAD` bytes longer than the plaintext
+pub fn seal_from_with_rng(
+    sender_secret_key: &[u8],
+    public_key: &[u8],
+    plaintext: &[u8],
+    context: &[u8],
+    rng: &mut RandomSource,
+) -> Result<Vec<u8>, String> {
+    let sender = parse_secret_key(sender_secret_key)?;
+    let recipient = parse_public_key(public_key)?;
+    seal_internal(
+        &recipient,
+        Some((&sender, &PublicKey::from(&sender))),
+        plaintext,
+        context,
+        rng,
+    )
+}
+
+/// Decrypts a message sealed by `seal_from_with_rng` and checks its sender
+///
+/// # Arguments
+/// * `secret_key` - The recipient's secret key (32 bytes)
+/// * `sender_public_key` - The expected sender's public key (32 bytes)
+/// * `sealed` - The sealed message
+/// * `context` - Data the message was sealed for
+///
+/// # Returns
+/// The message, or an error if it was not sealed by this sender for this key and context or was
+/// modified
+pub fn open_sealed_from(
+    secret_key: &[u8],
+    sender_public_key: &[u8],
+    sealed: &[u8],
+    context: &[u8],
+) -> Result<SecretBytes, String> {
+    let sender = parse_public_key(sender_public_key)?;
+    open_internal(
+        &parse_secret_key(secret_key)?,
+        Some(&sender),
+        sealed,
+        context,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open() {
+        let mut rng = rand::rng();
+        let secret_key = [7u8; 32];
+        let public_key = sealing_public_key(&secret_key);
+        let msg = b"key share".to_vec();
+
+        let sealed = seal_with_rng(&public_key, &msg, b"context", &mut rng).unwrap();
+        assert_eq!(msg.len() + SEAL_OVERHEAD, sealed.len());
+        assert_eq!(msg, *open_sealed(&secret_key, &sealed, b"context").unwrap());
+
+        // wrong key, wrong context or modified message
+        assert!(open_sealed(&[8u8; 32], &sealed, b"context").is_err());
+        assert!(open_sealed(&secret_key, &sealed, b"other").is_err());
+        let mut modified = sealed.clone();
+        modified[40] ^= 1;
+        assert!(open_sealed(&secret_key, &modified, b"context").is_err());
+        assert!(open_sealed(&secret_key, &sealed[..SEAL_OVERHEAD - 1], b"context").is_err());
+
+        // a low order point is rejected
+        assert!(seal_with_rng(&[0u8; 32], &msg, b"context", &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_seal_open_from() {
+        let mut rng = rand::rng();
+        let (sender, recipient) = ([1u8; 32], [2u8; 32]);
+        let sender_public_key = sealing_public_key(&sender);
+        let msg = b"argument".to_vec();
+
+        let sealed = seal_from_with_rng(
+            &sender,
+            &sealing_public_key(&recipient),
+            &msg,
+            b"context",
+            &mut rng,
+        )
+        .unwrap();
+        let opened = open_sealed_from(&recipient, &sender_public_key, &sealed, b"context");
+        assert_eq!(msg, *opened.unwrap());
+
+        // another sender, or no sender at all
+        let other_public_key = sealing_public_key(&[3u8; 32]);
+        assert!(open_sealed_from(&recipient, &other_public_key, &sealed, b"context").is_err());
+        assert!(open_sealed(&recipient, &sealed, b"context").is_err());
+        let anonymous = seal_with_rng(&sealing_public_key(&recipient), &msg, b"context", &mut rng);
+        let opened = open_sealed_from(
+            &recipient,
+            &sender_public_key,
+            &anonymous.unwrap(),
+            b"context",
+        );
+        assert!(opened.is_err());
+    }
+}
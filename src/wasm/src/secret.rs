@@ -0,0 +1,128 @@
+use crate::utils::die;
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Bytes that are wiped from memory when dropped (keys, opening values, ...)
+pub type SecretBytes = Zeroizing<Vec<u8>>;
+
+/// Decodes hex encoded secret bytes. The string is wiped once decoded and, unlike `hex_to_bytes`,
+/// an invalid encoding does not leak any part of the secret in the error message.
+///
+/// # Arguments
+/// * `hex_str` - Hexadecimal string to convert
+///
+/// # Returns
+/// The decoded bytes, wiped when dropped
+pub fn secret_from_hex(mut hex_str: String) -> SecretBytes {
+    let decoded = prefix_hex::decode::<Vec<u8>>(hex_str.as_str());
+    hex_str.zeroize();
+
+    match decoded {
+        Ok(bytes) => Zeroizing::new(bytes),
+        Err(_) => die("Invalid hex encoding of a secret value"),
+    }
+}
+
+/// Decodes hex encoded constants that may include secrets (e.g. the key), as done by
+/// `secret_from_hex`
+///
+/// # Arguments
+/// * `hex_strs` - Hexadecimal strings to convert
+///
+/// # Returns
+/// The decoded constants. The buffers are moved out of their `Zeroizing` wrapper rather than
+/// copied, so the caller is responsible for wiping them (e.g. `CompiledCircuitWithConstants`).
+pub fn secrets_from_hex(hex_strs: Vec<String>) -> Vec<Vec<u8>> {
+    hex_strs
+        .into_iter()
+        .map(|s| std::mem::take(&mut *secret_from_hex(s)))
+        .collect()
+}
+
+/// Compares two slices element by element without exiting early, so that the time taken does not
+/// depend on the position of the first difference. The lengths are not secret and slices of
+/// different lengths are not compared.
+///
+/// # Arguments
+/// * `a` - The first slice
+/// * `b` - The second slice
+///
+/// # Returns
+/// A `Choice` which is 1 if the slices are equal
+pub fn ct_eq_slices<T: ConstantTimeEq>(a: &[T], b: &[T]) -> Choice {
+    if a.len() != b.len() {
+        return Choice::from(0);
+    }
+
+    a.iter()
+        .zip(b)
+        .fold(Choice::from(1), |acc, (x, y)| acc & x.ct_eq(y))
+}
+
+/// Constant time equality of byte strings, to be used whenever one of them is secret or derived
+/// from a secret (commitments, keys, descriptions of an encrypted file, ...)
+///
+/// # Arguments
+/// * `a` - The first byte string
+/// * `b` - The second byte string
+///
+/// # Returns
+/// Whether the byte strings are equal
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    ct_eq_slices(a, b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // a byte counting how many times it is compared
+    struct Counted(u8);
+
+    impl ConstantTimeEq for Counted {
+        fn ct_eq(&self, other: &Self) -> Choice {
+            COMPARISONS.with(|c| c.set(c.get() + 1));
+            self.0.ct_eq(&other.0)
+        }
+    }
+
+    fn comparisons(a: &[u8], b: &[u8]) -> (bool, usize) {
+        let a: Vec<Counted> = a.iter().map(|&x| Counted(x)).collect();
+        let b: Vec<Counted> = b.iter().map(|&x| Counted(x)).collect();
+        COMPARISONS.with(|c| c.set(0));
+        let eq = ct_eq_slices(&a, &b).into();
+        (eq, COMPARISONS.with(|c| c.get()))
+    }
+
+    #[test]
+    fn test_ct_eq_does_not_short_circuit() {
+        let a = [7u8; 32];
+        for i in 0..32 {
+            let mut b = a;
+            b[i] ^= 1;
+            assert_eq!((false, 32), comparisons(&a, &b));
+        }
+        assert_eq!((true, 32), comparisons(&a, &a));
+        assert_eq!((false, 0), comparisons(&a, &a[1..]));
+
+        assert!(ct_eq(&a, &a));
+        assert!(!ct_eq(&a, &[0u8; 32]));
+        assert!(ct_eq(&[], &[]));
+    }
+
+    #[test]
+    fn test_secret_from_hex() {
+        assert_eq!(vec![0xab, 0xcd], *secret_from_hex("0xabcd".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid hex encoding of a secret value")]
+    fn test_secret_from_hex_does_not_leak() {
+        secret_from_hex("0xabcz".to_string());
+    }
+}
@@ -47,7 +47,7 @@ fn u32_array_to_u8_vec(array: &[u32; 8]) -> Vec<u8> {
 ///
 /// # Returns
 /// A 32-byte vector containing the compressed result
-pub fn sha256_compress(data: &Vec<&Vec<u8>>) -> Vec<u8> {
+pub fn sha256_compress(data: &[&Vec<u8>]) -> Vec<u8> {
     if data.len() != 1 && data.len() != 2 {
         die("Input data for compression must have exactly 1 or 2 elements.");
     }
@@ -66,7 +66,7 @@ pub fn sha256_compress(data: &Vec<&Vec<u8>>) -> Vec<u8> {
 }
 
 // Performs SHA-256 standard padding on the input data
-fn sha256_padding(input: &Vec<u8>, data_len: u64) -> Vec<u8> {
+fn sha256_padding(input: &[u8], data_len: u64) -> Vec<u8> {
     let mut padded_len = input.len() + 9;
     if padded_len < 64 {
         padded_len = 64
@@ -75,9 +75,7 @@ fn sha256_padding(input: &Vec<u8>, data_len: u64) -> Vec<u8> {
     }
 
     let mut padded = vec![0u8; padded_len - 8];
-    for i in 0..input.len() {
-        padded[i] = input[i];
-    }
+    padded[..input.len()].copy_from_slice(input);
     padded[input.len()] = 0x80;
     padded.extend(&(data_len * 8).to_be_bytes());
 
@@ -99,7 +97,7 @@ fn sha256_padding(input: &Vec<u8>, data_len: u64) -> Vec<u8> {
 /// - Input doesn't have exactly 2 or 3 elements
 /// - Previous hash (if present) is not 32 bytes
 /// - Data length is not 8 bytes
-pub fn sha256_compress_final(data: &Vec<&Vec<u8>>) -> Vec<u8> {
+pub fn sha256_compress_final(data: &[&Vec<u8>]) -> Vec<u8> {
     if data.len() != 2 && data.len() != 3 {
         let msg = format!(
             "Input data for the final compression must have exactly 2 or 3 elements. Got {}",
@@ -130,7 +128,7 @@ pub fn sha256_compress_final(data: &Vec<&Vec<u8>>) -> Vec<u8> {
     let curr_block = data[data.len() - 2];
     let data_len = u64::from_be_bytes(data[data.len() - 1].clone().try_into().unwrap());
 
-    let padded = sha256_padding(&curr_block, data_len);
+    let padded = sha256_padding(curr_block, data_len);
     let h1 = u8_array_to_u32_array(&padded[..32]);
     let h2 = u8_array_to_u32_array(&padded[32..64]);
     let mut res = prev_hash.compress(&h1, &h2);
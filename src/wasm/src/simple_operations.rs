@@ -1,5 +1,7 @@
+use crate::secret::ct_eq_slices;
 use crate::utils::die;
 use std::cmp::min;
+use subtle::Choice;
 
 /// Checks if all provided byte arrays are equal
 ///
@@ -8,23 +10,17 @@ use std::cmp::min;
 ///
 /// # Returns
 /// Single byte vector containing 1 if all arrays are equal, 0 if not equal
-pub fn equal(data: &Vec<&Vec<u8>>) -> Vec<u8> {
+pub fn equal(data: &[&Vec<u8>]) -> Vec<u8> {
     if data.len() < 2 {
         die("Need at least two elements to check for equality")
     }
 
-    for i in 1..data.len() {
-        if data[0].len() != data[i].len() {
-            return vec![0u8];
-        }
-        for j in 0..data[i].len() {
-            if data[0][j] != data[i][j] {
-                return vec![0u8];
-            }
-        }
-    }
+    // the values may be secret (e.g. a hash of the plaintext), so all of them are compared
+    let eq = data[1..].iter().fold(Choice::from(1), |acc, other| {
+        acc & ct_eq_slices(data[0], other)
+    });
 
-    vec![1u8]
+    vec![eq.unwrap_u8()]
 }
 
 /// Internal helper to copy bytes into padded buffer
@@ -46,7 +42,7 @@ fn copy_to_padded(src: &[u8], dst: &mut [u8]) {
 ///
 /// # Returns
 /// 16-byte array containing sum as big-endian u128, padded with leading zeros
-pub fn binary_add(data: &Vec<&Vec<u8>>) -> Vec<u8> {
+pub fn binary_add(data: &[&Vec<u8>]) -> Vec<u8> {
     if data.len() != 2 {
         die("Binary addition only accepts 2 arrays of bytes");
     }
@@ -57,8 +53,8 @@ pub fn binary_add(data: &Vec<&Vec<u8>>) -> Vec<u8> {
 
     let mut left = [0u8; 16];
     let mut right = [0u8; 16];
-    copy_to_padded(&data[0], &mut left);
-    copy_to_padded(&data[1], &mut right);
+    copy_to_padded(data[0], &mut left);
+    copy_to_padded(data[1], &mut right);
 
     (u128::from_be_bytes(left) + u128::from_be_bytes(right))
         .to_be_bytes()
@@ -72,7 +68,7 @@ pub fn binary_add(data: &Vec<&Vec<u8>>) -> Vec<u8> {
 ///
 /// # Returns
 /// 16-byte array containing product as big-endian u128, padded with leading zeros
-pub fn binary_mult(data: &Vec<&Vec<u8>>) -> Vec<u8> {
+pub fn binary_mult(data: &[&Vec<u8>]) -> Vec<u8> {
     if data.len() != 2 {
         die("Binary multiplication only accepts 2 arrays of bytes");
     }
@@ -83,8 +79,8 @@ pub fn binary_mult(data: &Vec<&Vec<u8>>) -> Vec<u8> {
 
     let mut left = [0u8; 16];
     let mut right = [0u8; 16];
-    copy_to_padded(&data[0], &mut left);
-    copy_to_padded(&data[1], &mut right);
+    copy_to_padded(data[0], &mut left);
+    copy_to_padded(data[1], &mut right);
 
     (u128::from_be_bytes(left) * u128::from_be_bytes(right))
         .to_be_bytes()
@@ -98,19 +94,85 @@ pub fn binary_mult(data: &Vec<&Vec<u8>>) -> Vec<u8> {
 ///
 /// # Returns
 /// Single byte array containing all input arrays concatenated
-pub fn concat_bytes(data: &Vec<&Vec<u8>>) -> Vec<u8> {
-    if data.len() == 0 {
+pub fn concat_bytes(data: &[&Vec<u8>]) -> Vec<u8> {
+    if data.is_empty() {
         return vec![];
     }
     if data.len() == 1 {
         return data[0].clone();
     }
-    let mut res = data[0].iter().map(|x| x.clone()).collect();
+    let mut res = data[0].to_vec();
 
-    for i in 1..data.len() {
-        let next = data[i].iter().map(|x| x.clone()).collect();
+    for d in &data[1..] {
+        let next = d.to_vec();
         res = [res, next].concat();
     }
 
     res
 }
+
+/// Extracts the bytes of an array between two positions. The positions are big-endian numbers of
+/// at most 8 bytes.
+///
+/// # Arguments
+/// * `data` - Vector containing exactly 3 byte array references: the array, the start position
+///   (included) and the end position (excluded)
+///
+/// # Returns
+/// The bytes of the array between the two positions
+pub fn slice_bytes(data: &[&Vec<u8>]) -> Vec<u8> {
+    if data.len() != 3 {
+        die("Slicing requires an array of bytes, a start and an end position");
+    }
+
+    if data[1].len() > 8 || data[2].len() > 8 {
+        die("Slicing only accepts positions of at most 8 bytes");
+    }
+
+    let mut start = [0u8; 8];
+    let mut end = [0u8; 8];
+    copy_to_padded(data[1], &mut start);
+    copy_to_padded(data[2], &mut end);
+    let (start, end) = (u64::from_be_bytes(start), u64::from_be_bytes(end));
+
+    if start > end || end > data[0].len() as u64 {
+        die("Slicing positions are out of the array's bounds");
+    }
+
+    data[0][start as usize..end as usize].to_vec()
+}
+
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equal_instruction() {
+        let a = vec![1u8, 2, 3];
+        let b = vec![1u8, 2, 4];
+        assert_eq!(vec![1u8], equal(&[&a, &a, &a]));
+        assert_eq!(vec![0u8], equal(&[&a, &b, &a]));
+        assert_eq!(vec![0u8], equal(&[&a, &a, &b]));
+        assert_eq!(vec![0u8], equal(&[&a, &vec![1u8, 2]]));
+    }
+
+    #[test]
+    fn test_slice_bytes() {
+        let data: Vec<u8> = (0..64).collect();
+        let pos = |p: u64| p.to_be_bytes().to_vec();
+        assert_eq!(
+            data[5..21].to_vec(),
+            slice_bytes(&[&data, &pos(5), &pos(21)])
+        );
+        assert_eq!(data, slice_bytes(&[&data, &vec![], &vec![64]]));
+        assert!(slice_bytes(&[&data, &pos(64), &pos(64)]).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Slicing positions are out of the array's bounds")]
+    fn test_slice_bytes_out_of_bounds() {
+        slice_bytes(&[&vec![0u8; 16], &vec![8], &vec![17]]);
+    }
+}
@@ -0,0 +1,733 @@
+use crate::agents::{
+    BuyerAgent, FinalStepInputs, FinalStepKind, KaryWindow, SearchWindow, VendorAgent,
+};
+use crate::circuits::{
+    evaluate_circuit_internal, evaluate_gate, get_evaluated_sons, CompiledCircuit,
+};
+use crate::commitment::Commitment;
+use crate::dispute::{verify_final_step, FinalStepContext, FinalStepProofs, FinalStepSubmission};
+use crate::transcript::{DisputeTranscript, TranscriptEntry, TranscriptHeader};
+use crate::utils::{die, split_ct_blocks};
+use std::collections::HashMap;
+
+// ============================= CONTRACT MODEL =============================
+
+/// States of the dispute smart contract. Mirrors `DisputeSOX.State` without the `End` state since
+/// payments are not modeled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisputeState {
+    ChallengeBuyer,
+    WaitVendorOpinion,
+    WaitVendorData,
+    WaitVendorDataLeft,
+    WaitVendorDataRight,
+    Complete,
+    Cancel,
+}
+
+/// Native model of the `DisputeSOX` smart contract. Every method returning `Err` corresponds to a
+/// call that would revert on-chain, in which case the model is left untouched.
+///
+/// The model can also run a k-ary search where the buyer answers several challenges per round,
+/// which the smart contract does not support (yet). With an arity of 1, it behaves exactly as the
+/// smart contract.
+pub struct DisputeModel {
+    /// Number of blocks of the ciphertext (m in the paper)
+    pub num_blocks: u32,
+
+    /// Number of gates in the circuit (n in the paper)
+    pub num_gates: u32,
+
+    /// The commitment value
+    pub commitment: Vec<u8>,
+
+    /// Lower bound of the search
+    pub a: u32,
+
+    /// Upper bound of the search
+    pub b: u32,
+
+    /// The challenge index (i in the paper). For a k-ary search, the first challenge of the round.
+    pub chall: u32,
+
+    /// Maximum number of challenges per round
+    pub arity: u32,
+
+    /// Current state of the dispute
+    pub state: DisputeState,
+
+    // responses of the buyer, missing entries are read as zero like a solidity mapping
+    buyer_responses: HashMap<u32, Vec<u8>>,
+
+    // every action that changed the state of the dispute
+    transcript: DisputeTranscript,
+}
+
+impl DisputeModel {
+    /// Creates the model in the same state as the freshly deployed smart contract
+    ///
+    /// # Arguments
+    /// * `num_blocks` - Number of blocks of the ciphertext
+    /// * `num_gates` - Number of gates in the circuit
+    /// * `commitment` - The commitment value
+    ///
+    /// # Returns
+    /// A `DisputeModel` waiting for the buyer's first response
+    pub fn new(num_blocks: u32, num_gates: u32, commitment: Vec<u8>) -> DisputeModel {
+        DisputeModel::with_arity(num_blocks, num_gates, commitment, 1)
+    }
+
+    /// Creates the model of a dispute run with a k-ary search
+    ///
+    /// # Arguments
+    /// * `num_blocks` - Number of blocks of the ciphertext
+    /// * `num_gates` - Number of gates in the circuit
+    /// * `commitment` - The commitment value
+    /// * `arity` - Maximum number of challenges per round
+    ///
+    /// # Returns
+    /// A `DisputeModel` waiting for the buyer's first responses
+    pub fn with_arity(
+        num_blocks: u32,
+        num_gates: u32,
+        commitment: Vec<u8>,
+        arity: u32,
+    ) -> DisputeModel {
+        let window = KaryWindow::initial(num_blocks, num_gates, arity);
+        let transcript = DisputeTranscript::from_header(TranscriptHeader {
+            num_blocks,
+            num_gates,
+            commitment: commitment.clone(),
+            arity,
+        });
+        DisputeModel {
+            num_blocks,
+            num_gates,
+            commitment,
+            a: window.a,
+            b: window.b,
+            chall: window.chall(),
+            arity,
+            state: DisputeState::ChallengeBuyer,
+            buyer_responses: HashMap::new(),
+            transcript,
+        }
+    }
+
+    /// Returns the buyer's response for index `i`, or 32 zero bytes if there is none
+    pub fn buyer_response(&self, i: u32) -> Vec<u8> {
+        self.buyer_responses
+            .get(&i)
+            .cloned()
+            .unwrap_or_else(|| vec![0u8; 32])
+    }
+
+    /// Returns the buyer's response to the current challenge
+    pub fn latest_buyer_response(&self) -> Vec<u8> {
+        self.buyer_response(self.chall)
+    }
+
+    /// Returns the buyer's responses to the challenges of the current round
+    pub fn latest_buyer_responses(&self) -> Vec<Vec<u8>> {
+        self.challenges()
+            .into_iter()
+            .map(|c| self.buyer_response(c))
+            .collect()
+    }
+
+    /// Models `respondChallenge`
+    pub fn respond_challenge(&mut self, response: &[u8]) -> Result<(), String> {
+        self.respond_challenges(&[response.to_vec()])
+    }
+
+    /// Generalization of `respondChallenge` to a k-ary search round
+    ///
+    /// # Arguments
+    /// * `responses` - The responses, in the order of `challenges()`
+    pub fn respond_challenges(&mut self, responses: &[Vec<u8>]) -> Result<(), String> {
+        self.expect_state(DisputeState::ChallengeBuyer)?;
+        let challenges = self.challenges();
+        if responses.len() != challenges.len() {
+            return Err("One response per challenge is expected".to_string());
+        }
+        if responses.iter().any(|r| r.len() != 32) {
+            return Err("The response must be 32 bytes long".to_string());
+        }
+
+        for (&c, r) in challenges.iter().zip(responses) {
+            self.buyer_responses.insert(c, r.clone());
+        }
+        self.transcript.record_responses(&challenges, responses);
+        self.state = DisputeState::WaitVendorOpinion;
+        Ok(())
+    }
+
+    /// Models `giveOpinion`. For a k-ary search, agreeing means agreeing with all the responses
+    /// of the round and disagreeing means disagreeing with the first one.
+    pub fn give_opinion(&mut self, vendor_agrees: bool) -> Result<(), String> {
+        let disagreement = if vendor_agrees {
+            self.challenges().len() as u32
+        } else {
+            0
+        };
+        self.give_opinion_multi(disagreement)
+    }
+
+    /// Generalization of `giveOpinion` to a k-ary search round
+    ///
+    /// # Arguments
+    /// * `disagreement` - Index in `challenges()` of the first response the vendor disagrees
+    ///   with, or the number of challenges if it agrees with all of them
+    pub fn give_opinion_multi(&mut self, disagreement: u32) -> Result<(), String> {
+        self.expect_state(DisputeState::WaitVendorOpinion)?;
+        if disagreement as usize > self.challenges().len() {
+            return Err("Invalid disagreement index".to_string());
+        }
+
+        let window = self.kary_window().after_opinion(disagreement);
+        let state = if !window.is_converged() {
+            DisputeState::ChallengeBuyer
+        } else {
+            match window.final_step(self.num_blocks, self.num_gates) {
+                Some(FinalStepKind::Commitment) => DisputeState::WaitVendorData,
+                Some(FinalStepKind::CommitmentLeft) => DisputeState::WaitVendorDataLeft,
+                Some(FinalStepKind::CommitmentRight) => DisputeState::WaitVendorDataRight,
+                None => return Err("An invalid state has been reached".to_string()),
+            }
+        };
+
+        (self.a, self.b, self.chall) = (window.a, window.b, window.chall());
+        self.state = state;
+        self.transcript.record_opinion_multi(disagreement);
+        Ok(())
+    }
+
+    /// Returns the current window of the binary search
+    pub fn window(&self) -> SearchWindow {
+        SearchWindow::new(self.a, self.b, self.chall)
+    }
+
+    /// Returns the current window of the k-ary search
+    pub fn kary_window(&self) -> KaryWindow {
+        KaryWindow::new(self.a, self.b, self.arity)
+    }
+
+    /// Returns the challenges of the current round
+    pub fn challenges(&self) -> Vec<u32> {
+        self.kary_window().challenges()
+    }
+
+    /// Models `submitCommitment` (step 8a)
+    ///
+    /// # Arguments
+    /// * `opening_value` - The opening value of the commitment
+    /// * `gate_num` - The number of the gate being evaluated
+    /// * `version` - The instruction set version
+    /// * `proofs` - The gate, its sons' values, the current accumulator value and the proofs
+    pub fn submit_commitment(
+        &mut self,
+        opening_value: &[u8],
+        gate_num: u32,
+        version: u32,
+        proofs: &FinalStepProofs,
+    ) -> Result<(), String> {
+        self.expect_state(DisputeState::WaitVendorData)?;
+        self.submit(&FinalStepSubmission::Commitment {
+            gate_num,
+            opening_value,
+            version,
+            proofs,
+        })
+    }
+
+    /// Models `submitCommitmentLeft` (step 8b). `proofs.proof3` is ignored.
+    ///
+    /// # Arguments
+    /// * `opening_value` - The opening value of the commitment
+    /// * `gate_num` - The number of the gate being evaluated
+    /// * `version` - The instruction set version
+    /// * `proofs` - The gate, its sons' values, the current accumulator value and the proofs
+    pub fn submit_commitment_left(
+        &mut self,
+        opening_value: &[u8],
+        gate_num: u32,
+        version: u32,
+        proofs: &FinalStepProofs,
+    ) -> Result<(), String> {
+        self.expect_state(DisputeState::WaitVendorDataLeft)?;
+        self.submit(&FinalStepSubmission::Commitment {
+            gate_num,
+            opening_value,
+            version,
+            proofs,
+        })
+    }
+
+    /// Models `submitCommitmentRight` (step 8c)
+    ///
+    /// # Arguments
+    /// * `proof` - Proof that the last gate evaluated to true in the buyer's last response
+    pub fn submit_commitment_right(&mut self, proof: &[Vec<Vec<u8>>]) -> Result<(), String> {
+        self.expect_state(DisputeState::WaitVendorDataRight)?;
+        self.submit(&FinalStepSubmission::Right { proof })
+    }
+
+    /// Returns the transcript of every action that changed the state of the dispute
+    pub fn transcript(&self) -> &DisputeTranscript {
+        &self.transcript
+    }
+
+    /// Returns the on-chain values read during the final step
+    pub fn final_step_context(&self) -> FinalStepContext {
+        FinalStepContext::new(
+            self.commitment.clone(),
+            self.num_blocks,
+            self.num_gates,
+            self.chall,
+            self.buyer_response(self.chall.saturating_sub(1)),
+            self.buyer_response(self.chall),
+        )
+    }
+
+    /// Models a timeout followed by `completeDispute` or `cancelDispute` from the party that was
+    /// not expected to act. Does nothing if the dispute is already decided.
+    pub fn timeout(&mut self) {
+        match self.state {
+            DisputeState::ChallengeBuyer => self.state = DisputeState::Complete,
+            DisputeState::WaitVendorOpinion
+            | DisputeState::WaitVendorData
+            | DisputeState::WaitVendorDataLeft
+            | DisputeState::WaitVendorDataRight => self.state = DisputeState::Cancel,
+            DisputeState::Complete | DisputeState::Cancel => return,
+        }
+        self.transcript.record_timeout();
+    }
+
+    // reverts if the dispute is not in the expected state
+    fn expect_state(&self, state: DisputeState) -> Result<(), String> {
+        if self.state != state {
+            return Err("Cannot run this function in the current state".to_string());
+        }
+        Ok(())
+    }
+
+    // runs the final verification, the model is left untouched if the transaction reverts
+    fn submit(&mut self, submission: &FinalStepSubmission) -> Result<(), String> {
+        self.state = match verify_final_step(&self.final_step_context(), submission) {
+            Ok(()) => DisputeState::Complete,
+            Err(e) if e.reverts() => return Err(e.to_string()),
+            Err(_) => DisputeState::Cancel,
+        };
+        self.transcript.append(TranscriptEntry::from(submission));
+        Ok(())
+    }
+}
+
+// ============================= STRATEGIES =============================
+
+/// Behaviour of the buyer during the dispute
+pub trait BuyerStrategy {
+    /// Returns the responses to the challenges of the current round, or `None` to let the
+    /// timeout expire
+    fn respond(&mut self, dispute: &DisputeModel) -> Option<Vec<Vec<u8>>>;
+}
+
+/// Behaviour of the vendor during the dispute
+pub trait VendorStrategy {
+    /// Returns the index of the first of the buyer's latest responses the vendor disagrees with
+    /// (the number of challenges if it agrees with all of them), or `None` to let the timeout
+    /// expire
+    fn opinion(&mut self, dispute: &DisputeModel) -> Option<u32>;
+
+    /// Sends the data of the final step (8a, 8b or 8c depending on the state of the dispute)
+    fn final_step(&mut self, dispute: &mut DisputeModel) -> Result<(), String>;
+}
+
+/// Ways for a party to build its view of the evaluated circuit
+#[derive(Clone, Debug)]
+pub enum Strategy {
+    /// Evaluates the circuit with the key sent by the vendor
+    Honest,
+
+    /// Evaluates the circuit honestly, then changes the value at the given index and consistently
+    /// recomputes the values of the following gates
+    LieAtGate(u32),
+
+    /// Evaluates the circuit with the given key instead of the one sent by the vendor
+    WrongKey(Vec<u8>),
+}
+
+/// Everything that is public once the vendor has sent the key, plus the opening value only known
+/// to the vendor.
+#[derive(Clone)]
+pub struct DisputeScenario {
+    /// The compiled circuit, with the key left as a missing constant
+    pub circuit: CompiledCircuit,
+
+    /// The ciphertext (IV included)
+    pub ct: Vec<u8>,
+
+    /// The key sent by the vendor
+    pub key: Vec<u8>,
+
+    /// Commitment to h_circuit and h_ct
+    pub commitment: Commitment,
+}
+
+impl DisputeScenario {
+    /// Returns a model of the dispute contract deployed for this scenario
+    pub fn deploy(&self) -> DisputeModel {
+        self.deploy_with_arity(1)
+    }
+
+    /// Returns a model of the dispute for this scenario using a k-ary search
+    pub fn deploy_with_arity(&self, arity: u32) -> DisputeModel {
+        DisputeModel::with_arity(
+            self.circuit.num_blocks,
+            self.circuit.circuit.len() as u32,
+            self.commitment.c.clone(),
+            arity,
+        )
+    }
+}
+
+#[cfg(test)]
+impl DisputeScenario {
+    /// Returns the scenario of a random file of `size` bytes encrypted with a random key
+    pub(crate) fn random(size: usize) -> DisputeScenario {
+        use rand::RngCore;
+
+        let mut rng = rand::rng();
+        let mut data = vec![0u8; size];
+        rng.fill_bytes(&mut data);
+        let mut key = vec![0u8; 16];
+        rng.fill_bytes(&mut key);
+
+        let precontract = crate::compute_precontract_values(&mut data, &key);
+        DisputeScenario {
+            circuit: CompiledCircuit::from_bytes(&precontract.circuit_bytes),
+            ct: precontract.ct,
+            key,
+            commitment: precontract.commitment,
+        }
+    }
+}
+
+/// A party that answers according to a fully evaluated circuit. Can act as the buyer or as the
+/// vendor.
+pub struct TraceParty {
+    buyer: BuyerAgent,
+    vendor: VendorAgent,
+    opening_value: Vec<u8>,
+    version: u32,
+}
+
+impl TraceParty {
+    /// Evaluates the scenario's circuit according to `strategy`
+    ///
+    /// # Arguments
+    /// * `scenario` - The dispute scenario
+    /// * `strategy` - How to evaluate the circuit
+    ///
+    /// # Returns
+    /// A `TraceParty` ready to take part in the dispute
+    pub fn new(scenario: &DisputeScenario, strategy: &Strategy) -> TraceParty {
+        let key = match strategy {
+            Strategy::WrongKey(key) => key.clone(),
+            _ => scenario.key.clone(),
+        };
+        let circuit = scenario.circuit.bind_missing_constants(vec![key]);
+        let version = circuit.version;
+        let ct_blocks = split_ct_blocks(&scenario.ct, circuit.block_size as usize);
+        let mut values = evaluate_circuit_internal(&ct_blocks, &circuit);
+        let constants = circuit.into_constants();
+
+        if let Strategy::LieAtGate(k) = strategy {
+            let k = *k as usize;
+            if k >= values.len() {
+                die("Cannot lie about a gate that is not in the circuit");
+            }
+            match values[k].last_mut() {
+                Some(byte) => *byte ^= 1,
+                None => values[k] = vec![1],
+            }
+
+            // the first gates are placeholders for the ciphertext blocks
+            let first_gate = (k + 1).max(ct_blocks.len());
+            for i in first_gate..values.len() {
+                let gate = &scenario.circuit.circuit[i];
+                let sons: Vec<Vec<u8>> = get_evaluated_sons(gate, &values, &constants)
+                    .into_iter()
+                    .cloned()
+                    .collect();
+                values[i] = evaluate_gate(gate, &sons, version).unwrap_or_else(|e| die(&e));
+            }
+        }
+
+        TraceParty {
+            buyer: BuyerAgent::from_values(values.clone(), ct_blocks.len() as u32),
+            vendor: VendorAgent::from_values(
+                scenario.circuit.clone(),
+                ct_blocks,
+                values,
+                constants,
+            ),
+            opening_value: scenario.commitment.o.clone(),
+            version,
+        }
+    }
+}
+
+impl BuyerStrategy for TraceParty {
+    fn respond(&mut self, dispute: &DisputeModel) -> Option<Vec<Vec<u8>>> {
+        self.buyer.respond_multi(&dispute.kary_window())
+    }
+}
+
+impl VendorStrategy for TraceParty {
+    fn opinion(&mut self, dispute: &DisputeModel) -> Option<u32> {
+        Some(
+            self.vendor
+                .opinion_multi(&dispute.kary_window(), &dispute.latest_buyer_responses()),
+        )
+    }
+
+    fn final_step(&mut self, dispute: &mut DisputeModel) -> Result<(), String> {
+        match self.vendor.final_step(&dispute.window()) {
+            Some(FinalStepInputs::Commitment {
+                kind: FinalStepKind::CommitmentLeft,
+                gate_num,
+                proofs,
+            }) => {
+                dispute.submit_commitment_left(&self.opening_value, gate_num, self.version, &proofs)
+            }
+            Some(FinalStepInputs::Commitment {
+                gate_num, proofs, ..
+            }) => dispute.submit_commitment(&self.opening_value, gate_num, self.version, &proofs),
+            Some(FinalStepInputs::CommitmentRight { proof }) => {
+                dispute.submit_commitment_right(&proof)
+            }
+            None => Err("The binary search has not converged".to_string()),
+        }
+    }
+}
+
+// ============================= SIMULATION =============================
+
+/// Parties of the dispute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Party {
+    Buyer,
+    Vendor,
+}
+
+/// Outcome of a simulated dispute
+#[derive(Debug)]
+pub struct SimulationReport {
+    /// The party that receives the payment
+    pub winner: Party,
+
+    /// Number of rounds answered by the buyer
+    pub rounds: u32,
+
+    /// Number of responses sent by the buyer, which drives the gas cost of the search
+    pub responses: u32,
+
+    /// The gate on which the dispute ended
+    pub challenge: u32,
+
+    /// The state in which the dispute ended (`Complete` or `Cancel`)
+    pub final_state: DisputeState,
+
+    /// The transcript of the dispute, which replays to the same outcome
+    pub transcript: DisputeTranscript,
+}
+
+/// Runs a dispute until one of the parties wins. A party that does not act or whose transaction
+/// reverts is considered to let the timeout expire.
+///
+/// # Arguments
+/// * `dispute` - The dispute contract, usually freshly deployed
+/// * `buyer` - The buyer's strategy
+/// * `vendor` - The vendor's strategy
+///
+/// # Returns
+/// A `SimulationReport` describing the outcome of the dispute
+pub fn simulate_dispute(
+    mut dispute: DisputeModel,
+    buyer: &mut dyn BuyerStrategy,
+    vendor: &mut dyn VendorStrategy,
+) -> SimulationReport {
+    let mut rounds = 0;
+    let mut responses = 0;
+
+    loop {
+        let acted = match dispute.state {
+            DisputeState::ChallengeBuyer => {
+                rounds += 1;
+                buyer.respond(&dispute).is_some_and(|r| {
+                    responses += r.len() as u32;
+                    dispute.respond_challenges(&r).is_ok()
+                })
+            }
+            DisputeState::WaitVendorOpinion => vendor
+                .opinion(&dispute)
+                .is_some_and(|o| dispute.give_opinion_multi(o).is_ok()),
+            DisputeState::WaitVendorData
+            | DisputeState::WaitVendorDataLeft
+            | DisputeState::WaitVendorDataRight => vendor.final_step(&mut dispute).is_ok(),
+            DisputeState::Complete | DisputeState::Cancel => break,
+        };
+
+        if !acted {
+            dispute.timeout();
+        }
+    }
+
+    SimulationReport {
+        winner: if dispute.state == DisputeState::Complete {
+            Party::Vendor
+        } else {
+            Party::Buyer
+        },
+        rounds,
+        responses,
+        challenge: dispute.chall,
+        final_state: dispute.state,
+        transcript: dispute.transcript,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn run(scenario: &DisputeScenario, buyer: &Strategy, vendor: &Strategy) -> SimulationReport {
+        let mut buyer = TraceParty::new(scenario, buyer);
+        let mut vendor = TraceParty::new(scenario, vendor);
+        simulate_dispute(scenario.deploy(), &mut buyer, &mut vendor)
+    }
+
+    // maximum number of rounds of the binary search for the scenario
+    fn max_rounds(scenario: &DisputeScenario) -> u32 {
+        max_rounds_kary(scenario, 1)
+    }
+
+    // maximum number of rounds of the k-ary search for the scenario, each round divides the size
+    // of the window by at least arity + 1
+    fn max_rounds_kary(scenario: &DisputeScenario, arity: u32) -> u32 {
+        let mut len = scenario.circuit.circuit.len() as u32 - scenario.circuit.num_blocks;
+        let mut rounds = 0;
+        while len > 0 {
+            len /= arity + 1;
+            rounds += 1;
+        }
+        rounds
+    }
+
+    #[test]
+    fn test_honest_parties() {
+        for size in [1, 64, 200, 1000] {
+            let s = DisputeScenario::random(size);
+            let report = run(&s, &Strategy::Honest, &Strategy::Honest);
+            assert_eq!(Party::Vendor, report.winner);
+            assert_eq!(s.circuit.circuit.len() as u32, report.challenge);
+            assert!(report.rounds <= max_rounds(&s));
+        }
+    }
+
+    #[test]
+    fn test_lying_buyer() {
+        let mut rng = rand::rng();
+        for _ in 0..16 {
+            let s = DisputeScenario::random(rng.random_range(1..1000));
+            let k = rng.random_range(0..s.circuit.circuit.len() as u32);
+            let report = run(&s, &Strategy::LieAtGate(k), &Strategy::Honest);
+            assert_eq!(Party::Vendor, report.winner, "lie at gate {}", k);
+            assert!(report.rounds <= max_rounds(&s));
+            if k >= s.circuit.num_blocks {
+                assert_eq!(k, report.challenge);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lying_vendor() {
+        let mut rng = rand::rng();
+        for _ in 0..16 {
+            let s = DisputeScenario::random(rng.random_range(1..1000));
+            let k = rng.random_range(s.circuit.num_blocks..s.circuit.circuit.len() as u32);
+            let report = run(&s, &Strategy::Honest, &Strategy::LieAtGate(k));
+            assert_eq!(Party::Buyer, report.winner, "lie at gate {}", k);
+        }
+    }
+
+    #[test]
+    fn test_kary_search() {
+        let mut rng = rand::rng();
+        for arity in [1, 2, 3, 7, 16] {
+            for _ in 0..8 {
+                let s = DisputeScenario::random(rng.random_range(1..2000));
+                let n = s.circuit.circuit.len() as u32;
+                let m = s.circuit.num_blocks;
+
+                let mut buyer = TraceParty::new(&s, &Strategy::Honest);
+                let mut vendor = TraceParty::new(&s, &Strategy::Honest);
+                let report = simulate_dispute(s.deploy_with_arity(arity), &mut buyer, &mut vendor);
+                assert_eq!(Party::Vendor, report.winner);
+                assert_eq!(n, report.challenge);
+                assert!(report.rounds <= max_rounds_kary(&s, arity));
+                assert!(report.responses <= report.rounds * arity);
+
+                let k = rng.random_range(m..n);
+                let mut buyer = TraceParty::new(&s, &Strategy::LieAtGate(k));
+                let mut vendor = TraceParty::new(&s, &Strategy::Honest);
+                let report = simulate_dispute(s.deploy_with_arity(arity), &mut buyer, &mut vendor);
+                assert_eq!(Party::Vendor, report.winner, "lie at gate {}", k);
+                assert_eq!(k, report.challenge);
+                assert!(report.rounds <= max_rounds_kary(&s, arity));
+
+                let mut buyer = TraceParty::new(&s, &Strategy::Honest);
+                let mut vendor = TraceParty::new(&s, &Strategy::LieAtGate(k));
+                let report = simulate_dispute(s.deploy_with_arity(arity), &mut buyer, &mut vendor);
+                assert_eq!(Party::Buyer, report.winner, "lie at gate {}", k);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let mut s = DisputeScenario::random(300);
+        let wrong_key = vec![0u8; 16];
+
+        // buyer evaluating with another key than the one sent
+        let report = run(
+            &s,
+            &Strategy::WrongKey(wrong_key.clone()),
+            &Strategy::Honest,
+        );
+        assert_eq!(Party::Vendor, report.winner);
+
+        // vendor sending a key that does not decrypt the file
+        s.key = wrong_key;
+        let report = run(&s, &Strategy::Honest, &Strategy::Honest);
+        assert_eq!(Party::Buyer, report.winner);
+        assert_eq!(DisputeState::Cancel, report.final_state);
+    }
+
+    #[test]
+    fn test_reverts() {
+        let s = DisputeScenario::random(100);
+        let mut dispute = s.deploy();
+        assert!(dispute.give_opinion(true).is_err());
+        assert!(dispute.respond_challenge(&[0u8; 31]).is_err());
+        assert!(dispute.respond_challenge(&[0u8; 32]).is_ok());
+        assert!(dispute.respond_challenge(&[0u8; 32]).is_err());
+        assert!(dispute.submit_commitment_right(&[]).is_err());
+        assert_eq!(DisputeState::WaitVendorOpinion, dispute.state);
+
+        dispute.timeout();
+        assert_eq!(DisputeState::Cancel, dispute.state);
+    }
+}
@@ -0,0 +1,830 @@
+use crate::accumulator::leaf_hash;
+use crate::circuits::{
+    constant_idx_to_array_idx, evaluate_gate, get_evaluated_sons, is_constant_idx, opcode_name,
+    CompiledCircuit, Gate,
+};
+use crate::secret::secrets_from_hex;
+use crate::utils::{bytes_to_hex, die, split_ct_blocks};
+use crate::EvaluatedCircuit;
+use lru::LruCache;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::num::NonZeroUsize;
+use wasm_bindgen::prelude::wasm_bindgen;
+use zeroize::Zeroize;
+
+// ============================= TRACES =============================
+
+/// Read access to the values of an evaluated circuit (its trace). Some representations only hold
+/// part of the values, in which case the missing ones are reported as unavailable.
+pub trait TraceSource {
+    /// Number of values of the full trace (one per gate)
+    fn num_values(&self) -> u32;
+
+    /// Value of gate `i`, or `None` if it is not available in this representation
+    fn value(&self, i: u32) -> Option<Cow<'_, [u8]>>;
+
+    /// The constants the circuit was evaluated with
+    fn constants(&self) -> &[Vec<u8>];
+
+    /// Leaf hashes (see `leaf_hash`) of the values of gates `start..=end`, or `None` if one of
+    /// them is not available
+    fn leaf_hashes(&self, start: u32, end: u32) -> Option<Vec<Vec<u8>>> {
+        hash_values(self, start, end)
+    }
+
+    /// Values of the sons of `gate` (constants included), or `None` if one of them is not
+    /// available
+    fn sons(&self, gate: &Gate) -> Option<Vec<Vec<u8>>> {
+        let mut sons = Vec::with_capacity(gate.sons.len());
+        for &s in &gate.sons {
+            if !is_constant_idx(s) {
+                sons.push(self.value(s)?.into_owned());
+            } else if !self.constants().is_empty() {
+                sons.push(self.constants().get(constant_idx_to_array_idx(s))?.clone());
+            }
+        }
+        Some(sons)
+    }
+}
+
+// leaf hashes of the values of gates `start..=end`, one gate at a time
+fn hash_values<T: TraceSource + ?Sized>(trace: &T, start: u32, end: u32) -> Option<Vec<Vec<u8>>> {
+    (start..=end)
+        .map(|i| trace.value(i).map(|v| leaf_hash(&v)))
+        .collect()
+}
+
+impl TraceSource for EvaluatedCircuit {
+    fn num_values(&self) -> u32 {
+        self.values.len() as u32
+    }
+
+    fn value(&self, i: u32) -> Option<Cow<'_, [u8]>> {
+        self.values
+            .get(i as usize)
+            .map(|v| Cow::Borrowed(v.as_slice()))
+    }
+
+    fn constants(&self) -> &[Vec<u8>] {
+        &self.constants
+    }
+
+    fn sons(&self, gate: &Gate) -> Option<Vec<Vec<u8>>> {
+        Some(
+            get_evaluated_sons(gate, &self.values, &self.constants)
+                .into_iter()
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+/// A contiguous window of a trace, e.g. a chunk received while the trace is being streamed
+pub struct PartialTrace {
+    /// Index of the first value of the window
+    pub offset: u32,
+
+    /// Values of gates `offset..offset + values.len()`
+    pub values: Vec<Vec<u8>>,
+
+    /// The constants the circuit was evaluated with
+    pub constants: Vec<Vec<u8>>,
+
+    /// Number of values of the full trace
+    pub num_values: u32,
+}
+
+impl TraceSource for PartialTrace {
+    fn num_values(&self) -> u32 {
+        self.num_values
+    }
+
+    fn value(&self, i: u32) -> Option<Cow<'_, [u8]>> {
+        let idx = i.checked_sub(self.offset)?;
+        self.values
+            .get(idx as usize)
+            .map(|v| Cow::Borrowed(v.as_slice()))
+    }
+
+    fn constants(&self) -> &[Vec<u8>] {
+        &self.constants
+    }
+}
+
+// ============================= LAZY TRACE =============================
+
+/// A trace that is never stored: the value of a gate is computed on demand by evaluating its
+/// dependency cone from the ciphertext and the constants. The most recently computed values are
+/// kept in a bounded LRU cache so that neighbouring queries don't recompute the same cones.
+///
+/// This is what a vendor keeps while waiting for a dispute: the circuit, the ciphertext and the
+/// key are enough to answer any challenge.
+#[wasm_bindgen]
+pub struct LazyTrace {
+    circuit: CompiledCircuit,
+    constants: Vec<Vec<u8>>,
+    ct_blocks: Vec<Vec<u8>>,
+    cache: RefCell<LruCache<u32, Vec<u8>>>,
+}
+
+impl LazyTrace {
+    /// Creates a lazy trace
+    ///
+    /// # Arguments
+    /// * `circuit` - The compiled circuit
+    /// * `ct_blocks` - Blocks of the ciphertext (one per dummy gate)
+    /// * `constants` - Values of the constants that are not bound in the circuit (e.g. the key)
+    /// * `capacity` - Maximum number of values kept in the cache (at least 1)
+    ///
+    /// # Returns
+    /// A new `LazyTrace`
+    pub fn from_circuit(
+        circuit: CompiledCircuit,
+        ct_blocks: Vec<Vec<u8>>,
+        constants: Vec<Vec<u8>>,
+        capacity: usize,
+    ) -> LazyTrace {
+        let num_dummies = circuit.circuit.iter().take_while(|g| g.is_dummy()).count();
+        if ct_blocks.len() != num_dummies {
+            die(&format!(
+                "The ciphertext has {} blocks, the circuit expects {}",
+                ct_blocks.len(),
+                num_dummies
+            ));
+        }
+        let constants = circuit.bind_missing_constants(constants).into_constants();
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+
+        LazyTrace {
+            circuit,
+            constants,
+            ct_blocks,
+            cache: RefCell::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Number of ciphertext blocks (i.e. of dummy gates)
+    fn num_blocks(&self) -> u32 {
+        self.ct_blocks.len() as u32
+    }
+
+    // values of the sons of `gate`, as returned by `get_evaluated_sons`
+    fn son_values(&self, gate: &Gate, known: &HashMap<u32, Vec<u8>>) -> Vec<Vec<u8>> {
+        let mut sons = Vec::with_capacity(gate.sons.len());
+        for &s in &gate.sons {
+            if is_constant_idx(s) {
+                if !self.constants.is_empty() {
+                    sons.push(self.constants[constant_idx_to_array_idx(s)].clone());
+                }
+            } else if s < self.num_blocks() {
+                sons.push(self.ct_blocks[s as usize].clone());
+            } else {
+                sons.push(known[&s].clone());
+            }
+        }
+        sons
+    }
+
+    fn evaluate(&self, i: u32, known: &HashMap<u32, Vec<u8>>) -> Vec<u8> {
+        let gate = &self.circuit.circuit[i as usize];
+        let sons = self.son_values(gate, known);
+        evaluate_gate(gate, &sons, self.circuit.version).unwrap_or_else(|e| die(&e))
+    }
+
+    /// Computes the value of gate `i`, evaluating the part of its dependency cone that is not in
+    /// the cache
+    ///
+    /// # Arguments
+    /// * `i` - Index of the gate
+    ///
+    /// # Returns
+    /// The value of the gate
+    pub fn compute_value(&self, i: u32) -> Vec<u8> {
+        if i as usize >= self.circuit.circuit.len() {
+            die(&format!("Gate {} is not in the circuit", i));
+        }
+        if i < self.num_blocks() {
+            return self.ct_blocks[i as usize].clone();
+        }
+        if let Some(v) = self.cache.borrow_mut().get(&i) {
+            return v.clone();
+        }
+
+        // walk the cone down to the ciphertext blocks, stopping at the cached values
+        let mut known = HashMap::new();
+        let mut needed = vec![];
+        let mut seen = HashSet::new();
+        let mut stack = vec![i];
+        while let Some(g) = stack.pop() {
+            if g < self.num_blocks() || !seen.insert(g) {
+                continue;
+            }
+            if let Some(v) = self.cache.borrow_mut().get(&g) {
+                known.insert(g, v.clone());
+                continue;
+            }
+            needed.push(g);
+            stack.extend(
+                self.circuit.circuit[g as usize]
+                    .sons
+                    .iter()
+                    .filter(|&&s| !is_constant_idx(s)),
+            );
+        }
+
+        // sons always come before their parent
+        needed.sort_unstable();
+        let mut cache = self.cache.borrow_mut();
+        for g in needed {
+            let v = self.evaluate(g, &known);
+            cache.put(g, v.clone());
+            known.insert(g, v);
+        }
+
+        known.remove(&i).unwrap()
+    }
+
+    /// Computes the leaf hashes of the gates' values from the first gate after the ciphertext
+    /// blocks up to `end` (included). The gates are evaluated in order and a value is dropped as
+    /// soon as the last gate using it is evaluated, so that the trace is never held in memory.
+    ///
+    /// # Arguments
+    /// * `end` - Index of the last gate
+    ///
+    /// # Returns
+    /// The leaf hashes of the values of gates `num_blocks..=end`
+    pub fn prefix_leaf_hashes(&self, end: u32) -> Vec<Vec<u8>> {
+        let m = self.num_blocks();
+        if end < m || end as usize >= self.circuit.circuit.len() {
+            die(&format!("Gate {} is not a challengeable gate", end));
+        }
+
+        let mut last_use = vec![0u32; end as usize + 1];
+        for g in m..=end {
+            for &s in &self.circuit.circuit[g as usize].sons {
+                if !is_constant_idx(s) {
+                    last_use[s as usize] = g;
+                }
+            }
+        }
+
+        let mut live = HashMap::new();
+        let mut hashes = Vec::with_capacity((end - m + 1) as usize);
+        for g in m..=end {
+            let v = self.evaluate(g, &live);
+            hashes.push(leaf_hash(&v));
+            for &s in &self.circuit.circuit[g as usize].sons {
+                if !is_constant_idx(s) && last_use[s as usize] == g {
+                    live.remove(&s);
+                }
+            }
+            if last_use[g as usize] > g {
+                live.insert(g, v);
+            }
+        }
+
+        hashes
+    }
+}
+
+#[wasm_bindgen]
+impl LazyTrace {
+    /// Creates a lazy trace
+    ///
+    /// # Arguments
+    /// * `circuit_bytes` - Serialized circuit
+    /// * `ct` - Ciphertext bytes
+    /// * `constants` - Hex-encoded values of the constants that are not bound in the circuit
+    /// * `capacity` - Maximum number of values kept in the cache (at least 1)
+    ///
+    /// # Returns
+    /// A new `LazyTrace`
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        circuit_bytes: &[u8],
+        ct: &[u8],
+        constants: Vec<String>,
+        capacity: usize,
+    ) -> LazyTrace {
+        let circuit = CompiledCircuit::from_bytes(circuit_bytes);
+        let ct_blocks = split_ct_blocks(ct, circuit.block_size as usize);
+        LazyTrace::from_circuit(circuit, ct_blocks, secrets_from_hex(constants), capacity)
+    }
+
+    /// Computes the value of gate `i`
+    ///
+    /// # Arguments
+    /// * `i` - Index of the gate
+    ///
+    /// # Returns
+    /// The value of the gate
+    pub fn gate_value(&self, i: u32) -> Vec<u8> {
+        self.compute_value(i)
+    }
+
+    /// Number of values currently in the cache
+    pub fn cached(&self) -> usize {
+        self.cache.borrow().len()
+    }
+}
+
+// the constants include the key
+impl Drop for LazyTrace {
+    fn drop(&mut self) {
+        self.constants.zeroize();
+    }
+}
+
+impl TraceSource for LazyTrace {
+    fn num_values(&self) -> u32 {
+        self.circuit.circuit.len() as u32
+    }
+
+    fn value(&self, i: u32) -> Option<Cow<'_, [u8]>> {
+        if i >= self.num_values() {
+            return None;
+        }
+        Some(Cow::Owned(self.compute_value(i)))
+    }
+
+    fn constants(&self) -> &[Vec<u8>] {
+        &self.constants
+    }
+
+    // the values are evaluated in a single pass instead of one cone per gate
+    fn leaf_hashes(&self, start: u32, end: u32) -> Option<Vec<Vec<u8>>> {
+        if start != self.num_blocks() || end >= self.num_values() {
+            return hash_values(self, start, end);
+        }
+        Some(self.prefix_leaf_hashes(end))
+    }
+}
+
+// ============================= EITHER TRACE =============================
+
+enum TraceKind {
+    Evaluated(EvaluatedCircuit),
+    Lazy(LazyTrace),
+}
+
+/// The trace used to answer the challenges and compute the final step proofs: either a stored
+/// evaluated circuit or a lazy trace
+#[wasm_bindgen]
+pub struct Trace(TraceKind);
+
+#[wasm_bindgen]
+impl Trace {
+    /// Uses a stored evaluated circuit
+    ///
+    /// # Arguments
+    /// * `evaluated_circuit_bytes` - Serialized evaluated circuit bytes
+    pub fn from_evaluated_circuit(evaluated_circuit_bytes: &[u8]) -> Trace {
+        Trace(TraceKind::Evaluated(EvaluatedCircuit::from_bytes(
+            evaluated_circuit_bytes,
+        )))
+    }
+
+    /// Uses a lazy trace, which computes the values on demand
+    ///
+    /// # Arguments
+    /// * `trace` - The lazy trace
+    pub fn from_lazy(trace: LazyTrace) -> Trace {
+        Trace(TraceKind::Lazy(trace))
+    }
+}
+
+impl Trace {
+    fn source(&self) -> &dyn TraceSource {
+        match &self.0 {
+            TraceKind::Evaluated(evaluated) => evaluated,
+            TraceKind::Lazy(lazy) => lazy,
+        }
+    }
+}
+
+impl TraceSource for Trace {
+    fn num_values(&self) -> u32 {
+        self.source().num_values()
+    }
+
+    fn value(&self, i: u32) -> Option<Cow<'_, [u8]>> {
+        self.source().value(i)
+    }
+
+    fn constants(&self) -> &[Vec<u8>] {
+        self.source().constants()
+    }
+
+    fn leaf_hashes(&self, start: u32, end: u32) -> Option<Vec<Vec<u8>>> {
+        self.source().leaf_hashes(start, end)
+    }
+
+    fn sons(&self, gate: &Gate) -> Option<Vec<Vec<u8>>> {
+        self.source().sons(gate)
+    }
+}
+
+// ============================= DIVERGENCE =============================
+
+/// Finds the first gate whose value differs between two traces. Values that are not available in
+/// one of the traces are skipped, and a value that only exists in the longest trace is a
+/// divergence.
+///
+/// # Arguments
+/// * `a` - The first trace
+/// * `b` - The second trace
+///
+/// # Returns
+/// The index of the first diverging gate, or `None` if the traces agree on every value available
+/// in both
+pub fn first_divergence(a: &dyn TraceSource, b: &dyn TraceSource) -> Option<u32> {
+    let common = a.num_values().min(b.num_values());
+    for i in 0..common {
+        if let (Some(x), Some(y)) = (a.value(i), b.value(i)) {
+            if x != y {
+                return Some(i);
+            }
+        }
+    }
+
+    if a.num_values() != b.num_values() {
+        Some(common)
+    } else {
+        None
+    }
+}
+
+/// Everything needed to understand the value of a gate in a trace
+pub struct GateExplanation {
+    /// Index of the gate
+    pub index: u32,
+
+    /// The gate
+    pub gate: Gate,
+
+    /// Label of the gate, if the circuit has debug metadata
+    pub label: Option<String>,
+
+    /// Version of the instruction set
+    pub version: u32,
+
+    /// Values of the gate's sons, `None` if they are not all available
+    pub sons: Option<Vec<Vec<u8>>>,
+
+    /// Value of the gate in the trace, `None` if it is not available
+    pub value: Option<Vec<u8>>,
+
+    /// Value of the gate recomputed from its sons with `evaluate_gate` (the same function as
+    /// the smart contract), `None` for the ciphertext blocks or if the sons are not available
+    pub recomputed: Option<Result<Vec<u8>, String>>,
+}
+
+impl GateExplanation {
+    /// Returns `false` if the gate's value in the trace differs from the recomputed value (or if
+    /// it cannot be recomputed), `true` otherwise
+    pub fn is_consistent(&self) -> bool {
+        match (&self.value, &self.recomputed) {
+            (Some(v), Some(Ok(r))) => v == r,
+            (_, Some(Err(_))) => false,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for GateExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = |v: &[u8]| bytes_to_hex(v.to_vec());
+        let opt_hex = |v: &Option<Vec<u8>>| match v {
+            Some(v) => hex(v),
+            None => "unavailable".to_string(),
+        };
+
+        if self.gate.is_dummy() {
+            writeln!(f, "gate {}: ciphertext block", self.index)?;
+        } else {
+            let name = opcode_name(self.version, self.gate.opcode).unwrap_or("unknown");
+            writeln!(
+                f,
+                "gate {}: {} (opcode {}, version {})",
+                self.index, name, self.gate.opcode, self.version
+            )?;
+            writeln!(f, "  encoded gate: {:?}", self.gate.flatten())?;
+        }
+        if let Some(label) = &self.label {
+            writeln!(f, "  label: {}", label)?;
+        }
+
+        match &self.sons {
+            Some(values) => {
+                // the constants are omitted from the values if the trace has none
+                for (j, value) in values.iter().enumerate() {
+                    let son = self.gate.sons.get(j).copied().unwrap_or_default();
+                    if is_constant_idx(son) {
+                        let c = constant_idx_to_array_idx(son);
+                        writeln!(f, "  son {} (constant {}): {}", j, c, hex(value))?;
+                    } else {
+                        writeln!(f, "  son {} (gate {}): {}", j, son, hex(value))?;
+                    }
+                }
+            }
+            None => writeln!(f, "  sons: unavailable")?,
+        }
+
+        writeln!(f, "  value:      {}", opt_hex(&self.value))?;
+        match &self.recomputed {
+            Some(Ok(r)) => write!(f, "  recomputed: {}", hex(r))?,
+            Some(Err(e)) => write!(f, "  recomputed: error ({})", e)?,
+            None => write!(f, "  recomputed: n/a")?,
+        }
+        if !self.is_consistent() {
+            write!(f, "\n  MISMATCH")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Explains the value of gate `i` of a trace by recomputing it from its sons
+///
+/// # Arguments
+/// * `circuit` - The compiled circuit
+/// * `trace` - A trace of the circuit
+/// * `i` - Index of the gate
+///
+/// # Returns
+/// A `GateExplanation`, or an error message if the gate is not in the circuit
+pub fn explain_gate(
+    circuit: &CompiledCircuit,
+    trace: &dyn TraceSource,
+    i: u32,
+) -> Result<GateExplanation, String> {
+    let gate = match circuit.circuit.get(i as usize) {
+        Some(gate) => gate.clone(),
+        None => return Err(format!("Gate {} is not in the circuit", i)),
+    };
+
+    let sons = if gate.is_dummy() {
+        Some(vec![])
+    } else {
+        trace.sons(&gate)
+    };
+    let recomputed = match &sons {
+        Some(sons) if !gate.is_dummy() => Some(evaluate_gate(&gate, sons, circuit.version)),
+        _ => None,
+    };
+
+    Ok(GateExplanation {
+        index: i,
+        label: circuit.gate_label(i),
+        version: circuit.version,
+        value: trace.value(i).map(Cow::into_owned),
+        gate,
+        sons,
+        recomputed,
+    })
+}
+
+/// Finds the first gate whose value differs between two evaluated circuits
+///
+/// # Arguments
+/// * `a` - First serialized evaluated circuit
+/// * `b` - Second serialized evaluated circuit
+///
+/// # Returns
+/// The index of the first diverging gate, or `None` if the evaluated circuits are identical
+#[wasm_bindgen]
+pub fn first_divergence_js(a: &[u8], b: &[u8]) -> Option<u32> {
+    first_divergence(
+        &EvaluatedCircuit::from_bytes(a),
+        &EvaluatedCircuit::from_bytes(b),
+    )
+}
+
+/// Explains the value of a gate of an evaluated circuit
+///
+/// # Arguments
+/// * `circuit_bytes` - Serialized circuit
+/// * `evaluated_circuit_bytes` - Serialized evaluated circuit
+/// * `i` - Index of the gate
+///
+/// # Returns
+/// A human readable explanation of the gate's value
+#[wasm_bindgen]
+pub fn explain_gate_js(circuit_bytes: &[u8], evaluated_circuit_bytes: &[u8], i: u32) -> String {
+    let circuit = CompiledCircuit::from_bytes(circuit_bytes);
+    let evaluated_circuit = EvaluatedCircuit::from_bytes(evaluated_circuit_bytes);
+    match explain_gate(&circuit, &evaluated_circuit, i) {
+        Ok(explanation) => explanation.to_string(),
+        Err(e) => die(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::{compile_basic_circuit, evaluate_circuit_internal};
+    use crate::dispute::{
+        compute_proof_right_internal, compute_proof_right_trace, compute_proofs_internal,
+        compute_proofs_left_internal, compute_proofs_left_trace, compute_proofs_trace,
+        hpre_internal, hpre_multi_internal, hpre_multi_trace, FinalStepProofs,
+    };
+    use rand::RngCore;
+
+    fn evaluate(circuit: &CompiledCircuit, ct: &[u8], key: &[u8]) -> EvaluatedCircuit {
+        let circuit = circuit.bind_missing_constants(vec![key.to_vec()]);
+        let ct_blocks = split_ct_blocks(ct, circuit.block_size as usize);
+        let values = evaluate_circuit_internal(&ct_blocks, &circuit);
+        EvaluatedCircuit {
+            values,
+            constants: circuit.into_constants(),
+        }
+    }
+
+    fn setup() -> (CompiledCircuit, EvaluatedCircuit) {
+        let mut rng = rand::rng();
+        let mut ct = vec![0u8; 300];
+        rng.fill_bytes(&mut ct);
+        let circuit = compile_basic_circuit(ct.len() as u32, &[0u8; 32]);
+        let trace = evaluate(&circuit, &ct, &[1u8; 16]);
+        (circuit, trace)
+    }
+
+    #[test]
+    fn test_first_divergence() {
+        let (circuit, trace) = setup();
+        let n = trace.num_values();
+        let m = circuit.num_blocks;
+        assert_eq!(None, first_divergence(&trace, &trace));
+
+        for k in [0, m, m + 5, n - 1] {
+            let mut lie = EvaluatedCircuit {
+                values: trace.values.clone(),
+                constants: trace.constants.clone(),
+            };
+            lie.values[k as usize][0] ^= 1;
+            assert_eq!(Some(k), first_divergence(&trace, &lie));
+            assert_eq!(Some(k), first_divergence(&lie, &trace));
+        }
+
+        // a shorter trace diverges where it ends
+        let short = EvaluatedCircuit {
+            values: trace.values[..m as usize].to_vec(),
+            constants: trace.constants.clone(),
+        };
+        assert_eq!(Some(m), first_divergence(&trace, &short));
+
+        // another key diverges on the first gate using it
+        let ct: Vec<u8> = trace.values[..m as usize].concat();
+        let other = evaluate(&circuit, &ct, &[2u8; 16]);
+        let i = first_divergence(&trace, &other).unwrap();
+        assert!(i >= m);
+        let gate = &circuit.circuit[i as usize];
+        assert!(gate.sons.iter().any(|&s| is_constant_idx(s)));
+    }
+
+    #[test]
+    fn test_partial_traces() {
+        let (_, trace) = setup();
+        let n = trace.num_values();
+        let mut lie = trace.values.clone();
+        lie[10][0] ^= 1;
+
+        let window = |offset: u32, len: u32| PartialTrace {
+            offset,
+            values: lie[offset as usize..(offset + len) as usize].to_vec(),
+            constants: trace.constants.clone(),
+            num_values: n,
+        };
+        assert!(n > 20);
+        assert_eq!(Some(10), first_divergence(&trace, &window(5, 8)));
+        assert_eq!(None, first_divergence(&trace, &window(11, 8)));
+        assert_eq!(None, first_divergence(&window(0, 8), &window(11, 8)));
+    }
+
+    #[test]
+    fn test_explain_gate() {
+        let (circuit, trace) = setup();
+        let m = circuit.num_blocks;
+        let n = trace.num_values();
+
+        let block = explain_gate(&circuit, &trace, 0).unwrap();
+        assert!(block.recomputed.is_none());
+        assert!(block.is_consistent());
+        assert!(block.to_string().contains("ciphertext block"));
+
+        for i in m..n {
+            let explanation = explain_gate(&circuit, &trace, i).unwrap();
+            assert!(explanation.is_consistent(), "{}", explanation);
+        }
+        assert!(explain_gate(&circuit, &trace, n).is_err());
+
+        let k = m + 3;
+        let mut lie = EvaluatedCircuit {
+            values: trace.values.clone(),
+            constants: trace.constants.clone(),
+        };
+        lie.values[k as usize][0] ^= 1;
+        let explanation = explain_gate(&circuit, &lie, k).unwrap();
+        assert!(!explanation.is_consistent());
+        assert!(explanation.to_string().contains("MISMATCH"));
+
+        // sons outside of a partial trace cannot be recomputed
+        let partial = PartialTrace {
+            offset: k,
+            values: vec![lie.values[k as usize].clone()],
+            constants: lie.constants.clone(),
+            num_values: n,
+        };
+        let explanation = explain_gate(&circuit, &partial, k).unwrap();
+        assert!(circuit.circuit[k as usize]
+            .sons
+            .iter()
+            .any(|&s| !is_constant_idx(s) && s != k));
+        assert!(explanation.sons.is_none());
+        assert!(explanation.recomputed.is_none());
+        assert_eq!(Some(lie.values[k as usize].clone()), explanation.value);
+    }
+
+    fn lazy(circuit: &CompiledCircuit, trace: &EvaluatedCircuit, capacity: usize) -> LazyTrace {
+        let m = circuit.num_blocks as usize;
+        LazyTrace::from_circuit(
+            circuit.clone(),
+            trace.values[..m].to_vec(),
+            vec![vec![1u8; 16]],
+            capacity,
+        )
+    }
+
+    #[test]
+    fn test_lazy_trace_values() {
+        let (circuit, trace) = setup();
+        let n = trace.num_values();
+
+        for capacity in [1, 4, n as usize] {
+            let lazy = lazy(&circuit, &trace, capacity);
+            assert_eq!(trace.constants, lazy.constants);
+            // backwards first, so that whole cones are evaluated
+            for i in (0..n).rev().chain(0..n) {
+                assert_eq!(trace.values[i as usize], lazy.compute_value(i));
+                assert!(lazy.cached() <= capacity);
+            }
+            assert_eq!(None, first_divergence(&trace, &lazy));
+            assert!(lazy.value(n).is_none());
+        }
+    }
+
+    #[test]
+    fn test_lazy_trace_proofs() {
+        let (circuit, trace) = setup();
+        let m = circuit.num_blocks;
+        let n = trace.num_values();
+        let ct_blocks = &trace.values[..m as usize];
+        let json = |p: &FinalStepProofs| serde_json::to_string(p).unwrap();
+        let stored = Trace(TraceKind::Evaluated(EvaluatedCircuit {
+            values: trace.values.clone(),
+            constants: trace.constants.clone(),
+        }));
+        let lazy = Trace::from_lazy(lazy(&circuit, &trace, 2));
+
+        let challenges: Vec<u32> = (m..n).step_by(3).collect();
+        for t in [&stored, &lazy] {
+            assert_eq!(
+                hpre_multi_internal(&trace.values, m as usize, &challenges),
+                hpre_multi_trace(t, m, &challenges)
+            );
+
+            for c in m + 1..n {
+                assert_eq!(
+                    hpre_internal(&trace.values, m as usize, c as usize),
+                    hpre_multi_trace(t, m, &[c])[0]
+                );
+                let expected = compute_proofs_internal(
+                    &circuit,
+                    &trace.values,
+                    &trace.constants,
+                    ct_blocks,
+                    c,
+                );
+                let proofs = compute_proofs_trace(&circuit, t, ct_blocks, c);
+                assert_eq!(json(&expected), json(&proofs));
+            }
+
+            let expected = compute_proofs_left_internal(
+                &circuit,
+                &trace.values,
+                &trace.constants,
+                ct_blocks,
+                m,
+            );
+            let proofs = compute_proofs_left_trace(&circuit, t, ct_blocks, m);
+            assert_eq!(json(&expected), json(&proofs));
+            assert_eq!(
+                compute_proof_right_internal(&trace.values, m, n),
+                compute_proof_right_trace(t, m, n)
+            );
+        }
+    }
+}
@@ -0,0 +1,684 @@
+use crate::accumulator::leaf_hash;
+use crate::canonical::{transcript_entry_bytes, transcript_header_bytes, Canonical};
+use crate::dispute::{FinalStepProofs, FinalStepSubmission};
+use crate::eip712::{is_signed_by, sign_digest, transcript_digest};
+use crate::simulator::{DisputeModel, DisputeState};
+use crate::utils::die;
+use crate::{js_array_to_proof, FinalStepComponents};
+use js_sys::Array;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/*
+ * A transcript records every action that changed the state of a dispute, in order. Each record
+ * stores the hash of the transcript up to and including its entry:
+ *
+ *      h_0 = keccak(header)
+ *      h_i = keccak(h_{i-1} || entry_i)
+ *
+ * where the header and the entries are in their canonical encoding (see `canonical.rs`). The last
+ * hash (the head) thus commits to the whole dispute: altering, removing or reordering any entry
+ * changes it. Anyone can rebuild a chain though, so the buyer and the vendor each sign the head
+ * (EIP-712, see `eip712.rs`) and a transcript is only accepted with both signatures.
+ */
+
+/// Parameters of the dispute fixed at deployment
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TranscriptHeader {
+    /// Number of blocks of the ciphertext
+    pub num_blocks: u32,
+
+    /// Number of gates in the circuit
+    pub num_gates: u32,
+
+    /// The commitment value
+    pub commitment: Vec<u8>,
+
+    /// Maximum number of challenges per round (1 for the smart contract)
+    pub arity: u32,
+}
+
+/// An action that changed the state of the dispute
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum TranscriptEntry {
+    /// The buyer answered the challenges of a round (`respondChallenge`)
+    Responses {
+        challenges: Vec<u32>,
+        responses: Vec<Vec<u8>>,
+    },
+
+    /// The vendor gave its opinion on the latest responses (`giveOpinion`). `disagreement` is the
+    /// index of the first response the vendor disagrees with, or the number of responses if it
+    /// agrees with all of them.
+    Opinion { disagreement: u32 },
+
+    /// The vendor sent the data of step 8a or 8b (`submitCommitment` or `submitCommitmentLeft`)
+    FinalStep {
+        gate_num: u32,
+        opening_value: Vec<u8>,
+        version: u32,
+        proofs: FinalStepProofs,
+    },
+
+    /// The vendor sent the data of step 8c (`submitCommitmentRight`)
+    FinalStepRight { proof: Vec<Vec<Vec<u8>>> },
+
+    /// The party expected to act let the timeout expire
+    Timeout,
+}
+
+impl From<&FinalStepSubmission<'_>> for TranscriptEntry {
+    fn from(submission: &FinalStepSubmission) -> TranscriptEntry {
+        match submission {
+            FinalStepSubmission::Commitment {
+                gate_num,
+                opening_value,
+                version,
+                proofs,
+            } => TranscriptEntry::FinalStep {
+                gate_num: *gate_num,
+                opening_value: opening_value.to_vec(),
+                version: *version,
+                proofs: (*proofs).clone(),
+            },
+            FinalStepSubmission::Right { proof } => TranscriptEntry::FinalStepRight {
+                proof: proof.to_vec(),
+            },
+        }
+    }
+}
+
+/// An entry and the hash of the transcript up to and including it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TranscriptRecord {
+    pub entry: TranscriptEntry,
+    pub hash: Vec<u8>,
+}
+
+/// Hash-chained record of a dispute, which can be audited and replayed without querying the chain
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DisputeTranscript {
+    pub(crate) header: TranscriptHeader,
+    pub(crate) records: Vec<TranscriptRecord>,
+
+    /// Buyer's signature of the head (65 bytes)
+    #[serde(default)]
+    pub(crate) buyer_signature: Option<Vec<u8>>,
+
+    /// Vendor's signature of the head (65 bytes)
+    #[serde(default)]
+    pub(crate) vendor_signature: Option<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl DisputeTranscript {
+    /// Starts the transcript of a freshly deployed dispute
+    ///
+    /// # Arguments
+    /// * `num_blocks` - Number of blocks of the ciphertext
+    /// * `num_gates` - Number of gates in the circuit
+    /// * `commitment` - The commitment value
+    /// * `arity` - Maximum number of challenges per round (1 for the smart contract)
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        num_blocks: u32,
+        num_gates: u32,
+        commitment: Vec<u8>,
+        arity: u32,
+    ) -> DisputeTranscript {
+        DisputeTranscript::from_header(TranscriptHeader {
+            num_blocks,
+            num_gates,
+            commitment,
+            arity,
+        })
+    }
+
+    /// Returns the hash committing to the whole transcript
+    pub fn head(&self) -> Vec<u8> {
+        match self.records.last() {
+            Some(record) => record.hash.clone(),
+            None => leaf_hash(&transcript_header_bytes(&self.header)),
+        }
+    }
+
+    /// Returns the number of recorded actions
+    pub fn len(&self) -> u32 {
+        self.records.len() as u32
+    }
+
+    /// Returns whether no action has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Records the buyer's response to the current challenge (`respondChallenge`)
+    pub fn record_response(&mut self, challenge: u32, response: &[u8]) {
+        self.record_responses(&[challenge], &[response.to_vec()]);
+    }
+
+    /// Records the vendor's opinion on the buyer's latest response (`giveOpinion`)
+    pub fn record_opinion(&mut self, vendor_agrees: bool) {
+        let disagreement = if vendor_agrees {
+            self.latest_round_size()
+        } else {
+            0
+        };
+        self.record_opinion_multi(disagreement);
+    }
+
+    /// Records the data of step 8a or 8b sent by the vendor
+    ///
+    /// # Arguments
+    /// * `gate_num` - The challenged gate
+    /// * `opening_value` - The opening value of the commitment
+    /// * `version` - The instruction set version
+    /// * `components` - The components returned by `VendorAgent.final_step_components`
+    pub fn record_final_step_js(
+        &mut self,
+        gate_num: u32,
+        opening_value: &[u8],
+        version: u32,
+        components: &FinalStepComponents,
+    ) {
+        self.record_final_step(gate_num, opening_value, version, &components.into());
+    }
+
+    /// Records the proof of step 8c sent by the vendor
+    pub fn record_final_step_right_js(&mut self, proof: Array) {
+        self.record_final_step_right(&js_array_to_proof(&proof));
+    }
+
+    /// Records that the party expected to act let the timeout expire
+    pub fn record_timeout(&mut self) {
+        self.append(TranscriptEntry::Timeout);
+    }
+
+    /// Computes the EIP-712 digest of the head, to be signed by the buyer and the vendor
+    ///
+    /// # Arguments
+    /// * `chain_id` - Identifier of the chain of the dispute
+    ///
+    /// # Returns
+    /// The digest (32 bytes)
+    pub fn digest(&self, chain_id: u64) -> Vec<u8> {
+        transcript_digest(&self.header.commitment, &self.head(), chain_id)
+            .unwrap_or_else(|e| die(&e))
+    }
+
+    /// Signs the head as the buyer. Recording another action discards the signature.
+    ///
+    /// # Arguments
+    /// * `secret_key` - The buyer's secret key (32 bytes)
+    /// * `chain_id` - Identifier of the chain of the dispute
+    pub fn sign_as_buyer(&mut self, secret_key: &[u8], chain_id: u64) {
+        self.buyer_signature = Some(sign_digest(secret_key, &self.digest(chain_id)));
+    }
+
+    /// Signs the head as the vendor. Recording another action discards the signature.
+    ///
+    /// # Arguments
+    /// * `secret_key` - The vendor's secret key (32 bytes)
+    /// * `chain_id` - Identifier of the chain of the dispute
+    pub fn sign_as_vendor(&mut self, secret_key: &[u8], chain_id: u64) {
+        self.vendor_signature = Some(sign_digest(secret_key, &self.digest(chain_id)));
+    }
+
+    /// Sets the buyer's signature of `digest`, e.g. made by a wallet
+    pub fn set_buyer_signature(&mut self, signature: Vec<u8>) {
+        self.buyer_signature = Some(signature);
+    }
+
+    /// Sets the vendor's signature of `digest`, e.g. made by a wallet
+    pub fn set_vendor_signature(&mut self, signature: Vec<u8>) {
+        self.vendor_signature = Some(signature);
+    }
+
+    /// Replays the transcript and returns `true` if the vendor won the dispute, `false` if the
+    /// buyer won, or `None` if the dispute is still running. Fails if the transcript is invalid.
+    pub fn vendor_won(&self) -> Option<bool> {
+        match self.replay().unwrap_or_else(|e| die(&e)).state {
+            DisputeState::Complete => Some(true),
+            DisputeState::Cancel => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Serializes the transcript in its canonical encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_canonical_bytes()
+    }
+
+    /// Deserializes a transcript from its canonical encoding and checks it, see `verify`
+    pub fn from_bytes(
+        bytes: &[u8],
+        buyer: &[u8],
+        vendor: &[u8],
+        chain_id: u64,
+    ) -> DisputeTranscript {
+        DisputeTranscript::try_from_bytes(bytes, buyer, vendor, chain_id)
+            .unwrap_or_else(|e| die(&e))
+    }
+
+    /// Serializes the transcript to JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|e| die(&e.to_string()))
+    }
+
+    /// Deserializes a transcript from JSON and checks it, see `verify`
+    pub fn from_json(json: &str, buyer: &[u8], vendor: &[u8], chain_id: u64) -> DisputeTranscript {
+        DisputeTranscript::try_from_json(json, buyer, vendor, chain_id).unwrap_or_else(|e| die(&e))
+    }
+}
+
+impl DisputeTranscript {
+    /// Starts a transcript with the given parameters
+    pub fn from_header(header: TranscriptHeader) -> DisputeTranscript {
+        DisputeTranscript {
+            header,
+            records: Vec::new(),
+            buyer_signature: None,
+            vendor_signature: None,
+        }
+    }
+
+    /// Returns the parameters of the dispute
+    pub fn header(&self) -> &TranscriptHeader {
+        &self.header
+    }
+
+    /// Returns the recorded entries and their hashes, in order
+    pub fn records(&self) -> &[TranscriptRecord] {
+        &self.records
+    }
+
+    /// Records the buyer's responses to the challenges of a round
+    pub fn record_responses(&mut self, challenges: &[u32], responses: &[Vec<u8>]) {
+        self.append(TranscriptEntry::Responses {
+            challenges: challenges.to_vec(),
+            responses: responses.to_vec(),
+        });
+    }
+
+    /// Records the vendor's opinion on the responses of a round
+    ///
+    /// # Arguments
+    /// * `disagreement` - Index of the first response the vendor disagrees with, or the number of
+    ///   responses if it agrees with all of them
+    pub fn record_opinion_multi(&mut self, disagreement: u32) {
+        self.append(TranscriptEntry::Opinion { disagreement });
+    }
+
+    /// Records the data of step 8a or 8b sent by the vendor
+    pub fn record_final_step(
+        &mut self,
+        gate_num: u32,
+        opening_value: &[u8],
+        version: u32,
+        proofs: &FinalStepProofs,
+    ) {
+        self.append(TranscriptEntry::FinalStep {
+            gate_num,
+            opening_value: opening_value.to_vec(),
+            version,
+            proofs: proofs.clone(),
+        });
+    }
+
+    /// Records the proof of step 8c sent by the vendor
+    pub fn record_final_step_right(&mut self, proof: &[Vec<Vec<u8>>]) {
+        self.append(TranscriptEntry::FinalStepRight {
+            proof: proof.to_vec(),
+        });
+    }
+
+    /// Appends an entry and extends the hash chain. The signatures of the previous head are
+    /// discarded.
+    pub fn append(&mut self, entry: TranscriptEntry) {
+        let hash = leaf_hash(&[self.head(), transcript_entry_bytes(&entry)].concat());
+        self.records.push(TranscriptRecord { entry, hash });
+        self.buyer_signature = None;
+        self.vendor_signature = None;
+    }
+
+    /// Checks the hash chain of the transcript. This does not say who wrote it, see `verify`.
+    ///
+    /// # Returns
+    /// `Ok(())` if every hash matches, an error message naming the first altered record otherwise
+    pub fn verify_chain(&self) -> Result<(), String> {
+        let mut chain = DisputeTranscript::from_header(self.header.clone());
+        for (i, record) in self.records.iter().enumerate() {
+            chain.append(record.entry.clone());
+            if chain.head() != record.hash {
+                return Err(format!("Record {} of the transcript has been altered", i));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the hash chain of the transcript and that both parties signed its head
+    ///
+    /// # Arguments
+    /// * `buyer` - Address of the buyer (20 bytes)
+    /// * `vendor` - Address of the vendor (20 bytes)
+    /// * `chain_id` - Identifier of the chain of the dispute
+    ///
+    /// # Returns
+    /// `Ok(())` if the transcript is intact and signed by both parties, an error message otherwise
+    pub fn verify(&self, buyer: &[u8], vendor: &[u8], chain_id: u64) -> Result<(), String> {
+        self.verify_chain()?;
+        let digest = transcript_digest(&self.header.commitment, &self.head(), chain_id)?;
+        for (party, signature, address) in [
+            ("buyer", &self.buyer_signature, buyer),
+            ("vendor", &self.vendor_signature, vendor),
+        ] {
+            match signature {
+                Some(signature) if is_signed_by(&digest, signature, address) => {}
+                Some(_) => return Err(format!("Invalid signature of the {}", party)),
+                None => return Err(format!("The transcript is not signed by the {}", party)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the hash chain and replays the transcript on a freshly deployed dispute
+    ///
+    /// # Returns
+    /// The model of the dispute after every recorded action, or an error message if the chain is
+    /// broken or an action could not have been performed
+    pub fn replay(&self) -> Result<DisputeModel, String> {
+        self.verify_chain()?;
+
+        let mut dispute = DisputeModel::with_arity(
+            self.header.num_blocks,
+            self.header.num_gates,
+            self.header.commitment.clone(),
+            self.header.arity,
+        );
+        for (i, record) in self.records.iter().enumerate() {
+            let res = match &record.entry {
+                TranscriptEntry::Responses {
+                    challenges,
+                    responses,
+                } => {
+                    if *challenges != dispute.challenges() {
+                        Err("The challenges do not match the dispute".to_string())
+                    } else {
+                        dispute.respond_challenges(responses)
+                    }
+                }
+                TranscriptEntry::Opinion { disagreement } => {
+                    dispute.give_opinion_multi(*disagreement)
+                }
+                TranscriptEntry::FinalStep {
+                    gate_num,
+                    opening_value,
+                    version,
+                    proofs,
+                } => match dispute.state {
+                    DisputeState::WaitVendorDataLeft => {
+                        dispute.submit_commitment_left(opening_value, *gate_num, *version, proofs)
+                    }
+                    _ => dispute.submit_commitment(opening_value, *gate_num, *version, proofs),
+                },
+                TranscriptEntry::FinalStepRight { proof } => dispute.submit_commitment_right(proof),
+                TranscriptEntry::Timeout => match dispute.state {
+                    DisputeState::Complete | DisputeState::Cancel => {
+                        Err("The dispute is already decided".to_string())
+                    }
+                    _ => {
+                        dispute.timeout();
+                        Ok(())
+                    }
+                },
+            };
+            res.map_err(|e| format!("Record {} cannot be replayed: {}", i, e))?;
+        }
+
+        Ok(dispute)
+    }
+
+    /// Deserializes a transcript from its canonical encoding and checks its hash chain and
+    /// signatures, see `verify`
+    pub fn try_from_bytes(
+        bytes: &[u8],
+        buyer: &[u8],
+        vendor: &[u8],
+        chain_id: u64,
+    ) -> Result<DisputeTranscript, String> {
+        let transcript = DisputeTranscript::from_canonical_bytes(bytes)?;
+        transcript.verify(buyer, vendor, chain_id)?;
+        Ok(transcript)
+    }
+
+    /// Deserializes a transcript from JSON and checks its hash chain and signatures, see `verify`
+    pub fn try_from_json(
+        json: &str,
+        buyer: &[u8],
+        vendor: &[u8],
+        chain_id: u64,
+    ) -> Result<DisputeTranscript, String> {
+        let transcript: DisputeTranscript = serde_json::from_str(json)
+            .map_err(|e| format!("Could not decode the transcript: {}", e))?;
+        transcript.verify(buyer, vendor, chain_id)?;
+        Ok(transcript)
+    }
+
+    // number of responses of the latest round
+    fn latest_round_size(&self) -> u32 {
+        self.records
+            .iter()
+            .rev()
+            .find_map(|r| match &r.entry {
+                TranscriptEntry::Responses { responses, .. } => Some(responses.len() as u32),
+                _ => None,
+            })
+            .unwrap_or(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eip712::{eth_address, sign_digest};
+    use crate::simulator::{
+        simulate_dispute, DisputeScenario, Party, SimulationReport, Strategy, TraceParty,
+    };
+
+    const BUYER_KEY: [u8; 32] = [0xb1; 32];
+    const VENDOR_KEY: [u8; 32] = [0x5e; 32];
+    const CHAIN_ID: u64 = 31337;
+
+    // transcript of a dispute between honest parties, signed by both
+    fn signed_transcript(scenario: &DisputeScenario) -> DisputeTranscript {
+        let mut transcript = run(scenario, &Strategy::Honest, &Strategy::Honest, 1).transcript;
+        transcript.sign_as_buyer(&BUYER_KEY, CHAIN_ID);
+        transcript.sign_as_vendor(&VENDOR_KEY, CHAIN_ID);
+        transcript
+    }
+
+    // checks the transcript against the parties' addresses
+    fn verify(transcript: &DisputeTranscript) -> Result<(), String> {
+        transcript.verify(
+            &eth_address(&BUYER_KEY),
+            &eth_address(&VENDOR_KEY),
+            CHAIN_ID,
+        )
+    }
+
+    fn run(
+        scenario: &DisputeScenario,
+        buyer: &Strategy,
+        vendor: &Strategy,
+        arity: u32,
+    ) -> SimulationReport {
+        let mut buyer = TraceParty::new(scenario, buyer);
+        let mut vendor = TraceParty::new(scenario, vendor);
+        simulate_dispute(scenario.deploy_with_arity(arity), &mut buyer, &mut vendor)
+    }
+
+    #[test]
+    fn test_replay() {
+        let s = DisputeScenario::random(500);
+        let k = s.circuit.num_blocks + 3;
+        for arity in [1, 3] {
+            for (buyer, vendor) in [
+                (Strategy::Honest, Strategy::Honest),
+                (Strategy::LieAtGate(k), Strategy::Honest),
+                (Strategy::Honest, Strategy::LieAtGate(k)),
+            ] {
+                let report = run(&s, &buyer, &vendor, arity);
+                let transcript = &report.transcript;
+                let replayed = transcript.replay().unwrap();
+                assert_eq!(report.final_state, replayed.state);
+                assert_eq!(transcript.head(), replayed.transcript().head());
+                assert_eq!(
+                    Some(report.winner == Party::Vendor),
+                    transcript.vendor_won()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_serialization() {
+        let s = DisputeScenario::random(200);
+        let transcript = signed_transcript(&s);
+        let (buyer, vendor) = (eth_address(&BUYER_KEY), eth_address(&VENDOR_KEY));
+
+        let from_bytes =
+            DisputeTranscript::try_from_bytes(&transcript.to_bytes(), &buyer, &vendor, CHAIN_ID)
+                .unwrap();
+        assert_eq!(transcript.head(), from_bytes.head());
+        assert_eq!(transcript.to_bytes(), from_bytes.to_bytes());
+
+        let from_json =
+            DisputeTranscript::try_from_json(&transcript.to_json(), &buyer, &vendor, CHAIN_ID)
+                .unwrap();
+        assert_eq!(transcript.head(), from_json.head());
+        assert_eq!(transcript.to_bytes(), from_json.to_bytes());
+    }
+
+    #[test]
+    fn test_signatures() {
+        let s = DisputeScenario::random(200);
+        let transcript = signed_transcript(&s);
+        assert_eq!(Ok(()), verify(&transcript));
+
+        // missing signature
+        let mut unsigned = transcript.clone();
+        unsigned.vendor_signature = None;
+        assert!(verify(&unsigned).is_err());
+        assert!(verify(&run(&s, &Strategy::Honest, &Strategy::Honest, 1).transcript).is_err());
+
+        // other signer, swapped parties or other chain
+        let mut other = transcript.clone();
+        other.sign_as_buyer(&[0x07; 32], CHAIN_ID);
+        assert!(verify(&other).is_err());
+        let swapped = transcript.verify(
+            &eth_address(&VENDOR_KEY),
+            &eth_address(&BUYER_KEY),
+            CHAIN_ID,
+        );
+        assert!(swapped.is_err());
+        let other_chain = transcript.verify(&eth_address(&BUYER_KEY), &eth_address(&VENDOR_KEY), 1);
+        assert!(other_chain.is_err());
+
+        // signatures of a wallet
+        let mut external = transcript.clone();
+        external.set_buyer_signature(sign_digest(&BUYER_KEY, &transcript.digest(CHAIN_ID)));
+        assert_eq!(Ok(()), verify(&external));
+
+        // recording an action discards the signatures of the previous head
+        let mut extended = transcript.clone();
+        extended.record_timeout();
+        assert_eq!(None, extended.buyer_signature);
+        assert!(verify(&extended).is_err());
+
+        // version 1 of the encoding has no signatures (2 times marker, length and signature)
+        let mut bytes = transcript.to_bytes();
+        bytes[5] = 1;
+        bytes.truncate(bytes.len() - 2 * (1 + 4 + 65));
+        let v1 = DisputeTranscript::from_canonical_bytes(&bytes).unwrap();
+        assert_eq!(transcript.head(), v1.head());
+        assert_eq!(None, v1.vendor_signature);
+        assert!(verify(&v1).is_err());
+    }
+
+    #[test]
+    fn test_tampering() {
+        let s = DisputeScenario::random(200);
+        let transcript = signed_transcript(&s);
+        assert!(transcript.len() > 2);
+        let (buyer, vendor) = (eth_address(&BUYER_KEY), eth_address(&VENDOR_KEY));
+
+        // altered response
+        let mut altered = transcript.clone();
+        if let TranscriptEntry::Responses { responses, .. } = &mut altered.records[0].entry {
+            responses[0][0] ^= 1;
+        }
+        assert!(altered.verify_chain().is_err());
+        assert!(
+            DisputeTranscript::try_from_bytes(&altered.to_bytes(), &buyer, &vendor, CHAIN_ID)
+                .is_err()
+        );
+
+        // removed record
+        let mut removed = transcript.clone();
+        removed.records.remove(1);
+        assert!(removed.verify_chain().is_err());
+
+        // rebuilding the chain after changing an opinion changes the head and the outcome
+        let mut rebuilt = DisputeTranscript::from_header(transcript.header().clone());
+        for record in transcript.records() {
+            match record.entry {
+                TranscriptEntry::Opinion { disagreement } => {
+                    rebuilt.record_opinion_multi(1 - disagreement)
+                }
+                _ => rebuilt.append(record.entry.clone()),
+            }
+        }
+        assert!(rebuilt.verify_chain().is_ok());
+        assert_ne!(transcript.head(), rebuilt.head());
+        assert!(rebuilt.replay().is_err());
+
+        // a rebuilt chain is consistent but cannot carry the parties' signatures
+        let mut forged = rebuilt.clone();
+        forged.buyer_signature = transcript.buyer_signature.clone();
+        forged.vendor_signature = transcript.vendor_signature.clone();
+        assert!(forged.verify_chain().is_ok());
+        assert!(verify(&forged).is_err());
+        assert!(
+            DisputeTranscript::try_from_bytes(&forged.to_bytes(), &buyer, &vendor, CHAIN_ID)
+                .is_err()
+        );
+        forged.sign_as_vendor(&VENDOR_KEY, CHAIN_ID);
+        assert!(verify(&forged).is_err());
+    }
+
+    #[test]
+    fn test_manual_recording() {
+        let s = DisputeScenario::random(100);
+        let mut dispute = s.deploy();
+        let mut transcript = DisputeTranscript::new(
+            dispute.num_blocks,
+            dispute.num_gates,
+            dispute.commitment.clone(),
+            1,
+        );
+
+        let chall = dispute.chall;
+        dispute.respond_challenge(&[7u8; 32]).unwrap();
+        transcript.record_response(chall, &[7u8; 32]);
+        dispute.give_opinion(true).unwrap();
+        transcript.record_opinion(true);
+        assert_eq!(dispute.transcript().head(), transcript.head());
+        assert_eq!(None, transcript.vendor_won());
+
+        transcript.record_timeout();
+        assert_eq!(Some(true), transcript.vendor_won());
+        transcript.record_timeout();
+        assert!(transcript.replay().is_err());
+    }
+}
@@ -17,6 +17,8 @@ extern "C" {
 /// # Arguments
 /// * `s` - Error message to display
 pub fn die(s: &str) -> ! {
+    // the console binding is only available when running in a JS host
+    #[cfg(target_arch = "wasm32")]
     error(s);
     panic!("{}", s);
 }